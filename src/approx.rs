@@ -0,0 +1,77 @@
+use crate::{Array, Value};
+
+impl Value {
+    /// Compare two values structurally, allowing floats to differ by at most `epsilon`
+    ///
+    /// All other values have to be exactly equal, arrays are compared entry by entry
+    /// and `NAN` is considered equal to `NAN`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = from_str::<Value>("0.3")?;
+    /// let expected = Value::from(0.1 + 0.2);
+    ///
+    /// assert_ne!(value, expected);
+    /// assert!(value.approx_eq(&expected, 1e-9));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => {
+                a == b || (a - b).abs() <= epsilon || (a.is_nan() && b.is_nan())
+            }
+            (Value::Array(a), Value::Array(b)) => array_approx_eq(a, b, epsilon),
+            (Value::Object(a), Value::Object(b)) => {
+                a.class == b.class && array_approx_eq(&a.properties, &b.properties, epsilon)
+            }
+            (Value::Instance(a), Value::Instance(b)) => {
+                a.class == b.class
+                    && a.args.len() == b.args.len()
+                    && a.args
+                        .iter()
+                        .zip(b.args.iter())
+                        .all(|(a, b)| a.approx_eq(b, epsilon))
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+fn array_approx_eq(a: &Array, b: &Array, epsilon: f64) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, a)| matches!(b.get(key), Some(b) if a.approx_eq(b, epsilon)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, from_str_with_options, ParserOptions, Value};
+
+    #[test]
+    fn test_approx_eq_nested() {
+        let options = ParserOptions::new().capture_objects(true);
+        let a = from_str_with_options::<Value>(
+            r#"["a" => [1.0, 2.5], "b" => new Foo(0.1), "c" => "x"]"#,
+            &options,
+        )
+        .unwrap();
+        let b = from_str_with_options::<Value>(
+            r#"["a" => [1.0000001, 2.5], "b" => new Foo(0.1000001), "c" => "x"]"#,
+            &options,
+        )
+        .unwrap();
+        assert!(a.approx_eq(&b, 1e-6));
+        assert!(!a.approx_eq(&b, 1e-9));
+
+        let c = from_str::<Value>(r#"["a" => [1.0, 2.5], "c" => "x"]"#).unwrap();
+        assert!(!a.approx_eq(&c, 1.0));
+        assert!(!Value::Int(1).approx_eq(&Value::Float(1.0), 1.0));
+        assert!(Value::Float(f64::NAN).approx_eq(&Value::Float(f64::NAN), 0.0));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::error::ArrayKeyErrorKind;
+use crate::string::is_array_key_numeric;
+use crate::{Key, Value};
+#[cfg(feature = "preserve_order")]
+use indexmap::map;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::hash_map as map;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::HashMap;
+
+/// The map php arrays are stored in
+///
+/// With the `preserve_order` feature this is an `IndexMap` which keeps the insertion order of the entries like php does.
+#[cfg(not(feature = "preserve_order"))]
+pub type Array = HashMap<Key, Value>;
+/// The map php arrays are stored in
+///
+/// With the `preserve_order` feature this is an `IndexMap` which keeps the insertion order of the entries like php does.
+#[cfg(feature = "preserve_order")]
+pub type Array = indexmap::IndexMap<Key, Value>;
+
+/// An entry of an array which might not exist yet, see [`Value::entry`]
+pub type Entry<'a> = map::Entry<'a, Key, Value>;
+
+/// A borrowed array key used for lookups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRef<'a> {
+    Int(i64),
+    String(&'a str),
+}
+
+/// Types that can be used to look up an entry in an array
+pub trait AsKeyRef {
+    fn as_key_ref(&self) -> KeyRef<'_>;
+}
+
+impl AsKeyRef for Key {
+    fn as_key_ref(&self) -> KeyRef<'_> {
+        match self {
+            Key::Int(int) => KeyRef::Int(*int),
+            Key::String(str) => KeyRef::String(str),
+        }
+    }
+}
+
+impl AsKeyRef for KeyRef<'_> {
+    fn as_key_ref(&self) -> KeyRef<'_> {
+        *self
+    }
+}
+
+impl AsKeyRef for str {
+    fn as_key_ref(&self) -> KeyRef<'_> {
+        KeyRef::String(self)
+    }
+}
+
+impl AsKeyRef for String {
+    fn as_key_ref(&self) -> KeyRef<'_> {
+        KeyRef::String(self)
+    }
+}
+
+impl AsKeyRef for i64 {
+    fn as_key_ref(&self) -> KeyRef<'_> {
+        KeyRef::Int(*self)
+    }
+}
+
+impl<T: AsKeyRef + ?Sized> AsKeyRef for &T {
+    fn as_key_ref(&self) -> KeyRef<'_> {
+        (**self).as_key_ref()
+    }
+}
+
+impl From<KeyRef<'_>> for Key {
+    fn from(key: KeyRef<'_>) -> Self {
+        match key {
+            KeyRef::Int(int) => Key::Int(int),
+            KeyRef::String(str) => Key::String(str.into()),
+        }
+    }
+}
+
+/// Convert a key like php does when using it as array key, numeric strings become integer keys
+pub(crate) fn normalize_key(key: KeyRef<'_>) -> Key {
+    match key {
+        KeyRef::String(str) if is_array_key_numeric(str) => Key::Int(str.parse().unwrap()),
+        key => key.into(),
+    }
+}
+
+/// Php style lookups and appending for arrays
+pub(crate) trait ArrayExt {
+    /// Get the value for a key, numeric string keys match the integer key
+    fn lookup<K: AsKeyRef + ?Sized>(&self, key: &K) -> Option<&Value>;
+
+    /// Get a mutable reference to the value for a key, numeric string keys match the integer key
+    fn lookup_mut<K: AsKeyRef + ?Sized>(&mut self, key: &K) -> Option<&mut Value>;
+
+    /// Remove the entry for a key, returning its value
+    fn remove_key<K: AsKeyRef + ?Sized>(&mut self, key: &K) -> Option<Value>;
+
+    /// The key used when appending a value, one more than the largest integer key or 0 if there are none
+    ///
+    /// Returns `None` if the largest integer key is `i64::MAX`, no value can be appended then.
+    fn next_index(&self) -> Option<i64>;
+
+    /// The entries of the array in the order php would iterate them
+    ///
+    /// Without the `preserve_order` feature the insertion order isn't known, the entries are sorted by key instead
+    /// to keep the result independent of the hash map order.
+    fn ordered(&self) -> Vec<(&Key, &Value)>;
+}
+
+// looking up by `str` would compare through `Borrow<str>`, which panics for integer keys
+impl ArrayExt for Array {
+    fn lookup<K: AsKeyRef + ?Sized>(&self, key: &K) -> Option<&Value> {
+        self.get(&lookup_key(self, key.as_key_ref()))
+    }
+
+    fn lookup_mut<K: AsKeyRef + ?Sized>(&mut self, key: &K) -> Option<&mut Value> {
+        let key = lookup_key(self, key.as_key_ref());
+        self.get_mut(&key)
+    }
+
+    #[cfg(not(feature = "preserve_order"))]
+    fn remove_key<K: AsKeyRef + ?Sized>(&mut self, key: &K) -> Option<Value> {
+        self.remove(&Key::from(key.as_key_ref()))
+    }
+
+    #[cfg(feature = "preserve_order")]
+    fn remove_key<K: AsKeyRef + ?Sized>(&mut self, key: &K) -> Option<Value> {
+        self.shift_remove(&Key::from(key.as_key_ref()))
+    }
+
+    fn next_index(&self) -> Option<i64> {
+        let mut next_index = NextIndex::default();
+        self.keys().for_each(|key| next_index.insert(key));
+        next_index.peek()
+    }
+
+    fn ordered(&self) -> Vec<(&Key, &Value)> {
+        #[allow(unused_mut)]
+        let mut entries: Vec<_> = self.iter().collect();
+        #[cfg(not(feature = "preserve_order"))]
+        entries.sort_by_key(|(key, _)| *key);
+        entries
+    }
+}
+
+/// The key to look up `key` with, numeric strings match the integer key unless the array contains the string key itself
+pub(crate) fn lookup_key(array: &Array, key: KeyRef<'_>) -> Key {
+    let exact = Key::from(key);
+    let contains_exact = array.contains_key(&exact);
+    match key {
+        // numeric string keys are only kept with `keep_numeric_string_keys`
+        KeyRef::String(_) if !contains_exact => normalize_key(key),
+        _ => exact,
+    }
+}
+
+/// Take the entries of an array in the order php would iterate them, see [`ArrayExt::ordered`]
+pub(crate) fn into_ordered(array: Array) -> Vec<(Key, Value)> {
+    #[allow(unused_mut)]
+    let mut entries: Vec<_> = array.into_iter().collect();
+    #[cfg(not(feature = "preserve_order"))]
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries
+}
+
+/// Tracks the key php uses for array entries without a key, one more than the largest integer key so far
+///
+/// Shared by all ways of building arrays so they number entries the same way.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct NextIndex(Option<i64>);
+
+impl NextIndex {
+    /// Record the key of an entry
+    pub fn insert(&mut self, key: &Key) {
+        if let Key::Int(int) = *key {
+            self.0 = Some(self.0.map_or(int, |max| max.max(int)));
+        }
+    }
+
+    /// The key for the next entry without a key, `None` once `i64::MAX` is used as key
+    pub fn peek(&self) -> Option<i64> {
+        match self.0 {
+            Some(max) => max.checked_add(1),
+            None => Some(0),
+        }
+    }
+
+    /// Take the key for the next entry without a key, `None` once `i64::MAX` is used as key
+    pub fn next(&mut self) -> Option<i64> {
+        let key = self.peek()?;
+        self.0 = Some(key);
+        Some(key)
+    }
+}
+
+/// Builder for the arrays created while parsing
+///
+/// Keeps track of the key for entries without a key while the entries are inserted.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct ArrayBuilder {
+    array: Array,
+    next_index: NextIndex,
+}
+
+impl ArrayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        ArrayBuilder {
+            array: Array::with_capacity(capacity),
+            next_index: NextIndex::default(),
+        }
+    }
+
+    /// Insert a value, replacing the previous value for the key if there was one
+    pub fn insert(&mut self, key: Key, value: Value) {
+        self.next_index.insert(&key);
+        self.array.insert(key, value);
+    }
+
+    /// Append a value with the next integer key, like `$array[] = $value` in php
+    ///
+    /// Fails like php does if the array already has an entry with key `i64::MAX`.
+    pub fn push(&mut self, value: Value) -> Result<(), String> {
+        let key = self
+            .next_index
+            .next()
+            .ok_or_else(|| ArrayKeyErrorKind::NextElementOccupied.to_string())?;
+        self.insert(Key::Int(key), value);
+        Ok(())
+    }
+
+    /// Add the entries of an unpacked array, like `[...$array]` in php
+    ///
+    /// Entries with integer keys are appended, other entries keep their key.
+    /// Without the `preserve_order` feature the order of the unpacked array isn't known,
+    /// so the entries are unpacked in sorted key order.
+    pub fn unpack(&mut self, array: Array) -> Result<(), String> {
+        for (key, value) in into_ordered(array) {
+            match key {
+                Key::Int(_) => self.push(value)?,
+                key => self.insert(key, value),
+            }
+        }
+        Ok(())
+    }
+
+    /// The key used when appending a value, see [`ArrayExt::next_index`]
+    pub fn next_index(&self) -> Option<i64> {
+        self.next_index.peek()
+    }
+
+    pub fn build(self) -> Array {
+        self.array
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let mut builder = ArrayBuilder::new();
+        for i in 0..20 {
+            builder.push(Value::Int(i)).unwrap();
+            builder.insert(format!("key{}", i).into(), Value::Int(i));
+        }
+        builder.insert(Key::Int(3), Value::Null);
+        assert_eq!(builder.next_index(), Some(20));
+
+        let mut array = builder.build();
+        assert_eq!(array.len(), 40);
+        assert_eq!(array.lookup(&3), Some(&Value::Null));
+        assert_eq!(array.lookup("key19"), Some(&Value::Int(19)));
+        assert_eq!(array.remove_key("key0"), Some(Value::Int(0)));
+        assert_eq!(array.lookup("key0"), None);
+        assert_eq!(array.next_index(), Some(20));
+    }
+}
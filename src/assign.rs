@@ -0,0 +1,217 @@
+use crate::array::ArrayExt;
+use crate::error::{ArrayKeyErrorKind, ExpressionError};
+use crate::serde_impl::{from_str_after, Assignment, Deserializer};
+use crate::{Array, Key, ParseError, ParserOptions, Value};
+use serde::Deserialize;
+
+/// Parse a php literal assigned to a variable, `$CONFIG = <literal>;`, returning the name of the variable and the value
+///
+/// The name of the variable is returned without the leading `$`.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_assignment_str, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let (name, config) = from_assignment_str::<Value>(r#"$CONFIG = array('debug' => true);"#)?;
+///
+/// assert_eq!(name, "CONFIG");
+/// assert_eq!(config["debug"], true);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_assignment_str<'a, T>(s: &'a str) -> Result<(&'a str, T), ParseError>
+where
+    T: Deserialize<'a>,
+{
+    from_assignment_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a php literal assigned to a variable, `$CONFIG = <literal>;`, using the provided options
+pub fn from_assignment_str_with_options<'a, T>(
+    s: &'a str,
+    options: &ParserOptions,
+) -> Result<(&'a str, T), ParseError>
+where
+    T: Deserialize<'a>,
+{
+    let mut name = "";
+    let value = from_str_after(s, options, |de| {
+        name = de.open_assignment()?;
+        Ok(())
+    })?;
+    Ok((name, value))
+}
+
+/// Parse a sequence of `$CONFIG['key'] = <value>;` statements into a single array
+///
+/// Config files of Nextcloud and many older php applications build their configuration by assigning
+/// to indices of one variable. Nested indices like `$CONFIG['a']['b']` create the intermediate arrays,
+/// and `$CONFIG[] = <value>` appends to the array like php does. Assigning to the variable itself,
+/// `$CONFIG = array(...);`, replaces the entire value.
+/// All statements have to assign to the same variable.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{assignments_from_str, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let config = assignments_from_str(r#"
+///     $CONFIG['dbtype'] = 'mysql';
+///     $CONFIG['trusted_domains'][] = 'localhost';
+///     $CONFIG['trusted_domains'][] = 'example.com';
+/// "#)?;
+///
+/// assert_eq!(config["dbtype"], "mysql");
+/// assert_eq!(config["trusted_domains"][1], "example.com");
+/// # Ok(())
+/// # }
+/// ```
+pub fn assignments_from_str(s: &str) -> Result<Value, ParseError> {
+    assignments_from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a sequence of `$CONFIG['key'] = <value>;` statements into a single array using the provided options
+pub fn assignments_from_str_with_options(
+    s: &str,
+    options: &ParserOptions,
+) -> Result<Value, ParseError> {
+    let mut de = Deserializer::with_options(s, options.clone());
+    let mut result = Value::Array(Array::new());
+    let mut name = None;
+    while let Some(Assignment {
+        variable,
+        path,
+        value,
+    }) = de.next_assignment()?
+    {
+        let error = |message: String| ExpressionError::new(s, variable.span.clone(), message);
+        match name {
+            None => name = Some(variable.slice()),
+            Some(name) if name != variable.slice() => {
+                return Err(error(format!(
+                    "Assignment to {}, expected {}",
+                    variable.slice(),
+                    name
+                ))
+                .into())
+            }
+            _ => {}
+        }
+        assign(&mut result, &path, value).map_err(error)?;
+    }
+    Ok(result)
+}
+
+/// Assign `value` to the element at `path` in `target`, creating the arrays along the way
+fn assign(target: &mut Value, path: &[Option<Key>], value: Value) -> Result<(), String> {
+    let (key, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *target = value;
+            return Ok(());
+        }
+    };
+    if target.is_null() {
+        *target = Value::Array(Array::new());
+    }
+    let array = match target {
+        Value::Array(array) => array,
+        _ => return Err(String::from("Cannot use a scalar value as an array")),
+    };
+    let key = match key {
+        Some(key) => key.clone(),
+        None => Key::Int(
+            array
+                .next_index()
+                .ok_or_else(|| ArrayKeyErrorKind::NextElementOccupied.to_string())?,
+        ),
+    };
+    assign(array.entry(key).or_insert(Value::Null), rest, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assignments_from_str, from_assignment_str, from_str, ParserOptions, Value};
+    use serde_derive::Deserialize;
+
+    #[test]
+    fn test_assignments() {
+        let value = assignments_from_str(
+            r#"
+            $CONFIG = ['removed' => true];
+            $CONFIG = ['a' => 1];
+            $CONFIG["b"]['c'] = [true];
+            $CONFIG['b']['c'][] = false;
+            $CONFIG[] = 'x';
+            $CONFIG[5] = 'y';
+            $CONFIG[] = 'z';
+            $CONFIG['a'] = 2;
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            from_str::<Value>("['a' => 2, 'b' => ['c' => [true, false]], 'x', 5 => 'y', 'z']")
+                .unwrap()
+        );
+
+        assert_eq!(
+            assignments_from_str("").unwrap(),
+            from_str::<Value>("[]").unwrap()
+        );
+        assert!(assignments_from_str("$CONFIG['a'] = 1; $OTHER['b'] = 2;").is_err());
+        assert!(assignments_from_str("$CONFIG['a'] = 1; $CONFIG['a']['b'] = 2;").is_err());
+        assert!(assignments_from_str("$CONFIG['a'] = 1").is_err());
+        assert!(assignments_from_str("$CONFIG['a'] 1;").is_err());
+        assert!(assignments_from_str("CONFIG['a'] = 1;").is_err());
+        let options = ParserOptions::new().php_file(true);
+        let value =
+            super::assignments_from_str_with_options("<?php\n$CONFIG['a'] = 1;\n?>\n", &options)
+                .unwrap();
+        assert_eq!(value["a"], 1);
+        assert!(assignments_from_str("$CONFIG = 'a'; $CONFIG['b'] = 1;").is_err());
+        assert!(assignments_from_str("$CONFIG[9223372036854775807] = 1; $CONFIG[] = 2;").is_err());
+        assert_eq!(
+            assignments_from_str("$CONFIG = 'a';").unwrap(),
+            Value::from("a")
+        );
+    }
+
+    #[test]
+    fn test_from_assignment() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Config {
+            debug: bool,
+            #[serde(rename = "db.host")]
+            host: String,
+        }
+
+        let (name, config) =
+            from_assignment_str::<Config>("$CONFIG = ['debug' => true, 'db.host' => 'localhost'];")
+                .unwrap();
+        assert_eq!(name, "CONFIG");
+        assert_eq!(
+            config,
+            Config {
+                debug: true,
+                host: "localhost".into()
+            }
+        );
+
+        let options = ParserOptions::new().php_file(true);
+        let (name, value) =
+            super::from_assignment_str_with_options::<Value>("<?php\n$conf = [1, 2];\n", &options)
+                .unwrap();
+        assert_eq!(name, "conf");
+        assert_eq!(value[1], 2);
+
+        assert!(from_assignment_str::<Value>("$CONFIG = [1] $b = 2;").is_err());
+        assert!(from_assignment_str::<Value>("$CONFIG['a'] = 1;").is_err());
+        assert!(from_assignment_str::<Value>("[1]").is_err());
+    }
+}
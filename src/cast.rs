@@ -0,0 +1,321 @@
+use crate::{Array, Key, Value};
+use std::fmt::{Display, Formatter};
+
+/// A php type that a value can be converted to using [`Value::cast_to`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhpType {
+    Bool,
+    Int,
+    Float,
+    String,
+    Array,
+    Null,
+}
+
+impl Display for PhpType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PhpType::Bool => "bool",
+            PhpType::Int => "int",
+            PhpType::Float => "float",
+            PhpType::String => "string",
+            PhpType::Array => "array",
+            PhpType::Null => "null",
+        })
+    }
+}
+
+impl Value {
+    /// Convert the value to another type following the rules of php's `settype()`
+    ///
+    /// Returns `None` if the value can't be converted, this is the case for captured expressions,
+    /// for object instantiations cast to anything other than a bool and for objects cast to anything
+    /// other than a bool or array. Casting an object to an array gives its properties.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{PhpType, Value};
+    ///
+    /// assert_eq!(Value::from("12 apples").cast_to(PhpType::Int), Some(Value::Int(12)));
+    /// assert_eq!(Value::from("0").cast_to(PhpType::Bool), Some(Value::Bool(false)));
+    /// assert_eq!(Value::Float(0.1 + 0.2).cast_to(PhpType::String), Some(Value::from("0.3")));
+    /// ```
+    pub fn cast_to(&self, ty: PhpType) -> Option<Value> {
+        if self.is_expr() || (self.is_instance() && ty != PhpType::Bool) {
+            return None;
+        }
+        if self.is_object() && !matches!(ty, PhpType::Bool | PhpType::Array) {
+            return None;
+        }
+        Some(match ty {
+            PhpType::Bool => Value::Bool(cast_bool(self)),
+            PhpType::Int => Value::Int(cast_int(self)),
+            PhpType::Float => Value::Float(cast_float(self)),
+            PhpType::String => match self {
+                Value::Bytes(_) => self.clone(),
+                value => Value::String(cast_string(value)),
+            },
+            PhpType::Array => match self {
+                Value::Array(_) => self.clone(),
+                Value::Object(object) => Value::Array(object.properties.clone()),
+                Value::Null => Value::Array(Array::new()),
+                value => {
+                    let mut map = Array::with_capacity(1);
+                    map.insert(Key::Int(0), value.clone());
+                    Value::Array(map)
+                }
+            },
+            PhpType::Null => Value::Null,
+        })
+    }
+}
+
+fn cast_bool(value: &Value) -> bool {
+    match value {
+        Value::Bool(bool) => *bool,
+        Value::Int(int) => *int != 0,
+        Value::Float(float) => *float != 0.0,
+        Value::String(str) => !(str.is_empty() || str == "0"),
+        Value::Bytes(bytes) => !bytes.is_empty(),
+        Value::Array(map) => !map.is_empty(),
+        Value::Null => false,
+        Value::Instance(_) | Value::Object(_) | Value::Expr(_) => true,
+    }
+}
+
+fn cast_int(value: &Value) -> i64 {
+    match value {
+        Value::Int(int) => *int,
+        Value::Float(float) => float_to_int(*float),
+        Value::String(str) => match numeric_prefix(str) {
+            NumericPrefix::Int(int) => int,
+            NumericPrefix::Float(float) => float_to_int(float),
+        },
+        Value::Bytes(bytes) => cast_int(&Value::String(String::from_utf8_lossy(bytes).into())),
+        value => cast_bool(value) as i64,
+    }
+}
+
+fn cast_float(value: &Value) -> f64 {
+    match value {
+        Value::Int(int) => *int as f64,
+        Value::Float(float) => *float,
+        Value::String(str) => match numeric_prefix(str) {
+            NumericPrefix::Int(int) => int as f64,
+            NumericPrefix::Float(float) => float,
+        },
+        Value::Bytes(bytes) => cast_float(&Value::String(String::from_utf8_lossy(bytes).into())),
+        value => cast_bool(value) as i64 as f64,
+    }
+}
+
+fn cast_string(value: &Value) -> String {
+    match value {
+        Value::Bool(true) => "1".into(),
+        Value::Bool(false) | Value::Null => String::new(),
+        Value::Int(int) => int.to_string(),
+        Value::Float(float) => float_to_string(*float, 14),
+        Value::String(str) => str.clone(),
+        Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into(),
+        Value::Array(_) => "Array".into(),
+        Value::Instance(_) | Value::Object(_) | Value::Expr(_) => unreachable!(),
+    }
+}
+
+/// Convert a float to int the way php does on 64 bit platforms, wrapping out of range values
+fn float_to_int(float: f64) -> i64 {
+    if !float.is_finite() {
+        0
+    } else if float >= i64::MIN as f64 && float < i64::MAX as f64 {
+        float as i64
+    } else {
+        float.trunc().rem_euclid(18446744073709551616.0) as u64 as i64
+    }
+}
+
+/// Format a float the way php does when converting it to a string with the given precision
+pub(crate) fn float_to_string(float: f64, precision: usize) -> String {
+    format_float(
+        float,
+        format!("{:.*e}", precision - 1, float.abs()),
+        precision,
+    )
+}
+
+/// Format a float using the shortest representation that round trips, like php does with `serialize_precision = -1`
+pub(crate) fn float_to_shortest_string(float: f64) -> String {
+    format_float(float, format!("{:e}", float.abs()), 17)
+}
+
+/// Format the scientific notation of the absolute value of a float the way php does
+fn format_float(float: f64, formatted: String, precision: usize) -> String {
+    if float.is_nan() {
+        return "NAN".into();
+    }
+    if float.is_infinite() {
+        return if float > 0.0 { "INF" } else { "-INF" }.into();
+    }
+    if float == 0.0 {
+        return if float.is_sign_negative() { "-0" } else { "0" }.into();
+    }
+
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+    let digits = digits.trim_end_matches('0');
+    let sign = if float < 0.0 { "-" } else { "" };
+    let decimal_point = exponent + 1;
+
+    if decimal_point < -3 || decimal_point > precision as i32 {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        format!(
+            "{}{}.{}E{}{}",
+            sign,
+            first,
+            rest,
+            exponent_sign,
+            exponent.abs()
+        )
+    } else if decimal_point <= 0 {
+        format!(
+            "{}0.{}{}",
+            sign,
+            "0".repeat(decimal_point.unsigned_abs() as usize),
+            digits
+        )
+    } else if digits.len() <= decimal_point as usize {
+        format!(
+            "{}{}{}",
+            sign,
+            digits,
+            "0".repeat(decimal_point as usize - digits.len())
+        )
+    } else {
+        let (int, fraction) = digits.split_at(decimal_point as usize);
+        format!("{}{}.{}", sign, int, fraction)
+    }
+}
+
+enum NumericPrefix {
+    Int(i64),
+    Float(f64),
+}
+
+/// Parse the leading numeric part of a string like php does when converting strings to numbers
+fn numeric_prefix(str: &str) -> NumericPrefix {
+    let trimmed = str.trim_start_matches([' ', '\t', '\n', '\r', '\x0B', '\x0C']);
+    let bytes = trimmed.as_bytes();
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        end += 1;
+    }
+    let int_start = end;
+    while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+        end += 1;
+    }
+    let int_end = end;
+    let mut is_float = false;
+    if bytes.get(end) == Some(&b'.') && bytes.get(end + 1).is_some_and(u8::is_ascii_digit) {
+        is_float = true;
+        end += 1;
+        while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+            end += 1;
+        }
+    }
+    if end > int_start && matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exponent_end = end + 1;
+        if matches!(bytes.get(exponent_end), Some(b'+' | b'-')) {
+            exponent_end += 1;
+        }
+        if bytes.get(exponent_end).is_some_and(u8::is_ascii_digit) {
+            is_float = true;
+            end = exponent_end;
+            while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+                end += 1;
+            }
+        }
+    }
+
+    if int_end == int_start && !is_float {
+        NumericPrefix::Int(0)
+    } else if is_float {
+        NumericPrefix::Float(trimmed[..end].parse().unwrap_or_default())
+    } else {
+        match trimmed[..end].parse() {
+            Ok(int) => NumericPrefix::Int(int),
+            // php saturates integer strings that don't fit
+            Err(_) if bytes[0] == b'-' => NumericPrefix::Int(i64::MIN),
+            Err(_) => NumericPrefix::Int(i64::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_scalars() {
+        assert_eq!(
+            Value::from("  -12.5e1xyz").cast_to(PhpType::Int),
+            Some(Value::Int(-125))
+        );
+        assert_eq!(
+            Value::from("0x1A").cast_to(PhpType::Int),
+            Some(Value::Int(0))
+        );
+        assert_eq!(
+            Value::from("abc").cast_to(PhpType::Float),
+            Some(Value::Float(0.0))
+        );
+        assert_eq!(
+            Value::from(".5").cast_to(PhpType::Float),
+            Some(Value::Float(0.5))
+        );
+        assert_eq!(
+            Value::from("99999999999999999999").cast_to(PhpType::Int),
+            Some(Value::Int(i64::MAX))
+        );
+        assert_eq!(
+            Value::Float(f64::NAN).cast_to(PhpType::Int),
+            Some(Value::Int(0))
+        );
+        assert_eq!(
+            Value::Float(1e20).cast_to(PhpType::Int),
+            Some(Value::Int(7766279631452241920))
+        );
+        assert_eq!(
+            Value::Float(0.0).cast_to(PhpType::Bool),
+            Some(Value::Bool(false))
+        );
+        assert_eq!(Value::Bool(true).cast_to(PhpType::String), Some("1".into()));
+        assert_eq!(
+            Value::Null.cast_to(PhpType::Array),
+            Some(Value::Array(Array::new()))
+        );
+        assert_eq!(Value::Int(3).cast_to(PhpType::Array).unwrap()[0], 3);
+        assert_eq!(Value::Expr("fn () => 1".into()).cast_to(PhpType::Int), None);
+    }
+
+    #[test]
+    fn test_float_to_string() {
+        assert_eq!(float_to_string(1.0, 14), "1");
+        assert_eq!(float_to_string(-1.5, 14), "-1.5");
+        assert_eq!(float_to_string(0.1 + 0.2, 14), "0.3");
+        assert_eq!(float_to_string(100000.0, 14), "100000");
+        assert_eq!(float_to_string(1e15, 14), "1.0E+15");
+        assert_eq!(float_to_string(1.5e-7, 14), "1.5E-7");
+        assert_eq!(float_to_string(0.0001, 14), "0.0001");
+        assert_eq!(
+            float_to_string(123456789012345678.0, 17),
+            "1.2345678901234568E+17"
+        );
+        assert_eq!(float_to_string(f64::NEG_INFINITY, 14), "-INF");
+        assert_eq!(float_to_shortest_string(0.1), "0.1");
+        assert_eq!(float_to_shortest_string(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(float_to_shortest_string(1e25), "1.0E+25");
+    }
+}
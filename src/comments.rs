@@ -0,0 +1,186 @@
+use crate::cst::{ArrayNode, SyntaxNode, SyntaxTree};
+use std::collections::BTreeMap;
+
+impl SyntaxTree {
+    /// Collect the comments in the source, attached to the array entry they document
+    ///
+    /// Comments are keyed by the path of array keys leading to the entry, joined with `.` the same way
+    /// as [`Value::flatten`](crate::Value::flatten) does. The comment text includes the `//`, `#` or `/* */` markers.
+    ///
+    /// Comments before an entry and comments between its key, value and comma are attached to that entry.
+    /// A comment on the same line after an entry is also attached to it, while comments on the same line as the
+    /// opening bracket of an array or on their own lines after the last entry are attached to the array itself.
+    /// Comments around the top level value use an empty key.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::SyntaxTree;
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let tree = SyntaxTree::parse(r#"[
+    ///     /** the instance name */
+    ///     'name' => 'foo',
+    ///     'redis' => [ // cache settings
+    ///         'host' => 'localhost', # or a socket
+    ///     ],
+    /// ]"#)?;
+    /// let comments = tree.comments();
+    ///
+    /// assert_eq!(comments["name"], ["/** the instance name */"]);
+    /// assert_eq!(comments["redis"], ["// cache settings"]);
+    /// assert_eq!(comments["redis.host"], ["# or a socket"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn comments(&self) -> BTreeMap<String, Vec<String>> {
+        let mut result = BTreeMap::new();
+        for token in &self.prelude {
+            add(&mut result, "", &token.leading);
+        }
+        collect_node(&mut result, "", &self.root, true);
+        for token in self.semicolon.iter().chain(&self.close_tag) {
+            add(&mut result, "", &token.leading);
+        }
+        add(&mut result, "", &self.trailing);
+        result
+    }
+}
+
+fn add(result: &mut BTreeMap<String, Vec<String>>, path: &str, trivia: &str) {
+    let mut comments = comments(trivia).peekable();
+    if comments.peek().is_some() {
+        result
+            .entry(path.into())
+            .or_default()
+            .extend(comments.map(String::from));
+    }
+}
+
+/// Split trivia into the part on the same line as the previous token and the rest
+fn split_line(trivia: &str) -> (&str, &str) {
+    match trivia.find('\n') {
+        Some(newline) => trivia.split_at(newline),
+        None => ("", trivia),
+    }
+}
+
+/// Collect the comments inside a node, including the comments before it if `leading` is set
+fn collect_node(
+    result: &mut BTreeMap<String, Vec<String>>,
+    path: &str,
+    node: &SyntaxNode,
+    leading: bool,
+) {
+    if leading {
+        add(result, path, &node.first_token().leading);
+    }
+    match node {
+        SyntaxNode::Literal(literal) => {
+            for token in &literal.tokens[1..] {
+                add(result, path, &token.leading);
+            }
+        }
+        SyntaxNode::Array(array) => {
+            for token in &array.open[1..] {
+                add(result, path, &token.leading);
+            }
+            collect_array(result, path, array);
+        }
+        SyntaxNode::Expr(tokens) => {
+            for token in &tokens[1..] {
+                add(result, path, &token.leading);
+            }
+        }
+    }
+}
+
+fn collect_array(result: &mut BTreeMap<String, Vec<String>>, path: &str, array: &ArrayNode) {
+    let mut previous = path.to_string();
+    for (key, entry) in array.keyed_entries() {
+        let entry_path = match key {
+            // unpacked arrays belong to the array they're unpacked into
+            None => path.to_string(),
+            Some(key) if path.is_empty() => key.to_string(),
+            Some(key) => format!("{}.{}", path, key),
+        };
+        let (same_line, own) = split_line(&entry.first_token().leading);
+        add(result, &previous, same_line);
+        add(result, &entry_path, own);
+        if let Some(key) = &entry.key {
+            add(result, &entry_path, &key.arrow.leading);
+        }
+        collect_node(result, &entry_path, &entry.value, entry.key.is_some());
+        if let Some(comma) = &entry.comma {
+            add(result, &entry_path, &comma.leading);
+        }
+        previous = entry_path;
+    }
+    let (same_line, own) = split_line(&array.close.leading);
+    add(result, &previous, same_line);
+    add(result, path, own);
+}
+
+/// Iterate over the comments in a piece of trivia, which only contains whitespace and complete comments
+fn comments(trivia: &str) -> impl Iterator<Item = &str> {
+    let mut rest = trivia;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        let len = if rest.starts_with("/*") {
+            rest.find("*/").map(|end| end + 2).unwrap_or(rest.len())
+        } else if rest.starts_with('#') || rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else {
+            return None;
+        };
+        let (comment, remaining) = rest.split_at(len);
+        rest = remaining;
+        Some(comment.trim_end())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SyntaxTree;
+
+    #[test]
+    fn test_comments() {
+        let tree = SyntaxTree::parse(
+            r#"// header
+array (
+  'a' => 1, // after a
+  /* before b */ 'b' /* key */ => /* value */ 2 /* comma */,
+  # before c
+  // still before c
+  'c' => array ( /* open c */
+    'x', // after x
+    // 'commented' => 'out',
+  ), // after c
+  'd' => fn() => /* kept in the closure */ 1,
+); // trailing"#,
+        )
+        .unwrap();
+        let comments = tree.comments();
+
+        assert_eq!(comments[""], ["// header", "// trailing"]);
+        assert_eq!(comments["a"], ["// after a"]);
+        assert_eq!(
+            comments["b"],
+            ["/* before b */", "/* key */", "/* value */", "/* comma */"]
+        );
+        assert_eq!(
+            comments["c"],
+            [
+                "# before c",
+                "// still before c",
+                "/* open c */",
+                "// 'commented' => 'out',",
+                "// after c"
+            ]
+        );
+        assert_eq!(comments["c.0"], ["// after x"]);
+        assert!(!comments.contains_key("d"));
+        assert_eq!(comments.len(), 5);
+    }
+}
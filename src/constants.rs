@@ -0,0 +1,139 @@
+use crate::Value;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// Resolves the value of constants used in the parsed source
+///
+/// Implemented for closures taking the constant name and returning its value, or `None` if the constant isn't defined.
+pub trait ConstantResolver: Send + Sync {
+    /// Get the value of the constant with the given name, without leading `\`
+    fn resolve(&self, name: &str) -> Option<Value>;
+}
+
+impl<F> ConstantResolver for F
+where
+    F: Fn(&str) -> Option<Value> + Send + Sync,
+{
+    fn resolve(&self, name: &str) -> Option<Value> {
+        self(name)
+    }
+}
+
+/// The constants available while parsing
+#[derive(Clone, Default)]
+pub(crate) struct Constants {
+    defined: HashMap<String, Value>,
+    resolver: Option<Arc<dyn ConstantResolver>>,
+    pub builtin: bool,
+    pub unresolved_as_string: bool,
+    /// Values for `__FILE__` and `__DIR__`
+    pub file: Option<(String, String)>,
+}
+
+impl Constants {
+    pub fn insert(&mut self, name: &str, value: Value) {
+        self.defined.insert(normalize_name(name).into(), value);
+    }
+
+    pub fn set_resolver(&mut self, resolver: Arc<dyn ConstantResolver>) {
+        self.resolver = Some(resolver);
+    }
+
+    /// Find the value of a constant
+    ///
+    /// Defined constants take precedence over the resolver, which takes precedence over builtin constants.
+    /// The magic `__FILE__` and `__DIR__` constants are matched case-insensitively, like php does.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let name = normalize_name(name);
+        if let Some((file, dir)) = &self.file {
+            if name.eq_ignore_ascii_case("__FILE__") {
+                return Some(Value::String(file.clone()));
+            }
+            if name.eq_ignore_ascii_case("__DIR__") {
+                return Some(Value::String(dir.clone()));
+            }
+        }
+        if let Some(value) = self.defined.get(name) {
+            return Some(value.clone());
+        }
+        self.resolver
+            .as_ref()
+            .and_then(|resolver| resolver.resolve(name))
+            .or_else(|| self.builtin.then(|| builtin_constant(name)).flatten())
+            .or_else(|| {
+                // php 7 only falls back to strings for global constants, not class constants
+                (self.unresolved_as_string && !is_class_constant(name))
+                    .then(|| Value::String(name.into()))
+            })
+    }
+}
+
+/// Check if the name refers to a constant of a class, like `Foo::BAR`
+pub(crate) fn is_class_constant(name: &str) -> bool {
+    name.contains("::")
+}
+
+fn normalize_name(name: &str) -> &str {
+    name.trim_start_matches('\\')
+}
+
+impl Debug for Constants {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Constants")
+            .field("defined", &self.defined)
+            .field("resolver", &self.resolver.is_some())
+            .field("builtin", &self.builtin)
+            .field("unresolved_as_string", &self.unresolved_as_string)
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+/// Get the value of a builtin php constant
+///
+/// Platform dependent constants use the values php has on 64-bit unix systems.
+fn builtin_constant(name: &str) -> Option<Value> {
+    Some(match name {
+        "PHP_INT_MAX" => Value::Int(i64::MAX),
+        "PHP_INT_MIN" => Value::Int(i64::MIN),
+        "PHP_INT_SIZE" => Value::Int(8),
+        "PHP_FLOAT_EPSILON" => Value::Float(f64::EPSILON),
+        "PHP_FLOAT_MAX" => Value::Float(f64::MAX),
+        "PHP_FLOAT_MIN" => Value::Float(f64::MIN_POSITIVE),
+        "PHP_FLOAT_DIG" => Value::Int(15),
+        "PHP_EOL" => Value::String("\n".into()),
+        "DIRECTORY_SEPARATOR" => Value::String("/".into()),
+        "PATH_SEPARATOR" => Value::String(":".into()),
+        "M_PI" => Value::Float(std::f64::consts::PI),
+        "M_E" => Value::Float(std::f64::consts::E),
+        "M_LOG2E" => Value::Float(std::f64::consts::LOG2_E),
+        "M_LOG10E" => Value::Float(std::f64::consts::LOG10_E),
+        "M_LN2" => Value::Float(std::f64::consts::LN_2),
+        "M_LN10" => Value::Float(std::f64::consts::LN_10),
+        "M_PI_2" => Value::Float(std::f64::consts::FRAC_PI_2),
+        "M_PI_4" => Value::Float(std::f64::consts::FRAC_PI_4),
+        "M_1_PI" => Value::Float(std::f64::consts::FRAC_1_PI),
+        "M_2_PI" => Value::Float(std::f64::consts::FRAC_2_PI),
+        "M_2_SQRTPI" => Value::Float(std::f64::consts::FRAC_2_SQRT_PI),
+        "M_SQRT2" => Value::Float(std::f64::consts::SQRT_2),
+        "M_SQRT1_2" => Value::Float(std::f64::consts::FRAC_1_SQRT_2),
+        "E_ERROR" => Value::Int(1),
+        "E_WARNING" => Value::Int(2),
+        "E_PARSE" => Value::Int(4),
+        "E_NOTICE" => Value::Int(8),
+        "E_CORE_ERROR" => Value::Int(16),
+        "E_CORE_WARNING" => Value::Int(32),
+        "E_COMPILE_ERROR" => Value::Int(64),
+        "E_COMPILE_WARNING" => Value::Int(128),
+        "E_USER_ERROR" => Value::Int(256),
+        "E_USER_WARNING" => Value::Int(512),
+        "E_USER_NOTICE" => Value::Int(1024),
+        "E_STRICT" => Value::Int(2048),
+        "E_RECOVERABLE_ERROR" => Value::Int(4096),
+        "E_DEPRECATED" => Value::Int(8192),
+        "E_USER_DEPRECATED" => Value::Int(16384),
+        "E_ALL" => Value::Int(32767),
+        _ => return None,
+    })
+}
@@ -0,0 +1,867 @@
+use crate::array::{ArrayBuilder, NextIndex};
+use crate::error::{
+    ArrayKeyError, ArrayKeyErrorKind, ExpectToken, NestingLimitError, ParseError, TrailingError,
+};
+use crate::eval::{evaluate, is_expression, TokenSource};
+use crate::lexer::{closure_length, SpannedToken, Token};
+use crate::parser::{ArraySyntax, Parser};
+use crate::{Array, Instance, Key, ParserOptions, Span, Value};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// Lossless syntax tree of a php literal
+///
+/// Unlike [`from_str`](crate::from_str), parsing into a syntax tree keeps all comments, whitespace and the
+/// original source of every token. Writing the tree with [`Display`] reproduces the parsed source byte for byte.
+///
+/// Closures, object instantiations and other expressions are kept as [`SyntaxNode::Expr`] nodes.
+/// With [`capture_objects`](ParserOptions::capture_objects) object instantiations are literal nodes
+/// with a [`Value::Instance`] value instead.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{SyntaxTree, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let source = r#"array (
+///   // the name of the instance
+///   'name' => "foo",
+/// );
+/// "#;
+/// let tree = SyntaxTree::parse(source)?;
+///
+/// assert_eq!(tree.to_string(), source);
+/// assert_eq!(tree.to_value()["name"], "foo");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxTree {
+    /// The `<?php` tag, `declare(...);` statements and `return` keyword before the literal in a php file
+    pub(crate) prelude: Vec<SyntaxToken>,
+    pub(crate) root: SyntaxNode,
+    pub(crate) semicolon: Option<SyntaxToken>,
+    /// The closing `?>` tag of a php file
+    pub(crate) close_tag: Option<SyntaxToken>,
+    /// Whitespace and comments after the last token
+    pub(crate) trailing: String,
+}
+
+/// A token with the whitespace and comments preceding it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub(crate) leading: String,
+    pub(crate) text: String,
+}
+
+/// A value in the syntax tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxNode {
+    /// A `null`, boolean, number or string literal, or a constant expression of literals
+    Literal(LiteralNode),
+    /// An array literal
+    Array(ArrayNode),
+    /// Any other expression, kept as tokens
+    Expr(Vec<SyntaxToken>),
+}
+
+/// A `null`, boolean, number or string literal
+///
+/// String literals concatenated with `.` and constant expressions like `1 << 3` form a single node,
+/// holding all tokens of the expression and the value it evaluates to.
+/// The same goes for object instantiations captured with [`capture_objects`](ParserOptions::capture_objects).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralNode {
+    pub(crate) tokens: Vec<SyntaxToken>,
+    pub(crate) value: Value,
+}
+
+/// An array literal with its entries
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayNode {
+    pub(crate) syntax: ArraySyntax,
+    /// `[` or `array` and `(`
+    pub(crate) open: Vec<SyntaxToken>,
+    pub(crate) entries: Vec<EntryNode>,
+    pub(crate) close: SyntaxToken,
+}
+
+/// An entry of an array literal, with optional key or `...` and trailing comma
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryNode {
+    pub(crate) key: Option<KeyNode>,
+    /// The `...` of an array unpacked into the containing array
+    pub(crate) spread: Option<SyntaxToken>,
+    pub(crate) value: SyntaxNode,
+    pub(crate) comma: Option<SyntaxToken>,
+}
+
+/// An explicit array key with the arrow following it
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyNode {
+    pub(crate) token: SyntaxToken,
+    pub(crate) key: Key,
+    pub(crate) arrow: SyntaxToken,
+}
+
+impl SyntaxTree {
+    /// Parse a php literal into a lossless syntax tree
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Self::parse_with_options(source, &ParserOptions::default())
+    }
+
+    /// Parse a php literal into a lossless syntax tree using the provided options
+    ///
+    /// The options are used to evaluate expressions, the way values are written is always kept as is.
+    /// With [`php_file`](ParserOptions::php_file) the tags and statements around the literal are kept in the tree.
+    pub fn parse_with_options(source: &str, options: &ParserOptions) -> Result<Self, ParseError> {
+        let mut parser = TreeParser::new(source, options.clone());
+        let prelude = if options.php_file {
+            parser.file_prelude()
+        } else {
+            Vec::new()
+        };
+        let root = parser.node()?;
+        let semicolon = parser.eat(Token::SemiColon);
+        let close_tag = if options.php_file {
+            parser.eat(Token::CloseTag)
+        } else {
+            None
+        };
+        if let Some(token) = parser.peek() {
+            return Err(TrailingError::new(source, token).into());
+        }
+        let end = parser.tokens.last().map(|token| token.span.end());
+        Ok(SyntaxTree {
+            prelude,
+            root,
+            semicolon,
+            close_tag,
+            trailing: source[end.unwrap_or_default()..].into(),
+        })
+    }
+
+    /// The top level value
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// Get the value represented by the tree
+    pub fn to_value(&self) -> Value {
+        self.root.to_value()
+    }
+
+    /// Get the syntax used by every array in the source
+    ///
+    /// Arrays are keyed by the path of array keys leading to them, joined with `.` the same way
+    /// as [`Value::flatten`](crate::Value::flatten) does, the top level array uses an empty key.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{ArraySyntax, SyntaxTree};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let tree = SyntaxTree::parse("array('list' => [1, 2], 'nested' => ['legacy' => array()])")?;
+    /// let syntax = tree.array_syntax();
+    ///
+    /// assert_eq!(syntax[""], ArraySyntax::Long);
+    /// assert_eq!(syntax["list"], ArraySyntax::Short);
+    /// assert_eq!(syntax["nested.legacy"], ArraySyntax::Long);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn array_syntax(&self) -> BTreeMap<String, ArraySyntax> {
+        let mut result = BTreeMap::new();
+        let mut pending = vec![(String::new(), &self.root)];
+        while let Some((path, node)) = pending.pop() {
+            if let SyntaxNode::Array(array) = node {
+                for (key, entry) in array.keyed_entries() {
+                    let key = match key {
+                        Some(key) => key,
+                        None => continue,
+                    };
+                    let entry_path = if path.is_empty() {
+                        key.to_string()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    pending.push((entry_path, &entry.value));
+                }
+                result.insert(path, array.syntax);
+            }
+        }
+        result
+    }
+}
+
+impl SyntaxToken {
+    /// The whitespace and comments before the token
+    pub fn leading_trivia(&self) -> &str {
+        &self.leading
+    }
+
+    /// The source of the token
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl SyntaxNode {
+    /// Get the value represented by the node
+    ///
+    /// Expressions are returned as [`Value::Expr`] containing their source.
+    pub fn to_value(&self) -> Value {
+        match self {
+            SyntaxNode::Literal(literal) => literal.value.clone(),
+            SyntaxNode::Array(array) => Value::Array(array.to_array()),
+            SyntaxNode::Expr(tokens) => {
+                let mut expr = String::new();
+                for (index, token) in tokens.iter().enumerate() {
+                    if index > 0 {
+                        expr.push_str(&token.leading);
+                    }
+                    expr.push_str(&token.text);
+                }
+                Value::Expr(expr)
+            }
+        }
+    }
+
+    /// Get the node as array, if it is one
+    pub fn as_array(&self) -> Option<&ArrayNode> {
+        match self {
+            SyntaxNode::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn first_token(&self) -> &SyntaxToken {
+        match self {
+            SyntaxNode::Literal(literal) => &literal.tokens[0],
+            SyntaxNode::Array(array) => &array.open[0],
+            SyntaxNode::Expr(tokens) => &tokens[0],
+        }
+    }
+
+    pub(crate) fn first_token_mut(&mut self) -> &mut SyntaxToken {
+        match self {
+            SyntaxNode::Literal(literal) => &mut literal.tokens[0],
+            SyntaxNode::Array(array) => &mut array.open[0],
+            SyntaxNode::Expr(tokens) => &mut tokens[0],
+        }
+    }
+}
+
+impl LiteralNode {
+    /// The parsed value of the literal
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// The tokens of the literal, a single token unless it's a concatenation or other expression
+    pub fn tokens(&self) -> &[SyntaxToken] {
+        &self.tokens
+    }
+}
+
+impl ArrayNode {
+    /// Whether the array uses `[...]` or `array(...)`
+    pub fn syntax(&self) -> ArraySyntax {
+        self.syntax
+    }
+
+    /// The entries of the array in source order
+    pub fn entries(&self) -> &[EntryNode] {
+        &self.entries
+    }
+
+    /// The closing bracket, including any whitespace and comments after the last entry
+    pub fn close(&self) -> &SyntaxToken {
+        &self.close
+    }
+
+    /// Get the entries keyed the way php would assign keys, later duplicate keys overwrite earlier ones
+    ///
+    /// Unpacked arrays don't have a key of their own.
+    pub(crate) fn keyed_entries(&self) -> impl Iterator<Item = (Option<Key>, &EntryNode)> {
+        let mut next_index = NextIndex::default();
+        self.entries.iter().map(move |entry| {
+            let key = entry
+                .assign_key(&mut next_index)
+                .expect("implicit keys are checked while parsing");
+            (key, entry)
+        })
+    }
+
+    fn to_array(&self) -> Array {
+        let mut array = ArrayBuilder::with_capacity(self.entries.len());
+        for (key, entry) in self.keyed_entries() {
+            match (key, entry.value.to_value()) {
+                (Some(key), value) => array.insert(key, value),
+                (None, Value::Array(unpacked)) => array
+                    .unpack(unpacked)
+                    .expect("implicit keys are checked while parsing"),
+                (None, _) => {}
+            }
+        }
+        array.build()
+    }
+}
+
+impl EntryNode {
+    /// The key php assigns to the entry, unpacked entries don't have a key of their own
+    ///
+    /// Fails if the entry needs an implicit key after an entry with key `i64::MAX`.
+    fn assign_key(&self, next_index: &mut NextIndex) -> Result<Option<Key>, ArrayKeyErrorKind> {
+        if self.spread.is_some() {
+            // unpacked entries with integer keys are appended
+            if let Value::Array(array) = self.value.to_value() {
+                for _ in array.keys().filter(|key| key.is_int()) {
+                    next_index
+                        .next()
+                        .ok_or(ArrayKeyErrorKind::NextElementOccupied)?;
+                }
+            }
+            return Ok(None);
+        }
+        let key = match &self.key {
+            Some(key) => key.key.clone(),
+            None => Key::Int(
+                next_index
+                    .next()
+                    .ok_or(ArrayKeyErrorKind::NextElementOccupied)?,
+            ),
+        };
+        next_index.insert(&key);
+        Ok(Some(key))
+    }
+
+    /// The explicit key of the entry, if it has one
+    pub fn key(&self) -> Option<&KeyNode> {
+        self.key.as_ref()
+    }
+
+    /// The value of the entry
+    pub fn value(&self) -> &SyntaxNode {
+        &self.value
+    }
+
+    /// The `...` before an array unpacked into the containing array, if any
+    pub fn spread(&self) -> Option<&SyntaxToken> {
+        self.spread.as_ref()
+    }
+
+    /// The comma following the entry, if any
+    pub fn comma(&self) -> Option<&SyntaxToken> {
+        self.comma.as_ref()
+    }
+
+    pub(crate) fn first_token(&self) -> &SyntaxToken {
+        match (&self.key, &self.spread) {
+            (Some(key), _) => &key.token,
+            (None, Some(spread)) => spread,
+            (None, None) => self.value.first_token(),
+        }
+    }
+
+    pub(crate) fn first_token_mut(&mut self) -> &mut SyntaxToken {
+        match (&mut self.key, &mut self.spread) {
+            (Some(key), _) => &mut key.token,
+            (None, Some(spread)) => spread,
+            (None, None) => self.value.first_token_mut(),
+        }
+    }
+}
+
+impl KeyNode {
+    /// The parsed key
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The key token
+    pub fn token(&self) -> &SyntaxToken {
+        &self.token
+    }
+}
+
+impl Display for SyntaxTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for token in &self.prelude {
+            write!(f, "{}", token)?;
+        }
+        write!(f, "{}", self.root)?;
+        if let Some(semicolon) = &self.semicolon {
+            write!(f, "{}", semicolon)?;
+        }
+        if let Some(close_tag) = &self.close_tag {
+            write!(f, "{}", close_tag)?;
+        }
+        write!(f, "{}", self.trailing)
+    }
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.leading, self.text)
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxNode::Literal(literal) => literal
+                .tokens
+                .iter()
+                .try_for_each(|token| write!(f, "{}", token)),
+            SyntaxNode::Array(array) => write!(f, "{}", array),
+            SyntaxNode::Expr(tokens) => tokens.iter().try_for_each(|token| write!(f, "{}", token)),
+        }
+    }
+}
+
+impl Display for ArrayNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for token in &self.open {
+            write!(f, "{}", token)?;
+        }
+        for entry in &self.entries {
+            write!(f, "{}", entry)?;
+        }
+        write!(f, "{}", self.close)
+    }
+}
+
+impl Display for EntryNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(key) = &self.key {
+            write!(f, "{}{}", key.token, key.arrow)?;
+        }
+        if let Some(spread) = &self.spread {
+            write!(f, "{}", spread)?;
+        }
+        write!(f, "{}", self.value)?;
+        if let Some(comma) = &self.comma {
+            write!(f, "{}", comma)?;
+        }
+        Ok(())
+    }
+}
+
+const VALUE_TOKENS: &[Token] = &[
+    Token::Null,
+    Token::Bool,
+    Token::LiteralString,
+    Token::Integer,
+    Token::Float,
+    Token::Array,
+    Token::SquareOpen,
+];
+
+struct TreeParser<'source> {
+    parser: Parser<'source>,
+    tokens: Vec<SpannedToken<'source>>,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'source> TreeParser<'source> {
+    fn new(source: &'source str, options: ParserOptions) -> Self {
+        let mut parser = Parser::new(source, options);
+        let tokens = std::iter::from_fn(|| parser.next_token()).collect();
+        TreeParser {
+            parser,
+            tokens,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn source(&self) -> &'source str {
+        self.parser.source()
+    }
+
+    fn peek(&self) -> Option<&SpannedToken<'source>> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Consume the next token, the caller has to make sure there is one
+    fn bump(&mut self) -> (SpannedToken<'source>, SyntaxToken) {
+        let token = self.tokens[self.pos].clone();
+        let syntax = self.syntax_token(self.pos);
+        self.pos += 1;
+        (token, syntax)
+    }
+
+    /// The token at `index` with the trivia between it and the previous token
+    fn syntax_token(&self, index: usize) -> SyntaxToken {
+        let token = &self.tokens[index];
+        let start = match index {
+            0 => 0,
+            index => self.tokens[index - 1].span.end(),
+        };
+        SyntaxToken {
+            leading: self.source()[start..token.span.start()].into(),
+            text: token.slice().into(),
+        }
+    }
+
+    /// Consume the next token if it is `token`
+    fn eat(&mut self, token: Token) -> Option<SyntaxToken> {
+        self.peek()
+            .is_some_and(|next| next.token == token)
+            .then(|| self.bump().1)
+    }
+
+    /// Consume the `<?php` tag, `declare(...);` statements and `return` keyword in front of the literal in a php file
+    fn file_prelude(&mut self) -> Vec<SyntaxToken> {
+        let is_identifier = |token: Option<&SpannedToken>, name: &str| {
+            token.is_some_and(|token| {
+                token.token == Token::Identifier && token.slice().eq_ignore_ascii_case(name)
+            })
+        };
+        let mut prelude = Vec::new();
+        prelude.extend(self.eat(Token::OpenTag));
+        while is_identifier(self.peek(), "declare")
+            && self
+                .peek_nth(1)
+                .is_some_and(|token| token.token == Token::BracketOpen)
+        {
+            while self.peek().is_some() {
+                let (token, syntax) = self.bump();
+                prelude.push(syntax);
+                if token.token == Token::SemiColon {
+                    break;
+                }
+            }
+        }
+        if is_identifier(self.peek(), "return") {
+            prelude.push(self.bump().1);
+        }
+        prelude
+    }
+
+    fn expect(&mut self, expected: &[Token]) -> Result<SyntaxToken, ParseError> {
+        self.peek().expect_token(expected, self.source())?;
+        Ok(self.bump().1)
+    }
+
+    fn node(&mut self) -> Result<SyntaxNode, ParseError> {
+        if is_expression(self) {
+            let start = self.pos;
+            let value = evaluate(self)?;
+            return Ok(SyntaxNode::Literal(LiteralNode {
+                tokens: (start..self.pos)
+                    .map(|index| self.syntax_token(index))
+                    .collect(),
+                value,
+            }));
+        }
+        let token = self.peek().cloned();
+        let token = match token {
+            Some(token)
+                if matches!(token.token, Token::Closure | Token::New | Token::Identifier) =>
+            {
+                token
+            }
+            token => token.expect_token(VALUE_TOKENS, self.source())?,
+        };
+        Ok(match token.token {
+            Token::Null | Token::Bool | Token::LiteralString | Token::Integer | Token::Float => {
+                let (token, syntax) = self.bump();
+                SyntaxNode::Literal(LiteralNode {
+                    value: self.parser.parse_literal(token)?,
+                    tokens: vec![syntax],
+                })
+            }
+            Token::Array | Token::SquareOpen => {
+                SyntaxNode::Array(self.nested(|parser| parser.array_node())?)
+            }
+            Token::New if self.parser.options().capture_objects => {
+                let start = self.pos;
+                let value = self.nested(|parser| parser.instance())?;
+                SyntaxNode::Literal(LiteralNode {
+                    tokens: (start..self.pos)
+                        .map(|index| self.syntax_token(index))
+                        .collect(),
+                    value,
+                })
+            }
+            _ => {
+                let start = token.span.start();
+                let end = start + closure_length(&self.source()[start..]).max(1);
+                let mut tokens = Vec::new();
+                while self.peek().is_some_and(|token| token.span.start() < end) {
+                    tokens.push(self.bump().1);
+                }
+                SyntaxNode::Expr(tokens)
+            }
+        })
+    }
+
+    /// Parse `new ClassName(args)` into a [`Value::Instance`], starting at the `new` token
+    fn instance(&mut self) -> Result<Value, ParseError> {
+        self.bump();
+        let class = self.expect(&[Token::Identifier])?.text;
+        let mut args = Vec::new();
+        if self.eat(Token::BracketOpen).is_some() {
+            while self.eat(Token::BracketClose).is_none() {
+                args.push(self.node()?.to_value());
+                if self.eat(Token::Comma).is_none() {
+                    self.expect(&[Token::BracketClose])?;
+                    break;
+                }
+            }
+        }
+        Ok(Value::Instance(Instance { class, args }))
+    }
+
+    /// Parse an array in either syntax, starting at the `array` or `[` token
+    fn array_node(&mut self) -> Result<ArrayNode, ParseError> {
+        if self
+            .peek()
+            .is_some_and(|token| token.token == Token::SquareOpen)
+        {
+            let open = vec![self.bump().1];
+            self.array(ArraySyntax::Short, open)
+        } else {
+            let open = vec![self.bump().1, self.expect(&[Token::BracketOpen])?];
+            self.array(ArraySyntax::Long, open)
+        }
+    }
+
+    fn array(
+        &mut self,
+        syntax: ArraySyntax,
+        open: Vec<SyntaxToken>,
+    ) -> Result<ArrayNode, ParseError> {
+        let close_token = syntax.close_bracket();
+        let mut entries = Vec::new();
+        let mut next_index = NextIndex::default();
+        loop {
+            if self.peek().is_some_and(|token| token.token == close_token) {
+                let close = self.bump().1;
+                return Ok(ArrayNode {
+                    syntax,
+                    open,
+                    entries,
+                    close,
+                });
+            }
+
+            self.parser.count_entry(entries.len() + 1)?;
+            let start = self.pos;
+            let spread = match self.peek() {
+                Some(token) if token.token == Token::Ellipsis => Some(self.bump().1),
+                _ => None,
+            };
+            let key = if spread.is_none()
+                && self
+                    .peek_nth(1)
+                    .is_some_and(|token| token.token == Token::Arrow)
+            {
+                let (token, syntax) = self.bump();
+                let key = self.parser.parse_array_key(token)?;
+                let arrow = self.bump().1;
+                Some(KeyNode {
+                    token: syntax,
+                    key,
+                    arrow,
+                })
+            } else {
+                None
+            };
+            let value = self.node()?;
+            let end = self.pos;
+            let comma = match self.peek() {
+                Some(token) if token.token == Token::Comma => Some(self.bump().1),
+                token => {
+                    token.expect_token(&[Token::Comma, close_token], self.source())?;
+                    None
+                }
+            };
+            let entry = EntryNode {
+                key,
+                spread,
+                value,
+                comma,
+            };
+            // checked here so keys can be assigned without errors once the tree is built
+            entry.assign_key(&mut next_index).map_err(|kind| {
+                let span = Span::new(
+                    self.tokens[start].span.start(),
+                    self.tokens[end - 1].span.end(),
+                );
+                ParseError::UnexpectedArrayKey(ArrayKeyError::new(kind, self.source(), span))
+            })?;
+            entries.push(entry);
+        }
+    }
+}
+
+impl<'source> TokenSource<'source> for TreeParser<'source> {
+    fn parser(&self) -> &Parser<'source> {
+        &self.parser
+    }
+
+    fn peek_nth(&mut self, n: usize) -> Option<&SpannedToken<'source>> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn next_token(&mut self) -> Option<SpannedToken<'source>> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += token.is_some() as usize;
+        token
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        let array = self.nested(|parser| parser.array_node())?;
+        Ok(Value::Array(array.to_array()))
+    }
+
+    fn nested<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, ParseError>,
+    ) -> Result<R, ParseError> {
+        let limit = self.parser.options().depth_limit();
+        if self.depth >= limit {
+            let source = self.source();
+            let span = self.peek().map_or_else(
+                || Span::new(source.len(), source.len()),
+                |token| token.span.clone(),
+            );
+            return Err(NestingLimitError::new(limit, source, span).into());
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SyntaxNode, SyntaxTree};
+    use crate::{from_str, from_str_with_options, ArraySyntax, ParserOptions, Value};
+
+    #[test]
+    fn test_lossless_roundtrip() {
+        let source = r#"
+/**
+ * header comment
+ */
+array (
+  'instanceid' => 'oc1234', # hash comment
+  'trusted_domains' =>
+  [
+    0 => 'localhost' , // line comment
+    'example.com',
+  ],
+  'debug'=>/* inline */true,
+  1.5 => null,
+  'handler' => fn ($x) => [$x, ')'],
+  'empty' => array(   ),
+  'float' => 1_000.5e-3,
+  'path' => '/var' . /* dir */ "/www"
+)   ;
+// trailing
+"#;
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), source);
+
+        let value = tree.to_value();
+        assert_eq!(value["instanceid"], "oc1234");
+        assert_eq!(value["trusted_domains"][1], "example.com");
+        assert_eq!(value["debug"], true);
+        assert!(value[1].is_null());
+        assert_eq!(value["handler"], Value::Expr("fn ($x) => [$x, ')']".into()));
+        assert_eq!(value["float"], 1.0005);
+        assert_eq!(value["path"], "/var/www");
+
+        let root = tree.root().as_array().unwrap();
+        assert_eq!(root.syntax(), ArraySyntax::Long);
+        assert_eq!(root.entries().len(), 8);
+        let domains = root.entries()[1].value().as_array().unwrap();
+        assert_eq!(domains.syntax(), ArraySyntax::Short);
+        assert_eq!(domains.entries()[0].comma().unwrap().leading_trivia(), " ");
+        assert_eq!(root.entries()[2].value().to_string(), "/* inline */true");
+        assert!(matches!(
+            root.entries()[4].value(),
+            SyntaxNode::Expr(tokens) if tokens.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_matches_parser() {
+        let source = r#"[1, 5 => 'a', 'b', "3" => [true, -1], 'x' => 0x1F]"#;
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.to_value(), from_str::<Value>(source).unwrap());
+    }
+
+    #[test]
+    fn test_spread() {
+        let source = r#"[...[1, 2], 3, ... /* more */ ['a' => 1, 5 => 4], 'b' => 2, 6]"#;
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.to_value(), from_str::<Value>(source).unwrap());
+
+        let root = tree.root().as_array().unwrap();
+        assert!(root.entries()[0].spread().is_some());
+        assert!(root.entries()[1].spread().is_none());
+        assert_eq!(root.entries()[2].spread().unwrap().leading_trivia(), " ");
+    }
+
+    #[test]
+    fn test_php_file() {
+        let source =
+            "<?php\n// config\ndeclare(strict_types=1);\n\nreturn ['debug' => true];\n?>\n";
+        let options = ParserOptions::new().php_file(true);
+        let tree = SyntaxTree::parse_with_options(source, &options).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(
+            tree.to_value(),
+            from_str_with_options::<Value>(source, &options).unwrap()
+        );
+        assert_eq!(tree.comments()[""], ["// config"]);
+
+        let source = "<?php return [1, 2] ?>";
+        let tree = SyntaxTree::parse_with_options(source, &options).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.to_value(), from_str::<Value>("[1, 2]").unwrap());
+
+        assert!(SyntaxTree::parse("<?php return [1];").is_err());
+    }
+
+    #[test]
+    fn test_capture_objects() {
+        let source =
+            "['db' => new \\Db\\Connection('localhost', ['port' => 3306]), 'cache' => new Cache]";
+        let options = ParserOptions::new().capture_objects(true);
+        let tree = SyntaxTree::parse_with_options(source, &options).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(
+            tree.to_value(),
+            from_str_with_options::<Value>(source, &options).unwrap()
+        );
+        assert!(matches!(tree.to_value()["db"], Value::Instance(_)));
+
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert!(matches!(tree.to_value()["db"], Value::Expr(_)));
+        assert!(SyntaxTree::parse_with_options("new Foo(1", &options).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(SyntaxTree::parse("").is_err());
+        assert!(SyntaxTree::parse("[1, 2").is_err());
+        assert!(SyntaxTree::parse("[1 2]").is_err());
+        assert!(SyntaxTree::parse("[1]; 2").is_err());
+        assert!(SyntaxTree::parse("['a' => ]").is_err());
+        assert!(SyntaxTree::parse("['a' => ...[1]]").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::serde_impl::Deserializer;
+use crate::{ParseError, ParserOptions, Value};
+use std::collections::HashMap;
+
+/// Parse a sequence of `define('NAME', <value>);` statements into a map of the defined constants
+///
+/// This handles config files in the style of wordpress' `wp-config.php`. Constants defined earlier
+/// in the file can be used in the values of later definitions. Like php, redefining a constant
+/// keeps the first value.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{defines_from_str, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let constants = defines_from_str(r#"
+///     define('DB_NAME', 'wordpress');
+///     define('WP_DEBUG', false);
+///     define('WP_CONTENT_DIR', '/var/www/' . DB_NAME);
+/// "#)?;
+///
+/// assert_eq!(constants["DB_NAME"], "wordpress");
+/// assert_eq!(constants["WP_DEBUG"], false);
+/// assert_eq!(constants["WP_CONTENT_DIR"], "/var/www/wordpress");
+/// # Ok(())
+/// # }
+/// ```
+pub fn defines_from_str(s: &str) -> Result<HashMap<String, Value>, ParseError> {
+    defines_from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a sequence of `define('NAME', <value>);` statements using the provided options
+pub fn defines_from_str_with_options(
+    s: &str,
+    options: &ParserOptions,
+) -> Result<HashMap<String, Value>, ParseError> {
+    let mut de = Deserializer::with_options(s, options.clone());
+    let mut constants = HashMap::new();
+    while let Some((name, value)) = de.next_define()? {
+        constants.entry(name).or_insert(value);
+    }
+    Ok(constants)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{defines_from_str, ParserOptions, Value};
+
+    #[test]
+    fn test_defines() {
+        let constants = defines_from_str(
+            r#"
+            define('A', 1);
+            DEFINE("B", [A, 'b' => true]);
+            define('C', 'c', true);
+            define('A', 2);
+            "#,
+        )
+        .unwrap();
+        assert_eq!(constants.len(), 3);
+        assert_eq!(constants["A"], Value::Int(1));
+        assert_eq!(constants["B"][0], Value::Int(1));
+        assert_eq!(constants["B"]["b"], Value::Bool(true));
+        assert_eq!(constants["C"], Value::from("c"));
+
+        assert!(defines_from_str("").unwrap().is_empty());
+        let options = ParserOptions::new().php_file(true);
+        let constants = super::defines_from_str_with_options(
+            "<?php\ndefine('A', 1);\ndefine('B', 2) ?>\n",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(constants["B"], Value::Int(2));
+        assert!(defines_from_str("define('A', 1); ?>").is_err());
+        assert!(defines_from_str("define('A', 1)").is_err());
+        assert!(defines_from_str("define('A', B);").is_err());
+        assert!(defines_from_str("echo('A', 1);").is_err());
+        assert!(defines_from_str("define(A, 1);").is_err());
+
+        let options = ParserOptions::new().constant("B", 2);
+        let constants = super::defines_from_str_with_options("define('A', B);", &options).unwrap();
+        assert_eq!(constants["A"], Value::Int(2));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::cast::float_to_shortest_string;
+use crate::{Array, Key, Value};
+use std::fmt::{Display, Formatter, Result};
+
+impl Value {
+    /// Render the value the way php's `var_dump` does, annotated with types and lengths
+    ///
+    /// Array entries are written in sorted key order and captured expressions are written as their source.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = from_str::<Value>(r#"["hello", "debug" => true]"#)?;
+    ///
+    /// assert_eq!(value.var_dump().to_string(), r#"array(2) {
+    ///   [0]=>
+    ///   string(5) "hello"
+    ///   ["debug"]=>
+    ///   bool(true)
+    /// }
+    /// "#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn var_dump(&self) -> VarDump<'_> {
+        VarDump { value: self }
+    }
+}
+
+/// Helper for rendering a value like `var_dump`, created by [`Value::var_dump`]
+pub struct VarDump<'a> {
+    value: &'a Value,
+}
+
+impl Display for VarDump<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write_value(f, self.value, 0, &mut 0)
+    }
+}
+
+fn write_value(f: &mut Formatter<'_>, value: &Value, indent: usize, objects: &mut usize) -> Result {
+    match value {
+        Value::Bool(bool) => writeln!(f, "bool({})", bool),
+        Value::Int(int) => writeln!(f, "int({})", int),
+        Value::Float(float) => writeln!(f, "float({})", float_to_shortest_string(*float)),
+        Value::String(str) => writeln!(f, "string({}) \"{}\"", str.len(), str),
+        Value::Bytes(bytes) => writeln!(
+            f,
+            "string({}) \"{}\"",
+            bytes.len(),
+            String::from_utf8_lossy(bytes)
+        ),
+        Value::Null => writeln!(f, "NULL"),
+        Value::Array(map) => {
+            writeln!(f, "array({}) {{", map.len())?;
+            write_entries(f, map, indent, objects)?;
+            writeln!(f, "{:indent$}}}", "", indent = indent)
+        }
+        Value::Object(object) => {
+            *objects += 1;
+            writeln!(
+                f,
+                "object({})#{} ({}) {{",
+                object.class,
+                objects,
+                object.properties.len()
+            )?;
+            write_entries(f, &object.properties, indent, objects)?;
+            writeln!(f, "{:indent$}}}", "", indent = indent)
+        }
+        Value::Instance(instance) => {
+            *objects += 1;
+            writeln!(
+                f,
+                "object({})#{} (0) {{",
+                instance.class.trim_start_matches('\\'),
+                objects
+            )?;
+            writeln!(f, "{:indent$}}}", "", indent = indent)
+        }
+        Value::Expr(expr) => writeln!(f, "{}", expr),
+    }
+}
+
+/// Write the entries of an array or the properties of an object in sorted key order
+fn write_entries(f: &mut Formatter<'_>, map: &Array, indent: usize, objects: &mut usize) -> Result {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    for (key, value) in entries {
+        match key {
+            Key::Int(int) => writeln!(f, "{:indent$}  [{}]=>", "", int, indent = indent)?,
+            Key::String(str) => writeln!(f, "{:indent$}  [\"{}\"]=>", "", str, indent = indent)?,
+        }
+        write!(f, "{:indent$}  ", "", indent = indent)?;
+        write_value(f, value, indent + 2, objects)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, from_str_with_options, ParserOptions, Value};
+
+    #[test]
+    fn test_var_dump_nested() {
+        let options = ParserOptions::new().capture_objects(true);
+        let value = from_str_with_options::<Value>(
+            r#"["a" => [1.5, null, 'é'], "b" => [], "c" => new \Foo(), "d" => new Bar()]"#,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            value.var_dump().to_string(),
+            r#"array(4) {
+  ["a"]=>
+  array(3) {
+    [0]=>
+    float(1.5)
+    [1]=>
+    NULL
+    [2]=>
+    string(2) "é"
+  }
+  ["b"]=>
+  array(0) {
+  }
+  ["c"]=>
+  object(Foo)#1 (0) {
+  }
+  ["d"]=>
+  object(Bar)#2 (0) {
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_var_dump_object() {
+        let value = from_str::<Value>("(object) ['b' => (object) [], 'a' => 1]").unwrap();
+        assert_eq!(
+            value.var_dump().to_string(),
+            r#"object(stdClass)#1 (2) {
+  ["a"]=>
+  int(1)
+  ["b"]=>
+  object(stdClass)#2 (0) {
+  }
+}
+"#
+        );
+    }
+}
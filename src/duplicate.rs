@@ -0,0 +1,74 @@
+use crate::{Key, Span};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// A key that occurs more than once in the same array, see [`ParserOptions::on_duplicate_key`](crate::ParserOptions::on_duplicate_key)
+///
+/// Like php, the value of the last occurrence is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    pub key: Key,
+    /// The span of the first occurrence of the key
+    pub first: Span,
+    /// The span of the repeated key
+    ///
+    /// For entries without an explicit key this is the span of the start of the value,
+    /// for entries unpacked with `...` it's the span of the `...`.
+    pub duplicate: Span,
+}
+
+/// How struct fields that are set more than once in the same array are handled, see [`ParserOptions::duplicate_fields`](crate::ParserOptions::duplicate_fields)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateFields {
+    /// Fail with [`ParseError::DuplicateField`](crate::ParseError::DuplicateField), pointing to both occurrences
+    #[default]
+    Error,
+    /// Use the value of the last occurrence, like php
+    ///
+    /// The array is parsed into a [`Value`](crate::Value) before deserializing the struct,
+    /// errors in the fields of the struct don't point to the input.
+    LastWins,
+}
+
+/// Callback for duplicate keys registered with [`ParserOptions::on_duplicate_key`](crate::ParserOptions::on_duplicate_key)
+#[derive(Clone)]
+pub(crate) struct DuplicateKeyHandler(Arc<dyn Fn(&DuplicateKey) + Send + Sync>);
+
+impl DuplicateKeyHandler {
+    pub fn new(handler: impl Fn(&DuplicateKey) + Send + Sync + 'static) -> Self {
+        DuplicateKeyHandler(Arc::new(handler))
+    }
+
+    pub fn call(&self, duplicate: &DuplicateKey) {
+        (self.0)(duplicate)
+    }
+}
+
+impl Debug for DuplicateKeyHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("DuplicateKeyHandler")
+    }
+}
+
+/// The keys of a single array with the span of their first occurrence
+#[derive(Default)]
+pub(crate) struct SeenKeys(HashMap<Key, Span>);
+
+impl SeenKeys {
+    /// Record a key of the array, returning the duplicate if the key was seen before
+    pub fn insert(&mut self, key: &Key, span: Span) -> Option<DuplicateKey> {
+        match self.0.entry(key.clone()) {
+            Entry::Occupied(first) => Some(DuplicateKey {
+                key: key.clone(),
+                first: first.get().clone(),
+                duplicate: span,
+            }),
+            Entry::Vacant(entry) => {
+                entry.insert(span);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use crate::cst::{ArrayNode, EntryNode, KeyNode, SyntaxNode, SyntaxToken, SyntaxTree};
+use crate::error::SerializeError;
+use crate::flatten::segment_key;
+use crate::{to_string, to_string_with_options, Array, ArraySyntax, Key, SerializeOptions, Value};
+
+impl SyntaxTree {
+    /// Set the value at the path, keeping the formatting and comments of everything else
+    ///
+    /// Numeric path segments become integer keys, following the php rules for array keys.
+    /// Existing values are replaced in place, missing keys are appended to the end of their array
+    /// using the indentation of the existing entries. New arrays use the same syntax as the array containing them. When a path goes through a non-array value,
+    /// that value is replaced by an array. An empty path replaces the top level value.
+    ///
+    /// Fails if the value can't be written as php literal.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::SyntaxTree;
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let mut tree = SyntaxTree::parse(r#"array (
+    ///   // keep me
+    ///   'debug' => false,
+    /// )"#)?;
+    /// tree.set_path(["debug"], &true.into())?;
+    /// tree.set_path(["redis", "host"], &"localhost".into())?;
+    ///
+    /// assert_eq!(tree.to_string(), r#"array (
+    ///   // keep me
+    ///   'debug' => true,
+    ///   'redis' => array('host' => 'localhost'),
+    /// )"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_path<I, K>(&mut self, path: I, value: &Value) -> Result<(), SerializeError>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let path: Vec<Key> = path
+            .into_iter()
+            .map(|segment| segment_key(segment.as_ref()))
+            .collect();
+        set_node(&mut self.root, &path, value, ArraySyntax::Short)
+    }
+
+    /// Remove the entry at the path, keeping the formatting and comments of everything else
+    ///
+    /// Returns the removed value, or `None` if the path doesn't exist. Comments directly before the
+    /// removed entry are removed with it. Entries without an explicit key that follow the removed entry
+    /// are given an explicit key so their keys don't change.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::SyntaxTree;
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let mut tree = SyntaxTree::parse("['a' => 1, /* b */ 'b' => 2, 'c' => 3]")?;
+    ///
+    /// assert_eq!(tree.remove_path(["b"]), Some(2.into()));
+    /// assert_eq!(tree.to_string(), "['a' => 1, 'c' => 3]");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_path<I, K>(&mut self, path: I) -> Option<Value>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let path: Vec<Key> = path
+            .into_iter()
+            .map(|segment| segment_key(segment.as_ref()))
+            .collect();
+        remove_node(&mut self.root, &path)
+    }
+}
+
+fn token(leading: &str, text: &str) -> SyntaxToken {
+    SyntaxToken {
+        leading: leading.into(),
+        text: text.into(),
+    }
+}
+
+/// Build the node for a value, writing arrays with the given syntax
+fn value_node(value: &Value, syntax: ArraySyntax) -> Result<SyntaxNode, SerializeError> {
+    let php = to_string_with_options(value, &SerializeOptions::new().array_syntax(syntax))?;
+    Ok(SyntaxTree::parse(&php)
+        .expect("written php literals can be parsed")
+        .root)
+}
+
+/// Replace a node, moving the whitespace and comments before it to the new node
+fn replace(node: &mut SyntaxNode, mut value: SyntaxNode) {
+    value.first_token_mut().leading = std::mem::take(&mut node.first_token_mut().leading);
+    *node = value;
+}
+
+/// Set the value at the path, new arrays use the syntax of the array they replace or the closest containing array
+fn set_node(
+    node: &mut SyntaxNode,
+    path: &[Key],
+    value: &Value,
+    syntax: ArraySyntax,
+) -> Result<(), SerializeError> {
+    let syntax = node.as_array().map(ArrayNode::syntax).unwrap_or(syntax);
+    let (key, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            replace(node, value_node(value, syntax)?);
+            return Ok(());
+        }
+    };
+    if node.as_array().is_none() {
+        replace(node, value_node(&Value::Array(Array::new()), syntax)?);
+    }
+    let array = match node {
+        SyntaxNode::Array(array) => array,
+        _ => unreachable!(),
+    };
+    match array.position(key) {
+        Some(index) => set_node(&mut array.entries[index].value, rest, value, syntax),
+        None => {
+            let mut new = value_node(&Value::Array(Array::new()), syntax)?;
+            set_node(&mut new, rest, value, syntax)?;
+            array.push_entry(key.clone(), new);
+            Ok(())
+        }
+    }
+}
+
+fn remove_node(node: &mut SyntaxNode, path: &[Key]) -> Option<Value> {
+    let (key, rest) = path.split_first()?;
+    let array = match node {
+        SyntaxNode::Array(array) => array,
+        _ => return None,
+    };
+    if !rest.is_empty() {
+        let index = array.position(key)?;
+        return remove_node(&mut array.entries[index].value, rest);
+    }
+
+    let keys: Vec<Option<Key>> = array.keyed_entries().map(|(key, _)| key).collect();
+    let value = array.entries[array.position(key)?].value.to_value();
+    for index in (0..keys.len())
+        .rev()
+        .filter(|index| keys[*index].as_ref() == Some(key))
+    {
+        let removed = array.entries.remove(index);
+        // the line before the removed entry ends with the comments for the previous entry
+        let leading = &removed.first_token().leading;
+        let kept = leading
+            .find('\n')
+            .map_or("", |newline| &leading[..=newline]);
+        // drop the rest of the line after the removed entry, which contains comments for the removed entry
+        let next = match array.entries.get_mut(index) {
+            Some(entry) => entry.first_token_mut(),
+            None => &mut array.close,
+        };
+        match next.leading.find('\n') {
+            Some(newline) if !kept.is_empty() => next.leading.replace_range(..=newline, kept),
+            Some(newline) => next.leading.replace_range(..newline, ""),
+            None => next.leading.insert_str(0, kept),
+        }
+    }
+
+    // removing an entry can change the implicit keys of the entries after it
+    let remaining = keys
+        .into_iter()
+        .filter(|existing| existing.as_ref() != Some(key));
+    let changed: Vec<_> = array
+        .keyed_entries()
+        .zip(remaining)
+        .enumerate()
+        .filter_map(|(index, ((new, _), old))| match (new, old) {
+            (Some(new), Some(old)) if new != old => Some((index, old)),
+            _ => None,
+        })
+        .collect();
+    for (index, key) in changed {
+        array.entries[index].set_key(key);
+    }
+    Some(value)
+}
+
+/// The whitespace to put before a new entry, based on the whitespace before an existing entry
+fn indentation(trivia: &str) -> &str {
+    match trivia.rfind('\n') {
+        Some(newline) if trivia[newline..].trim().is_empty() => &trivia[newline..],
+        Some(_) => "\n",
+        // the first entry of an inline array usually has no whitespace before it
+        None if !trivia.is_empty() && trivia.trim().is_empty() => trivia,
+        None => " ",
+    }
+}
+
+impl ArrayNode {
+    /// The index of the entry that determines the value for the key
+    fn position(&self, key: &Key) -> Option<usize> {
+        self.keyed_entries()
+            .enumerate()
+            .filter(|(_, (existing, _))| existing.as_ref() == Some(key))
+            .map(|(index, _)| index)
+            .last()
+    }
+
+    fn push_entry(&mut self, key: Key, mut value: SyntaxNode) {
+        let (leading, trailing_comma) = match self.entries.last_mut() {
+            Some(last) => {
+                let trailing_comma = last.comma.is_some();
+                last.comma.get_or_insert_with(|| token("", ","));
+                (
+                    indentation(&last.first_token().leading).to_string(),
+                    trailing_comma,
+                )
+            }
+            None => (String::new(), false),
+        };
+        value.first_token_mut().leading = " ".into();
+        self.entries.push(EntryNode {
+            key: Some(KeyNode {
+                token: token(&leading, &to_string(&key).unwrap()),
+                key,
+                arrow: token(" ", "=>"),
+            }),
+            spread: None,
+            value,
+            comma: trailing_comma.then(|| token("", ",")),
+        });
+    }
+}
+
+impl EntryNode {
+    /// Give the entry an explicit key, keeping the whitespace and comments before it
+    fn set_key(&mut self, key: Key) {
+        let leading = match &mut self.key {
+            Some(existing) => std::mem::take(&mut existing.token.leading),
+            None => std::mem::replace(&mut self.value.first_token_mut().leading, " ".into()),
+        };
+        self.key = Some(KeyNode {
+            token: token(&leading, &to_string(&key).unwrap()),
+            key,
+            arrow: token(" ", "=>"),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, SyntaxTree, Value};
+
+    #[test]
+    fn test_set_path() {
+        let source = r#"array (
+  'instanceid' => 'oc1234', // the instance
+  /* trusted domains */
+  'trusted_domains' =>
+  array (
+    0 => 'localhost',
+    1 => 'example.com'
+  ),
+  'debug' => 1.5,
+);
+"#;
+        let mut tree = SyntaxTree::parse(source).unwrap();
+        tree.set_path(["trusted_domains", "2"], &"cloud.example.com".into())
+            .unwrap();
+        tree.set_path(["debug"], &true.into()).unwrap();
+        tree.set_path(["instanceid", "nested"], &Value::Null)
+            .unwrap();
+        tree.set_path(["new"], &from_str("[1, 2]").unwrap())
+            .unwrap();
+
+        assert_eq!(
+            tree.to_string(),
+            r#"array (
+  'instanceid' => array('nested' => null), // the instance
+  /* trusted domains */
+  'trusted_domains' =>
+  array (
+    0 => 'localhost',
+    1 => 'example.com',
+    2 => 'cloud.example.com'
+  ),
+  'debug' => true,
+  'new' => array(1, 2),
+);
+"#
+        );
+        tree.set_path(["x"], &f64::INFINITY.into()).unwrap();
+        assert!(tree.to_string().ends_with("  'x' => INF,\n);\n"));
+
+        let mut tree = SyntaxTree::parse("[]").unwrap();
+        tree.set_path(["a", "b"], &1.into()).unwrap();
+        assert_eq!(tree.to_string(), "['a' => ['b' => 1]]");
+        tree.set_path(["c"], &2.into()).unwrap();
+        tree.set_path(["d"], &3.into()).unwrap();
+        assert_eq!(tree.to_string(), "['a' => ['b' => 1], 'c' => 2, 'd' => 3]");
+        tree.set_path(Vec::<&str>::new(), &"root".into()).unwrap();
+        assert_eq!(tree.to_string(), "'root'");
+    }
+
+    #[test]
+    fn test_remove_path() {
+        let source = r#"[
+    'a', // first
+    'b', // second
+    'c', // third
+    'd' => ['x' => 1, 'y' => 2],
+    'd' => ['x' => 3],
+]"#;
+        let mut tree = SyntaxTree::parse(source).unwrap();
+        let before = tree.to_value();
+        assert_eq!(tree.remove_path(["0"]), Some("a".into()));
+        assert_eq!(tree.remove_path(["d", "x"]), Some(3.into()));
+        assert_eq!(tree.remove_path(["d", "y"]), None);
+        assert_eq!(tree.remove_path(["missing"]), None);
+        assert_eq!(
+            tree.to_string(),
+            r#"[
+    1 => 'b', // second
+    2 => 'c', // third
+    'd' => ['x' => 1, 'y' => 2],
+    'd' => [],
+]"#
+        );
+        let after = tree.to_value();
+        assert_eq!(after[1], before[1]);
+        assert_eq!(after[2], before[2]);
+
+        // the comment after the previous entry is kept
+        assert_eq!(tree.remove_path(["2"]), Some("c".into()));
+        assert_eq!(
+            tree.to_string(),
+            r#"[
+    1 => 'b', // second
+    'd' => ['x' => 1, 'y' => 2],
+    'd' => [],
+]"#
+        );
+
+        assert!(tree.remove_path(["d"]).is_some());
+        assert_eq!(tree.to_value()["d"], Value::Null);
+
+        let mut tree = SyntaxTree::parse("[\n    'a' => 1, // a\n    'b' => 2]").unwrap();
+        tree.remove_path(["b"]);
+        assert_eq!(tree.to_string(), "[\n    'a' => 1, // a\n]");
+    }
+}
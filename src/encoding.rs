@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Replacement for bytes that aren't valid UTF-8, a single byte so every byte of the input keeps its offset
+const INVALID_BYTE: char = '\0';
+
+/// Decode the raw bytes of a php file into a string that can be parsed
+///
+/// A leading UTF-8 byte order mark is removed. Input that isn't valid UTF-8 is decoded as Latin-1
+/// (ISO-8859-1), which older config files are commonly saved in and which maps every byte to a character.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{decode_bytes, from_str, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let source = decode_bytes(b"['name' => 'Jos\xE9']");
+/// let map = from_str::<Value>(&source)?;
+///
+/// assert_eq!(map["name"], "José");
+/// # Ok(())
+/// # }
+/// ```
+pub fn decode_bytes(bytes: &[u8]) -> Cow<'_, str> {
+    let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(str) => Cow::Borrowed(str),
+        Err(_) => Cow::Owned(bytes.iter().map(|&byte| char::from(byte)).collect()),
+    }
+}
+
+/// Replace the bytes that aren't valid UTF-8 so the input can be lexed as a string
+///
+/// Every byte is replaced by a single byte, so spans in the result are byte offsets into the input.
+/// String literals are read from the input itself to keep their invalid bytes, see [`Parser::with_bytes`](crate::parser::Parser::with_bytes).
+pub(crate) fn escape_invalid_utf8(bytes: &[u8]) -> Cow<'_, str> {
+    let mut rest = match std::str::from_utf8(bytes) {
+        Ok(str) => return Cow::Borrowed(str),
+        Err(_) => bytes,
+    };
+    let mut escaped = String::with_capacity(bytes.len());
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                escaped.push_str(valid);
+                return Cow::Owned(escaped);
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                escaped.push_str(std::str::from_utf8(valid).unwrap());
+                let invalid_len = err.error_len().unwrap_or(invalid.len());
+                escaped.extend(std::iter::repeat(INVALID_BYTE).take(invalid_len));
+                rest = &invalid[invalid_len..];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_bytes, escape_invalid_utf8};
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_escape_invalid_utf8() {
+        assert!(matches!(
+            escape_invalid_utf8(b"['\xC3\xA9']"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            escape_invalid_utf8(b"['\xE9', '\xC3\xA9']"),
+            "['\0', '\u{e9}']"
+        );
+        assert_eq!(escape_invalid_utf8(b"\x80\xFF\xF4"), "\0\0\0");
+        assert_eq!(escape_invalid_utf8(b"a\xC3"), "a\0");
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        assert!(matches!(decode_bytes(b"[1]"), Cow::Borrowed("[1]")));
+        assert!(matches!(
+            decode_bytes(b"\xEF\xBB\xBF['\xC3\xA9']"),
+            Cow::Borrowed("['é']")
+        ));
+        assert_eq!(decode_bytes(b"['\xE9\xFF']"), "['éÿ']");
+        assert_eq!(decode_bytes(b"\xEF\xBB\xBF['\xE9']"), "['é']");
+    }
+}
@@ -0,0 +1,112 @@
+use crate::serde_impl::{ArrayCursor, Deserializer};
+use crate::{Key, ParseError, ParserOptions, Value};
+
+/// Parse the entries of a php array one at a time
+///
+/// Each entry is only parsed once it's requested, so large arrays can be processed without
+/// first building the entire [`Value`] in memory.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{entries_from_str, Key, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let mut entries = entries_from_str(r#"["foo" => 1, "bar" => [true]]"#);
+///
+/// assert_eq!(entries.next().transpose()?, Some((Key::from("foo"), Value::Int(1))));
+/// let (key, value) = entries.next().unwrap()?;
+/// assert_eq!(key, Key::from("bar"));
+/// assert_eq!(value[0], true);
+/// assert!(entries.next().is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub fn entries_from_str(s: &str) -> Entries<'_> {
+    entries_from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse the entries of a php array one at a time using the provided options
+pub fn entries_from_str_with_options<'a>(s: &'a str, options: &ParserOptions) -> Entries<'a> {
+    Entries {
+        de: Deserializer::with_options(s, options.clone()),
+        cursor: None,
+        finished: false,
+    }
+}
+
+/// Iterator over the entries of a php array, created by [`entries_from_str`]
+///
+/// Iteration stops after the first error.
+pub struct Entries<'de> {
+    de: Deserializer<'de>,
+    cursor: Option<ArrayCursor>,
+    finished: bool,
+}
+
+impl Entries<'_> {
+    fn next_entry(&mut self) -> Result<Option<(Key, Value)>, ParseError> {
+        let cursor = match &mut self.cursor {
+            Some(cursor) => cursor,
+            None => self.cursor.insert(self.de.open_entries()?),
+        };
+        match self.de.next_entry(cursor)? {
+            Some(entry) => Ok(Some(entry)),
+            None => {
+                self.de.end()?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<(Key, Value), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.next_entry().transpose();
+        self.finished = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{entries_from_str, Key, Value};
+    use maplit::hashmap;
+
+    #[test]
+    fn test_entries() {
+        let entries: Vec<_> = entries_from_str(r#"array(1, "a" => [2], 5 => 3, 4);"#)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (Key::Int(0), Value::Int(1)),
+                (
+                    Key::from("a"),
+                    Value::from(hashmap! {Key::Int(0) => Value::Int(2)})
+                ),
+                (Key::Int(5), Value::Int(3)),
+                (Key::Int(6), Value::Int(4)),
+            ]
+        );
+
+        let mut entries = entries_from_str(r#"[1, "a" => ] 2"#);
+        assert_eq!(
+            entries.next().unwrap().unwrap(),
+            (Key::Int(0), Value::Int(1))
+        );
+        assert!(entries.next().unwrap().is_err());
+        assert!(entries.next().is_none());
+
+        let mut entries = entries_from_str(r#"[1] 2"#);
+        assert!(entries.next().unwrap().is_ok());
+        assert!(entries.next().unwrap().is_err());
+    }
+}
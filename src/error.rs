@@ -1,37 +1,560 @@
+use crate::duplicate::DuplicateKey;
 use crate::lexer::{SpannedToken, Token};
 use crate::num::ParseIntError;
+use crate::report::ErrorReport;
 use crate::string::UnescapeError;
-use logos::Span;
-use miette::{Diagnostic, SourceOffset, SourceSpan};
+use crate::suggest::suggest_keyword;
+use crate::{Key, Span};
+#[cfg(feature = "miette")]
+use miette::{
+    Diagnostic, LabeledSpan, MietteError, MietteSpanContents, Severity, SourceCode, SourceSpan,
+    SpanContents,
+};
 use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
+use std::io;
 use std::num::ParseFloatError;
 use std::str::ParseBoolError;
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Any error that occurred while trying to parse the php literal
-#[derive(Error, Debug, Clone, Diagnostic)]
+#[derive(Error, Debug, Clone)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
 pub enum ParseError {
     #[error(transparent)]
-    #[diagnostic(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
     /// A token that wasn't expected was found while parsing
     UnexpectedToken(#[from] UnexpectedTokenError),
     #[error(transparent)]
-    #[diagnostic(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
     /// A malformed integer, float, boolean or string literal was found
     InvalidPrimitive(#[from] PrimitiveError),
     #[error("Array key not valid for this position")]
-    #[diagnostic(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
     /// An array key was found that is invalid for this position
     UnexpectedArrayKey(ArrayKeyError),
     #[error(transparent)]
-    #[diagnostic(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
     /// Trailing characters after parsing
     TrailingCharacters(#[from] TrailingError),
-    #[error("{0}")]
-    #[diagnostic(code(php_literal_parser::serde))]
+    #[error(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
     /// Error while populating serde type
-    Serde(String),
+    Serde(#[from] SerdeError),
+    #[error(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
+    /// Multiple fields failed to deserialize
+    Fields(#[from] FieldErrors),
+    #[error(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
+    /// A registered function call handler returned an error
+    FunctionCall(#[from] FunctionCallError),
+    #[error(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
+    /// An expression could not be evaluated
+    Expression(#[from] ExpressionError),
+    #[error("Failed to read the input: {0}")]
+    #[cfg_attr(feature = "miette", diagnostic(code(php_literal_parser::io)))]
+    /// Reading the input failed
+    Io(Arc<io::Error>),
+    #[error(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
+    /// Arrays or expressions are nested deeper than the maximum depth
+    NestingLimit(#[from] NestingLimitError),
+    #[error("Maximum number of {0} array entries exceeded")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(
+            code(php_literal_parser::element_limit),
+            help("the limit can be changed with `ParserOptions::max_elements`")
+        )
+    )]
+    /// The input contains more array entries than the maximum number of elements
+    ElementLimit(usize),
+    #[error("Maximum number of {0} entries in a single array exceeded")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(
+            code(php_literal_parser::array_entry_limit),
+            help("the limit can be changed with `ParserOptions::max_array_entries`")
+        )
+    )]
+    /// An array contains more entries than the maximum
+    ArrayEntryLimit(usize),
+    #[error("Maximum string length of {0} bytes exceeded")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(
+            code(php_literal_parser::string_length_limit),
+            help("the limit can be changed with `ParserOptions::max_string_length`")
+        )
+    )]
+    /// A string is longer than the maximum string length
+    StringLengthLimit(usize),
+    #[error(transparent)]
+    #[cfg_attr(feature = "miette", diagnostic(transparent))]
+    /// A struct field is set more than once in the same array
+    DuplicateField(#[from] DuplicateFieldError),
+}
+
+/// The kind of a [`ParseError`], for matching on errors without destructuring them, see [`ParseError::kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// See [`ParseError::UnexpectedToken`]
+    UnexpectedToken,
+    /// See [`ParseError::InvalidPrimitive`]
+    InvalidPrimitive,
+    /// See [`ParseError::UnexpectedArrayKey`]
+    UnexpectedArrayKey,
+    /// See [`ParseError::TrailingCharacters`]
+    TrailingCharacters,
+    /// See [`ParseError::Serde`]
+    Serde,
+    /// See [`ParseError::Fields`]
+    Fields,
+    /// See [`ParseError::FunctionCall`]
+    FunctionCall,
+    /// See [`ParseError::Expression`]
+    Expression,
+    /// See [`ParseError::Io`]
+    Io,
+    /// See [`ParseError::NestingLimit`]
+    NestingLimit,
+    /// See [`ParseError::ElementLimit`]
+    ElementLimit,
+    /// See [`ParseError::ArrayEntryLimit`]
+    ArrayEntryLimit,
+    /// See [`ParseError::StringLengthLimit`]
+    StringLengthLimit,
+    /// See [`ParseError::DuplicateField`]
+    DuplicateField,
+}
+
+impl ErrorKind {
+    /// Whether the error is caused by one of the limits of [`ParserOptions`](crate::ParserOptions) being exceeded
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::NestingLimit
+                | ErrorKind::ElementLimit
+                | ErrorKind::ArrayEntryLimit
+                | ErrorKind::StringLengthLimit
+        )
+    }
+}
+
+impl ParseError {
+    /// The kind of the error
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, ErrorKind, Value};
+    ///
+    /// let error = from_str::<Value>("[1, 2] 3").unwrap_err();
+    /// match error.kind() {
+    ///     ErrorKind::TrailingCharacters => {}
+    ///     kind => panic!("unexpected error {:?}", kind),
+    /// }
+    /// assert!(error.is_trailing());
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ParseError::UnexpectedToken(_) => ErrorKind::UnexpectedToken,
+            ParseError::InvalidPrimitive(_) => ErrorKind::InvalidPrimitive,
+            ParseError::UnexpectedArrayKey(_) => ErrorKind::UnexpectedArrayKey,
+            ParseError::TrailingCharacters(_) => ErrorKind::TrailingCharacters,
+            ParseError::Serde(_) => ErrorKind::Serde,
+            ParseError::Fields(_) => ErrorKind::Fields,
+            ParseError::FunctionCall(_) => ErrorKind::FunctionCall,
+            ParseError::Expression(_) => ErrorKind::Expression,
+            ParseError::Io(_) => ErrorKind::Io,
+            ParseError::NestingLimit(_) => ErrorKind::NestingLimit,
+            ParseError::ElementLimit(_) => ErrorKind::ElementLimit,
+            ParseError::ArrayEntryLimit(_) => ErrorKind::ArrayEntryLimit,
+            ParseError::StringLengthLimit(_) => ErrorKind::StringLengthLimit,
+            ParseError::DuplicateField(_) => ErrorKind::DuplicateField,
+        }
+    }
+
+    /// Whether a token that wasn't expected was found
+    pub fn is_unexpected_token(&self) -> bool {
+        self.kind() == ErrorKind::UnexpectedToken
+    }
+
+    /// Whether a malformed literal was found
+    pub fn is_invalid_primitive(&self) -> bool {
+        self.kind() == ErrorKind::InvalidPrimitive
+    }
+
+    /// Whether an array key was found that is invalid for its position
+    pub fn is_unexpected_array_key(&self) -> bool {
+        self.kind() == ErrorKind::UnexpectedArrayKey
+    }
+
+    /// Whether there are trailing characters after the parsed value
+    pub fn is_trailing(&self) -> bool {
+        self.kind() == ErrorKind::TrailingCharacters
+    }
+
+    /// Whether the parsed value doesn't fit the type it's deserialized into
+    pub fn is_serde(&self) -> bool {
+        self.kind() == ErrorKind::Serde
+    }
+
+    /// Whether multiple fields failed to deserialize
+    pub fn is_fields(&self) -> bool {
+        self.kind() == ErrorKind::Fields
+    }
+
+    /// Whether a function call handler returned an error
+    pub fn is_function_call(&self) -> bool {
+        self.kind() == ErrorKind::FunctionCall
+    }
+
+    /// Whether an expression could not be evaluated
+    pub fn is_expression(&self) -> bool {
+        self.kind() == ErrorKind::Expression
+    }
+
+    /// Whether a struct field is set more than once
+    pub fn is_duplicate_field(&self) -> bool {
+        self.kind() == ErrorKind::DuplicateField
+    }
+
+    /// Whether reading the input failed
+    pub fn is_io(&self) -> bool {
+        self.kind() == ErrorKind::Io
+    }
+
+    /// Whether one of the limits of [`ParserOptions`](crate::ParserOptions) was exceeded
+    pub fn is_limit(&self) -> bool {
+        self.kind().is_limit()
+    }
+
+    /// A stable code identifying the kind of error, like `PLP0001`
+    ///
+    /// Codes are never reused or changed, new kinds of errors get a new code.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken(_) => "PLP0001",
+            ParseError::InvalidPrimitive(_) => "PLP0002",
+            ParseError::UnexpectedArrayKey(_) => "PLP0003",
+            ParseError::TrailingCharacters(_) => "PLP0004",
+            ParseError::Serde(_) => "PLP0005",
+            ParseError::Fields(_) => "PLP0006",
+            ParseError::FunctionCall(_) => "PLP0007",
+            ParseError::Expression(_) => "PLP0008",
+            ParseError::Io(_) => "PLP0009",
+            ParseError::NestingLimit(_) => "PLP0010",
+            ParseError::ElementLimit(_) => "PLP0011",
+            ParseError::ArrayEntryLimit(_) => "PLP0012",
+            ParseError::StringLengthLimit(_) => "PLP0013",
+            ParseError::DuplicateField(_) => "PLP0014",
+        }
+    }
+
+    /// A machine-readable description of the error that can be serialized, for example as json
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    ///
+    /// let error = from_str::<Value>("[1, 2 3]").unwrap_err();
+    /// let report = error.report();
+    ///
+    /// assert_eq!(report.code, "PLP0001");
+    /// assert_eq!(report.span.unwrap().column, 7);
+    /// assert_eq!(report.expected, ["','", "']'"]);
+    /// ```
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport::new(self)
+    }
+
+    /// The span of the offending part of the input, with its line and column resolved
+    ///
+    /// For [`Fields`](ParseError::Fields) this is the span of the first failing field,
+    /// errors that aren't caused by a specific part of the input have no span.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken(err) => Some(err.span()),
+            ParseError::InvalidPrimitive(err) => Some(err.span()),
+            ParseError::UnexpectedArrayKey(err) => Some(err.span()),
+            ParseError::TrailingCharacters(err) => Some(err.span()),
+            ParseError::Fields(errors) => errors.errors().first()?.error().span(),
+            ParseError::FunctionCall(err) => Some(err.span()),
+            ParseError::Expression(err) => Some(err.span()),
+            ParseError::NestingLimit(err) => Some(err.span()),
+            ParseError::Serde(err) => err.span(),
+            ParseError::DuplicateField(err) => Some(err.span()),
+            ParseError::Io(_)
+            | ParseError::ElementLimit(_)
+            | ParseError::ArrayEntryLimit(_)
+            | ParseError::StringLengthLimit(_) => None,
+        }
+    }
+
+    /// Combine the error with the source it was parsed from, for rendering the error with a snippet of the source
+    ///
+    /// Errors only store the location of the offending part of the input,
+    /// the source is only needed when the error is rendered.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// use miette::{GraphicalReportHandler, GraphicalTheme};
+    ///
+    /// let source = "[1, 2 3]";
+    /// let error = from_str::<Value>(source).unwrap_err();
+    ///
+    /// let mut rendered = String::new();
+    /// GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+    ///     .render_report(&mut rendered, &error.with_source(source))
+    ///     .unwrap();
+    /// assert!(rendered.contains("[1, 2 3]"));
+    /// ```
+    #[cfg(feature = "miette")]
+    pub fn with_source<'a>(&'a self, source: &'a str) -> WithSource<'a> {
+        WithSource {
+            error: self,
+            source: SourceText { source, name: None },
+        }
+    }
+
+    /// The arrays enclosing the offending part of the input, innermost first
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Key, Value};
+    ///
+    /// let error = from_str::<Value>("['trusted_domains' => [\n  'localhost' 1,\n]]").unwrap_err();
+    /// let context = error.context();
+    ///
+    /// assert_eq!(context[0].key, Key::Int(0));
+    /// assert_eq!(context[1].key, Key::from("trusted_domains"));
+    /// assert_eq!(
+    ///     context[1].to_string(),
+    ///     "while parsing the value for key 'trusted_domains' (array started at line 1)"
+    /// );
+    /// ```
+    pub fn context(&self) -> &[Breadcrumb] {
+        match self {
+            ParseError::UnexpectedToken(err) => err.context(),
+            ParseError::InvalidPrimitive(err) => err.context(),
+            ParseError::UnexpectedArrayKey(err) => err.context(),
+            ParseError::TrailingCharacters(err) => err.context(),
+            ParseError::Serde(err) => &err.context,
+            ParseError::FunctionCall(err) => err.context(),
+            ParseError::Expression(err) => err.context(),
+            ParseError::DuplicateField(err) => err.context(),
+            ParseError::NestingLimit(err) => err.context(),
+            ParseError::Fields(_)
+            | ParseError::Io(_)
+            | ParseError::ElementLimit(_)
+            | ParseError::ArrayEntryLimit(_)
+            | ParseError::StringLengthLimit(_) => &[],
+        }
+    }
+
+    /// Set the arrays enclosing the offending part of the input if the error doesn't have them yet
+    pub(crate) fn or_context(mut self, context: impl FnOnce() -> Vec<Breadcrumb>) -> Self {
+        let current = match &mut self {
+            ParseError::UnexpectedToken(err) => &mut err.context,
+            ParseError::InvalidPrimitive(err) => &mut err.context,
+            ParseError::UnexpectedArrayKey(err) => &mut err.context,
+            ParseError::TrailingCharacters(err) => &mut err.context,
+            ParseError::Serde(err) => &mut err.context,
+            ParseError::FunctionCall(err) => &mut err.context,
+            ParseError::Expression(err) => &mut err.context,
+            ParseError::DuplicateField(err) => &mut err.context,
+            ParseError::NestingLimit(err) => &mut err.context,
+            _ => return self,
+        };
+        if current.is_empty() {
+            *current = context().into();
+        }
+        self
+    }
+
+    /// Byte offset of the start of the offending part of the input
+    pub fn offset(&self) -> Option<usize> {
+        self.span().map(|span| span.start())
+    }
+
+    /// Line of the start of the offending part of the input, starting at 1
+    pub fn line(&self) -> Option<usize> {
+        Some(self.span()?.position()?.line)
+    }
+
+    /// Column in characters of the start of the offending part of the input, starting at 1
+    pub fn column(&self) -> Option<usize> {
+        Some(self.span()?.position()?.column)
+    }
+}
+
+/// Accessors for the location of errors pointing to a part of the input
+macro_rules! impl_error_span {
+    ($($error:ty),*) => {
+        $(
+            impl $error {
+                /// The span of the offending part of the input, with its line and column resolved
+                pub fn span(&self) -> Span {
+                    self.err_span.clone()
+                }
+
+                /// Byte offset of the start of the offending part of the input
+                pub fn offset(&self) -> usize {
+                    self.err_span.start()
+                }
+
+                /// Line of the start of the offending part of the input, starting at 1
+                pub fn line(&self) -> usize {
+                    self.err_span.position().unwrap().line
+                }
+
+                /// Column in characters of the start of the offending part of the input, starting at 1
+                pub fn column(&self) -> usize {
+                    self.err_span.position().unwrap().column
+                }
+
+                /// The arrays enclosing the offending part of the input, innermost first
+                pub fn context(&self) -> &[Breadcrumb] {
+                    &self.context
+                }
+            }
+        )*
+    };
+}
+
+impl_error_span!(
+    UnexpectedTokenError,
+    PrimitiveError,
+    ArrayKeyError,
+    TrailingError,
+    FunctionCallError,
+    ExpressionError,
+    NestingLimitError
+);
+
+/// A [`ParseError`] with the source it was parsed from, see [`ParseError::with_source`]
+#[cfg(feature = "miette")]
+#[derive(Debug, Clone, Copy)]
+pub struct WithSource<'a> {
+    error: &'a ParseError,
+    source: SourceText<'a>,
+}
+
+#[cfg(feature = "miette")]
+impl<'a> WithSource<'a> {
+    /// Set the name of the source, like the path of the parsed file, to show next to the snippet of the source
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// use miette::{GraphicalReportHandler, GraphicalTheme};
+    ///
+    /// let source = "[1, 2 3]";
+    /// let error = from_str::<Value>(source).unwrap_err();
+    ///
+    /// let mut rendered = String::new();
+    /// GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+    ///     .render_report(&mut rendered, &error.with_source(source).with_name("config.php"))
+    ///     .unwrap();
+    /// assert!(rendered.contains("config.php:1:7"));
+    /// ```
+    pub fn with_name(mut self, name: &'a str) -> Self {
+        self.source.name = Some(name);
+        self
+    }
+
+    pub fn error(&self) -> &'a ParseError {
+        self.error
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        self.source.name
+    }
+}
+
+/// The source of an error with an optional name, see [`WithSource::with_name`]
+#[cfg(feature = "miette")]
+#[derive(Debug, Clone, Copy)]
+struct SourceText<'a> {
+    source: &'a str,
+    name: Option<&'a str>,
+}
+
+#[cfg(feature = "miette")]
+impl SourceCode for SourceText<'_> {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents = self
+            .source
+            .read_span(span, context_lines_before, context_lines_after)?;
+        match self.name {
+            Some(name) => Ok(Box::new(MietteSpanContents::new_named(
+                name.into(),
+                contents.data(),
+                *contents.span(),
+                contents.line(),
+                contents.column(),
+                contents.line_count(),
+            ))),
+            None => Ok(contents),
+        }
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Display for WithSource<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.error, f)
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Error for WithSource<'_> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for WithSource<'_> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.error.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.error.related()
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(Arc::new(err))
+    }
 }
 
 impl serde::de::Error for ParseError {
@@ -39,34 +562,169 @@ impl serde::de::Error for ParseError {
     where
         T: Display,
     {
-        ParseError::Serde(msg.to_string())
+        SerdeError::new(msg.to_string()).into()
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        SerdeError {
+            missing_field: Some(Box::new(field)),
+            ..SerdeError::new(format!("missing field `{}`", field))
+        }
+        .into()
+    }
+}
+
+/// Any error that occurred while trying to write a php literal
+#[derive(Error, Debug, Clone)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+pub enum SerializeError {
+    #[error("{0}")]
+    #[cfg_attr(feature = "miette", diagnostic(code(php_literal_parser::serde)))]
+    /// Error returned by a serde `Serialize` implementation
+    Serde(String),
+    #[error("Array keys must be strings or integers")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(code(php_literal_parser::invalid_array_key))
+    )]
+    /// A map key can't be used as php array key
+    InvalidKey,
+    #[error("Integer {0} is out of range for php")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(code(php_literal_parser::integer_out_of_range))
+    )]
+    /// An integer doesn't fit in php's 64 bit integers
+    IntegerOutOfRange(String),
+    #[error("Float {0} can't be written as php literal")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(code(php_literal_parser::non_finite_float))
+    )]
+    /// A float is infinite or NaN
+    NonFiniteFloat(f64),
+}
+
+impl serde::ser::Error for SerializeError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        SerializeError::Serde(msg.to_string())
     }
 }
 
 /// A token that wasn't expected was found while parsing
-#[derive(Debug, Clone, Diagnostic)]
-#[diagnostic(code(php_literal_parser::unexpected_token))]
+#[derive(Debug, Clone)]
 pub struct UnexpectedTokenError {
-    #[source_code]
-    src: String,
-    #[label("Expected {}", self.expected)]
-    err_span: SourceSpan,
+    err_span: Span,
+    context: Box<[Breadcrumb]>,
     pub expected: TokenList,
     pub found: Option<Token>,
+    /// Boxed to keep the size of `ParseError` down, it's returned through every level of nesting
+    invalid: Option<Box<InvalidToken>>,
 }
 
 impl UnexpectedTokenError {
-    pub fn new(
-        expected: &[Token],
-        found: Option<Token>,
-        src: String,
-        err_span: SourceSpan,
-    ) -> Self {
+    pub fn new(expected: &[Token], found: Option<Token>, source: &str, err_span: Span) -> Self {
         UnexpectedTokenError {
-            src,
-            err_span,
+            err_span: err_span.with_position(source),
+            context: Box::default(),
             expected: expected.into(),
             found,
+            invalid: None,
+        }
+    }
+
+    fn with_invalid(mut self, invalid: Option<InvalidToken>) -> Self {
+        self.invalid = invalid.map(Box::new);
+        self
+    }
+
+    /// Description of the found input when it isn't a valid token or is an unknown identifier
+    pub fn invalid(&self) -> Option<&InvalidToken> {
+        self.invalid.as_deref()
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for UnexpectedTokenError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("php_literal_parser::unexpected_token"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        match self.invalid() {
+            Some(InvalidToken::UnknownIdentifier {
+                suggestion: Some(suggestion),
+                ..
+            }) => Some(Box::new(format!("did you mean `{}`?", suggestion))),
+            _ => None,
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(format!("Expected {}", self.expected)),
+            self.err_span.clone(),
+        ))))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.context.iter().map(|crumb| crumb as &dyn Diagnostic),
+        ))
+    }
+}
+
+/// Input that isn't a valid token or an identifier that isn't known, found where a different token was expected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidToken {
+    /// A string literal, heredoc or nowdoc without closing quote or identifier
+    UnterminatedString,
+    /// A `$` that isn't followed by a variable name
+    StrayDollar,
+    /// A character that doesn't start any token
+    Character(char),
+    /// An identifier that isn't a constant or keyword, like a misspelled `true`
+    UnknownIdentifier {
+        name: String,
+        /// The keyword the identifier is most likely a misspelling of
+        suggestion: Option<&'static str>,
+    },
+}
+
+impl InvalidToken {
+    fn from_token(token: &SpannedToken, expected: &[Token]) -> Option<Self> {
+        let slice = token.slice();
+        match token.token {
+            // constants are accepted where values are expected, so the identifier isn't known
+            Token::Identifier if expected.contains(&Token::Null) => {
+                Some(InvalidToken::UnknownIdentifier {
+                    name: slice.into(),
+                    suggestion: suggest_keyword(slice),
+                })
+            }
+            Token::Identifier => None,
+            Token::Error if slice.starts_with(['\'', '"']) || slice.starts_with("<<<") => {
+                Some(InvalidToken::UnterminatedString)
+            }
+            Token::Error if slice.starts_with('$') => Some(InvalidToken::StrayDollar),
+            Token::Error => slice.chars().next().map(InvalidToken::Character),
+            _ => None,
+        }
+    }
+}
+
+impl Display for InvalidToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidToken::UnterminatedString => write!(f, "Unterminated string literal"),
+            InvalidToken::StrayDollar => write!(f, "'$' without variable name"),
+            InvalidToken::Character(char) => write!(f, "Unexpected character {:?}", char),
+            InvalidToken::UnknownIdentifier { name, .. } => {
+                write!(f, "Unknown constant or keyword '{}'", name)
+            }
         }
     }
 }
@@ -81,6 +739,12 @@ impl Debug for TokenList {
     }
 }
 
+impl TokenList {
+    pub fn tokens(&self) -> &[Token] {
+        &self.0
+    }
+}
+
 impl From<&[Token]> for TokenList {
     fn from(list: &[Token]) -> Self {
         TokenList(list.into())
@@ -109,16 +773,17 @@ impl Display for TokenList {
 
 impl Display for UnexpectedTokenError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.found {
-            Some(Token::Error) => {
+        match (&self.found, self.invalid()) {
+            (_, Some(invalid)) => write!(f, "{}, expected one of {}", invalid, self.expected),
+            (Some(Token::Error), None) => {
                 write!(f, "No valid token found, expected one of {}", self.expected)
             }
-            Some(token) => write!(
+            (Some(token), None) => write!(
                 f,
                 "Unexpected token, found {} expected one of {}",
                 token, self.expected
             ),
-            None => write!(
+            (None, None) => write!(
                 f,
                 "Unexpected end of input expected one of {}",
                 self.expected
@@ -130,17 +795,31 @@ impl Display for UnexpectedTokenError {
 impl Error for UnexpectedTokenError {}
 
 /// A malformed integer, float, boolean or string literal was found
-#[derive(Debug, Clone, Error, Diagnostic)]
-#[diagnostic(code(php_literal_parser::invalid_primitive))]
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(code(php_literal_parser::invalid_primitive))
+)]
 #[error("{kind}")]
 pub struct PrimitiveError {
-    #[source_code]
-    src: String,
-    #[label("{}", self.kind.desc())]
-    err_span: SourceSpan,
+    #[cfg_attr(feature = "miette", label("{}", self.kind.desc()))]
+    err_span: Span,
+    #[cfg_attr(feature = "miette", related)]
+    context: Box<[Breadcrumb]>,
     pub kind: PrimitiveErrorKind,
 }
 
+impl PrimitiveError {
+    /// The reason a string literal couldn't be parsed, for invalid string literals
+    pub fn unescape_error(&self) -> Option<&UnescapeError> {
+        match &self.kind {
+            PrimitiveErrorKind::InvalidStringLiteral(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone)]
 #[allow(clippy::enum_variant_names)]
 pub enum PrimitiveErrorKind {
@@ -150,8 +829,8 @@ pub enum PrimitiveErrorKind {
     InvalidIntLiteral(#[from] ParseIntError),
     #[error("Invalid float literal: {0}")]
     InvalidFloatLiteral(#[from] ParseFloatError),
-    #[error("Invalid string literal")]
-    InvalidStringLiteral,
+    #[error("Invalid string literal: {0}")]
+    InvalidStringLiteral(#[from] UnescapeError),
 }
 
 impl PrimitiveErrorKind {
@@ -160,25 +839,34 @@ impl PrimitiveErrorKind {
             PrimitiveErrorKind::InvalidBoolLiteral(_) => "Not a boolean",
             PrimitiveErrorKind::InvalidIntLiteral(err) => err.desc(),
             PrimitiveErrorKind::InvalidFloatLiteral(_) => "Not a valid float",
-            PrimitiveErrorKind::InvalidStringLiteral => "Not a string literal",
+            PrimitiveErrorKind::InvalidStringLiteral(err) => err.reason.desc(),
         }
     }
-}
 
-impl From<UnescapeError> for PrimitiveErrorKind {
-    fn from(_: UnescapeError) -> Self {
-        PrimitiveErrorKind::InvalidStringLiteral
+    /// The part of the literal that's invalid
+    fn narrow_span(&self, literal: Span) -> Span {
+        match self {
+            PrimitiveErrorKind::InvalidStringLiteral(err) => {
+                let start = literal.start() + err.offset;
+                Span::new(start, start + err.len)
+            }
+            _ => literal,
+        }
     }
 }
 
-#[derive(Debug, Clone, Error, Diagnostic)]
-#[diagnostic(code(php_literal_parser::invalid_array_key))]
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(code(php_literal_parser::invalid_array_key))
+)]
 #[error("Invalid array key")]
 pub struct ArrayKeyError {
-    #[source_code]
-    src: String,
-    #[label("{}", self.kind)]
-    err_span: SourceSpan,
+    #[cfg_attr(feature = "miette", label("{}", self.kind))]
+    err_span: Span,
+    #[cfg_attr(feature = "miette", related)]
+    context: Box<[Breadcrumb]>,
     kind: ArrayKeyErrorKind,
 }
 
@@ -186,6 +874,8 @@ pub struct ArrayKeyError {
 pub enum ArrayKeyErrorKind {
     IntegerExpected,
     NonConsecutive,
+    /// An entry without key follows an entry with key `i64::MAX`
+    NextElementOccupied,
 }
 
 impl Display for ArrayKeyErrorKind {
@@ -196,6 +886,9 @@ impl Display for ArrayKeyErrorKind {
             match self {
                 ArrayKeyErrorKind::IntegerExpected => "Expected integer key",
                 ArrayKeyErrorKind::NonConsecutive => "Expected consecutive integer key",
+                ArrayKeyErrorKind::NextElementOccupied => {
+                    "Cannot add element to the array as the next element is already occupied"
+                }
             }
         )
     }
@@ -204,32 +897,394 @@ impl Display for ArrayKeyErrorKind {
 impl ArrayKeyError {
     pub fn new(kind: ArrayKeyErrorKind, source: &str, err_span: Span) -> Self {
         ArrayKeyError {
-            src: source.into(),
-            err_span: map_span(&err_span),
+            err_span: err_span.with_position(source),
+            context: Box::default(),
             kind,
         }
     }
 }
 
-#[derive(Debug, Clone, Error, Diagnostic)]
-#[diagnostic(code(php_literal_parser::trailing))]
-#[error("Trailing characters after parsing")]
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(
+        code(php_literal_parser::trailing),
+        help("use `from_str_prefix` to parse a literal followed by other content")
+    )
+)]
+#[error("Trailing characters after parsing, found `{text}`")]
 pub struct TrailingError {
-    #[source_code]
-    src: String,
-    #[label("end of parsed value")]
-    err_span: SourceSpan,
+    #[cfg_attr(feature = "miette", label("unexpected {found} after the parsed value"))]
+    err_span: Span,
+    #[cfg_attr(feature = "miette", related)]
+    context: Box<[Breadcrumb]>,
+    /// The first token after the parsed value
+    pub found: Token,
+    /// The source text of the first token after the parsed value, shortened if it's long
+    pub text: String,
 }
 
 impl TrailingError {
-    pub fn new(source: &str, err_span: Span) -> Self {
+    /// Maximum number of characters of the trailing token included in the error
+    const MAX_TEXT_LENGTH: usize = 32;
+
+    pub fn new(source: &str, found: &SpannedToken) -> Self {
+        let slice = found.slice();
+        let text = match slice.char_indices().nth(Self::MAX_TEXT_LENGTH) {
+            Some((end, _)) => format!("{}...", &slice[..end]),
+            None => slice.to_string(),
+        };
         TrailingError {
-            src: source.into(),
-            err_span: map_span(&err_span),
+            err_span: found.span.clone().with_position(source),
+            context: Box::default(),
+            found: found.token,
+            text,
+        }
+    }
+}
+
+/// An error returned by a serde `Deserialize` implementation, like a value with the wrong type or a missing field
+#[derive(Debug, Clone, Error)]
+#[error("{message}")]
+pub struct SerdeError {
+    err_span: Option<Span>,
+    context: Box<[Breadcrumb]>,
+    /// The field that's missing for missing field errors
+    ///
+    /// Boxed to keep `ParseError` small, it's returned through every level of nested arrays.
+    #[allow(clippy::redundant_allocation)]
+    pub(crate) missing_field: Option<Box<&'static str>>,
+    pub message: String,
+}
+
+impl SerdeError {
+    pub fn new(message: String) -> Self {
+        SerdeError {
+            err_span: None,
+            context: Box::default(),
+            missing_field: None,
+            message,
+        }
+    }
+
+    /// The span of the value that was being deserialized when the error occurred, with its line and column resolved
+    pub fn span(&self) -> Option<Span> {
+        self.err_span.clone()
+    }
+
+    /// Attach the span of the value being deserialized if the error doesn't have a span yet
+    pub(crate) fn or_span(mut self, source: &str, err_span: Option<Span>) -> Self {
+        if self.err_span.is_none() {
+            self.err_span = err_span.map(|span| span.with_position(source));
+        }
+        self
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for SerdeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("php_literal_parser::serde"))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let span = self.err_span.clone()?;
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(self.message.clone()),
+            span,
+        ))))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.context.iter().map(|crumb| crumb as &dyn Diagnostic),
+        ))
+    }
+}
+
+/// A registered function call handler returned an error
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(code(php_literal_parser::function_call))
+)]
+#[error("Failed to evaluate function call: {message}")]
+pub struct FunctionCallError {
+    #[cfg_attr(feature = "miette", label("{message}"))]
+    err_span: Span,
+    #[cfg_attr(feature = "miette", related)]
+    context: Box<[Breadcrumb]>,
+    pub message: String,
+}
+
+impl FunctionCallError {
+    pub fn new(source: &str, err_span: Span, message: String) -> Self {
+        FunctionCallError {
+            err_span: err_span.with_position(source),
+            context: Box::default(),
+            message,
+        }
+    }
+}
+
+/// An expression could not be evaluated
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(feature = "miette", diagnostic(code(php_literal_parser::expression)))]
+#[error("Failed to evaluate expression: {message}")]
+pub struct ExpressionError {
+    #[cfg_attr(feature = "miette", label("{message}"))]
+    err_span: Span,
+    #[cfg_attr(feature = "miette", related)]
+    context: Box<[Breadcrumb]>,
+    pub message: String,
+}
+
+impl ExpressionError {
+    pub fn new(source: &str, err_span: Span, message: String) -> Self {
+        ExpressionError {
+            err_span: err_span.with_position(source),
+            context: Box::default(),
+            message,
+        }
+    }
+}
+
+/// Arrays or expressions are nested deeper than the maximum depth, see [`ParserOptions::max_depth`](crate::ParserOptions::max_depth)
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(
+        code(php_literal_parser::nesting_limit),
+        help("the limit can be changed with `ParserOptions::max_depth`")
+    )
+)]
+#[error("Maximum nesting depth of {limit} exceeded")]
+pub struct NestingLimitError {
+    #[cfg_attr(feature = "miette", label("nested {depth} levels deep"))]
+    err_span: Span,
+    #[cfg_attr(feature = "miette", related)]
+    context: Box<[Breadcrumb]>,
+    /// The maximum nesting depth
+    pub limit: usize,
+    /// The depth of the array or expression that exceeds the limit
+    pub depth: usize,
+}
+
+impl NestingLimitError {
+    /// `err_span` is the span of the opening bracket or operator that exceeds the limit
+    pub fn new(limit: usize, source: &str, err_span: Span) -> Self {
+        NestingLimitError {
+            err_span: err_span.with_position(source),
+            context: Box::default(),
+            limit,
+            depth: limit + 1,
+        }
+    }
+}
+
+/// A struct field that is set more than once in the same array, see [`ParserOptions::duplicate_fields`](crate::ParserOptions::duplicate_fields)
+#[derive(Debug, Clone, Error)]
+#[error("Duplicate field '{}'", self.duplicate.key)]
+pub struct DuplicateFieldError {
+    /// Boxed to keep the size of `ParseError` down, it's returned through every level of nesting
+    duplicate: Box<DuplicateKey>,
+    context: Box<[Breadcrumb]>,
+}
+
+impl DuplicateFieldError {
+    pub fn new(duplicate: DuplicateKey, source: &str) -> Self {
+        DuplicateFieldError {
+            duplicate: Box::new(DuplicateKey {
+                first: duplicate.first.with_position(source),
+                duplicate: duplicate.duplicate.with_position(source),
+                ..duplicate
+            }),
+            context: Box::default(),
+        }
+    }
+
+    /// The duplicated key with the spans of both occurrences, with their line and column resolved
+    pub fn duplicate(&self) -> &DuplicateKey {
+        &self.duplicate
+    }
+
+    /// The span of the repeated key, with its line and column resolved
+    pub fn span(&self) -> Span {
+        self.duplicate.duplicate.clone()
+    }
+
+    /// The arrays enclosing the struct, innermost first
+    pub fn context(&self) -> &[Breadcrumb] {
+        &self.context
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for DuplicateFieldError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("php_literal_parser::duplicate_field"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(
+            "remove one of the entries, or use `ParserOptions::duplicate_fields` to use the last value like php",
+        ))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(
+            vec![
+                LabeledSpan::new_with_span(
+                    Some("first set here".into()),
+                    self.duplicate.first.clone(),
+                ),
+                LabeledSpan::new_with_span(
+                    Some("set again here".into()),
+                    self.duplicate.duplicate.clone(),
+                ),
+            ]
+            .into_iter(),
+        ))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.context.iter().map(|crumb| crumb as &dyn Diagnostic),
+        ))
+    }
+}
+
+/// An array enclosing the value where an error occurred
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breadcrumb {
+    /// The key of the entry in the array containing the error
+    pub key: Key,
+    /// The span of the opening bracket of the array, with its line and column resolved
+    pub array: Span,
+}
+
+impl Display for Breadcrumb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "while parsing the value for key '{}'", self.key)?;
+        if let Some(position) = self.array.position() {
+            write!(f, " (array started at line {})", position.line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Breadcrumb {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for Breadcrumb {
+    fn severity(&self) -> Option<Severity> {
+        Some(Severity::Advice)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some("array started here".into()),
+            self.array.clone(),
+        ))))
+    }
+}
+
+/// An error that occurred while deserializing a single field
+#[derive(Debug, Clone)]
+pub struct FieldError {
+    path: String,
+    error: ParseError,
+}
+
+impl FieldError {
+    pub fn new(path: String, error: ParseError) -> Self {
+        FieldError { path, error }
+    }
+
+    /// The dotted path of array keys leading to the field, empty for the top level value
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn error(&self) -> &ParseError {
+        &self.error
+    }
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.error)
+        } else {
+            write!(f, "{}: {}", self.path, self.error)
         }
     }
 }
 
+impl Error for FieldError {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for FieldError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.error.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.error.labels()
+    }
+}
+
+/// Errors for all fields that failed to deserialize
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(feature = "miette", diagnostic(code(php_literal_parser::fields)))]
+pub struct FieldErrors {
+    #[cfg_attr(feature = "miette", related)]
+    errors: Vec<FieldError>,
+}
+
+impl FieldErrors {
+    pub fn new(errors: Vec<FieldError>) -> Self {
+        FieldErrors { errors }
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// A single line describing how many fields failed
+    pub(crate) fn summary(&self) -> String {
+        match self.errors.len() {
+            1 => "1 field failed to deserialize".into(),
+            count => format!("{} fields failed to deserialize", count),
+        }
+    }
+}
+
+impl Display for FieldErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary())?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for FieldErrors {}
+
 pub trait ExpectToken<'source> {
     fn expect_token(
         self,
@@ -248,8 +1303,8 @@ impl<'source> ExpectToken<'source> for Option<SpannedToken<'source>> {
             UnexpectedTokenError::new(
                 expected,
                 None,
-                source.into(),
-                map_span(&(source.len()..source.len())),
+                source,
+                Span::new(source.len(), source.len()),
             )
             .into()
         })
@@ -257,7 +1312,7 @@ impl<'source> ExpectToken<'source> for Option<SpannedToken<'source>> {
     }
 }
 
-impl<'a, 'source> ExpectToken<'source> for Option<&'a SpannedToken<'source>> {
+impl<'source> ExpectToken<'source> for Option<&SpannedToken<'source>> {
     fn expect_token(
         self,
         expected: &[Token],
@@ -267,8 +1322,8 @@ impl<'a, 'source> ExpectToken<'source> for Option<&'a SpannedToken<'source>> {
             UnexpectedTokenError::new(
                 expected,
                 None,
-                source.into(),
-                map_span(&(source.len()..source.len())),
+                source,
+                Span::new(source.len(), source.len()),
             )
             .into()
         })
@@ -285,21 +1340,16 @@ impl<'source> ExpectToken<'source> for SpannedToken<'source> {
         if expected.iter().any(|expect| self.token.eq(expect)) {
             Ok(self)
         } else {
-            Err(UnexpectedTokenError::new(
-                expected,
-                Some(self.token),
-                source.into(),
-                map_span(&self.span),
+            let invalid = InvalidToken::from_token(&self, expected);
+            Err(
+                UnexpectedTokenError::new(expected, Some(self.token), source, self.span)
+                    .with_invalid(invalid)
+                    .into(),
             )
-            .into())
         }
     }
 }
 
-fn map_span(span: &Span) -> SourceSpan {
-    SourceSpan::new(SourceOffset::from(span.start), span.end - span.start)
-}
-
 pub trait ResultExt<T> {
     fn with_span(self, span: Span, source: &str) -> Result<T, ParseError>;
 }
@@ -307,10 +1357,11 @@ pub trait ResultExt<T> {
 impl<T, E: Into<PrimitiveErrorKind>> ResultExt<T> for Result<T, E> {
     fn with_span(self, span: Span, source: &str) -> Result<T, ParseError> {
         self.map_err(|error| {
+            let kind: PrimitiveErrorKind = error.into();
             PrimitiveError {
-                src: source.into(),
-                err_span: map_span(&span),
-                kind: error.into(),
+                err_span: kind.narrow_span(span).with_position(source),
+                context: Box::default(),
+                kind,
             }
             .into()
         })
@@ -0,0 +1,680 @@
+use crate::constants::is_class_constant;
+use crate::error::{ExpectToken, ExpressionError, ParseError};
+use crate::lexer::{SpannedToken, Token};
+use crate::parser::Parser;
+use crate::{Array, Key, Object, PhpType, Value};
+
+/// Tokens an expression can be evaluated from
+pub(crate) trait TokenSource<'source> {
+    fn parser(&self) -> &Parser<'source>;
+
+    /// Look ahead `n` tokens past the next one
+    fn peek_nth(&mut self, n: usize) -> Option<&SpannedToken<'source>>;
+
+    fn next_token(&mut self) -> Option<SpannedToken<'source>>;
+
+    /// Parse the array literal starting at the next token
+    fn parse_array(&mut self) -> Result<Value, ParseError>;
+
+    /// Run `f` for a value nested one level deeper, failing when the maximum depth is exceeded
+    fn nested<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, ParseError>,
+    ) -> Result<R, ParseError>;
+}
+
+const OPERAND_TOKENS: &[Token] = &[
+    Token::LiteralString,
+    Token::Integer,
+    Token::Float,
+    Token::Bool,
+    Token::Null,
+    Token::Identifier,
+    Token::BitNot,
+    Token::BracketOpen,
+    Token::SquareOpen,
+    Token::Array,
+];
+
+fn is_binary_operator(token: Token) -> bool {
+    matches!(
+        token,
+        Token::Dot
+            | Token::BitAnd
+            | Token::BitOr
+            | Token::BitXor
+            | Token::ShiftLeft
+            | Token::ShiftRight
+            | Token::Plus
+    )
+}
+
+/// Check if the token can only start a value as part of an expression
+pub(crate) fn starts_expression(parser: &Parser, token: &SpannedToken) -> bool {
+    match token.token {
+        Token::BitNot | Token::BracketOpen => true,
+        Token::Identifier => {
+            is_set_state(token.slice())
+                || (parser.options().lenient && is_class_constant(token.slice()))
+                || resolve_constant(parser, token.slice()).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Check if the next value is an expression that has to be evaluated
+pub(crate) fn is_expression<'source, T: TokenSource<'source>>(tokens: &mut T) -> bool {
+    let first = match tokens.peek_nth(0) {
+        Some(first) => first.clone(),
+        None => return false,
+    };
+    let operand_length = match first.token {
+        _ if starts_expression(tokens.parser(), &first) => return true,
+        Token::LiteralString | Token::Integer | Token::Float | Token::Bool | Token::Null => 1,
+        Token::SquareOpen | Token::Array => match array_length(tokens) {
+            Some(length) => length,
+            None => return false,
+        },
+        _ => return false,
+    };
+    tokens
+        .peek_nth(operand_length)
+        .is_some_and(|next| is_binary_operator(next.token))
+}
+
+/// Check if the next value contains constructs that can't be evaluated, like variables, calls to unknown
+/// functions, unresolved constants or unsupported operators
+///
+/// The values inside array literals are checked on their own, so only the offending value is captured in lenient mode.
+pub(crate) fn is_unsupported<'source, T: TokenSource<'source>>(tokens: &mut T) -> bool {
+    // for each open bracket, whether it's the start of an array literal
+    let mut brackets: Vec<bool> = Vec::new();
+    let mut index = 0;
+    while let Some(token) = tokens.peek_nth(index).cloned() {
+        let in_array = brackets.contains(&true);
+        match token.token {
+            Token::SquareOpen => brackets.push(true),
+            Token::BracketOpen => {
+                let after_array = index > 0
+                    && tokens
+                        .peek_nth(index - 1)
+                        .is_some_and(|previous| previous.token == Token::Array);
+                brackets.push(after_array)
+            }
+            Token::BracketClose | Token::SquareClose if brackets.is_empty() => return false,
+            Token::BracketClose | Token::SquareClose => {
+                brackets.pop();
+            }
+            Token::Comma | Token::SemiColon | Token::Arrow if brackets.is_empty() => return false,
+            _ if in_array => {}
+            Token::Variable | Token::Error | Token::Assign => return true,
+            Token::Identifier if !is_supported_identifier(tokens, index) => return true,
+            _ => {}
+        }
+        index += 1;
+    }
+    false
+}
+
+/// Check if the identifier at `index` is a constant, object cast or function call that can be evaluated
+fn is_supported_identifier<'source, T: TokenSource<'source>>(tokens: &mut T, index: usize) -> bool {
+    let name = match tokens.peek_nth(index) {
+        Some(token) => token.slice(),
+        None => return false,
+    };
+    let is_token = |token: Option<&SpannedToken>, expected: Token| {
+        token.is_some_and(|token| token.token == expected)
+    };
+    if index > 0 && is_token(tokens.peek_nth(index - 1), Token::New) {
+        return true;
+    }
+    if is_token(tokens.peek_nth(index + 1), Token::BracketOpen) {
+        return is_set_state(name) || tokens.parser().options().functions.handles(name);
+    }
+    if name.eq_ignore_ascii_case("object")
+        && index > 0
+        && is_token(tokens.peek_nth(index - 1), Token::BracketOpen)
+        && is_token(tokens.peek_nth(index + 1), Token::BracketClose)
+    {
+        return true;
+    }
+    is_class_constant(name) || resolve_constant(tokens.parser(), name).is_some()
+}
+
+/// Find the number of tokens of the array literal starting at the next token
+///
+/// This looks ahead over the full array, so it's only done for arrays that could be an operand.
+fn array_length<'source, T: TokenSource<'source>>(tokens: &mut T) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut index = 0;
+    loop {
+        match tokens.peek_nth(index)?.token {
+            Token::BracketOpen | Token::SquareOpen => depth += 1,
+            Token::BracketClose | Token::SquareClose if depth <= 1 => return Some(index + 1),
+            Token::BracketClose | Token::SquareClose => depth -= 1,
+            Token::Error => return None,
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+/// Evaluate an expression of literals, constants, string concatenation, bitwise operators and array unions
+///
+/// Operators follow php's precedence, `~` binds tightest followed by `+`, `<<` and `>>`, `.`, `&`, `^` and `|`.
+pub(crate) fn evaluate<'source, T: TokenSource<'source>>(
+    tokens: &mut T,
+) -> Result<Value, ParseError> {
+    binary(tokens, 0)
+}
+
+/// Operators by precedence, from loosest to tightest
+const PRECEDENCE: &[&[Token]] = &[
+    &[Token::BitOr],
+    &[Token::BitXor],
+    &[Token::BitAnd],
+    &[Token::Dot],
+    &[Token::ShiftLeft, Token::ShiftRight],
+    &[Token::Plus],
+];
+
+/// The precedence level of a binary operator
+fn precedence(token: Token) -> Option<usize> {
+    PRECEDENCE
+        .iter()
+        .position(|operators| operators.contains(&token))
+}
+
+/// Evaluate the operators binding at least as tight as `level`
+///
+/// Operators of the same level are applied in a loop, so only nested operands of tighter operators recurse.
+fn binary<'source, T: TokenSource<'source>>(
+    tokens: &mut T,
+    level: usize,
+) -> Result<Value, ParseError> {
+    let mut left = unary(tokens)?;
+    while let Some((operator, operator_level)) = tokens.peek_nth(0).and_then(|token| {
+        let operator_level = precedence(token.token).filter(|found| *found >= level)?;
+        Some((token.clone(), operator_level))
+    }) {
+        tokens.next_token();
+        let right = binary(tokens, operator_level + 1)?;
+        left = apply_operator(operator.token, left, right)
+            .map_err(|message| expression_error(tokens.parser(), &operator, message))?;
+        if let Some(string) = left.as_bytes() {
+            tokens.parser().check_string_length(string.len())?;
+        }
+    }
+    Ok(left)
+}
+
+/// Apply a binary operator to its evaluated operands
+pub(crate) fn apply_operator(operator: Token, left: Value, right: Value) -> Result<Value, String> {
+    match operator {
+        Token::Dot => {
+            let mut string = to_bytes(left, operator)?;
+            string.extend(to_bytes(right, operator)?);
+            return Ok(Value::from_bytes(string));
+        }
+        Token::Plus => return union(left, right, operator),
+        _ => {}
+    }
+    let lhs = to_int(&left, operator)?;
+    let rhs = to_int(&right, operator)?;
+    Ok(Value::Int(match operator {
+        Token::BitOr => lhs | rhs,
+        Token::BitXor => lhs ^ rhs,
+        Token::BitAnd => lhs & rhs,
+        Token::ShiftLeft | Token::ShiftRight if rhs < 0 => {
+            return Err("Bit shift by negative number".into());
+        }
+        Token::ShiftLeft => lhs.checked_shl(rhs as u32).unwrap_or(0),
+        Token::ShiftRight => lhs
+            .checked_shr(rhs as u32)
+            .unwrap_or(if lhs < 0 { -1 } else { 0 }),
+        _ => unreachable!(),
+    }))
+}
+
+/// Apply the `~` operator to its evaluated operand
+pub(crate) fn bit_not(operand: &Value) -> Result<Value, String> {
+    Ok(Value::Int(!to_int(operand, Token::BitNot)?))
+}
+
+fn unary<'source, T: TokenSource<'source>>(tokens: &mut T) -> Result<Value, ParseError> {
+    if tokens
+        .peek_nth(0)
+        .is_some_and(|token| matches!(token.token, Token::SquareOpen | Token::Array))
+    {
+        return tokens.parse_array();
+    }
+    let source = tokens.parser().source();
+    let token = tokens.next_token().expect_token(OPERAND_TOKENS, source)?;
+    match token.token {
+        Token::BitNot => {
+            let operand = tokens.nested(unary)?;
+            bit_not(&operand).map_err(|message| expression_error(tokens.parser(), &token, message))
+        }
+        Token::BracketOpen if is_object_cast(tokens) => {
+            tokens.next_token();
+            tokens.next_token();
+            let operand = tokens.nested(unary)?;
+            Ok(to_object(operand))
+        }
+        Token::BracketOpen => {
+            let value = tokens.nested(|tokens| binary(tokens, 0))?;
+            tokens
+                .next_token()
+                .expect_token(&[Token::BracketClose], source)?;
+            Ok(value)
+        }
+        Token::Identifier if is_set_state(token.slice()) => {
+            tokens
+                .next_token()
+                .expect_token(&[Token::BracketOpen], source)?;
+            let properties = tokens.nested(|tokens| binary(tokens, 0))?;
+            tokens
+                .next_token()
+                .expect_token(&[Token::BracketClose], source)?;
+            let (class, _) = token.slice().rsplit_once("::").unwrap();
+            match properties {
+                Value::Array(properties) => Ok(Value::Object(Object {
+                    class: class.trim_start_matches('\\').into(),
+                    properties,
+                })),
+                _ => Err(expression_error(
+                    tokens.parser(),
+                    &token,
+                    "__set_state expects an array of properties",
+                )),
+            }
+        }
+        Token::Identifier => match resolve_constant(tokens.parser(), token.slice()) {
+            Some(value) => Ok(value),
+            None if tokens.parser().options().lenient && is_class_constant(token.slice()) => {
+                Ok(Value::Expr(token.slice().into()))
+            }
+            None => Err(undefined_constant(tokens.parser(), &token)),
+        },
+        _ => tokens.parser().parse_literal(token),
+    }
+}
+
+/// Check if the identifier is the `__set_state` method `var_export()` uses to restore objects
+fn is_set_state(name: &str) -> bool {
+    matches!(name.rsplit_once("::"), Some((_, method)) if method.eq_ignore_ascii_case("__set_state"))
+}
+
+/// Check if the tokens following an opening bracket are `object)`
+fn is_object_cast<'source, T: TokenSource<'source>>(tokens: &mut T) -> bool {
+    tokens.peek_nth(0).is_some_and(|token| {
+        token.token == Token::Identifier && token.slice().eq_ignore_ascii_case("object")
+    }) && tokens
+        .peek_nth(1)
+        .is_some_and(|token| token.token == Token::BracketClose)
+}
+
+/// Cast a value to an object the way php does, scalars become the `scalar` property of a `stdClass`
+pub(crate) fn to_object(value: Value) -> Value {
+    let properties = match value {
+        Value::Object(_) | Value::Instance(_) | Value::Expr(_) => return value,
+        Value::Array(properties) => properties,
+        Value::Null => Array::new(),
+        value => std::iter::once((Key::from("scalar"), value)).collect(),
+    };
+    Value::Object(Object {
+        class: "stdClass".into(),
+        properties,
+    })
+}
+
+/// Get the value of a constant, `Foo::class` evaluates to the fully qualified name of the class
+///
+/// With [`bareword_strings`](crate::ParserOptions::bareword_strings), unknown plain names evaluate to their name.
+pub(crate) fn resolve_constant(parser: &Parser, name: &str) -> Option<Value> {
+    match name.rsplit_once("::") {
+        Some((class, constant)) if constant.eq_ignore_ascii_case("class") => {
+            Some(Value::String(class.trim_start_matches('\\').into()))
+        }
+        _ => parser.options().constants.get(name).or_else(|| {
+            let is_bareword = !name.contains(['\\', ':']);
+            (parser.options().bareword_strings && is_bareword).then(|| Value::String(name.into()))
+        }),
+    }
+}
+
+/// Add the entries of `right` for keys that aren't in `left` yet, like php's `+` for arrays
+fn union(left: Value, right: Value, operator: Token) -> Result<Value, String> {
+    match (left, right) {
+        (Value::Array(mut left), Value::Array(right)) => {
+            for (key, value) in right {
+                left.entry(key).or_insert(value);
+            }
+            Ok(Value::Array(left))
+        }
+        _ => Err(unsupported_operand(operator)),
+    }
+}
+
+fn unsupported_operand(operator: Token) -> String {
+    format!("Unsupported operand type for {}", operator)
+}
+
+/// Convert an operand of a bitwise operator to an integer
+fn to_int(value: &Value, operator: Token) -> Result<i64, String> {
+    match value {
+        Value::Int(int) => Ok(*int),
+        Value::Bool(_) | Value::Null | Value::Float(_) => match value.cast_to(PhpType::Int) {
+            Some(Value::Int(int)) => Ok(int),
+            _ => unreachable!(),
+        },
+        _ => Err(unsupported_operand(operator)),
+    }
+}
+
+/// Convert an operand of the concatenation operator to the bytes of a string
+fn to_bytes(value: Value, operator: Token) -> Result<Vec<u8>, String> {
+    match value {
+        Value::String(string) => Ok(string.into_bytes()),
+        Value::Bytes(bytes) => Ok(bytes),
+        Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::Null => {
+            match value.cast_to(PhpType::String) {
+                Some(Value::String(string)) => Ok(string.into_bytes()),
+                _ => unreachable!(),
+            }
+        }
+        _ => Err(unsupported_operand(operator)),
+    }
+}
+
+pub(crate) fn undefined_constant(parser: &Parser, token: &SpannedToken) -> ParseError {
+    expression_error(
+        parser,
+        token,
+        format!("Undefined constant \"{}\"", token.slice()),
+    )
+}
+
+pub(crate) fn expression_error(
+    parser: &Parser,
+    token: &SpannedToken,
+    message: impl Into<String>,
+) -> ParseError {
+    ExpressionError::new(parser.source(), token.span.clone(), message.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, from_str_with_options, ParseError, ParserOptions, SyntaxTree, Value};
+    use serde_derive::Deserialize;
+
+    #[test]
+    fn test_bitwise() {
+        let value = from_str::<Value>(
+            "[1 | 2, 6 & 3, 6 ^ 3, ~0, 1 << 3, -16 >> 2, 1 << 64, 1 | 2 << 1 & 7, (1 | 2) << 1, true | 2]",
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            from_str::<Value>("[3, 2, 5, -1, 8, -4, 0, 5, 6, 3]").unwrap()
+        );
+
+        assert!(matches!(
+            from_str::<Value>("1 << -1"),
+            Err(ParseError::Expression(_))
+        ));
+        assert!(matches!(
+            from_str::<Value>("'a' | 1"),
+            Err(ParseError::Expression(_))
+        ));
+        assert!(from_str::<Value>("(1 | 2").is_err());
+        assert!(from_str::<Value>("[1 |]").is_err());
+    }
+
+    #[test]
+    fn test_array_union() {
+        #[derive(Debug, Deserialize)]
+        struct Config {
+            debug: bool,
+            domains: Vec<String>,
+        }
+
+        let options = ParserOptions::new().constant(
+            "DEFAULTS",
+            from_str::<Value>("['debug' => false, 'domains' => ['localhost']]").unwrap(),
+        );
+        let config: Config =
+            from_str_with_options("['debug' => true] + DEFAULTS", &options).unwrap();
+        assert!(config.debug);
+        assert_eq!(config.domains, ["localhost"]);
+
+        let source = "[\n  'list' => [1] + /* defaults */ [2, 3],\n]";
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.to_value(), from_str::<Value>(source).unwrap());
+        assert_eq!(
+            tree.to_value()["list"],
+            from_str::<Value>("[1, 3]").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_objects() {
+        #[derive(Debug, Deserialize)]
+        struct Config {
+            host: String,
+        }
+
+        let source = r#"array (
+  'cache' => 
+  \Foo\Cache::__set_state(array(
+     'host' => 'localhost',
+     'options' => 
+    (object) array(
+       'ttl' => 60,
+    ),
+  )),
+  'scalar' => (object) 'value',
+  'empty' => ( OBJECT ) null,
+)"#;
+        let value = from_str::<Value>(source).unwrap();
+        let cache = value["cache"].as_object().unwrap();
+        assert_eq!(cache.class, "Foo\\Cache");
+        assert_eq!(
+            cache.properties.get("host"),
+            Some(&Value::from("localhost"))
+        );
+        let options = cache
+            .properties
+            .get("options")
+            .unwrap()
+            .as_object()
+            .unwrap();
+        assert!(options.is_std_class());
+        assert_eq!(options.properties.get("ttl"), Some(&Value::Int(60)));
+        assert_eq!(
+            value["scalar"]
+                .as_object()
+                .unwrap()
+                .properties
+                .get("scalar"),
+            Some(&Value::from("value"))
+        );
+        assert!(value["empty"].as_object().unwrap().properties.is_empty());
+        assert_eq!(
+            SyntaxTree::parse(source).unwrap().to_value(),
+            from_str::<Value>(source).unwrap()
+        );
+
+        let config: Config = from_str("\\stdClass::__set_state(['host' => 'localhost'])").unwrap();
+        assert_eq!(config.host, "localhost");
+        let map: std::collections::HashMap<String, i64> = from_str("(object) ['a' => 1]").unwrap();
+        assert_eq!(map["a"], 1);
+
+        assert!(matches!(
+            from_str::<Value>("\\Foo::__set_state(1)"),
+            Err(ParseError::Expression(_))
+        ));
+        assert!(from_str::<Value>("\\Foo::__set_state").is_err());
+        assert!(from_str::<Value>("(object ['a' => 1]").is_err());
+    }
+
+    #[test]
+    fn test_constants() {
+        #[derive(Debug, Deserialize)]
+        struct Config {
+            level: u32,
+            name: String,
+        }
+
+        let options = ParserOptions::new()
+            .constant("E_ALL", 32767)
+            .constant("E_NOTICE", 8)
+            .constant("E_DEPRECATED", 8192)
+            .constant("APP_NAME", "app");
+        let config: Config = from_str_with_options(
+            "['level' => E_ALL & ~(E_NOTICE | \\E_DEPRECATED), 'name' => APP_NAME]",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(config.level, 24567);
+        assert_eq!(config.name, "app");
+
+        assert!(from_str_with_options::<Value>("[APP_NAME | 1]", &options).is_err());
+        assert!(from_str::<Value>("[E_ALL]").is_err());
+    }
+
+    #[test]
+    fn test_builtin_constants() {
+        let options = ParserOptions::new().builtin_constants(true);
+        let value = from_str_with_options::<Value>(
+            "[PHP_INT_MIN, \\M_PI, DIRECTORY_SEPARATOR, E_ALL & ~E_NOTICE & ~E_STRICT]",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(value[0], i64::MIN);
+        assert_eq!(value[1], std::f64::consts::PI);
+        assert_eq!(value[2], "/");
+        assert_eq!(value[3], 30711);
+
+        let options = options.constant("E_ALL", 1);
+        assert_eq!(
+            from_str_with_options::<Value>("E_ALL", &options).unwrap(),
+            1
+        );
+        assert!(from_str_with_options::<Value>("[php_int_max]", &options).is_err());
+        assert!(from_str::<Value>("[PHP_INT_MAX]").is_err());
+    }
+
+    #[test]
+    fn test_constant_resolver() {
+        let options = ParserOptions::new()
+            .builtin_constants(true)
+            .constant("DEFINED", 1)
+            .constant_resolver(|name: &str| match name {
+                "DEFINED" | "PHP_EOL" => Some(Value::Int(2)),
+                "FLAG" => Some(Value::Int(4)),
+                _ => None,
+            });
+        let value = from_str_with_options::<Value>(
+            "[DEFINED, PHP_EOL, \\FLAG | 1, PHP_INT_SIZE]",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(value, from_str::<Value>("[1, 2, 5, 8]").unwrap());
+        assert!(matches!(
+            from_str_with_options::<Value>("[FLAG | MISSING]", &options),
+            Err(ParseError::Expression(_))
+        ));
+
+        let options = options.unresolved_constants_as_strings(true);
+        let value = from_str_with_options::<Value>("[FLAG, MISSING, \\Missing]", &options).unwrap();
+        assert_eq!(
+            value,
+            from_str::<Value>("[4, 'MISSING', 'Missing']").unwrap()
+        );
+        let options = ParserOptions::new()
+            .unresolved_constants_as_strings(true)
+            .function("upper", |args| {
+                Ok(args[0].to_string().to_uppercase().into())
+            });
+        assert_eq!(
+            from_str_with_options::<Value>("upper(foo)", &options).unwrap(),
+            "FOO"
+        );
+    }
+
+    #[test]
+    fn test_class_constants() {
+        let options = ParserOptions::new()
+            .constant("Monolog\\Logger::WARNING", 300)
+            .constant_resolver(|name: &str| (name == "App\\Flags::ALL").then_some(Value::Int(7)))
+            .unresolved_constants_as_strings(true);
+        let value = from_str_with_options::<Value>(
+            "[\\Monolog\\Logger::WARNING, App\\Flags::ALL & ~1]",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(value, from_str::<Value>("[300, 6]").unwrap());
+        assert!(from_str_with_options::<Value>("[Foo::BAR]", &options).is_err());
+
+        let options = options.lenient(true);
+        let value = from_str_with_options::<Value>("['level' => Foo::BAR]", &options).unwrap();
+        assert_eq!(value["level"], Value::Expr("Foo::BAR".into()));
+    }
+
+    #[test]
+    fn test_class_name() {
+        use std::collections::HashMap;
+
+        let source = r"[
+            'factories' => [
+                \App\Service::class => App\Factory::CLASS,
+                Foo::class => 'bar',
+            ],
+        ]";
+        let factories: HashMap<String, HashMap<String, String>> = from_str(source).unwrap();
+        assert_eq!(factories["factories"]["App\\Service"], "App\\Factory");
+        assert_eq!(factories["factories"]["Foo"], "bar");
+        assert_eq!(
+            SyntaxTree::parse(source).unwrap().to_value(),
+            from_str::<Value>(source).unwrap()
+        );
+
+        let options = ParserOptions::new().constant("ARRAY", Value::Array(Default::default()));
+        assert!(matches!(
+            from_str_with_options::<Value>("[ARRAY => 1]", &options),
+            Err(ParseError::Expression(_))
+        ));
+        assert!(from_str::<Value>("[MISSING => 1]").is_err());
+    }
+
+    #[test]
+    fn test_magic_constants() {
+        let options = ParserOptions::new().file_path("/var/www/config.php");
+        let value = from_str_with_options::<Value>(
+            "[__DIR__ . '/data', __file__, 'log' . 1 << 2 . '.txt']",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(value[0], "/var/www/data");
+        assert_eq!(value[1], "/var/www/config.php");
+        assert_eq!(value[2], "log4.txt");
+
+        let options = ParserOptions::new().file_path("config.php");
+        assert_eq!(
+            from_str_with_options::<Value>("__DIR__", &options).unwrap(),
+            "."
+        );
+        assert!(from_str::<Value>("[__DIR__ . '/data']").is_err());
+    }
+
+    #[test]
+    fn test_syntax_tree() {
+        let source = "[ 'flags' => 1 /* a */ | 1 << 2, 'x' => ~ 0 ]";
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.to_value()["flags"], 5);
+        assert_eq!(tree.to_value()["x"], -1);
+    }
+}
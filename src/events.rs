@@ -0,0 +1,237 @@
+use crate::array::ArrayBuilder;
+use crate::serde_impl::{Deserializer, EventCursor};
+use crate::{Key, ParseError, ParserOptions, Span, Value};
+
+/// Parse a php literal as a sequence of events
+///
+/// Arrays are reported as an [`Event::ArrayStart`], followed by an [`Event::Key`] and the events of the value
+/// for each entry, and an [`Event::ArrayEnd`]. Each event comes with the span of the source it was parsed from.
+///
+/// Events are only parsed once they're requested, so large arrays can be processed without
+/// building a [`Value`] for the entire array.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{events_from_str, Event, Key, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let source = r#"["foo" => [true]]"#;
+/// let events = events_from_str(source)
+///     .map(|event| event.map(|(event, _span)| event))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// assert_eq!(
+///     events,
+///     vec![
+///         Event::ArrayStart,
+///         Event::Key(Key::from("foo")),
+///         Event::ArrayStart,
+///         Event::Key(Key::Int(0)),
+///         Event::Scalar(Value::Bool(true)),
+///         Event::ArrayEnd,
+///         Event::ArrayEnd,
+///     ]
+/// );
+///
+/// let (_, span) = events_from_str(source).nth(1).unwrap()?;
+/// assert_eq!(&source[span.start()..span.end()], r#""foo""#);
+/// # Ok(())
+/// # }
+/// ```
+pub fn events_from_str(s: &str) -> Events<'_> {
+    events_from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a php literal as a sequence of events using the provided options
+pub fn events_from_str_with_options<'a>(s: &'a str, options: &ParserOptions) -> Events<'a> {
+    Events {
+        de: Deserializer::with_options(s, options.clone()),
+        cursor: EventCursor::default(),
+        finished: false,
+    }
+}
+
+/// Parse a php literal into a [`Value`] without recursing for nested arrays
+///
+/// Unlike [`from_str`](crate::from_str), which recurses for every nesting level through serde,
+/// nested arrays are built from the [events](events_from_str) of the literal with an explicit stack,
+/// so parsing deeper input doesn't use more stack. Values inside expressions are still parsed recursively.
+///
+/// The depth is still limited by [`ParserOptions::max_depth`], since dropping a [`Value`] recurses for every nesting level.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{value_from_str_with_options, ParserOptions};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let source = format!("{}1{}", "[".repeat(1000), "]".repeat(1000));
+/// let options = ParserOptions::new().max_depth(1000);
+/// let value = value_from_str_with_options(&source, &options)?;
+///
+/// assert!(value.is_array());
+/// # Ok(())
+/// # }
+/// ```
+pub fn value_from_str(s: &str) -> Result<Value, ParseError> {
+    value_from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a php literal into a [`Value`] without recursing for nested arrays using the provided options,
+/// see [`value_from_str`]
+pub fn value_from_str_with_options(s: &str, options: &ParserOptions) -> Result<Value, ParseError> {
+    // the open arrays, with the key of the array in its parent
+    let mut stack: Vec<(ArrayBuilder, Option<Key>)> = Vec::new();
+    let mut key = None;
+    let mut result = None;
+    for event in events_from_str_with_options(s, options) {
+        let value = match event?.0 {
+            Event::ArrayStart => {
+                stack.push((ArrayBuilder::new(), key.take()));
+                continue;
+            }
+            Event::Key(next) => {
+                key = Some(next);
+                continue;
+            }
+            Event::Scalar(value) => value,
+            Event::ArrayEnd => {
+                let (array, parent_key) = stack.pop().expect("array end without array start");
+                key = parent_key;
+                Value::Array(array.build())
+            }
+        };
+        match stack.last_mut() {
+            Some((array, _)) => array.insert(key.take().expect("array entry without key"), value),
+            None => result = Some(value),
+        }
+    }
+    // the events end with an error or after the complete value
+    Ok(result.expect("events ended before the value was complete"))
+}
+
+/// An event emitted while parsing a php literal, see [`events_from_str`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The start of an array
+    ArrayStart,
+    /// The key of the next array entry, for entries without an explicit key the span is the span of the value
+    Key(Key),
+    /// Any value that isn't an array
+    ///
+    /// Expressions are evaluated into a single value, the span covers the entire expression.
+    /// Arrays that are the result of an expression or unpacked with `...` are reported as events
+    /// with the span of the expression.
+    Scalar(Value),
+    /// The end of an array
+    ArrayEnd,
+}
+
+/// Iterator over the events of a php literal, created by [`events_from_str`]
+///
+/// Iteration stops after the first error.
+pub struct Events<'de> {
+    de: Deserializer<'de>,
+    cursor: EventCursor,
+    finished: bool,
+}
+
+impl Iterator for Events<'_> {
+    type Item = Result<(Event, Span), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.de.next_event(&mut self.cursor).transpose();
+        self.finished = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Event::*;
+    use crate::{events_from_str, from_str, value_from_str, Event, Key, ParseError, Value};
+
+    fn events(source: &str) -> Result<Vec<(Event, &str)>, ParseError> {
+        events_from_str(source)
+            .map(|event| event.map(|(event, span)| (event, &source[span.start()..span.end()])))
+            .collect()
+    }
+
+    #[test]
+    fn test_events() {
+        assert_eq!(
+            events(r#"array(1, "a" => [], 5 => 'a' . 'b', [1] + [2, 3],);"#).unwrap(),
+            vec![
+                (ArrayStart, "array("),
+                (Key(Key::Int(0)), "1"),
+                (Scalar(Value::Int(1)), "1"),
+                (Key(Key::from("a")), r#""a""#),
+                (ArrayStart, "["),
+                (ArrayEnd, "]"),
+                (Key(Key::Int(5)), "5"),
+                (Scalar(Value::from("ab")), "'a' . 'b'"),
+                (Key(Key::Int(6)), "["),
+                (ArrayStart, "[1] + [2, 3]"),
+                (Key(Key::Int(0)), "[1] + [2, 3]"),
+                (Scalar(Value::Int(1)), "[1] + [2, 3]"),
+                (Key(Key::Int(1)), "[1] + [2, 3]"),
+                (Scalar(Value::Int(3)), "[1] + [2, 3]"),
+                (ArrayEnd, "[1] + [2, 3]"),
+                (ArrayEnd, ")"),
+            ]
+        );
+
+        // without `preserve_order` the order of the unpacked array isn't known and it's unpacked in sorted key order
+        let mut unpacked = vec![
+            (Key(Key::from("a")), "..."),
+            (Scalar(Value::Int(2)), "..."),
+            (Key(Key::Int(1)), "..."),
+            (Scalar(Value::Int(3)), "..."),
+        ];
+        if cfg!(not(feature = "preserve_order")) {
+            unpacked.rotate_left(2);
+        }
+        let mut expected = vec![
+            (ArrayStart, "["),
+            (Key(Key::Int(0)), "1"),
+            (Scalar(Value::Int(1)), "1"),
+        ];
+        expected.extend(unpacked);
+        expected.push((ArrayEnd, "]"));
+        assert_eq!(events(r#"[1, ...['a' => 2, 3]]"#).unwrap(), expected);
+        assert_eq!(
+            events("'a'").unwrap(),
+            vec![(Scalar(Value::from("a")), "'a'")]
+        );
+
+        let mut iter = events_from_str("[1, 'a' => ] 2");
+        assert!(iter.by_ref().take(4).all(|event| event.is_ok()));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        assert!(events("[1] 2").is_err());
+        assert!(events("[1 2]").is_err());
+    }
+
+    #[test]
+    fn test_value_from_str() {
+        for source in [
+            "'a'",
+            "[]",
+            r#"array(1, "a" => [[]], 5 => 'a' . 'b', [1] + [2, 3], ...['x' => [1], 4], 'a' => 2);"#,
+        ] {
+            assert_eq!(
+                value_from_str(source).unwrap(),
+                from_str::<Value>(source).unwrap()
+            );
+        }
+        assert!(value_from_str("[1] 2").is_err());
+        assert!(value_from_str("[[1]").is_err());
+    }
+}
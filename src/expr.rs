@@ -0,0 +1,610 @@
+use crate::array::ArrayBuilder;
+use crate::error::{ExpectToken, NestingLimitError, ResultExt, TrailingError};
+use crate::eval::{apply_operator, bit_not, to_object};
+use crate::lexer::{SpannedToken, Token};
+use crate::parser::{key_from_value, Parser};
+use crate::string::{parse_interpolated, StringPart};
+use crate::{Array, Instance, Object, ParseError, ParserOptions, Span, Value};
+use std::collections::VecDeque;
+
+/// A parsed php expression that hasn't been evaluated yet
+///
+/// Parsing an expression with [`expr_from_str`] doesn't resolve constants, variables or function calls,
+/// the caller decides how to handle these when calling [`Expr::evaluate`] with a [`Resolver`].
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{expr_from_str, Expr, Resolver, Value};
+/// # use std::error::Error;
+///
+/// struct Env;
+///
+/// impl Resolver for Env {
+///     fn constant(&self, name: &str) -> Option<Value> {
+///         (name == "ROOT").then(|| Value::from("/var/www"))
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let expr = expr_from_str("['data' => ROOT . '/data']")?;
+/// let Expr::Array(entries) = &expr else { panic!() };
+/// assert_eq!(
+///     entries[0].value,
+///     Expr::Concat(
+///         Box::new(Expr::Constant("ROOT".into())),
+///         Box::new(Expr::Literal("/data".into())),
+///     )
+/// );
+///
+/// let value = expr.evaluate(&Env)?;
+/// assert_eq!(value["data"], "/var/www/data");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A scalar literal
+    Literal(Value),
+    /// An array literal
+    Array(Vec<ArrayEntry>),
+    /// A constant or class constant like `PHP_EOL` or `Foo::BAR`
+    Constant(String),
+    /// A variable, without the leading `$`
+    Variable(String),
+    /// A function call
+    Call { name: String, args: Vec<Expr> },
+    /// String concatenation with `.`
+    Concat(Box<Expr>, Box<Expr>),
+    /// A bitwise operator or array union
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Bitwise negation with `~`
+    BitNot(Box<Expr>),
+    /// An `(object)` cast
+    ObjectCast(Box<Expr>),
+    /// An object restored with `Class::__set_state(<properties>)`
+    SetState {
+        class: String,
+        properties: Box<Expr>,
+    },
+    /// An object instantiation, `new Class(args)`
+    New { class: String, args: Vec<Expr> },
+}
+
+/// An entry of an array literal
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayEntry {
+    /// The explicit key of the entry
+    pub key: Option<Expr>,
+    pub value: Expr,
+    /// Whether the value is unpacked into the array with `...`
+    pub spread: bool,
+}
+
+/// Binary operators other than concatenation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    BitOr,
+    BitXor,
+    BitAnd,
+    ShiftLeft,
+    ShiftRight,
+    /// Array union with `+`
+    Union,
+}
+
+impl BinaryOp {
+    fn from_token(token: Token) -> Option<Self> {
+        Some(match token {
+            Token::BitOr => BinaryOp::BitOr,
+            Token::BitXor => BinaryOp::BitXor,
+            Token::BitAnd => BinaryOp::BitAnd,
+            Token::ShiftLeft => BinaryOp::ShiftLeft,
+            Token::ShiftRight => BinaryOp::ShiftRight,
+            Token::Plus => BinaryOp::Union,
+            _ => return None,
+        })
+    }
+
+    fn token(self) -> Token {
+        match self {
+            BinaryOp::BitOr => Token::BitOr,
+            BinaryOp::BitXor => Token::BitXor,
+            BinaryOp::BitAnd => Token::BitAnd,
+            BinaryOp::ShiftLeft => Token::ShiftLeft,
+            BinaryOp::ShiftRight => Token::ShiftRight,
+            BinaryOp::Union => Token::Plus,
+        }
+    }
+}
+
+/// Provides the values of constants, variables and function calls when evaluating an [`Expr`]
+///
+/// All methods return `None` by default, which fails the evaluation. [`ParserOptions`] implements this
+/// trait using its defined constants, variables and function handlers.
+pub trait Resolver {
+    /// Get the value of a constant
+    fn constant(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Get the value of a variable, the name is passed without `$`
+    fn variable(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Call a function with evaluated arguments, `None` if the function is unknown
+    fn call(&self, _name: &str, _args: &[Value]) -> Option<Result<Value, String>> {
+        None
+    }
+}
+
+impl Resolver for ParserOptions {
+    fn constant(&self, name: &str) -> Option<Value> {
+        self.constants.get(name)
+    }
+
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.variables.as_ref()?.get(name).cloned()
+    }
+
+    fn call(&self, name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+        self.functions
+            .handles(name)
+            .then(|| self.functions.call(name, args))
+    }
+}
+
+impl Expr {
+    /// Evaluate the expression, using `resolver` for constants, variables and function calls
+    ///
+    /// `Foo::class` is evaluated to the name of the class without consulting the resolver.
+    pub fn evaluate<R: Resolver + ?Sized>(&self, resolver: &R) -> Result<Value, String> {
+        Ok(match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Array(entries) => Value::Array(evaluate_array(entries, resolver)?),
+            Expr::Constant(name) => match name.rsplit_once("::") {
+                Some((class, constant)) if constant.eq_ignore_ascii_case("class") => {
+                    Value::String(class.trim_start_matches('\\').into())
+                }
+                _ => resolver
+                    .constant(name)
+                    .ok_or_else(|| format!("Undefined constant \"{}\"", name))?,
+            },
+            Expr::Variable(name) => resolver
+                .variable(name)
+                .ok_or_else(|| format!("Undefined variable ${}", name))?,
+            Expr::Call { name, args } => {
+                let args = evaluate_all(args, resolver)?;
+                resolver
+                    .call(name, &args)
+                    .unwrap_or_else(|| Err(format!("Call to undefined function {}()", name)))?
+            }
+            Expr::Concat(left, right) => apply_operator(
+                Token::Dot,
+                left.evaluate(resolver)?,
+                right.evaluate(resolver)?,
+            )?,
+            Expr::Binary(op, left, right) => apply_operator(
+                op.token(),
+                left.evaluate(resolver)?,
+                right.evaluate(resolver)?,
+            )?,
+            Expr::BitNot(operand) => bit_not(&operand.evaluate(resolver)?)?,
+            Expr::ObjectCast(operand) => to_object(operand.evaluate(resolver)?),
+            Expr::SetState { class, properties } => match properties.evaluate(resolver)? {
+                Value::Array(properties) => Value::Object(Object {
+                    class: class.clone(),
+                    properties,
+                }),
+                _ => return Err("__set_state expects an array of properties".into()),
+            },
+            Expr::New { class, args } => Value::Instance(Instance {
+                class: class.clone(),
+                args: evaluate_all(args, resolver)?,
+            }),
+        })
+    }
+}
+
+fn evaluate_all<R: Resolver + ?Sized>(exprs: &[Expr], resolver: &R) -> Result<Vec<Value>, String> {
+    exprs.iter().map(|expr| expr.evaluate(resolver)).collect()
+}
+
+fn evaluate_array<R: Resolver + ?Sized>(
+    entries: &[ArrayEntry],
+    resolver: &R,
+) -> Result<Array, String> {
+    let mut array = ArrayBuilder::with_capacity(entries.len());
+    for entry in entries {
+        let value = entry.value.evaluate(resolver)?;
+        if entry.spread {
+            match value {
+                Value::Array(unpacked) => array.unpack(unpacked)?,
+                _ => return Err("Only arrays can be unpacked".into()),
+            }
+            continue;
+        }
+        match &entry.key {
+            Some(key) => {
+                let key = key_from_value(key.evaluate(resolver)?)
+                    .ok_or_else(|| String::from("Illegal offset type"))?;
+                array.insert(key, value);
+            }
+            None => array.push(value)?,
+        }
+    }
+    Ok(array.build())
+}
+
+/// Parse a php expression without evaluating it
+///
+/// See [`Expr`] for an example.
+pub fn expr_from_str(s: &str) -> Result<Expr, ParseError> {
+    expr_from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a php expression without evaluating it using the provided options
+///
+/// Only the options that limit the size of the input apply, constants, variables and function calls are kept in the expression.
+pub fn expr_from_str_with_options(s: &str, options: &ParserOptions) -> Result<Expr, ParseError> {
+    let mut parser = ExprParser {
+        parser: Parser::new(s, options.clone()),
+        peeked: VecDeque::new(),
+        depth: 0,
+    };
+    let expr = parser.binary(0)?;
+    if parser.peek_is(0, Token::SemiColon) {
+        parser.next_token();
+    }
+    match parser.next_token() {
+        None => Ok(expr),
+        Some(token) => Err(TrailingError::new(s, &token).into()),
+    }
+}
+
+const OPERAND_TOKENS: &[Token] = &[
+    Token::LiteralString,
+    Token::Integer,
+    Token::Float,
+    Token::Bool,
+    Token::Null,
+    Token::Identifier,
+    Token::Variable,
+    Token::New,
+    Token::BitNot,
+    Token::BracketOpen,
+    Token::SquareOpen,
+    Token::Array,
+];
+
+/// Operators by precedence, from loosest to tightest
+const PRECEDENCE: &[&[Token]] = &[
+    &[Token::BitOr],
+    &[Token::BitXor],
+    &[Token::BitAnd],
+    &[Token::Dot],
+    &[Token::ShiftLeft, Token::ShiftRight],
+    &[Token::Plus],
+];
+
+struct ExprParser<'source> {
+    parser: Parser<'source>,
+    peeked: VecDeque<SpannedToken<'source>>,
+    depth: usize,
+}
+
+impl<'source> ExprParser<'source> {
+    fn source(&self) -> &'source str {
+        self.parser.source()
+    }
+
+    fn next_token(&mut self) -> Option<SpannedToken<'source>> {
+        self.peeked.pop_front().or_else(|| self.parser.next_token())
+    }
+
+    fn peek(&mut self) -> Option<&SpannedToken<'source>> {
+        if self.peeked.is_empty() {
+            let next = self.parser.next_token()?;
+            self.peeked.push_back(next);
+        }
+        self.peeked.front()
+    }
+
+    fn peek_is(&mut self, n: usize, token: Token) -> bool {
+        while self.peeked.len() <= n {
+            match self.parser.next_token() {
+                Some(next) => self.peeked.push_back(next),
+                None => return false,
+            }
+        }
+        self.peeked[n].token == token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<SpannedToken<'source>, ParseError> {
+        let source = self.source();
+        self.next_token().expect_token(&[token], source)
+    }
+
+    /// Parse the operators binding at least as tight as `level`
+    fn binary(&mut self, level: usize) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        while let Some((operator, operator_level)) = PRECEDENCE
+            .iter()
+            .enumerate()
+            .skip(level)
+            .find_map(|(found, operators)| {
+                let operator = operators.iter().find(|&&op| self.peek_is(0, op))?;
+                Some((*operator, found))
+            })
+        {
+            self.next_token();
+            let right = Box::new(self.binary(operator_level + 1)?);
+            left = match BinaryOp::from_token(operator) {
+                Some(op) => Expr::Binary(op, Box::new(left), right),
+                None => Expr::Concat(Box::new(left), right),
+            };
+        }
+        Ok(left)
+    }
+
+    /// Parse an operand, failing when operands are nested deeper than the maximum depth
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let limit = self.parser.options().depth_limit();
+        if self.depth >= limit {
+            let source = self.source();
+            let span = self.peek().map_or_else(
+                || Span::new(source.len(), source.len()),
+                |token| token.span.clone(),
+            );
+            return Err(NestingLimitError::new(limit, source, span).into());
+        }
+        self.depth += 1;
+        let result = self.operand();
+        self.depth -= 1;
+        result
+    }
+
+    fn operand(&mut self) -> Result<Expr, ParseError> {
+        let source = self.source();
+        let token = self.next_token().expect_token(OPERAND_TOKENS, source)?;
+        Ok(match token.token {
+            Token::BitNot => Expr::BitNot(Box::new(self.unary()?)),
+            Token::BracketOpen if self.is_object_cast() => {
+                self.next_token();
+                self.next_token();
+                Expr::ObjectCast(Box::new(self.unary()?))
+            }
+            Token::BracketOpen => {
+                let expr = self.binary(0)?;
+                self.expect(Token::BracketClose)?;
+                expr
+            }
+            Token::SquareOpen => Expr::Array(self.entries(Token::SquareClose)?),
+            Token::Array => {
+                self.expect(Token::BracketOpen)?;
+                Expr::Array(self.entries(Token::BracketClose)?)
+            }
+            Token::New => {
+                let class = self.expect(Token::Identifier)?.slice().to_string();
+                let args = if self.peek_is(0, Token::BracketOpen) {
+                    self.next_token();
+                    self.arguments()?
+                } else {
+                    Vec::new()
+                };
+                Expr::New { class, args }
+            }
+            Token::Identifier if self.peek_is(0, Token::BracketOpen) => {
+                self.next_token();
+                let name = token.slice();
+                match name.rsplit_once("::") {
+                    Some((class, method)) if method.eq_ignore_ascii_case("__set_state") => {
+                        let properties = Box::new(self.binary(0)?);
+                        self.expect(Token::BracketClose)?;
+                        Expr::SetState {
+                            class: class.trim_start_matches('\\').into(),
+                            properties,
+                        }
+                    }
+                    _ => Expr::Call {
+                        name: name.into(),
+                        args: self.arguments()?,
+                    },
+                }
+            }
+            Token::Identifier => Expr::Constant(token.slice().into()),
+            Token::Variable => Expr::Variable(token.slice()[1..].into()),
+            Token::LiteralString => string(&self.parser, token)?,
+            _ => Expr::Literal(self.parser.parse_literal(token)?),
+        })
+    }
+
+    fn is_object_cast(&mut self) -> bool {
+        self.peek_is(0, Token::Identifier)
+            && self.peeked[0].slice().eq_ignore_ascii_case("object")
+            && self.peek_is(1, Token::BracketClose)
+    }
+
+    /// Parse the arguments of a call up to and including the closing bracket
+    fn arguments(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut args = Vec::new();
+        while !self.peek_is(0, Token::BracketClose) {
+            args.push(self.binary(0)?);
+            if !self.peek_is(0, Token::BracketClose) {
+                self.expect(Token::Comma)?;
+            }
+        }
+        self.next_token();
+        Ok(args)
+    }
+
+    /// Parse the entries of an array literal up to and including the closing bracket
+    fn entries(&mut self, close: Token) -> Result<Vec<ArrayEntry>, ParseError> {
+        let mut entries = Vec::new();
+        while !self.peek_is(0, close) {
+            self.parser.count_entry(entries.len() + 1)?;
+            let spread = self.peek_is(0, Token::Ellipsis);
+            if spread {
+                self.next_token();
+            }
+            let mut key = None;
+            let mut value = self.binary(0)?;
+            if !spread && self.peek_is(0, Token::Arrow) {
+                self.next_token();
+                key = Some(std::mem::replace(&mut value, self.binary(0)?));
+            }
+            entries.push(ArrayEntry { key, value, spread });
+            if !self.peek_is(0, close) {
+                let source = self.source();
+                self.next_token()
+                    .expect_token(&[Token::Comma, close], source)?;
+            }
+        }
+        self.next_token();
+        Ok(entries)
+    }
+}
+
+/// Parse a string literal, splitting interpolated variables into concatenations
+fn string(parser: &Parser, token: SpannedToken) -> Result<Expr, ParseError> {
+    let parts = parse_interpolated(parser.literal_bytes(&token))
+        .with_span(token.span.clone(), token.source)?;
+    let mut parts = parts
+        .into_iter()
+        .map(|part| match part {
+            StringPart::Text(text) => {
+                parser.check_string_length(text.len())?;
+                Ok(Expr::Literal(Value::from_bytes(text)))
+            }
+            StringPart::Variable(name) => Ok(Expr::Variable(name)),
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?
+        .into_iter();
+    let first = parts
+        .next()
+        .unwrap_or(Expr::Literal(Value::String(String::new())));
+    Ok(parts.fold(first, |left, right| {
+        Expr::Concat(Box::new(left), Box::new(right))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArrayEntry, BinaryOp, Expr, Resolver};
+    use crate::{expr_from_str, from_str, ParserOptions, Value};
+
+    #[test]
+    fn test_parse_expr() {
+        assert_eq!(
+            expr_from_str("FLAG | 1 << 2").unwrap(),
+            Expr::Binary(
+                BinaryOp::BitOr,
+                Box::new(Expr::Constant("FLAG".into())),
+                Box::new(Expr::Binary(
+                    BinaryOp::ShiftLeft,
+                    Box::new(Expr::Literal(Value::Int(1))),
+                    Box::new(Expr::Literal(Value::Int(2))),
+                )),
+            )
+        );
+        assert_eq!(
+            expr_from_str(r#"getenv("HOME") . "/$dir";"#).unwrap(),
+            Expr::Concat(
+                Box::new(Expr::Call {
+                    name: "getenv".into(),
+                    args: vec![Expr::Literal("HOME".into())],
+                }),
+                Box::new(Expr::Concat(
+                    Box::new(Expr::Literal("/".into())),
+                    Box::new(Expr::Variable("dir".into())),
+                )),
+            )
+        );
+        assert_eq!(
+            expr_from_str("array('a' => 1, ...$rest,)").unwrap(),
+            Expr::Array(vec![
+                ArrayEntry {
+                    key: Some(Expr::Literal("a".into())),
+                    value: Expr::Literal(Value::Int(1)),
+                    spread: false,
+                },
+                ArrayEntry {
+                    key: None,
+                    value: Expr::Variable("rest".into()),
+                    spread: true,
+                },
+            ])
+        );
+        assert!(matches!(
+            expr_from_str("(object) ['a' => 1]").unwrap(),
+            Expr::ObjectCast(_)
+        ));
+        assert!(matches!(
+            expr_from_str("\\Foo::__set_state([])").unwrap(),
+            Expr::SetState { class, .. } if class == "Foo"
+        ));
+        assert!(matches!(
+            expr_from_str("new Foo(1, [2])").unwrap(),
+            Expr::New { args, .. } if args.len() == 2
+        ));
+
+        assert!(expr_from_str("1 |").is_err());
+        assert!(expr_from_str("[1, 2").is_err());
+        assert!(expr_from_str("foo(1 2)").is_err());
+        assert!(expr_from_str("1 2").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        struct Env;
+
+        impl Resolver for Env {
+            fn variable(&self, name: &str) -> Option<Value> {
+                (name == "rest").then(|| from_str("['b' => 2, 3]").unwrap())
+            }
+
+            fn call(&self, name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+                (name == "strtoupper").then(|| Ok(args[0].as_str().unwrap().to_uppercase().into()))
+            }
+        }
+
+        let expr =
+            expr_from_str("['a' => strtoupper('x') . Foo::class, ...$rest, ~0, [1] + [2, 3]]")
+                .unwrap();
+        assert_eq!(
+            expr.evaluate(&Env).unwrap(),
+            from_str::<Value>("['a' => 'XFoo', 'b' => 2, 3, -1, [1, 3]]").unwrap()
+        );
+
+        assert_eq!(
+            expr_from_str("MISSING").unwrap().evaluate(&Env),
+            Err("Undefined constant \"MISSING\"".into())
+        );
+        assert_eq!(
+            expr_from_str("foo()").unwrap().evaluate(&Env),
+            Err("Call to undefined function foo()".into())
+        );
+        // implicit keys continue after the largest integer key, like in the parser
+        assert_eq!(
+            expr_from_str("[5 => 'a', 2 => 'b', 'c', ...$rest, 'd']")
+                .unwrap()
+                .evaluate(&Env)
+                .unwrap(),
+            from_str::<Value>("[5 => 'a', 2 => 'b', 6 => 'c', 'b' => 2, 7 => 3, 8 => 'd']")
+                .unwrap()
+        );
+        assert!(expr_from_str("'a' | 1").unwrap().evaluate(&Env).is_err());
+        assert!(expr_from_str("[[] => 1]").unwrap().evaluate(&Env).is_err());
+
+        let options = ParserOptions::new()
+            .constant("ROOT", "/srv")
+            .variable("name", "app");
+        assert_eq!(
+            expr_from_str("ROOT . \"/$name\"")
+                .unwrap()
+                .evaluate(&options),
+            Ok(Value::from("/srv/app"))
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::array::{normalize_key, ArrayExt};
+use crate::{Array, Key, KeyRef, Value};
+use std::collections::BTreeMap;
+
+impl Value {
+    /// Flatten nested arrays into a map with dotted keys
+    ///
+    /// Every non-array value and every empty array becomes an entry keyed by the path of array keys leading to it,
+    /// joined with `.`. Dots and backslashes inside a key are escaped with a backslash, so `['a.b' => 1]` is
+    /// flattened to `a\.b` and [`unflatten`](Value::unflatten) can restore the original keys.
+    /// A value that isn't an array is returned under an empty key.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = from_str::<Value>(r#"["redis" => ["cluster" => [["host" => "a"], ["host" => "b"]]]]"#)?;
+    /// let flat = value.flatten();
+    ///
+    /// assert_eq!(flat["redis.cluster.0.host"], "a");
+    /// assert_eq!(flat["redis.cluster.1.host"], "b");
+    /// assert_eq!(Value::unflatten(flat), value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flatten(&self) -> BTreeMap<String, Value> {
+        let mut result = BTreeMap::new();
+        match self {
+            Value::Array(map) if !map.is_empty() => flatten_into(&mut result, "", map),
+            value => {
+                result.insert(String::new(), value.clone());
+            }
+        }
+        result
+    }
+
+    /// Build nested arrays from a map with dotted keys, the inverse of [`flatten`](Value::flatten)
+    ///
+    /// Paths are split on `.`, a backslash escapes the character following it.
+    /// Numeric path segments become integer keys, following the php rules for array keys.
+    /// When a path goes through a non-array value, that value is replaced by an array.
+    pub fn unflatten<I, K>(entries: I) -> Value
+    where
+        I: IntoIterator<Item = (K, Value)>,
+        K: AsRef<str>,
+    {
+        let mut result = Value::Array(Array::new());
+        for (path, value) in entries {
+            let path = path.as_ref();
+            if path.is_empty() {
+                result = value;
+            } else {
+                result.set_path(split_path(path), value);
+            }
+        }
+        result
+    }
+
+    /// Set a nested value, creating intermediate arrays as needed
+    ///
+    /// Numeric path segments become integer keys, following the php rules for array keys.
+    /// When a path goes through a non-array value, that value is replaced by an array.
+    /// An empty path replaces the value itself.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::Value;
+    ///
+    /// let mut config = Value::Null;
+    /// config.set_path(["redis", "cluster", "0", "host"], "localhost".into());
+    ///
+    /// assert_eq!(config["redis"]["cluster"][0]["host"], "localhost");
+    /// ```
+    pub fn set_path<I, K>(&mut self, path: I, value: Value)
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        *self.path_mut(path) = value;
+    }
+
+    fn path_mut<I, K>(&mut self, path: I) -> &mut Value
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let mut target = self;
+        for segment in path {
+            if !target.is_array() {
+                *target = Value::Array(Array::new());
+            }
+            let map = match target {
+                Value::Array(map) => map,
+                _ => unreachable!(),
+            };
+            target = map
+                .entry(segment_key(segment.as_ref()))
+                .or_insert(Value::Null);
+        }
+        target
+    }
+}
+
+/// Recursively move entries with dotted keys into nested arrays
+///
+/// Entries without dots are inserted first so dotted keys are merged into existing arrays,
+/// dotted keys are applied in sorted order to keep the result independent of the hash map order.
+pub(crate) fn expand_dotted_keys(value: Value) -> Value {
+    let map = match value {
+        Value::Array(map) => map,
+        value => return value,
+    };
+    let (mut dotted, plain): (Vec<_>, Vec<_>) = map
+        .into_iter()
+        .partition(|(key, _)| matches!(key, Key::String(key) if key.contains('.')));
+    dotted.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut result = Value::Array(
+        plain
+            .into_iter()
+            .map(|(key, value)| (key, expand_dotted_keys(value)))
+            .collect(),
+    );
+    for (key, value) in dotted {
+        let target = result.path_mut(key.to_string().split('.'));
+        *target = merge(
+            std::mem::replace(target, Value::Null),
+            expand_dotted_keys(value),
+        );
+    }
+    result
+}
+
+/// Merge two values, entries from `value` take precedence
+fn merge(existing: Value, value: Value) -> Value {
+    match (existing, value) {
+        (Value::Array(mut existing), Value::Array(map)) => {
+            for (key, value) in map {
+                let merged = match existing.remove_key(&key) {
+                    Some(current) => merge(current, value),
+                    None => value,
+                };
+                existing.insert(key, merged);
+            }
+            Value::Array(existing)
+        }
+        (_, value) => value,
+    }
+}
+
+pub(crate) fn segment_key(segment: &str) -> Key {
+    normalize_key(KeyRef::String(segment))
+}
+
+fn flatten_into(result: &mut BTreeMap<String, Value>, prefix: &str, map: &Array) {
+    for (key, value) in map {
+        let segment = escape_segment(&key.to_string());
+        let path = if prefix.is_empty() {
+            segment
+        } else {
+            format!("{}.{}", prefix, segment)
+        };
+        match value {
+            Value::Array(map) if !map.is_empty() => flatten_into(result, &path, map),
+            value => {
+                result.insert(path, value.clone());
+            }
+        }
+    }
+}
+
+fn escape_segment(segment: &str) -> String {
+    segment.replace('\\', "\\\\").replace('.', "\\.")
+}
+
+/// Split a flattened path on unescaped dots
+fn split_path(path: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        let segment = segments.last_mut().unwrap();
+        match c {
+            '\\' => segment.extend(chars.next()),
+            '.' => segments.push(String::new()),
+            c => segment.push(c),
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, Value};
+
+    #[test]
+    fn test_flatten_roundtrip() {
+        let value = from_str::<Value>(
+            r#"[
+                "db" => ["host" => "localhost", "port" => 3306],
+                "trusted_domains" => ["a", "b"],
+                "empty" => [],
+                "debug" => false,
+            ]"#,
+        )
+        .unwrap();
+        let flat = value.flatten();
+        assert_eq!(
+            flat.keys().collect::<Vec<_>>(),
+            vec![
+                "db.host",
+                "db.port",
+                "debug",
+                "empty",
+                "trusted_domains.0",
+                "trusted_domains.1"
+            ]
+        );
+        assert_eq!(flat["empty"], Value::Array(Default::default()));
+        assert_eq!(Value::unflatten(flat), value);
+    }
+
+    #[test]
+    fn test_unflatten_conflict() {
+        let value = Value::unflatten([("a", Value::Int(1)), ("a.b", Value::Int(2))]);
+        assert_eq!(value["a"]["b"], 2);
+
+        assert_eq!(Value::Int(1).flatten()[""], 1);
+        assert_eq!(Value::unflatten(Value::Int(1).flatten()), 1);
+    }
+
+    #[test]
+    fn test_flatten_escaped_keys() {
+        let value =
+            from_str::<Value>(r#"['a.b' => 1, 'a' => ['b' => 2, 'c\\' => ['.' => 3]]]"#).unwrap();
+        let flat = value.flatten();
+        assert_eq!(
+            flat.keys().collect::<Vec<_>>(),
+            vec![r"a.b", r"a.c\\.\.", r"a\.b"]
+        );
+        assert_eq!(flat[r"a\.b"], 1);
+        assert_eq!(Value::unflatten(flat), value);
+    }
+
+    #[test]
+    fn test_set_path() {
+        let mut value =
+            from_str::<Value>(r#"["redis" => ["host" => "a"], "debug" => true]"#).unwrap();
+        value.set_path(["redis", "port"], Value::Int(6379));
+        value.set_path(["debug", "level"], Value::Int(2));
+        value.set_path(vec!["apps".to_string(), "10".into()], "files".into());
+
+        assert_eq!(value["redis"]["host"], "a");
+        assert_eq!(value["redis"]["port"], 6379);
+        assert_eq!(value["debug"]["level"], 2);
+        assert_eq!(value["apps"][10], "files");
+
+        value.set_path(Vec::<&str>::new(), Value::Null);
+        assert_eq!(value, Value::Null);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::Value;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// Handler for calls to a specific function, receives the evaluated arguments
+pub type FunctionHandler = Arc<dyn Fn(&[Value]) -> Result<Value, String> + Send + Sync>;
+
+/// Evaluates calls to functions that don't have a registered handler
+///
+/// This is implemented for closures taking the function name and arguments.
+pub trait FunctionEvaluator: Send + Sync {
+    /// Evaluate a call to the function `name` with the evaluated arguments
+    ///
+    /// The name is passed in lowercase without leading `\`.
+    /// Returns `None` if the function isn't supported, which fails the parse like an error does.
+    fn call(&self, name: &str, args: &[Value]) -> Option<Result<Value, String>>;
+}
+
+impl<F> FunctionEvaluator for F
+where
+    F: Fn(&str, &[Value]) -> Option<Result<Value, String>> + Send + Sync,
+{
+    fn call(&self, name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+        self(name, args)
+    }
+}
+
+/// Registered function call handlers, keyed by lowercase function name
+#[derive(Clone, Default)]
+pub(crate) struct FunctionHandlers {
+    handlers: HashMap<String, FunctionHandler>,
+    evaluator: Option<Arc<dyn FunctionEvaluator>>,
+}
+
+impl FunctionHandlers {
+    pub fn insert(&mut self, name: &str, handler: FunctionHandler) {
+        self.handlers.insert(normalize_name(name), handler);
+    }
+
+    pub fn set_evaluator(&mut self, evaluator: Arc<dyn FunctionEvaluator>) {
+        self.evaluator = Some(evaluator);
+    }
+
+    /// Check if calls to the function are evaluated, matching the name case-insensitively like php does
+    pub fn handles(&self, name: &str) -> bool {
+        self.evaluator.is_some()
+            || (!self.handlers.is_empty() && self.handlers.contains_key(&normalize_name(name)))
+    }
+
+    /// Evaluate a call using the handler registered for the function or the evaluator
+    pub fn call(&self, name: &str, args: &[Value]) -> Result<Value, String> {
+        let name = normalize_name(name);
+        if let Some(handler) = self.handlers.get(&name) {
+            return handler(args);
+        }
+        self.evaluator
+            .as_ref()
+            .and_then(|evaluator| evaluator.call(&name, args))
+            .unwrap_or_else(|| Err(format!("Call to undefined function {}()", name)))
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim_start_matches('\\').to_ascii_lowercase()
+}
+
+impl Debug for FunctionHandlers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionHandlers")
+            .field("handlers", &self.handlers.keys())
+            .field("evaluator", &self.evaluator.is_some())
+            .finish()
+    }
+}
@@ -1,11 +1,14 @@
-use logos::{Lexer, Logos, Span};
+use crate::Span;
+use logos::{Lexer, Logos};
 use parse_display::Display;
 use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
 
 #[derive(Logos, Debug, PartialEq, Clone, Copy, Display)]
 #[logos(skip r"(#|//)[^\n]*")]
 #[logos(skip r"/\*([^*]|\*[^/])+\*/")]
 #[logos(skip r"[ \t\n\f]+")]
+#[logos(skip "\u{FEFF}")]
 pub enum Token {
     #[token("array")]
     #[display("'array'")]
@@ -34,21 +37,151 @@ pub enum Token {
     #[token(",")]
     #[display("','")]
     Comma,
+    #[token(".")]
+    #[display("'.'")]
+    Dot,
+    #[token("...")]
+    #[display("'...'")]
+    Ellipsis,
+    #[token("&")]
+    #[display("'&'")]
+    BitAnd,
+    #[token("|")]
+    #[display("'|'")]
+    BitOr,
+    #[token("^")]
+    #[display("'^'")]
+    BitXor,
+    #[token("~")]
+    #[display("'~'")]
+    BitNot,
+    #[token("+")]
+    #[display("'+'")]
+    Plus,
+    #[token("<<")]
+    #[display("'<<'")]
+    ShiftLeft,
+    #[token(">>")]
+    #[display("'>>'")]
+    ShiftRight,
     #[display("string literal")]
     #[regex("(\"([^\"\\\\]|\\\\.)*\")|(\'([^\'\\\\]|\\\\.)*\')")]
+    #[regex(r#"<<<[ \t]*("[a-zA-Z_][a-zA-Z0-9_]*"|'[a-zA-Z_][a-zA-Z0-9_]*'|[a-zA-Z_][a-zA-Z0-9_]*)\r?\n"#, heredoc)]
     LiteralString,
     #[display("float literal")]
+    #[token("INF")]
+    #[token("-INF")]
+    #[token("NAN")]
     #[regex("-?((([0-9]+(_[0-9]+)*|([0-9]*(_[0-9]+)*[\\.][0-9]+(_[0-9]+)*)|([0-9]+(_[0-9]+)*[\\.][0-9]*(_[0-9]+)*)))[eE][+-]?[0-9]+(_[0-9]+)*|([0-9]*(_[0-9]+)*[\\.][0-9]+(_[0-9]+)*)|([0-9]+(_[0-9]+)*[\\.][0-9]*(_[0-9]+)*))")]
     Float,
     #[display("integer literal")]
-    #[regex("-?(0|[1-9][0-9]*(_[0-9]+)*|0[xX][0-9a-fA-F]+(_[0-9a-fA-F]+)*|0[0-7]+(_[0-7]+)*|0[bB][01]+(_[01]+)*)")]
+    #[regex("-?(0|[1-9][0-9]*(_[0-9]+)*|0[xX][0-9a-fA-F]+(_[0-9a-fA-F]+)*|0[0-7]+(_[0-7]+)*|0[oO][0-7]+(_[0-7]+)*|0[bB][01]+(_[01]+)*)")]
     Integer,
     #[token(";")]
     #[display("';'")]
     SemiColon,
+    #[regex("<\\?(?i:php)")]
+    #[display("'<?php'")]
+    OpenTag,
+    #[token("?>")]
+    #[display("'?>'")]
+    CloseTag,
+    #[token("=")]
+    #[display("'='")]
+    Assign,
+    #[regex(r"\$[a-zA-Z_][a-zA-Z0-9_]*")]
+    #[display("variable")]
+    Variable,
+    #[regex(r"(?i:static[ \t\n\f]+)?(?i:fn|function)", skip_closure)]
+    #[display("closure")]
+    Closure,
+    #[regex("(?i)new")]
+    #[display("'new'")]
+    New,
+    #[regex(r"\\?[a-zA-Z_][a-zA-Z0-9_]*(\\[a-zA-Z_][a-zA-Z0-9_]*)*(::[a-zA-Z_][a-zA-Z0-9_]*)?")]
+    #[display("identifier")]
+    Identifier,
     Error,
 }
 
+/// Extend a heredoc or nowdoc token up to and including the closing identifier
+fn heredoc(lex: &mut Lexer<Token>) -> bool {
+    let label = lex.slice()[3..]
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'');
+    match heredoc_length(label, lex.remainder()) {
+        Some(len) => {
+            lex.bump(len);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Find the end of the closing identifier of a heredoc with the body starting at the start of `source`
+fn heredoc_length(label: &str, source: &str) -> Option<usize> {
+    let mut line_start = 0;
+    loop {
+        let line = &source[line_start..];
+        let indented = line.trim_start_matches([' ', '\t']);
+        if let Some(after) = indented.strip_prefix(label) {
+            if !after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+                return Some(source.len() - after.len());
+            }
+        }
+        line_start += line.find('\n')? + 1;
+    }
+}
+
+/// Extend a closure token over the full closure, up to the `,`, `;` or closing bracket ending it
+fn skip_closure(lex: &mut Lexer<Token>) {
+    let len = closure_length(lex.remainder());
+    lex.bump(len);
+}
+
+/// Find the length of the closure body at the start of `source` by scanning for the first
+/// delimiter that isn't nested in brackets, strings or comments
+pub fn closure_length(source: &str) -> usize {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut end = 0;
+    let mut pos = 0;
+    while let Some(&byte) = bytes.get(pos) {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => break,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' | b';' if depth == 0 => break,
+            b'\'' | b'"' => {
+                pos += 1;
+                while let Some(&inner) = bytes.get(pos) {
+                    match inner {
+                        b'\\' => pos += 1,
+                        _ if inner == byte => break,
+                        _ => {}
+                    }
+                    pos += 1;
+                }
+            }
+            b'#' => pos += memchr::memchr(b'\n', &bytes[pos..]).unwrap_or(bytes.len() - pos),
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                pos += memchr::memchr(b'\n', &bytes[pos..]).unwrap_or(bytes.len() - pos)
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                pos += memchr::memmem::find(&bytes[pos + 2..], b"*/")
+                    .map(|comment_end| comment_end + 3)
+                    .unwrap_or(bytes.len() - pos);
+            }
+            _ => {}
+        }
+        pos += 1;
+        if !byte.is_ascii_whitespace() {
+            end = pos.min(bytes.len());
+        }
+    }
+    end
+}
+
 #[test]
 fn test_lex() {
     let source = r###"
@@ -138,7 +271,7 @@ fn test_lex() {
 
 #[test]
 fn test_lex_int() {
-    let source = r###"0,123,0x123,0123,0b111,12_34_56"###;
+    let source = r###"0,123,0x123,0123,0b111,12_34_56,0o17"###;
     let mut lex = Token::lexer(source);
 
     assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
@@ -156,6 +289,9 @@ fn test_lex_int() {
     assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
     assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
 
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+
     assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
     assert_eq!(lex.next().transpose().unwrap(), None);
 }
@@ -184,6 +320,20 @@ fn test_lex_float() {
     assert_eq!(lex.next().transpose().unwrap(), None);
 }
 
+#[test]
+fn test_lex_non_finite() {
+    let mut lex = Token::lexer("INF,-INF,NAN,INFO");
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Float));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Float));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Float));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Identifier));
+    assert_eq!(lex.next().transpose().unwrap(), None);
+}
+
 #[test]
 fn test_lex_comments() {
     let source = r###"
@@ -207,6 +357,124 @@ fn test_lex_comments() {
     assert_eq!(lex.next().transpose().unwrap(), Some(Token::BracketClose));
 }
 
+#[test]
+fn test_lex_closure() {
+    let source = r###"
+    [
+        'arrow' => fn ($x) => [$x, ')'],
+        'function' => function () use ($y) { return "}"; /* ] */ },
+        'static' => static fn () => 1
+    ]
+    "###;
+    let mut lex = Token::lexer(source);
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareOpen));
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Arrow));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Closure));
+    assert_eq!(lex.slice(), "fn ($x) => [$x, ')']");
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Arrow));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Closure));
+    assert_eq!(
+        lex.slice(),
+        r#"function () use ($y) { return "}"; /* ] */ }"#
+    );
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Arrow));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Closure));
+    assert_eq!(lex.slice(), "static fn () => 1");
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareClose));
+    assert_eq!(lex.next().transpose().unwrap(), None);
+}
+
+#[test]
+fn test_lex_heredoc() {
+    let source = "[<<<EOT\n  a\n  EOTX\n  EOT, <<<'NOW'\nb\nNOW\n, <<<\"Q\"\nQ]";
+    let mut lex = Token::lexer(source);
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareOpen));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.slice(), "<<<EOT\n  a\n  EOTX\n  EOT");
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.slice(), "<<<'NOW'\nb\nNOW");
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.slice(), "<<<\"Q\"\nQ");
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareClose));
+    assert_eq!(lex.next().transpose().unwrap(), None);
+
+    let mut lex = Token::lexer("<<<EOT\nunterminated");
+    assert!(lex.next().unwrap().is_err());
+}
+
+#[test]
+fn test_lex_bitwise() {
+    let mut lex = Token::lexer("~E_ALL&1|2^3<<4>>5");
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::BitNot));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Identifier));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::BitAnd));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::BitOr));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::BitXor));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::ShiftLeft));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::ShiftRight));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), None);
+}
+
+#[test]
+fn test_lex_class_constant() {
+    let mut lex = Token::lexer(r"[\Monolog\Logger::WARNING, Foo::BAR]");
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareOpen));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Identifier));
+    assert_eq!(lex.slice(), r"\Monolog\Logger::WARNING");
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Identifier));
+    assert_eq!(lex.slice(), "Foo::BAR");
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareClose));
+}
+
+#[test]
+fn test_lex_concat() {
+    let mut lex = Token::lexer("'a'.'b' . .5");
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Dot));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::LiteralString));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Dot));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Float));
+    assert_eq!(lex.next().transpose().unwrap(), None);
+}
+
+#[test]
+fn test_lex_spread() {
+    let mut lex = Token::lexer("[...[1], ...FOO]");
+
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareOpen));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Ellipsis));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareOpen));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Integer));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareClose));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Comma));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Ellipsis));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::Identifier));
+    assert_eq!(lex.next().transpose().unwrap(), Some(Token::SquareClose));
+    assert_eq!(lex.next().transpose().unwrap(), None);
+}
+
 #[derive(Clone)]
 pub struct SpannedToken<'source> {
     pub token: Token,
@@ -216,7 +484,7 @@ pub struct SpannedToken<'source> {
 
 impl<'source> SpannedToken<'source> {
     pub fn slice(&self) -> &'source str {
-        &self.source[self.span.clone()]
+        &self.source[self.span.range()]
     }
 }
 
@@ -231,13 +499,19 @@ impl<'source> Debug for SpannedToken<'source> {
     }
 }
 
-pub struct TokenStream<'source> {
-    lexer: Lexer<'source, Token>,
+pub enum TokenStream<'source> {
+    Lexer(Lexer<'source, Token>),
+    /// Tokens that were lexed before, to parse the same input again without lexing it
+    Replay(Rc<[SpannedToken<'source>]>, usize),
 }
 
 impl<'source> TokenStream<'source> {
     pub fn new(lexer: Lexer<'source, Token>) -> Self {
-        TokenStream { lexer }
+        TokenStream::Lexer(lexer)
+    }
+
+    pub fn replay(tokens: Rc<[SpannedToken<'source>]>) -> Self {
+        TokenStream::Replay(tokens, 0)
     }
 }
 
@@ -245,11 +519,20 @@ impl<'source> Iterator for TokenStream<'source> {
     type Item = SpannedToken<'source>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token = self.lexer.next()?.unwrap_or(Token::Error);
-        Some(SpannedToken {
-            token,
-            span: self.lexer.span(),
-            source: self.lexer.source(),
-        })
+        match self {
+            TokenStream::Lexer(lexer) => {
+                let token = lexer.next()?.unwrap_or(Token::Error);
+                Some(SpannedToken {
+                    token,
+                    span: lexer.span().into(),
+                    source: lexer.source(),
+                })
+            }
+            TokenStream::Replay(tokens, pos) => {
+                let token = tokens.get(*pos)?.clone();
+                *pos += 1;
+                Some(token)
+            }
+        }
     }
 }
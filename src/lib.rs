@@ -1,6 +1,6 @@
 //! Parser for php literals.
 //!
-//! Allows parsing of php string, bool, number and array literals.
+//! Allows parsing of php string, bool, number and array literals and writing serde types back as php literals.
 //!
 //! ## Usage
 //!
@@ -46,28 +46,114 @@
 //! ```
 //!
 #![forbid(unsafe_code)]
+mod approx;
+mod array;
+mod assign;
+mod cast;
+mod comments;
+mod constants;
+mod cst;
+mod define;
+mod dump;
+mod duplicate;
+mod edit;
+mod encoding;
+mod entries;
 mod error;
+mod eval;
+mod events;
+mod expr;
+mod flatten;
+mod functions;
 mod lexer;
+mod macros;
 mod num;
+mod options;
 mod parser;
+mod prune;
+#[cfg(feature = "query")]
+mod query;
+#[cfg(feature = "fancy")]
+mod render;
+mod report;
+mod ser;
 mod serde_impl;
+mod span;
 mod string;
-
+mod suggest;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+mod unserialize;
+mod validate;
+mod value_de;
+mod value_ser;
+
+use crate::array::{lookup_key, ArrayExt};
+use crate::serde_impl::{EXPR_TOKEN, INSTANCE_TOKEN, NUMBER_TOKEN, OBJECT_TOKEN};
 use crate::string::is_array_key_numeric;
-pub use error::ParseError;
+use crate::value_de::as_list;
+#[doc(hidden)]
+pub use array::ArrayBuilder;
+pub use array::{Array, AsKeyRef, Entry, KeyRef};
+pub use assign::{
+    assignments_from_str, assignments_from_str_with_options, from_assignment_str,
+    from_assignment_str_with_options,
+};
+pub use cast::PhpType;
+pub use constants::ConstantResolver;
+pub use cst::{ArrayNode, EntryNode, KeyNode, LiteralNode, SyntaxNode, SyntaxToken, SyntaxTree};
+pub use define::{defines_from_str, defines_from_str_with_options};
+pub use dump::VarDump;
+pub use duplicate::{DuplicateFields, DuplicateKey};
+pub use encoding::decode_bytes;
+pub use entries::{entries_from_str, entries_from_str_with_options, Entries};
+#[cfg(feature = "miette")]
+pub use error::WithSource;
+pub use error::{
+    Breadcrumb, DuplicateFieldError, ErrorKind, FieldError, FieldErrors, InvalidToken,
+    NestingLimitError, ParseError, SerdeError, SerializeError,
+};
+pub use events::{
+    events_from_str, events_from_str_with_options, value_from_str, value_from_str_with_options,
+    Event, Events,
+};
+pub use expr::{expr_from_str, expr_from_str_with_options, ArrayEntry, BinaryOp, Expr, Resolver};
+pub use functions::{FunctionEvaluator, FunctionHandler};
+pub use options::{ParserOptions, QuoteStyle, SerializeOptions};
+pub use parser::ArraySyntax;
+#[cfg(feature = "fancy")]
+pub use render::RenderStyle;
+pub use report::{ErrorReport, ReportSpan};
+pub use ser::{
+    to_canonical_string, to_string, to_string_pretty, to_string_with_options, Serializer,
+};
 use serde::de::{self, MapAccess, SeqAccess, Visitor};
-use serde::{Deserialize, Deserializer};
-pub use serde_impl::from_str;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize};
+#[cfg(feature = "tokio")]
+pub use serde_impl::{from_async_read_to_end, from_async_read_to_end_with_options};
+pub use serde_impl::{
+    from_reader, from_reader_with_options, from_slice, from_slice_with_options, from_str,
+    from_str_partial, from_str_partial_with_options, from_str_prefix, from_str_prefix_with_options,
+    from_str_with_options,
+};
+pub use span::{Position, Span};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
+pub use string::{UnescapeError, UnescapeErrorReason};
+pub use validate::{validate, validate_with_options};
+pub use value_de::from_value;
+pub use value_ser::to_value;
 
 /// A php value, can be either a bool, int, float, string, an array or null
 ///
+/// When parsing in lenient mode, constructs that can't be evaluated are captured as an `Expr` containing their source.
+///
 /// note that in php all arrays are associative and thus represented by a map in rust.
 ///
 /// You can convert a `Value` into a regular rust type by pattern matching or using the `into_` functions.
@@ -78,7 +164,10 @@ use std::ops::Index;
 /// or the key is not found
 ///
 /// ```rust
+/// # #[cfg(not(feature = "preserve_order"))]
 /// # use maplit::hashmap;
+/// # #[cfg(feature = "preserve_order")]
+/// # use indexmap::indexmap as hashmap;
 /// # use php_literal_parser::Value;
 /// #
 /// # fn main() {
@@ -97,8 +186,16 @@ pub enum Value {
     Int(i64),
     Float(f64),
     String(String),
-    Array(HashMap<Key, Value>),
+    /// A string that isn't valid UTF-8, php strings are byte strings that can contain arbitrary binary data
+    Bytes(Vec<u8>),
+    Array(Array),
     Null,
+    /// Source of an expression that can't be evaluated, only produced in lenient mode
+    Expr(String),
+    /// An object instantiation, only produced when capturing objects
+    Instance(Instance),
+    /// An object with its properties, from an `(object)` cast or a `__set_state` call as written by `var_export()`
+    Object(Object),
 }
 
 impl Value {
@@ -127,11 +224,51 @@ impl Value {
         matches!(self, Value::Array(_))
     }
 
+    /// Check if the value is a string that isn't valid UTF-8
+    pub fn is_bytes(&self) -> bool {
+        matches!(self, Value::Bytes(_))
+    }
+
     /// Check if the value is null
     pub fn is_null(&self) -> bool {
         matches!(self, Value::Null)
     }
 
+    /// Check if the value is a captured expression
+    pub fn is_expr(&self) -> bool {
+        matches!(self, Value::Expr(_))
+    }
+
+    /// Check if the value is an object instantiation
+    pub fn is_instance(&self) -> bool {
+        matches!(self, Value::Instance(_))
+    }
+
+    /// Check if the value is an object
+    pub fn is_object(&self) -> bool {
+        matches!(self, Value::Object(_))
+    }
+
+    /// Get the name of the type of the value as returned by php's `gettype()`
+    ///
+    /// ```rust
+    /// # use php_literal_parser::Value;
+    /// assert_eq!(Value::Float(1.5).php_type(), "double");
+    /// assert_eq!(Value::Null.php_type(), "NULL");
+    /// ```
+    pub fn php_type(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "boolean",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "double",
+            Value::String(_) | Value::Bytes(_) => "string",
+            Value::Array(_) => "array",
+            Value::Null => "NULL",
+            Value::Instance(_) | Value::Object(_) => "object",
+            Value::Expr(_) => "unknown type",
+        }
+    }
+
     /// Convert the value into a bool if it is one
     pub fn into_bool(self) -> Option<bool> {
         match self {
@@ -167,7 +304,10 @@ impl Value {
     /// Convert the value into a hashmap if it is one
     pub fn into_hashmap(self) -> Option<HashMap<Key, Value>> {
         match self {
+            #[cfg(not(feature = "preserve_order"))]
             Value::Array(map) => Some(map),
+            #[cfg(feature = "preserve_order")]
+            Value::Array(map) => Some(map.into_iter().collect()),
             _ => None,
         }
     }
@@ -180,6 +320,29 @@ impl Value {
         }
     }
 
+    /// Get the bytes of the value if it is a string, either valid UTF-8 or not
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(str) => Some(str.as_bytes()),
+            Value::Bytes(bytes) => Some(bytes.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Create a string value from bytes, [`Value::String`] if the bytes are valid UTF-8 and [`Value::Bytes`] otherwise
+    ///
+    /// ```rust
+    /// # use php_literal_parser::Value;
+    /// assert_eq!(Value::from_bytes(b"foo".to_vec()), Value::String("foo".into()));
+    /// assert_eq!(Value::from_bytes(vec![0xFF]), Value::Bytes(vec![0xFF]));
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>) -> Value {
+        match String::from_utf8(bytes) {
+            Ok(string) => Value::String(string),
+            Err(err) => Value::Bytes(err.into_bytes()),
+        }
+    }
+
     /// Get the value as i64 if it is an int
     pub fn as_int(&self) -> Option<i64> {
         match self {
@@ -196,6 +359,136 @@ impl Value {
         }
     }
 
+    /// Get the source of the expression if it is a captured expression
+    pub fn as_expr(&self) -> Option<&str> {
+        match self {
+            Value::Expr(expr) => Some(expr.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Get the object instantiation if it is one
+    pub fn as_instance(&self) -> Option<&Instance> {
+        match self {
+            Value::Instance(instance) => Some(instance),
+            _ => None,
+        }
+    }
+
+    /// Get the object if it is one
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Get an array entry by string key, comparing keys case-insensitively
+    ///
+    /// An exactly matching key is preferred, otherwise the first key that matches ignoring ascii case is used.
+    /// With the `preserve_order` feature that's the first matching key in insertion order, without it the
+    /// insertion order isn't known and the smallest matching key is used, so the choice doesn't depend on the hash map order.
+    /// Returns `None` if the value isn't an array or no key matches.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = from_str::<Value>(r#"["dbhost" => "localhost"]"#)?;
+    ///
+    /// assert_eq!(value.get_ignore_case("DBHost"), Some(&Value::from("localhost")));
+    /// assert_eq!(value.get_ignore_case("dbname"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_ignore_case(&self, key: &str) -> Option<&Value> {
+        let map = match self {
+            Value::Array(map) => map,
+            _ => return None,
+        };
+        map.lookup(key).or_else(|| {
+            map.ordered()
+                .into_iter()
+                .find(|(candidate, _)| {
+                    matches!(candidate, Key::String(candidate) if candidate.eq_ignore_ascii_case(key))
+                })
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// Get a mutable reference to an array entry, for modifying a parsed value in place
+    ///
+    /// Returns `None` if the value isn't an array or doesn't contain the key.
+    /// Like in php, numeric string keys such as `"1"` find the entry with the integer key.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let mut value = from_str::<Value>("['db' => ['host' => 'localhost'], 0 => 1]")?;
+    ///
+    /// if let Some(db) = value.get_mut("db") {
+    ///     *db.get_mut("host").unwrap() = "db.example.com".into();
+    /// }
+    /// *value.get_mut(&0).unwrap() = Value::Int(2);
+    ///
+    /// assert_eq!(value["db"]["host"], "db.example.com");
+    /// assert_eq!(value[0], 2);
+    /// assert_eq!(value.get_mut("missing"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_mut<K: AsKeyRef + ?Sized>(&mut self, key: &K) -> Option<&mut Value> {
+        match self {
+            Value::Array(map) => map.lookup_mut(key),
+            _ => None,
+        }
+    }
+
+    /// Get the entry for a key, for inserting or updating its value in place
+    ///
+    /// Like assigning with `value["key"] = ...`, null values are turned into an empty array first
+    /// and numeric string keys such as `"1"` are converted to integer keys.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the value is neither an array nor null.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let mut config = from_str::<Value>("['debug' => true, 'hosts' => ['a']]")?;
+    ///
+    /// config.entry("debug").or_insert(false.into());
+    /// config.entry("timeout").or_insert(Value::Int(30));
+    /// config
+    ///     .entry("hosts")
+    ///     .and_modify(|hosts| hosts[1] = "b".into())
+    ///     .or_insert_with(|| Value::Array(Default::default()));
+    ///
+    /// assert_eq!(config["debug"], true);
+    /// assert_eq!(config["timeout"], 30);
+    /// assert_eq!(config["hosts"][1], "b");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn entry<K: Into<Key>>(&mut self, key: K) -> Entry<'_> {
+        let key = key.into();
+        let array = array_for_key(self, key.as_key_ref());
+        let key = lookup_key(array, key.as_key_ref());
+        array.entry(key)
+    }
+
     /// Iterate over array key and value pairs if it is an array
     pub fn iter(&self) -> impl Iterator<Item = (&Key, &Value)> {
         let map = match self {
@@ -291,12 +584,30 @@ impl From<&str> for Value {
     }
 }
 
+#[cfg(not(feature = "preserve_order"))]
 impl From<HashMap<Key, Value>> for Value {
     fn from(value: HashMap<Key, Value>) -> Self {
         Value::Array(value)
     }
 }
 
+#[cfg(feature = "preserve_order")]
+impl From<HashMap<Key, Value>> for Value {
+    fn from(value: HashMap<Key, Value>) -> Self {
+        Value::Array(value.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl From<Array> for Value {
+    fn from(value: Array) -> Self {
+        Value::Array(value)
+    }
+}
+
+/// Human readable representation of the value, strings are written without quotes
+///
+/// Use [`Value::to_php_string`] to get a php literal that can be parsed again.
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -304,6 +615,7 @@ impl Display for Value {
             Value::Int(val) => write!(f, "{}", val),
             Value::Float(val) => write!(f, "{}", val),
             Value::String(val) => write!(f, "{}", val),
+            Value::Bytes(val) => write!(f, "{}", String::from_utf8_lossy(val)),
             Value::Array(val) => {
                 writeln!(f, "[")?;
                 for (key, value) in val.iter() {
@@ -312,10 +624,120 @@ impl Display for Value {
                 write!(f, "]")
             }
             Value::Null => write!(f, "null"),
+            Value::Expr(expr) => write!(f, "{}", expr),
+            Value::Instance(instance) => write!(f, "{}", instance),
+            Value::Object(object) => write!(f, "{}", object),
         }
     }
 }
 
+/// A captured `new ClassName(args)` expression
+///
+/// Instances can also be deserialized directly, for fields that always hold an object instantiation.
+///
+/// ```rust
+/// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let options = ParserOptions::new().capture_objects(true);
+/// let value = from_str_with_options::<Value>(r#"new \Redis(['host' => 'localhost'])"#, &options)?;
+/// let instance = value.as_instance().unwrap();
+///
+/// assert_eq!(instance.class, "\\Redis");
+/// assert_eq!(instance.args[0]["host"], "localhost");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Instance {
+    /// The class name as written in the source
+    pub class: String,
+    /// The constructor arguments
+    pub args: Vec<Value>,
+}
+
+impl<'de> Deserialize<'de> for Instance {
+    fn deserialize<D>(deserializer: D) -> Result<Instance, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Instance(instance) => Ok(instance),
+            value => Err(de::Error::invalid_type(
+                value.unexpected(),
+                &"an object instantiation",
+            )),
+        }
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "new {}(", self.class)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// An object with its properties
+///
+/// Objects are parsed from `(object)` casts and from the `\Class::__set_state(array(...))` calls `var_export()` writes for objects.
+/// The class name is stored without leading backslash.
+///
+/// ```rust
+/// use php_literal_parser::{from_str, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let value = from_str::<Value>(r#"\stdClass::__set_state(array('host' => 'localhost'))"#)?;
+/// let object = value.as_object().unwrap();
+///
+/// assert_eq!(object.class, "stdClass");
+/// assert_eq!(object.properties.get("host"), Some(&Value::from("localhost")));
+/// assert_eq!(from_str::<Value>("(object) ['host' => 'localhost']")?, value);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Object {
+    /// The name of the class
+    pub class: String,
+    /// The properties of the object
+    pub properties: Array,
+}
+
+impl Object {
+    /// Check if this is an instance of `stdClass`, the class of objects created by `(object)` casts
+    pub fn is_std_class(&self) -> bool {
+        self.class.eq_ignore_ascii_case("stdClass")
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_std_class() {
+            f.write_str("(object) ")?;
+        } else {
+            write!(f, "\\{}::__set_state(", self.class)?;
+        }
+        writeln!(f, "[")?;
+        for (key, value) in self.properties.iter() {
+            write!(f, "\t{} => {},", key, value)?;
+        }
+        write!(f, "]")?;
+        if !self.is_std_class() {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
 /// A php array key, can be either an int or string
 #[derive(Debug, Eq, Clone)]
 pub enum Key {
@@ -405,27 +827,28 @@ impl Ord for Key {
         match (self, other) {
             (Key::Int(self_int), Key::Int(other_int)) => self_int.cmp(other_int),
             (Key::String(self_string), Key::String(other_string)) => self_string.cmp(other_string),
-            (Key::String(self_string), Key::Int(other_int)) => {
-                self_string.cmp(&other_int.to_string())
-            }
-            (Key::Int(self_int), Key::String(other_string)) => {
-                self_int.to_string().cmp(other_string)
-            }
+            // integer keys come before numeric string keys with the same text to stay consistent with `Eq`
+            (Key::String(self_string), Key::Int(other_int)) => self_string
+                .as_str()
+                .cmp(other_int.to_string().as_str())
+                .then(Ordering::Greater),
+            (Key::Int(self_int), Key::String(other_string)) => self_int
+                .to_string()
+                .as_str()
+                .cmp(other_string)
+                .then(Ordering::Less),
         }
     }
 }
 
+/// Keys are only equal to keys of the same type, so `"1"` and `1` can both be kept with
+/// [`ParserOptions::keep_numeric_string_keys`]
 impl PartialEq for Key {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Key::Int(self_int), Key::Int(other_int)) => self_int.eq(other_int),
             (Key::String(self_string), Key::String(other_string)) => self_string.eq(other_string),
-            (Key::String(self_string), Key::Int(other_int)) => {
-                self_string.eq(&other_int.to_string())
-            }
-            (Key::Int(self_int), Key::String(other_string)) => {
-                self_int.to_string().eq(other_string)
-            }
+            _ => false,
         }
     }
 }
@@ -439,16 +862,12 @@ impl Borrow<str> for Key {
     }
 }
 
-impl<Q: ?Sized> Index<&Q> for Value
-where
-    Key: Borrow<Q>,
-    Q: Eq + Hash,
-{
+impl<Q: AsKeyRef + ?Sized> Index<&Q> for Value {
     type Output = Value;
 
     fn index(&self, index: &Q) -> &Self::Output {
         match self {
-            Value::Array(map) => map.get(index).unwrap_or(&Value::Null),
+            Value::Array(map) => map.lookup(index).unwrap_or(&Value::Null),
             _ => &Value::Null,
         }
     }
@@ -459,7 +878,7 @@ impl Index<Key> for Value {
 
     fn index(&self, index: Key) -> &Self::Output {
         match self {
-            Value::Array(map) => map.get(&index).unwrap_or(&Value::Null),
+            Value::Array(map) => map.lookup(&index).unwrap_or(&Value::Null),
             _ => &Value::Null,
         }
     }
@@ -476,6 +895,69 @@ impl Index<i64> for Value {
     }
 }
 
+/// Get the array to insert the key into, turning null into an empty array
+fn array_for_key<'a>(value: &'a mut Value, key: KeyRef<'_>) -> &'a mut Array {
+    if let Value::Null = value {
+        *value = Value::Array(Array::new());
+    }
+    match value {
+        Value::Array(map) => map,
+        _ => panic!(
+            "cannot access key '{}' in php value of type {}",
+            Key::from(key),
+            value.php_type()
+        ),
+    }
+}
+
+/// Get the entry for a key to assign to, inserting null if the key doesn't exist
+///
+/// Numeric string keys are converted to integer keys like php does.
+fn index_or_insert<'a>(value: &'a mut Value, index: KeyRef<'_>) -> &'a mut Value {
+    let array = array_for_key(value, index);
+    array.entry(lookup_key(array, index)).or_insert(Value::Null)
+}
+
+/// Assign to an array entry, inserting the key if it doesn't exist
+///
+/// Null values are turned into an empty array first.
+/// Like in php, numeric string keys such as `"1"` are converted to integer keys.
+///
+/// ## Panics
+///
+/// Panics if the value is neither an array nor null.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::Value;
+///
+/// let mut config = Value::Null;
+/// config["redis"]["host"] = "localhost".into();
+/// config["redis"]["port"] = Value::Int(6379);
+/// config["hosts"][0] = "example.com".into();
+///
+/// assert_eq!(config["redis"]["host"], "localhost");
+/// assert_eq!(config["hosts"][0], "example.com");
+/// ```
+impl<Q: AsKeyRef + ?Sized> IndexMut<&Q> for Value {
+    fn index_mut(&mut self, index: &Q) -> &mut Self::Output {
+        index_or_insert(self, index.as_key_ref())
+    }
+}
+
+impl IndexMut<Key> for Value {
+    fn index_mut(&mut self, index: Key) -> &mut Self::Output {
+        index_or_insert(self, index.as_key_ref())
+    }
+}
+
+impl IndexMut<i64> for Value {
+    fn index_mut(&mut self, index: i64) -> &mut Self::Output {
+        index_or_insert(self, KeyRef::Int(index))
+    }
+}
+
 impl Display for Key {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -487,6 +969,9 @@ impl Display for Key {
 
 #[test]
 fn test_index() {
+    #[cfg(feature = "preserve_order")]
+    use indexmap::indexmap as hashmap;
+    #[cfg(not(feature = "preserve_order"))]
     use maplit::hashmap;
     let map = Value::Array(hashmap! {
         Key::String("key".to_string()) => Value::String("value".to_string()),
@@ -497,6 +982,15 @@ fn test_index() {
     assert_eq!(map[Key::Int(1)], true);
 }
 
+#[test]
+fn test_php_type() {
+    assert_eq!(Value::Bool(true).php_type(), "boolean");
+    assert_eq!(Value::Int(1).php_type(), "integer");
+    assert_eq!(Value::String("1".into()).php_type(), "string");
+    assert_eq!(Value::Array(Array::new()).php_type(), "array");
+    assert_eq!(Value::Expr("fn () => 1".into()).php_type(), "unknown type");
+}
+
 struct ValueVisitor;
 
 impl<'de> Visitor<'de> for ValueVisitor {
@@ -613,6 +1107,20 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::String(v))
     }
 
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Value::from_bytes(v.into()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Value::from_bytes(v))
+    }
+
     fn visit_unit<E>(self) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -624,25 +1132,59 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         A: SeqAccess<'de>,
     {
-        let mut result = HashMap::new();
-        let mut next_key = 0;
+        let mut result = ArrayBuilder::with_capacity(seq.size_hint().unwrap_or_default());
         while let Some(value) = seq.next_element::<Value>()? {
-            let key = Key::Int(next_key);
-            next_key += 1;
-            result.insert(key, value);
+            if let Err(error) = result.push(value) {
+                return Err(de::Error::custom(error));
+            }
         }
-        Ok(Value::Array(result))
+        Ok(Value::Array(result.build()))
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, <A as MapAccess<'de>>::Error>
     where
         A: MapAccess<'de>,
     {
-        let mut result = HashMap::new();
+        let mut result = ArrayBuilder::new();
+        let first = match map.next_key::<Key>()? {
+            Some(Key::String(key)) if key == EXPR_TOKEN => {
+                return Ok(Value::Expr(map.next_value()?));
+            }
+            Some(Key::String(key)) if key == INSTANCE_TOKEN => {
+                let class = map.next_value()?;
+                let args = match map.next_key::<Key>()? {
+                    Some(_) => map.next_value()?,
+                    None => Vec::new(),
+                };
+                return Ok(Value::Instance(Instance { class, args }));
+            }
+            Some(Key::String(key)) if key == NUMBER_TOKEN => {
+                let text: String = map.next_value()?;
+                return text
+                    .parse()
+                    .map(Value::Int)
+                    .or_else(|_| text.parse().map(Value::Float))
+                    .map_err(|_| de::Error::custom(format!("invalid number {}", text)));
+            }
+            Some(Key::String(key)) if key == OBJECT_TOKEN => {
+                let class = map.next_value()?;
+                while let Some((key, value)) = map.next_entry()? {
+                    result.insert(key, value);
+                }
+                return Ok(Value::Object(Object {
+                    class,
+                    properties: result.build(),
+                }));
+            }
+            first => first,
+        };
+        if let Some(key) = first {
+            result.insert(key, map.next_value()?);
+        }
         while let Some((key, value)) = map.next_entry()? {
             result.insert(key, value);
         }
-        Ok(Value::Array(result))
+        Ok(Value::Array(result.build()))
     }
 }
 
@@ -655,6 +1197,70 @@ impl<'de> Deserialize<'de> for Value {
     }
 }
 
+/// Arrays with consecutive integer keys starting at 0 are serialized as sequences,
+/// other arrays as maps and `null` as unit.
+///
+/// Captured expressions are serialized as a newtype struct containing their source,
+/// object instantiations as a struct with `class` and `args` fields and objects as a struct with
+/// `class` and `properties` fields, when writing php literals all are written back as php source.
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Value::Bool(bool) => serializer.serialize_bool(*bool),
+            Value::Int(int) => serializer.serialize_i64(*int),
+            Value::Float(float) => serializer.serialize_f64(*float),
+            Value::String(str) => serializer.serialize_str(str),
+            Value::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            Value::Array(map) => SerializeArray(map).serialize(serializer),
+            Value::Null => serializer.serialize_unit(),
+            Value::Expr(expr) => serializer.serialize_newtype_struct(EXPR_TOKEN, expr),
+            Value::Instance(instance) => instance.serialize(serializer),
+            Value::Object(object) => object.serialize(serializer),
+        }
+    }
+}
+
+struct SerializeArray<'a>(&'a Array);
+
+impl Serialize for SerializeArray<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match as_list(self.0) {
+            Some(list) => serializer.collect_seq(list),
+            None => serializer.collect_map(self.0.ordered()),
+        }
+    }
+}
+
+impl Serialize for Object {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut object = serializer.serialize_struct(OBJECT_TOKEN, 2)?;
+        object.serialize_field("class", &self.class)?;
+        object.serialize_field("properties", &SerializeArray(&self.properties))?;
+        object.end()
+    }
+}
+
+impl Serialize for Instance {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut instance = serializer.serialize_struct(INSTANCE_TOKEN, 2)?;
+        instance.serialize_field("class", &self.class)?;
+        instance.serialize_field("args", &self.args)?;
+        instance.end()
+    }
+}
+
 struct KeyVisitor;
 
 impl<'de> Visitor<'de> for KeyVisitor {
@@ -775,6 +1381,14 @@ impl<'de> Visitor<'de> for KeyVisitor {
         }
     }
 
+    /// String keys that shouldn't be converted to integer keys are passed as bytes
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Key::String(String::from_utf8_lossy(v).into()))
+    }
+
     fn visit_unit<E>(self) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -791,3 +1405,15 @@ impl<'de> Deserialize<'de> for Key {
         deserializer.deserialize_any(KeyVisitor)
     }
 }
+
+impl Serialize for Key {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Key::Int(int) => serializer.serialize_i64(*int),
+            Key::String(str) => serializer.serialize_str(str),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/// Construct a [`Value`](crate::Value) using php like array syntax
+///
+/// Arrays are written as `[key => value, ...]`, entries without a key get the next integer key
+/// like they would in php. `null` creates [`Value::Null`](crate::Value::Null), other values and keys
+/// can be any expression that converts into a `Value` or [`Key`](crate::Key).
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str, php_value, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let port = 3306;
+/// let value = php_value!([
+///     "db" => ["host" => "localhost", "port" => port, "options" => []],
+///     "debug" => false,
+///     "replicas" => ["a", 5 => "b", "c"],
+///     "cache" => null,
+/// ]);
+///
+/// assert_eq!(value, from_str::<Value>(r#"[
+///     "db" => ["host" => "localhost", "port" => 3306, "options" => []],
+///     "debug" => false,
+///     "replicas" => ["a", 5 => "b", "c"],
+///     "cache" => null,
+/// ]"#)?);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! php_value {
+    (null) => {
+        $crate::Value::Null
+    };
+    ([]) => {
+        $crate::Value::Array($crate::Array::new())
+    };
+    ([ $($tt:tt)+ ]) => {{
+        let mut array = $crate::ArrayBuilder::new();
+        $crate::php_value!(@entry array () $($tt)+);
+        $crate::Value::Array(array.build())
+    }};
+
+    // collect the tokens of an entry until we know if it's a key or a value without key
+    (@entry $array:ident ($($key:tt)+) => $($rest:tt)*) => {
+        $crate::php_value!(@value $array ($($key)+) () $($rest)*);
+    };
+    (@entry $array:ident ($($value:tt)+) , $($rest:tt)*) => {
+        $array.push($crate::php_value!($($value)+)).unwrap();
+        $crate::php_value!(@entry $array () $($rest)*);
+    };
+    (@entry $array:ident ($($value:tt)+)) => {
+        $array.push($crate::php_value!($($value)+)).unwrap();
+    };
+    (@entry $array:ident ()) => {};
+    (@entry $array:ident ($($acc:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::php_value!(@entry $array ($($acc)* $next) $($rest)*);
+    };
+
+    // collect the tokens of the value for a key
+    (@value $array:ident ($($key:tt)+) ($($value:tt)+) , $($rest:tt)*) => {
+        $array.insert($crate::Key::from($($key)+), $crate::php_value!($($value)+));
+        $crate::php_value!(@entry $array () $($rest)*);
+    };
+    (@value $array:ident ($($key:tt)+) ($($value:tt)+)) => {
+        $array.insert($crate::Key::from($($key)+), $crate::php_value!($($value)+));
+    };
+    (@value $array:ident ($($key:tt)+) ($($acc:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::php_value!(@value $array ($($key)+) ($($acc)* $next) $($rest)*);
+    };
+
+    ($($value:tt)+) => {
+        $crate::Value::from($($value)+)
+    };
+}
+
+/// Construct a [`Value`](crate::Value) array from `key => value` entries
+///
+/// Shorthand for [`php_value!`] with the outer brackets left out.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{php_array, php_value};
+///
+/// assert_eq!(php_array!["foo" => 1, "bar"], php_value!(["foo" => 1, "bar"]));
+/// ```
+#[macro_export]
+macro_rules! php_array {
+    ($($tt:tt)*) => {
+        $crate::php_value!([$($tt)*])
+    };
+}
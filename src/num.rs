@@ -22,8 +22,8 @@ impl ParseIntError {
     }
 }
 
-/// Mostly copied from std, adjusted for php specifics
-pub fn parse_int(src: &str) -> Result<i64, ParseIntError> {
+/// Split an integer literal into its sign, radix and digits
+fn split_int(src: &str) -> Result<(i64, u32, &[u8]), ParseIntError> {
     if src.is_empty() {
         return Err(ParseIntError::Empty);
     }
@@ -43,6 +43,7 @@ pub fn parse_int(src: &str) -> Result<i64, ParseIntError> {
     let (radix, digits) = match digits {
         [b'0', b'x', tail @ ..] => (16, tail),
         [b'0', b'b', tail @ ..] => (2, tail),
+        [b'0', b'o' | b'O', tail @ ..] => (8, tail),
         [b'0', tail @ ..] if !tail.is_empty() => (8, tail),
         tail => (10, tail),
     };
@@ -50,6 +51,12 @@ pub fn parse_int(src: &str) -> Result<i64, ParseIntError> {
     if digits.is_empty() {
         return Err(ParseIntError::Empty);
     }
+    Ok((sign, radix, digits))
+}
+
+/// Mostly copied from std, adjusted for php specifics
+pub fn parse_int(src: &str) -> Result<i64, ParseIntError> {
+    let (sign, radix, digits) = split_int(src)?;
 
     let mut result: i64 = 0;
 
@@ -72,3 +79,63 @@ pub fn parse_int(src: &str) -> Result<i64, ParseIntError> {
     }
     Ok(result * sign)
 }
+
+/// Parse an integer literal as float, the way php handles integer literals that are too large for an integer
+pub fn parse_int_as_float(src: &str) -> Result<f64, ParseIntError> {
+    let (sign, radix, digits) = split_int(src)?;
+    if radix == 10 {
+        if !digits.iter().all(|&c| c.is_ascii_digit() || c == b'_') {
+            return Err(ParseIntError::InvalidDigit);
+        }
+        let digits: String = digits
+            .iter()
+            .filter(|&&c| c != b'_')
+            .map(|&c| c as char)
+            .collect();
+        let result: f64 = digits.parse().map_err(|_| ParseIntError::Empty)?;
+        return Ok(result * sign as f64);
+    }
+
+    let mut result = 0.0;
+    for &c in digits {
+        if c != b'_' {
+            let x = (c as char)
+                .to_digit(radix)
+                .ok_or(ParseIntError::InvalidDigit)?;
+            result = result * radix as f64 + x as f64;
+        }
+    }
+    Ok(result * sign as f64)
+}
+
+/// Convert a number literal to the decimal notation serde_json uses for arbitrary precision numbers
+///
+/// Returns `None` for `INF` and `NAN` and for non-decimal integers that don't fit in 64 bits.
+#[cfg(feature = "arbitrary_precision")]
+pub fn decimal_text(literal: &str, float: bool) -> Option<String> {
+    let literal = literal.replace('_', "");
+    if !float {
+        return match split_int(&literal) {
+            Ok((_, 10, _)) => Some(literal.trim_start_matches('+').into()),
+            _ => parse_int(&literal).ok().map(|int| int.to_string()),
+        };
+    }
+    if literal.contains(['I', 'N']) {
+        return None;
+    }
+    let (sign, unsigned) = match literal.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", literal.as_str()),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(index) => unsigned.split_at(index),
+        None => (unsigned, ""),
+    };
+    // keep a fractional part so the number stays a float
+    let fraction = if mantissa.ends_with('.') { "0" } else { "" };
+    let zero = if mantissa.starts_with('.') { "0" } else { "" };
+    Some(format!(
+        "{}{}{}{}{}",
+        sign, zero, mantissa, fraction, exponent
+    ))
+}
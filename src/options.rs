@@ -0,0 +1,700 @@
+use crate::constants::{ConstantResolver, Constants};
+use crate::duplicate::{DuplicateFields, DuplicateKey, DuplicateKeyHandler};
+use crate::functions::{FunctionEvaluator, FunctionHandlers};
+use crate::{ArraySyntax, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const DEFAULT_MAX_DEPTH: usize = 128;
+const DEFAULT_MAX_ERRORS: usize = 100;
+
+/// Options to control how php literals are parsed
+///
+/// Every parse function has a variant taking options, like [`from_str_with_options`](crate::from_str_with_options),
+/// [`from_reader_with_options`](crate::from_reader_with_options), [`entries_from_str_with_options`](crate::entries_from_str_with_options),
+/// [`SyntaxTree::parse_with_options`](crate::SyntaxTree::parse_with_options) and [`expr_from_str_with_options`](crate::expr_from_str_with_options).
+/// New options are added as builder methods, so the same options can be reused for every function.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let options = ParserOptions::new().lenient(true);
+/// let map = from_str_with_options::<Value>(r#"["foo" => fn () => 1 + 1]"#, &options)?;
+///
+/// assert!(map["foo"].is_expr());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParserOptions {
+    pub(crate) lenient: bool,
+    pub(crate) capture_objects: bool,
+    pub(crate) bareword_strings: bool,
+    pub(crate) empty_string_as_none: bool,
+    pub(crate) empty_string_as_default: bool,
+    pub(crate) expand_dotted_keys: bool,
+    pub(crate) lowercase_keys: bool,
+    pub(crate) keep_numeric_string_keys: bool,
+    pub(crate) field_paths: bool,
+    pub(crate) php_file: bool,
+    /// Maximum nesting depth, `None` for the default
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_elements: Option<usize>,
+    pub(crate) max_array_entries: Option<usize>,
+    pub(crate) max_string_length: Option<usize>,
+    /// Maximum number of collected errors, `None` for the default
+    pub(crate) max_errors: Option<usize>,
+    pub(crate) duplicate_key_handler: Option<DuplicateKeyHandler>,
+    pub(crate) duplicate_fields: DuplicateFields,
+    pub(crate) functions: FunctionHandlers,
+    pub(crate) constants: Constants,
+    /// Variables to interpolate into strings, `None` if strings aren't interpolated
+    pub(crate) variables: Option<HashMap<String, Value>>,
+}
+
+impl ParserOptions {
+    /// Create the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn depth_limit(&self) -> usize {
+        self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
+    pub(crate) fn error_limit(&self) -> usize {
+        self.max_errors.unwrap_or(DEFAULT_MAX_ERRORS).max(1)
+    }
+
+    /// Capture constructs that can't be evaluated, like closures, variables, calls to unknown functions, unresolved constants
+    /// and unsupported operators, as [`Value::Expr`] instead of failing the parse
+    ///
+    /// Only the value containing the construct is captured, so the rest of the array can still be used.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Capture `new ClassName(args)` expressions with literal arguments as [`Value::Instance`]
+    ///
+    /// In lenient mode, object instantiations are captured as [`Value::Expr`] when this is disabled.
+    pub fn capture_objects(mut self, capture_objects: bool) -> Self {
+        self.capture_objects = capture_objects;
+        self
+    }
+
+    /// Treat unquoted names that aren't a known constant as strings, in both keys and values
+    ///
+    /// This matches php before 8.0, which used the name of an undefined constant as string after emitting a notice.
+    /// Only plain names are accepted, undefined namespaced and class constants still fail the parse.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().bareword_strings(true).constant("PORT", 80);
+    /// let map = from_str_with_options::<Value>("[host => localhost, port => PORT]", &options)?;
+    ///
+    /// assert_eq!(map["host"], "localhost");
+    /// assert_eq!(map["port"], 80);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bareword_strings(mut self, bareword_strings: bool) -> Self {
+        self.bareword_strings = bareword_strings;
+        self
+    }
+
+    /// Deserialize an empty string as `None` when an `Option` is expected
+    pub fn empty_string_as_none(mut self, empty_string_as_none: bool) -> Self {
+        self.empty_string_as_none = empty_string_as_none;
+        self
+    }
+
+    /// Deserialize an empty string as `false` or `0` when a boolean or number is expected
+    pub fn empty_string_as_default(mut self, empty_string_as_default: bool) -> Self {
+        self.empty_string_as_default = empty_string_as_default;
+        self
+    }
+
+    /// Split string keys containing `.` and present them as nested arrays, allowing nested structs
+    /// to be deserialized from flat arrays like `['db.host' => 'localhost']`
+    ///
+    /// This parses the entire input before deserializing.
+    pub fn expand_dotted_keys(mut self, expand_dotted_keys: bool) -> Self {
+        self.expand_dotted_keys = expand_dotted_keys;
+        self
+    }
+
+    /// Convert string array keys to lowercase while parsing, so differently cased keys
+    /// end up as the same key and match lowercase struct fields
+    ///
+    /// Only ascii characters are converted, matching php's `strtolower`.
+    pub fn lowercase_keys(mut self, lowercase_keys: bool) -> Self {
+        self.lowercase_keys = lowercase_keys;
+        self
+    }
+
+    /// Keep string keys containing an integer, like `'1'`, as string keys instead of converting them
+    /// to integer keys like php does
+    ///
+    /// Php only converts canonical integers, `'08'`, `'+1'`, `'1.0'` and `' 1'` are always string keys.
+    pub fn keep_numeric_string_keys(mut self, keep_numeric_string_keys: bool) -> Self {
+        self.keep_numeric_string_keys = keep_numeric_string_keys;
+        self
+    }
+
+    /// Return errors of values inside arrays as [`ParseError::Fields`](crate::ParseError::Fields), even when only a single value failed,
+    /// so the path of the failing value is always available
+    ///
+    /// Paths are the keys leading to the value joined by `.`, like `redis.hosts.2`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParseError, ParserOptions};
+    /// use serde_derive::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Redis {
+    ///     timeouts: Vec<u32>,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Config {
+    ///     redis: Redis,
+    /// }
+    ///
+    /// let options = ParserOptions::new().field_paths(true);
+    /// let source = "['redis' => ['timeouts' => [1, 2, 'three']]]";
+    /// let error = from_str_with_options::<Config>(source, &options).unwrap_err();
+    ///
+    /// match error {
+    ///     ParseError::Fields(errors) => assert_eq!(errors.errors()[0].path(), "redis.timeouts.2"),
+    ///     error => panic!("unexpected error {}", error),
+    /// }
+    /// ```
+    pub fn field_paths(mut self, field_paths: bool) -> Self {
+        self.field_paths = field_paths;
+        self
+    }
+
+    /// Parse the contents of a php file returning the literal, skipping an optional `<?php` tag,
+    /// `declare(...);` statements and the `return` keyword before the literal
+    /// and an optional closing `?>` tag after it
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().php_file(true);
+    /// let source = "<?php\ndeclare(strict_types=1);\n\nreturn ['debug' => true];\n?>\n";
+    /// let map = from_str_with_options::<Value>(source, &options)?;
+    ///
+    /// assert_eq!(map["debug"], true);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn php_file(mut self, php_file: bool) -> Self {
+        self.php_file = php_file;
+        self
+    }
+
+    /// Set the maximum depth arrays and expressions can be nested, defaults to 128
+    ///
+    /// Parsing recurses for every nesting level, input that's nested deeper fails with
+    /// [`ParseError::NestingLimit`](crate::ParseError::NestingLimit) instead of overflowing the stack.
+    /// Use [`value_from_str_with_options`](crate::value_from_str_with_options) to parse arrays nested deeper
+    /// than the stack allows.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Set the maximum number of array entries in the entire input, not limited by default
+    ///
+    /// Input with more entries fails with [`ParseError::ElementLimit`](crate::ParseError::ElementLimit),
+    /// entries of arrays unpacked with `...` are counted again for every array they are unpacked in.
+    pub fn max_elements(mut self, max_elements: usize) -> Self {
+        self.max_elements = Some(max_elements);
+        self
+    }
+
+    /// Set the maximum number of entries in a single array, not limited by default
+    ///
+    /// Arrays with more entries fail with [`ParseError::ArrayEntryLimit`](crate::ParseError::ArrayEntryLimit).
+    pub fn max_array_entries(mut self, max_array_entries: usize) -> Self {
+        self.max_array_entries = Some(max_array_entries);
+        self
+    }
+
+    /// Set the maximum length in bytes of parsed strings, not limited by default
+    ///
+    /// The limit applies to strings after unescaping and interpolating variables, and to the result of concatenations.
+    /// Longer strings fail with [`ParseError::StringLengthLimit`](crate::ParseError::StringLengthLimit).
+    pub fn max_string_length(mut self, max_string_length: usize) -> Self {
+        self.max_string_length = Some(max_string_length);
+        self
+    }
+
+    /// Set the maximum number of errors collected before giving up, defaults to 100
+    ///
+    /// When deserializing, entries that fail to parse are left out and the rest of the input is still checked,
+    /// so all failing entries are reported together as [`ParseError::Fields`](crate::ParseError::Fields).
+    /// Once `max_errors` errors are found the parse stops and only those errors are returned.
+    ///
+    /// Finding every error takes one more pass over the input per failing entry, so the time spent
+    /// on input with many failing entries grows with `max_errors`. Set it to `1` to stop at the first error.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = Some(max_errors);
+        self
+    }
+
+    /// Call `handler` for every key that occurs more than once in the same array
+    ///
+    /// The parse continues as normal with the last value for the key winning, like in php.
+    /// Keys that only become duplicates through [`lowercase_keys`](ParserOptions::lowercase_keys) are reported as well.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// use std::sync::{Arc, Mutex};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let duplicates = Arc::new(Mutex::new(Vec::new()));
+    /// let collected = duplicates.clone();
+    /// let options = ParserOptions::new()
+    ///     .on_duplicate_key(move |duplicate| collected.lock().unwrap().push(duplicate.clone()));
+    /// let source = "['debug' => false, 'debug' => true]";
+    /// let map = from_str_with_options::<Value>(source, &options)?;
+    ///
+    /// assert_eq!(map["debug"], true);
+    /// let duplicates = duplicates.lock().unwrap();
+    /// assert_eq!(duplicates.len(), 1);
+    /// assert_eq!(duplicates[0].first.start(), 1);
+    /// assert_eq!(duplicates[0].duplicate.start(), 19);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_duplicate_key<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&DuplicateKey) + Send + Sync + 'static,
+    {
+        self.duplicate_key_handler = Some(DuplicateKeyHandler::new(handler));
+        self
+    }
+
+    /// Set how struct fields that are set more than once in the same array are handled
+    ///
+    /// By default deserializing a struct from an array that contains a field twice fails with an error pointing to both occurrences.
+    /// Duplicates are passed to the [`on_duplicate_key`](ParserOptions::on_duplicate_key) handler with either setting.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, DuplicateFields, ParserOptions};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     debug: bool,
+    /// }
+    ///
+    /// let source = "['debug' => false, 'debug' => true]";
+    /// let error = from_str_with_options::<Config>(source, &ParserOptions::new()).err().unwrap();
+    /// assert_eq!(error.to_string(), "Duplicate field 'debug'");
+    ///
+    /// let options = ParserOptions::new().duplicate_fields(DuplicateFields::LastWins);
+    /// let config = from_str_with_options::<Config>(source, &options).unwrap();
+    /// assert!(config.debug);
+    /// ```
+    pub fn duplicate_fields(mut self, duplicate_fields: DuplicateFields) -> Self {
+        self.duplicate_fields = duplicate_fields;
+        self
+    }
+
+    /// Register a handler for calls to the function `name` with literal arguments
+    ///
+    /// Function names are matched case-insensitively, the value returned by the handler is used in place of the call.
+    /// An error returned by the handler fails the parse.
+    ///
+    /// The handler is called once for every call in the input, the result is reused when the input is
+    /// deserialized again to find more failing fields, see [`max_errors`](ParserOptions::max_errors).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().function("strtoupper", |args| match args {
+    ///     [Value::String(str)] => Ok(str.to_uppercase().into()),
+    ///     _ => Err("strtoupper expects a single string".into()),
+    /// });
+    /// let map = from_str_with_options::<Value>(r#"["name" => strtoupper("foo")]"#, &options)?;
+    ///
+    /// assert_eq!(map["name"], "FOO");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn function<F>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.functions.insert(name, Arc::new(handler));
+        self
+    }
+
+    /// Evaluate calls to functions without a registered [`function`](ParserOptions::function) handler using `evaluator`
+    ///
+    /// This allows deciding which functions to support at runtime, like a whitelist of functions that read the environment.
+    /// The evaluator receives the lowercase function name and the evaluated arguments, calls to functions it doesn't support fail the parse.
+    /// Like with [`function`](ParserOptions::function) handlers, it's called once for every call in the input.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new()
+    ///     .file_path("/var/www/config/config.php")
+    ///     .function_evaluator(|name: &str, args: &[Value]| match (name, args) {
+    ///         ("getenv", [Value::String(name)]) => Some(Ok(match name.as_str() {
+    ///             "DB_HOST" => "db.example.com".into(),
+    ///             _ => false.into(),
+    ///         })),
+    ///         ("dirname", [Value::String(path)]) => Some(Ok(match path.rsplit_once('/') {
+    ///             Some((parent, _)) => parent.into(),
+    ///             None => ".".into(),
+    ///         })),
+    ///         ("sys_get_temp_dir", []) => Some(Ok("/tmp".into())),
+    ///         _ => None,
+    ///     });
+    /// let map = from_str_with_options::<Value>(
+    ///     "['host' => getenv('DB_HOST'), 'root' => dirname(__DIR__), 'tmp' => sys_get_temp_dir()]",
+    ///     &options,
+    /// )?;
+    ///
+    /// assert_eq!(map["host"], "db.example.com");
+    /// assert_eq!(map["root"], "/var/www");
+    /// assert_eq!(map["tmp"], "/tmp");
+    /// assert!(from_str_with_options::<Value>("exec('ls')", &options).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn function_evaluator<E: FunctionEvaluator + 'static>(mut self, evaluator: E) -> Self {
+        self.functions.set_evaluator(Arc::new(evaluator));
+        self
+    }
+
+    /// Define a constant that can be used in place of a value or as operand of the bitwise operators
+    ///
+    /// Constant names are case-sensitive, a leading `\` is ignored.
+    /// Class constants are defined by their qualified name, like `Monolog\Logger::WARNING`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new()
+    ///     .constant("E_ALL", 32767)
+    ///     .constant("E_DEPRECATED", 8192);
+    /// let map = from_str_with_options::<Value>(r#"["level" => E_ALL & ~\E_DEPRECATED]"#, &options)?;
+    ///
+    /// assert_eq!(map["level"], 24575);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn constant(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.constants.insert(name, value.into());
+        self
+    }
+
+    /// Define a variable that is interpolated into double quoted strings and heredocs
+    ///
+    /// Defining a variable enables interpolation of `$name`, `{$name}` and `${name}` in strings,
+    /// using a variable that isn't defined is an error. Without any defined variables, strings are taken literally.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new()
+    ///     .variable("host", "example.com")
+    ///     .variable("prefix", "oc");
+    /// let map = from_str_with_options::<Value>(
+    ///     r#"['url' => "https://$host/path", 'table' => "{$prefix}_users", 'raw' => '$host']"#,
+    ///     &options,
+    /// )?;
+    ///
+    /// assert_eq!(map["url"], "https://example.com/path");
+    /// assert_eq!(map["table"], "oc_users");
+    /// assert_eq!(map["raw"], "$host");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn variable(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.variables
+            .get_or_insert_with(HashMap::new)
+            .insert(name.into(), value.into());
+        self
+    }
+
+    /// Evaluate common builtin constants like `PHP_INT_MAX`, `PHP_EOL`, `M_PI`, `DIRECTORY_SEPARATOR` and the `E_*` error levels
+    ///
+    /// Platform dependent constants use the values of php on 64-bit unix systems.
+    /// Constants defined with [`constant`](ParserOptions::constant) or a [`constant_resolver`](ParserOptions::constant_resolver)
+    /// take precedence over builtin constants.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().builtin_constants(true);
+    /// let map = from_str_with_options::<Value>(
+    ///     "['max' => PHP_INT_MAX, 'level' => E_ALL & ~E_DEPRECATED, 'eol' => PHP_EOL]",
+    ///     &options,
+    /// )?;
+    ///
+    /// assert_eq!(map["max"], i64::MAX);
+    /// assert_eq!(map["level"], 24575);
+    /// assert_eq!(map["eol"], "\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builtin_constants(mut self, builtin_constants: bool) -> Self {
+        self.constants.builtin = builtin_constants;
+        self
+    }
+
+    /// Use a resolver for constants that aren't defined with [`constant`](ParserOptions::constant)
+    ///
+    /// The resolver receives the name of the constant without leading `\`, class constants are passed
+    /// as qualified name like `Monolog\Logger::WARNING`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().constant_resolver(|name: &str| {
+    ///     let name = name.strip_prefix("MY_APP_")?;
+    ///     Some(Value::from(name.to_ascii_lowercase()))
+    /// });
+    /// let map = from_str_with_options::<Value>("['mode' => MY_APP_DEBUG]", &options)?;
+    ///
+    /// assert_eq!(map["mode"], "debug");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn constant_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: ConstantResolver + 'static,
+    {
+        self.constants.set_resolver(Arc::new(resolver));
+        self
+    }
+
+    /// Evaluate constants that can't be resolved to their name as string, like php 7 does, instead of failing the parse
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().unresolved_constants_as_strings(true);
+    /// let map = from_str_with_options::<Value>("['driver' => redis]", &options)?;
+    ///
+    /// assert_eq!(map["driver"], "redis");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unresolved_constants_as_strings(
+        mut self,
+        unresolved_constants_as_strings: bool,
+    ) -> Self {
+        self.constants.unresolved_as_string = unresolved_constants_as_strings;
+        self
+    }
+
+    /// Set the path of the file being parsed, used as value for the `__FILE__` and `__DIR__` constants
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let options = ParserOptions::new().file_path("/var/www/config/config.php");
+    /// let map = from_str_with_options::<Value>("['datadirectory' => __DIR__ . '/../data']", &options)?;
+    ///
+    /// assert_eq!(map["datadirectory"], "/var/www/config/../data");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn file_path(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        self.constants.file = Some((
+            path.to_string_lossy().into_owned(),
+            dir.to_string_lossy().into_owned(),
+        ));
+        self
+    }
+}
+
+/// Quotes used for strings when writing php literals
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum QuoteStyle {
+    /// `'foo'`, only `\` and `'` are escaped
+    #[default]
+    Single,
+    /// `"foo"`, control characters and `$` are escaped
+    Double,
+}
+
+/// Options to control how php literals are written
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{to_string_with_options, ArraySyntax, QuoteStyle, SerializeOptions};
+/// # use std::collections::BTreeMap;
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let options = SerializeOptions::new()
+///     .array_syntax(ArraySyntax::Long)
+///     .quote_style(QuoteStyle::Double)
+///     .indent(4);
+/// let map: BTreeMap<_, _> = vec![("foo", vec![1, 2])].into_iter().collect();
+///
+/// assert_eq!(to_string_with_options(&map, &options)?, r#"array(
+///     "foo" => array(
+///         1,
+///         2
+///     )
+/// )"#);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SerializeOptions {
+    pub(crate) array_syntax: ArraySyntax,
+    pub(crate) quote_style: QuoteStyle,
+    pub(crate) indent: usize,
+    pub(crate) sort_keys: bool,
+    pub(crate) explicit_keys: bool,
+    pub(crate) trailing_comma: bool,
+    pub(crate) align_arrows: bool,
+    pub(crate) reject_non_finite: bool,
+    pub(crate) compact: bool,
+}
+
+impl SerializeOptions {
+    /// Create the default options, writing everything on a single line with short array syntax and single quotes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `[...]` or `array(...)` for arrays
+    pub fn array_syntax(mut self, array_syntax: ArraySyntax) -> Self {
+        self.array_syntax = array_syntax;
+        self
+    }
+
+    /// Use single or double quotes for strings
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Write every array entry on its own line, indented by the given number of spaces per level
+    ///
+    /// An indent of 0 writes everything on a single line.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Write array entries sorted by key instead of in iteration order
+    ///
+    /// Integer keys are written before string keys, this makes the output deterministic
+    /// even for types like `HashMap` that don't have a stable iteration order.
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Always write the keys of array entries
+    ///
+    /// By default, sequences and entries with consecutive integer keys starting at 0 are written without keys (`[1, 2]`),
+    /// with this option they are written as `[0 => 1, 1 => 2]`.
+    pub fn explicit_keys(mut self, explicit_keys: bool) -> Self {
+        self.explicit_keys = explicit_keys;
+        self
+    }
+
+    /// Write a comma after the last entry of an array
+    ///
+    /// Only applies when entries are written on their own line with [`indent`](SerializeOptions::indent).
+    pub fn trailing_comma(mut self, trailing_comma: bool) -> Self {
+        self.trailing_comma = trailing_comma;
+        self
+    }
+
+    /// Pad the keys of an array so the `=>` arrows of all entries line up
+    ///
+    /// Only applies when entries are written on their own line with [`indent`](SerializeOptions::indent).
+    pub fn align_arrows(mut self, align_arrows: bool) -> Self {
+        self.align_arrows = align_arrows;
+        self
+    }
+
+    /// Leave out the optional spaces after commas and around `=>` arrows
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Fail with [`SerializeError::NonFiniteFloat`](crate::SerializeError::NonFiniteFloat) for infinite and NaN floats
+    ///
+    /// By default, these are written as the `INF`, `-INF` and `NAN` constants.
+    pub fn reject_non_finite(mut self, reject_non_finite: bool) -> Self {
+        self.reject_non_finite = reject_non_finite;
+        self
+    }
+}
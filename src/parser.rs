@@ -1,22 +1,54 @@
 use crate::error::{ExpectToken, ParseError, ResultExt};
+use crate::eval::{expression_error, resolve_constant, undefined_constant};
 use crate::lexer::{SpannedToken, Token, TokenStream};
-use crate::num::parse_int;
-use crate::string::{is_array_key_numeric, parse_string};
-use crate::{Key, Value};
+use crate::num::{parse_int, parse_int_as_float, ParseIntError};
+use crate::string::{is_array_key_numeric, parse_interpolated, parse_string, StringPart};
+use crate::{Key, ParserOptions, PhpType, Value};
 use logos::Logos;
+use std::collections::HashMap;
 use std::iter::Peekable;
 use std::num::ParseFloatError;
+use std::rc::Rc;
 
 pub struct Parser<'source> {
     source: &'source str,
+    /// The input the source was decoded from, string literals are read from these bytes
+    bytes: &'source [u8],
     tokens: Peekable<TokenStream<'source>>,
+    options: ParserOptions,
+    /// Number of array entries parsed so far
+    elements: usize,
 }
 
 impl<'source> Parser<'source> {
-    pub fn new(source: &'source str) -> Self {
+    pub fn new(source: &'source str, options: ParserOptions) -> Self {
+        Self::with_bytes(source, source.as_bytes(), options)
+    }
+
+    /// Parse `source` with the string literals taken from `bytes`
+    ///
+    /// `source` has to have the same length as `bytes`, with only the bytes that aren't valid UTF-8 replaced,
+    /// see [`escape_invalid_utf8`](crate::encoding::escape_invalid_utf8).
+    pub(crate) fn with_bytes(
+        source: &'source str,
+        bytes: &'source [u8],
+        options: ParserOptions,
+    ) -> Self {
+        debug_assert_eq!(source.len(), bytes.len());
         Parser {
             source,
+            bytes,
             tokens: TokenStream::new(Token::lexer(source)).peekable(),
+            options,
+            elements: 0,
+        }
+    }
+
+    /// Take the tokens from a previous parse of the same source instead of lexing it again
+    pub(crate) fn replay(self, tokens: Rc<[SpannedToken<'source>]>) -> Self {
+        Parser {
+            tokens: TokenStream::replay(tokens).peekable(),
+            ..self
         }
     }
 
@@ -27,9 +59,9 @@ impl<'source> Parser<'source> {
     pub fn parse_literal(&self, token: SpannedToken) -> Result<Value, ParseError> {
         let value = match token.token {
             Token::Bool => Value::Bool(self.parse_bool_token(token)?),
-            Token::Integer => Value::Int(self.parse_int_token(token)?),
+            Token::Integer => self.parse_number_token(token)?,
             Token::Float => Value::Float(self.parse_float_token(token)?),
-            Token::LiteralString => Value::String(self.parse_string_token(token)?),
+            Token::LiteralString => Value::from_bytes(self.parse_string_bytes(token)?),
             Token::Null => Value::Null,
             _ => unreachable!(),
         };
@@ -49,12 +81,74 @@ impl<'source> Parser<'source> {
         parse_int(token.slice()).with_span(token.span, token.source)
     }
 
+    /// Parse an integer literal, integers that don't fit in 64 bits are parsed as float like php does
+    pub fn parse_number_token(&self, token: SpannedToken) -> Result<Value, ParseError> {
+        match parse_int(token.slice()) {
+            Ok(int) => Ok(Value::Int(int)),
+            Err(ParseIntError::Overflow) => parse_int_as_float(token.slice())
+                .map(Value::Float)
+                .with_span(token.span, token.source),
+            Err(err) => Err(err).with_span(token.span, token.source),
+        }
+    }
+
     pub fn parse_float_token(&self, token: SpannedToken) -> Result<f64, ParseError> {
         parse_float(token.slice()).with_span(token.span, token.source)
     }
 
+    /// Parse a string literal, replacing invalid UTF-8 in the string
     pub fn parse_string_token(&self, token: SpannedToken) -> Result<String, ParseError> {
-        parse_string(token.slice()).with_span(token.span, token.source)
+        let bytes = self.parse_string_bytes(token)?;
+        Ok(String::from_utf8(bytes)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
+    }
+
+    /// Parse a string literal into the bytes of the string
+    pub fn parse_string_bytes(&self, token: SpannedToken) -> Result<Vec<u8>, ParseError> {
+        let string = match &self.options.variables {
+            Some(variables) => self.interpolate(token, variables)?,
+            None => parse_string(self.literal_bytes(&token)).with_span(token.span, token.source)?,
+        };
+        self.check_string_length(string.len())?;
+        Ok(string)
+    }
+
+    /// Parse a string literal, replacing variables with their value
+    fn interpolate(
+        &self,
+        token: SpannedToken,
+        variables: &HashMap<String, Value>,
+    ) -> Result<Vec<u8>, ParseError> {
+        let parts = parse_interpolated(self.literal_bytes(&token))
+            .with_span(token.span.clone(), token.source)?;
+        let mut string = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Text(text) => string.extend_from_slice(&text),
+                StringPart::Variable(name) => {
+                    let value = variables.get(&name).ok_or_else(|| {
+                        expression_error(self, &token, format!("Undefined variable ${}", name))
+                    })?;
+                    match value {
+                        Value::Array(_) => None,
+                        value => value.cast_to(PhpType::String),
+                    }
+                    .and_then(|value| {
+                        value
+                            .as_bytes()
+                            .map(|bytes| string.extend_from_slice(bytes))
+                    })
+                    .ok_or_else(|| {
+                        expression_error(
+                            self,
+                            &token,
+                            format!("Can't interpolate {} ${}", value.php_type(), name),
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(string)
     }
 
     pub fn parse_array_key(&self, token: SpannedToken) -> Result<Key, ParseError> {
@@ -65,38 +159,107 @@ impl<'source> Parser<'source> {
                 Token::Float,
                 Token::LiteralString,
                 Token::Null,
+                Token::Identifier,
             ],
             self.source,
         )?;
-        Ok(match self.parse_literal(token)? {
-            Value::Int(int) => Key::Int(int),
-            Value::Float(float) => Key::Int(float as i64),
-            Value::String(str) if is_array_key_numeric(&str) => Key::Int(parse_int(&str).unwrap()),
-            Value::String(str) => Key::String(str),
-            Value::Bool(bool) => Key::Int(if bool { 1 } else { 0 }),
-            Value::Null => Key::String(String::from("")),
-            _ => unreachable!(),
+        let value = match token.token {
+            Token::Identifier => match resolve_constant(self, token.slice()) {
+                Some(Value::Array(_) | Value::Expr(_) | Value::Instance(_) | Value::Object(_)) => {
+                    return Err(expression_error(self, &token, "Illegal offset type"))
+                }
+                Some(value) => value,
+                None => return Err(undefined_constant(self, &token)),
+            },
+            _ => self.parse_literal(token)?,
+        };
+        let key = match value {
+            Value::String(str) if self.options.keep_numeric_string_keys => Some(Key::String(str)),
+            value => key_from_value(value),
+        };
+        Ok(match key {
+            Some(Key::String(str)) if self.options.lowercase_keys => {
+                Key::String(str.to_ascii_lowercase())
+            }
+            Some(key) => key,
+            None => unreachable!(),
         })
     }
 
+    /// Fail if a string of `length` bytes is longer than the maximum string length
+    pub(crate) fn check_string_length(&self, length: usize) -> Result<(), ParseError> {
+        match self.options.max_string_length {
+            Some(limit) if length > limit => Err(ParseError::StringLengthLimit(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count an array entry against the element limits, `entries` is the number of entries
+    /// in its array including this one
+    pub(crate) fn count_entry(&mut self, entries: usize) -> Result<(), ParseError> {
+        if let Some(limit) = self.options.max_array_entries {
+            if entries > limit {
+                return Err(ParseError::ArrayEntryLimit(limit));
+            }
+        }
+        self.elements += 1;
+        match self.options.max_elements {
+            Some(limit) if self.elements > limit => Err(ParseError::ElementLimit(limit)),
+            _ => Ok(()),
+        }
+    }
+
     pub fn source(&self) -> &'source str {
         self.source
     }
+
+    /// The raw bytes of a token, which can contain invalid UTF-8 in string literals
+    pub(crate) fn literal_bytes(&self, token: &SpannedToken) -> &'source [u8] {
+        &self.bytes[token.span.range()]
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// Define a constant for the values parsed after it
+    pub(crate) fn define_constant(&mut self, name: &str, value: Value) {
+        self.options.constants.insert(name, value);
+    }
+}
+
+/// Convert a value used as array key the way php does, `None` for values that are illegal offsets
+pub(crate) fn key_from_value(value: Value) -> Option<Key> {
+    Some(match value {
+        Value::Int(int) => Key::Int(int),
+        Value::Float(float) => Key::Int(float as i64),
+        Value::String(str) if is_array_key_numeric(&str) => Key::Int(str.parse().unwrap()),
+        Value::String(str) => Key::String(str),
+        Value::Bytes(bytes) => Key::String(String::from_utf8_lossy(&bytes).into()),
+        Value::Bool(bool) => Key::Int(if bool { 1 } else { 0 }),
+        Value::Null => Key::String(String::from("")),
+        _ => return None,
+    })
 }
 
+/// Parse a float literal, `INF` and `NAN` are handled by rust's float parsing
 fn parse_float(literal: &str) -> Result<f64, ParseFloatError> {
     let stripped = literal.replace('_', "");
     stripped.parse()
 }
 
-#[derive(Eq, PartialEq, Copy, Clone)]
+/// The syntax used for an array literal
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
 pub enum ArraySyntax {
+    /// `[1, 2]`
+    #[default]
     Short,
+    /// `array(1, 2)`
     Long,
 }
 
 impl ArraySyntax {
-    pub fn close_bracket(&self) -> Token {
+    pub(crate) fn close_bracket(&self) -> Token {
         match self {
             ArraySyntax::Long => Token::BracketClose,
             ArraySyntax::Short => Token::SquareClose,
@@ -0,0 +1,109 @@
+use crate::{Array, Key, Value};
+
+impl Value {
+    /// Keep only the array entries for which the predicate returns `true`
+    ///
+    /// Only the entries of this array are visited, nested arrays are left untouched.
+    /// Does nothing if the value isn't an array.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let mut value = from_str::<Value>(r#"["debug" => false, "deprecated_option" => 1]"#)?;
+    /// value.retain(|key, _| key.as_str() != Some("deprecated_option"));
+    ///
+    /// assert_eq!(value, from_str::<Value>(r#"["debug" => false]"#)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&Key, &mut Value) -> bool,
+    {
+        if let Value::Array(map) = self {
+            map.retain(f);
+        }
+    }
+
+    /// Recursively transform or remove array entries
+    ///
+    /// Nested arrays are processed before the entry containing them is passed to `f`,
+    /// so an array that ends up empty after pruning its entries can be removed itself.
+    /// Entries for which `f` returns `None` are removed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = from_str::<Value>(r#"["db" => ["host" => null], "name" => "foo"]"#)?;
+    /// let pruned = value.filter_map(|_, value| match value {
+    ///     Value::Null => None,
+    ///     Value::Array(map) if map.is_empty() => None,
+    ///     value => Some(value),
+    /// });
+    ///
+    /// assert_eq!(pruned, from_str::<Value>(r#"["name" => "foo"]"#)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn filter_map<F>(self, mut f: F) -> Value
+    where
+        F: FnMut(&Key, Value) -> Option<Value>,
+    {
+        filter_map_value(self, &mut f)
+    }
+}
+
+fn filter_map_value<F>(value: Value, f: &mut F) -> Value
+where
+    F: FnMut(&Key, Value) -> Option<Value>,
+{
+    match value {
+        Value::Array(map) => Value::Array(
+            map.into_iter()
+                .filter_map(|(key, value)| {
+                    let value = filter_map_value(value, f);
+                    f(&key, value).map(|value| (key, value))
+                })
+                .collect::<Array>(),
+        ),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, Value};
+
+    #[test]
+    fn test_filter_map_nested() {
+        let value = from_str::<Value>(
+            r#"[
+                "db" => ["host" => "localhost", "port" => 3306],
+                "apps" => ["files" => ["enabled" => false]],
+                "version" => 1,
+            ]"#,
+        )
+        .unwrap();
+        let value = value.filter_map(|key, value| match (key, value) {
+            (key, _) if key.as_str() == Some("enabled") => None,
+            (_, Value::Array(map)) if map.is_empty() => None,
+            (_, Value::Int(int)) => Some(Value::Int(int * 2)),
+            (_, value) => Some(value),
+        });
+        assert_eq!(
+            value,
+            from_str::<Value>(
+                r#"["db" => ["host" => "localhost", "port" => 6612], "version" => 2]"#
+            )
+            .unwrap()
+        );
+    }
+}
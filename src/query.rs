@@ -0,0 +1,238 @@
+use crate::array::ArrayExt;
+use crate::cast::float_to_shortest_string;
+use crate::string::is_array_key_numeric;
+use crate::{Array, Key, Value};
+use std::fmt::Write;
+
+impl Value {
+    /// Encode the value as a query string the way php's `http_build_query` does
+    ///
+    /// Nested arrays are encoded as `a%5Bb%5D%5B0%5D=x`, booleans as `1` and `0` and
+    /// `null` values, empty arrays and expressions are left out. Keys are written in sorted order.
+    ///
+    /// Only available with the `query` feature enabled.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = from_str::<Value>(r#"["a" => ["b" => ["x", "y z"]], "debug" => true]"#)?;
+    ///
+    /// assert_eq!(value.to_query_string(), "a%5Bb%5D%5B0%5D=x&a%5Bb%5D%5B1%5D=y+z&debug=1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_query_string(&self) -> String {
+        let mut out = String::new();
+        if let Value::Array(map) = self {
+            write_entries(&mut out, None, map);
+        }
+        out
+    }
+
+    /// Decode a query string into an array the way php's `parse_str` does
+    ///
+    /// All values are decoded as strings, `a[]=x` appends to the array and
+    /// `.` or space in the top level name are replaced with `_`.
+    ///
+    /// Only available with the `query` feature enabled.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::Value;
+    ///
+    /// let value = Value::from_query_string("a[b][]=x&a[b][]=y+z&debug=1");
+    ///
+    /// assert_eq!(value["a"]["b"][1], "y z");
+    /// assert_eq!(value["debug"], "1");
+    /// ```
+    pub fn from_query_string(query: &str) -> Value {
+        let mut result = Value::Array(Array::new());
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let name = url_decode(name);
+            // like php, values that can't be appended because the next key is taken are dropped
+            let target = split_name(&name).and_then(|(name, segments)| {
+                let target = entry(&mut result, Some(&name))?;
+                segments
+                    .into_iter()
+                    .try_fold(target, |target, segment| entry(target, segment))
+            });
+            if let Some(target) = target {
+                *target = Value::String(url_decode(value));
+            }
+        }
+        result
+    }
+}
+
+fn write_entries(out: &mut String, prefix: Option<&str>, map: &Array) {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    for (key, value) in entries {
+        let name = match prefix {
+            Some(prefix) => format!("{}%5B{}%5D", prefix, url_encode(key.to_string())),
+            None => url_encode(key.to_string()),
+        };
+        let encoded = match value {
+            Value::Array(map) => {
+                write_entries(out, Some(&name), map);
+                continue;
+            }
+            Value::Object(object) => {
+                write_entries(out, Some(&name), &object.properties);
+                continue;
+            }
+            Value::Bool(bool) => (*bool as u8).to_string(),
+            Value::Int(int) => int.to_string(),
+            Value::Float(float) => url_encode(float_to_shortest_string(*float)),
+            Value::String(str) => url_encode(str),
+            Value::Bytes(bytes) => url_encode(bytes),
+            Value::Null | Value::Expr(_) | Value::Instance(_) => continue,
+        };
+        if !out.is_empty() {
+            out.push('&');
+        }
+        write!(out, "{}={}", name, encoded).unwrap();
+    }
+}
+
+/// Split a parameter name into the top level name and the bracketed segments, `None` segments are appends
+fn split_name(name: &str) -> Option<(String, Vec<Option<&str>>)> {
+    let (base, mut rest) = match name.find('[') {
+        Some(index) if index > 0 => name.split_at(index),
+        _ if name.is_empty() => return None,
+        _ => (name, ""),
+    };
+    let mut segments = Vec::new();
+    if !rest.is_empty() && !rest.contains(']') {
+        // php replaces an unclosed first bracket with `_` and keeps the rest of the name
+        return Some((
+            name.replacen('[', "_", 1).replace(['.', ' '], "_"),
+            segments,
+        ));
+    }
+    while let Some(inner) = rest.strip_prefix('[') {
+        match inner.find(']') {
+            Some(end) => {
+                let segment = &inner[..end];
+                segments.push(if segment.is_empty() {
+                    None
+                } else {
+                    Some(segment)
+                });
+                rest = &inner[end + 1..];
+            }
+            // php ignores everything after an unclosed bracket
+            None => break,
+        }
+    }
+    Some((base.replace(['.', ' '], "_"), segments))
+}
+
+/// Get the entry with the key in the array, creating it if needed, or append a new entry if there is no key
+///
+/// Returns `None` if a new entry can't be appended because the array has an entry with key `i64::MAX`.
+fn entry<'a>(target: &'a mut Value, key: Option<&str>) -> Option<&'a mut Value> {
+    if !target.is_array() {
+        *target = Value::Array(Array::new());
+    }
+    let map = match target {
+        Value::Array(map) => map,
+        _ => unreachable!(),
+    };
+    let key = match key {
+        Some(key) if is_array_key_numeric(key) => match key.parse() {
+            Ok(int) => Key::Int(int),
+            Err(_) => Key::String(key.into()),
+        },
+        Some(key) => Key::String(key.into()),
+        None => Key::Int(map.next_index()?),
+    };
+    Some(map.entry(key).or_insert(Value::Null))
+}
+
+fn url_encode(str: impl AsRef<[u8]>) -> String {
+    let str = str.as_ref();
+    let mut out = String::with_capacity(str.len());
+    for &byte in str {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' => out.push(byte as char),
+            b' ' => out.push('+'),
+            byte => write!(out, "%{:02X}", byte).unwrap(),
+        }
+    }
+    out
+}
+
+fn url_decode(str: &str) -> String {
+    let bytes = str.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => out.push(b' '),
+            b'%' => match bytes
+                .get(index + 1..index + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    out.push(byte);
+                    index += 2;
+                }
+                None => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, Value};
+
+    #[test]
+    fn test_query_roundtrip() {
+        let value = from_str::<Value>(
+            r#"[
+                "user name" => "Jöhn & co",
+                "ids" => [1, 2, 3],
+                "filter" => ["type" => "a=b", "nested" => ["deep" => 1.5]],
+                "skipped" => null,
+            ]"#,
+        )
+        .unwrap();
+        let query = value.to_query_string();
+        assert_eq!(
+            query,
+            "filter%5Bnested%5D%5Bdeep%5D=1.5&filter%5Btype%5D=a%3Db&ids%5B0%5D=1&ids%5B1%5D=2&ids%5B2%5D=3&user+name=J%C3%B6hn+%26+co"
+        );
+        let decoded = Value::from_query_string(&query);
+        assert_eq!(decoded["user_name"], "Jöhn & co");
+        assert_eq!(decoded["ids"][2], "3");
+        assert_eq!(decoded["filter"]["nested"]["deep"], "1.5");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let value = Value::from_query_string("a[]=1&a[]=2&a[5]=x&a[]=3&b[c=1&=skip&flag&%zz=1");
+        assert_eq!(value["a"][0], "1");
+        assert_eq!(value["a"][1], "2");
+        assert_eq!(value["a"][5], "x");
+        assert_eq!(value["a"][6], "3");
+        assert_eq!(value["b_c"], "1");
+        assert_eq!(value["flag"], "");
+        assert_eq!(value["%zz"], "1");
+
+        let value = Value::from_query_string("a[9223372036854775807]=1&a[]=2&b=3");
+        assert_eq!(value["a"].keys().count(), 1);
+        assert_eq!(value["b"], "3");
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{ParseError, WithSource};
+use miette::{GraphicalReportHandler, GraphicalTheme};
+
+/// How errors are rendered by [`ParseError::render`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStyle {
+    /// A snippet of the source with the offending part highlighted using ansi colors, for terminals
+    Colored,
+    /// A snippet of the source with the offending part marked in plain ascii text, for log files
+    Plain,
+    /// A single line with the location, message and code of the error, for structured logging
+    Compact,
+}
+
+impl ParseError {
+    /// Render the error for displaying to users
+    ///
+    /// `source` is the input the error occurred in, it's used to show the offending part of the input.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, RenderStyle, Value};
+    ///
+    /// let source = "[\n  1 2\n]";
+    /// let error = from_str::<Value>(source).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     error.render(source, RenderStyle::Compact),
+    ///     "2:5: Unexpected token, found integer literal expected one of ',' or ']' [PLP0001]"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str, style: RenderStyle) -> String {
+        self.with_source(source).render(style)
+    }
+}
+
+impl WithSource<'_> {
+    /// Render the error for displaying to users, see [`ParseError::render`]
+    ///
+    /// The name of the source is included in every style.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, RenderStyle, Value};
+    ///
+    /// let source = "[1, 2 3]";
+    /// let error = from_str::<Value>(source).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     error.with_source(source).with_name("config.php").render(RenderStyle::Compact),
+    ///     "config.php:1:7: Unexpected token, found integer literal expected one of ',' or ']' [PLP0001]"
+    /// );
+    /// ```
+    pub fn render(&self, style: RenderStyle) -> String {
+        let theme = match style {
+            RenderStyle::Colored => GraphicalTheme::unicode(),
+            RenderStyle::Plain => GraphicalTheme::none(),
+            RenderStyle::Compact => return render_compact(self.error(), self.name()),
+        };
+        let mut rendered = String::new();
+        GraphicalReportHandler::new_themed(theme)
+            .render_report(&mut rendered, self)
+            .expect("writing to a string can't fail");
+        rendered
+    }
+}
+
+fn render_compact(error: &ParseError, name: Option<&str>) -> String {
+    let message = match error {
+        ParseError::Fields(errors) => {
+            let fields: Vec<_> = errors.errors().iter().map(ToString::to_string).collect();
+            fields.join("; ")
+        }
+        error => error.to_string(),
+    };
+    let location = match (name, error.line(), error.column()) {
+        (Some(name), Some(line), Some(column)) => format!("{}:{}:{}: ", name, line, column),
+        (None, Some(line), Some(column)) => format!("{}:{}: ", line, column),
+        (Some(name), _, _) => format!("{}: ", name),
+        (None, _, _) => String::new(),
+    };
+    format!("{}{} [{}]", location, message, error.error_code())
+}
@@ -0,0 +1,120 @@
+use crate::error::FieldError;
+use crate::ParseError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// A machine-readable description of a [`ParseError`], see [`ParseError::report`]
+///
+/// Serializes to a structure like
+///
+/// ```json
+/// {
+///   "code": "PLP0001",
+///   "message": "Unexpected token, found integer literal expected one of ',' or ']'",
+///   "path": null,
+///   "span": {"start": 6, "end": 7, "line": 1, "column": 7},
+///   "expected": ["','", "']'"],
+///   "related": []
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// The stable code of the error kind, see [`ParseError::error_code`]
+    pub code: &'static str,
+    pub message: String,
+    /// The dotted path of the field the error occurred in, for errors of failing fields
+    pub path: Option<String>,
+    /// The offending part of the input
+    pub span: Option<ReportSpan>,
+    /// The tokens that were expected, for unexpected tokens
+    pub expected: Vec<String>,
+    /// The reports of all failing fields, for [`ParseError::Fields`]
+    pub related: Vec<ErrorReport>,
+}
+
+/// Location of an error in the input, lines and columns start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportSpan {
+    /// Byte offset of the start of the span
+    pub start: usize,
+    /// Byte offset of the end of the span, exclusive
+    pub end: usize,
+    pub line: usize,
+    /// Column in characters
+    pub column: usize,
+}
+
+impl ErrorReport {
+    pub(crate) fn new(error: &ParseError) -> Self {
+        let span = error.span().map(|span| {
+            let position = span.position().unwrap();
+            ReportSpan {
+                start: span.start(),
+                end: span.end(),
+                line: position.line,
+                column: position.column,
+            }
+        });
+        let expected = match error {
+            ParseError::UnexpectedToken(err) => err
+                .expected
+                .tokens()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            _ => Vec::new(),
+        };
+        let related = match error {
+            ParseError::Fields(errors) => errors.errors().iter().map(field_report).collect(),
+            _ => Vec::new(),
+        };
+        ErrorReport {
+            code: error.error_code(),
+            message: match error {
+                ParseError::Fields(errors) => errors.summary(),
+                error => error.to_string(),
+            },
+            path: None,
+            span,
+            expected,
+            related,
+        }
+    }
+}
+
+fn field_report(field: &FieldError) -> ErrorReport {
+    ErrorReport {
+        path: Some(field.path().into()),
+        ..ErrorReport::new(field.error())
+    }
+}
+
+impl Serialize for ErrorReport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut report = serializer.serialize_struct("ErrorReport", 6)?;
+        report.serialize_field("code", self.code)?;
+        report.serialize_field("message", &self.message)?;
+        report.serialize_field("path", &self.path)?;
+        report.serialize_field("span", &self.span)?;
+        report.serialize_field("expected", &self.expected)?;
+        report.serialize_field("related", &self.related)?;
+        report.end()
+    }
+}
+
+impl Serialize for ReportSpan {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut span = serializer.serialize_struct("ReportSpan", 4)?;
+        span.serialize_field("start", &self.start)?;
+        span.serialize_field("end", &self.end)?;
+        span.serialize_field("line", &self.line)?;
+        span.serialize_field("column", &self.column)?;
+        span.end()
+    }
+}
@@ -0,0 +1,1328 @@
+use crate::cast::float_to_shortest_string;
+use crate::error::SerializeError;
+use crate::serde_impl::{EXPR_TOKEN, INSTANCE_TOKEN, OBJECT_TOKEN};
+use crate::{to_value, Array, ArraySyntax, Instance, Object, QuoteStyle, SerializeOptions, Value};
+use serde::ser::{self, Impossible, Serialize};
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+type Result<T> = std::result::Result<T, SerializeError>;
+
+/// Serializer that writes php literals
+///
+/// Maps and structs are written as associative arrays, sequences and tuples as lists,
+/// `None` and unit values as `null`. Enums use the same representation that is accepted when deserializing:
+/// unit variants are written as string and other variants as an array with the variant name as only key.
+pub struct Serializer {
+    output: String,
+    options: SerializeOptions,
+    depth: usize,
+    /// Special handling for the next value that is serialized
+    pending: Option<Pending>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pending {
+    /// The next string is php source that is written as is
+    Raw,
+    /// The next sequence is a list of constructor arguments
+    Args,
+}
+
+/// Write a value as php literal
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::to_string;
+/// use serde::Serialize;
+/// # use std::error::Error;
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     debug: bool,
+///     name: String,
+///     ports: Vec<u16>,
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let config = Config {
+///     debug: true,
+///     name: "it's".into(),
+///     ports: vec![80, 443],
+/// };
+///
+/// assert_eq!(
+///     to_string(&config)?,
+///     r#"['debug' => true, 'name' => 'it\'s', 'ports' => [80, 443]]"#
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    to_string_with_options(value, &SerializeOptions::default())
+}
+
+/// Write a value as php literal using the provided options
+pub fn to_string_with_options<T>(value: &T, options: &SerializeOptions) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer {
+        output: String::new(),
+        options: options.clone(),
+        depth: 0,
+        pending: None,
+    };
+    if options.sort_keys {
+        Sorted(&to_value(value)?).serialize(&mut serializer)?;
+    } else {
+        value.serialize(&mut serializer)?;
+    }
+    Ok(serializer.output)
+}
+
+/// Write a value as indented php literal with all array keys in sorted order
+///
+/// The output is stable regardless of the iteration order of maps in the value,
+/// making it suitable for diffing and snapshots.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::to_string_pretty;
+/// # use std::collections::HashMap;
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let mut map = HashMap::new();
+/// map.insert("foo", vec![1, 2]);
+/// map.insert("bar", vec![]);
+///
+/// assert_eq!(to_string_pretty(&map)?, r#"[
+///     'bar' => [],
+///     'foo' => [
+///         1,
+///         2
+///     ]
+/// ]"#);
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_string_pretty<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    to_string_with_options(value, &SerializeOptions::new().indent(4).sort_keys(true))
+}
+
+/// Write a value as compact php literal with a canonical representation
+///
+/// All array keys are written in sorted order without any optional whitespace and numbers are written
+/// in their shortest form. Equal values always produce the same output, making it suitable for hashing.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str, to_canonical_string, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let a = from_str::<Value>("array('b' => [1, 2], 'a' => 1.50)")?;
+/// let b = from_str::<Value>("[\n    'a' => 1.5, // comment\n    'b' => [0 => 1, 1 => 2],\n]")?;
+///
+/// assert_eq!(to_canonical_string(&a)?, "['a'=>1.5,'b'=>[1,2]]");
+/// assert_eq!(to_canonical_string(&a)?, to_canonical_string(&b)?);
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_canonical_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    to_string_with_options(
+        value,
+        &SerializeOptions::new().compact(true).sort_keys(true),
+    )
+}
+
+/// Serialize a value with the entries of all arrays sorted by key
+struct Sorted<'a>(&'a Value);
+
+impl Serialize for Sorted<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self.0 {
+            Value::Array(map) => SortedArray(map).serialize(serializer),
+            Value::Object(Object { class, properties }) => {
+                let mut object = serializer.serialize_struct(OBJECT_TOKEN, 2)?;
+                ser::SerializeStruct::serialize_field(&mut object, "class", class)?;
+                ser::SerializeStruct::serialize_field(
+                    &mut object,
+                    "properties",
+                    &SortedArray(properties),
+                )?;
+                ser::SerializeStruct::end(object)
+            }
+            Value::Instance(Instance { class, args }) => {
+                let mut instance = serializer.serialize_struct(INSTANCE_TOKEN, 2)?;
+                ser::SerializeStruct::serialize_field(&mut instance, "class", class)?;
+                let args: Vec<_> = args.iter().map(Sorted).collect();
+                ser::SerializeStruct::serialize_field(&mut instance, "args", &args)?;
+                ser::SerializeStruct::end(instance)
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+struct SortedArray<'a>(&'a Array);
+
+impl Serialize for SortedArray<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut entries: Vec<_> = self.0.iter().collect();
+        entries.sort_by_key(|(key, _)| *key);
+        serializer.collect_map(entries.into_iter().map(|(key, value)| (key, Sorted(value))))
+    }
+}
+
+impl Value {
+    /// Write the value as php literal that can be parsed back into the same value
+    ///
+    /// Strings and keys are quoted and escaped, captured expressions and object instantiations are written as php source.
+    /// Floats are written using the shortest notation that parses back to the exact same float.
+    /// Infinite and NaN floats are written as the `INF` and `NAN` constants.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let value = Value::from("it's");
+    /// let php = value.to_php_string()?;
+    ///
+    /// assert_eq!(php, r"'it\'s'");
+    /// assert_eq!(from_str::<Value>(&php)?, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_php_string(&self) -> Result<String> {
+        to_string(self)
+    }
+}
+
+impl Serializer {
+    fn write_str(&mut self, str: &str) {
+        match self.options.quote_style {
+            QuoteStyle::Single => {
+                self.output.push('\'');
+                for c in str.chars() {
+                    if matches!(c, '\\' | '\'') {
+                        self.output.push('\\');
+                    }
+                    self.output.push(c);
+                }
+                self.output.push('\'');
+            }
+            QuoteStyle::Double => {
+                self.output.push('"');
+                for c in str.chars() {
+                    self.write_double_quoted_char(c);
+                }
+                self.output.push('"');
+            }
+        }
+    }
+
+    fn write_double_quoted_char(&mut self, c: char) {
+        match c {
+            '\\' | '"' | '$' => {
+                self.output.push('\\');
+                self.output.push(c);
+            }
+            '\n' => self.output.push_str("\\n"),
+            '\r' => self.output.push_str("\\r"),
+            '\t' => self.output.push_str("\\t"),
+            '\x0B' => self.output.push_str("\\v"),
+            '\x0C' => self.output.push_str("\\f"),
+            c if c.is_ascii_control() => write!(self.output, "\\x{:02X}", c as u8).unwrap(),
+            c => self.output.push(c),
+        }
+    }
+
+    /// Write a byte string as double quoted string, bytes that aren't valid UTF-8 are written as `\x` escapes
+    fn write_bytes(&mut self, mut bytes: &[u8]) {
+        self.output.push('"');
+        while !bytes.is_empty() {
+            let (valid, invalid_len) = match std::str::from_utf8(bytes) {
+                Ok(valid) => (valid, 0),
+                Err(err) => (
+                    std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap(),
+                    err.error_len().unwrap_or(bytes.len() - err.valid_up_to()),
+                ),
+            };
+            for c in valid.chars() {
+                self.write_double_quoted_char(c);
+            }
+            let invalid = &bytes[valid.len()..valid.len() + invalid_len];
+            for byte in invalid {
+                write!(self.output, "\\x{:02X}", byte).unwrap();
+            }
+            bytes = &bytes[valid.len() + invalid_len..];
+        }
+        self.output.push('"');
+    }
+
+    /// Write a float in the notation php uses with `serialize_precision = -1`, which round trips exactly
+    ///
+    /// Infinite and NaN floats are written as the `INF` and `NAN` constants, unless they are rejected by the options
+    fn write_float(&mut self, float: f64) -> Result<()> {
+        if !float.is_finite() {
+            if self.options.reject_non_finite {
+                return Err(SerializeError::NonFiniteFloat(float));
+            }
+            self.output.push_str(&float_to_shortest_string(float));
+            return Ok(());
+        }
+        let formatted = float_to_shortest_string(float);
+        self.output.push_str(&formatted);
+        // make sure the value is parsed back as float
+        if !formatted.contains(['.', 'E']) {
+            self.output.push_str(".0");
+        }
+        Ok(())
+    }
+
+    fn open_array(&mut self) {
+        self.output.push_str(match self.options.array_syntax {
+            ArraySyntax::Short => "[",
+            ArraySyntax::Long => "array(",
+        });
+        self.depth += 1;
+    }
+
+    fn close_array(&mut self, empty: bool) {
+        self.depth -= 1;
+        if !empty {
+            if self.options.trailing_comma && self.options.indent > 0 {
+                self.output.push(',');
+            }
+            self.newline();
+        }
+        self.output.push(match self.options.array_syntax {
+            ArraySyntax::Short => ']',
+            ArraySyntax::Long => ')',
+        });
+    }
+
+    /// Start a new indented line if indentation is enabled
+    fn newline(&mut self) {
+        if self.options.indent > 0 {
+            self.output.push('\n');
+            let width = self.options.indent * self.depth;
+            write!(self.output, "{:width$}", "", width = width).unwrap();
+        }
+    }
+
+    /// Start writing a variant with content as `['Variant' => ...`
+    fn open_variant(&mut self, variant: &str) {
+        self.open_array();
+        self.newline();
+        self.write_str(variant);
+        self.write_arrow();
+    }
+
+    fn write_arrow(&mut self) {
+        self.output
+            .push_str(if self.options.compact { "=>" } else { " => " });
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = SerializeError;
+
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.output.push_str(if v { "true" } else { "false" });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.output.push_str(&v.to_string());
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        let int = i64::try_from(v).map_err(|_| SerializeError::IntegerOutOfRange(v.to_string()))?;
+        self.serialize_i64(int)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        let int = i64::try_from(v).map_err(|_| SerializeError::IntegerOutOfRange(v.to_string()))?;
+        self.serialize_i64(int)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        let int = i64::try_from(v).map_err(|_| SerializeError::IntegerOutOfRange(v.to_string()))?;
+        self.serialize_i64(int)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.write_float(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        if self.pending.take() == Some(Pending::Raw) {
+            self.output.push_str(v);
+        } else {
+            self.write_str(v);
+        }
+        Ok(())
+    }
+
+    // php strings are byte strings, bytes that aren't valid utf8 are escaped
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        match std::str::from_utf8(v) {
+            Ok(str) => self.write_str(str),
+            Err(_) => self.write_bytes(v),
+        }
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.output.push_str("null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == EXPR_TOKEN {
+            self.pending = Some(Pending::Raw);
+        }
+        let result = value.serialize(&mut *self);
+        self.pending = None;
+        result
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.open_variant(variant);
+        value.serialize(&mut *self)?;
+        self.close_array(false);
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        if self.pending.take() == Some(Pending::Args) {
+            return Ok(Compound::new(self, Kind::Args));
+        }
+        self.open_array();
+        Ok(Compound::new(self, Kind::Array))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.open_variant(variant);
+        self.open_array();
+        Ok(Compound::new(self, Kind::Variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.open_array();
+        Ok(Compound::new(self, Kind::Array))
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        if name == INSTANCE_TOKEN {
+            self.output.push_str("new ");
+            return Ok(Compound::new(self, Kind::Instance));
+        }
+        if name == OBJECT_TOKEN {
+            return Ok(Compound::new(self, Kind::Object));
+        }
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.open_variant(variant);
+        self.open_array();
+        Ok(Compound::new(self, Kind::Variant))
+    }
+}
+
+/// Writes the entries of an array
+pub struct Compound<'a> {
+    ser: &'a mut Serializer,
+    first: bool,
+    kind: Kind,
+    /// The key php would assign to the next entry, as long as all previous keys could be left out
+    next_index: Option<i64>,
+    /// Whether the key of the current map entry was left out
+    implicit_key: bool,
+    /// Where the key of the current map entry starts
+    key_start: usize,
+    /// Start and end of the written keys, used for aligning the arrows
+    keys: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Array,
+    /// An array wrapped in a variant array that needs to be closed too
+    Variant,
+    /// An object instantiation, with the class and arguments as fields
+    Instance,
+    /// An object, with the class and properties as fields
+    Object,
+    /// An object written as `__set_state` call that needs to be closed
+    SetState,
+    /// Constructor arguments, written without brackets on a single line
+    Args,
+}
+
+impl<'a> Compound<'a> {
+    fn new(ser: &'a mut Serializer, kind: Kind) -> Self {
+        Compound {
+            ser,
+            first: true,
+            kind,
+            next_index: Some(0),
+            implicit_key: false,
+            key_start: 0,
+            keys: Vec::new(),
+        }
+    }
+
+    fn separator(&mut self) {
+        if self.kind == Kind::Args {
+            if !self.first {
+                self.ser.output.push(',');
+                if !self.ser.options.compact {
+                    self.ser.output.push(' ');
+                }
+            }
+        } else {
+            if !self.first {
+                self.ser.output.push(',');
+                if self.ser.options.indent == 0 && !self.ser.options.compact {
+                    self.ser.output.push(' ');
+                }
+            }
+            self.ser.newline();
+        }
+        self.first = false;
+    }
+
+    fn element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.separator();
+        if self.kind != Kind::Args && self.ser.options.explicit_keys {
+            let index = self.next_index.get_or_insert(0);
+            write!(self.ser.output, "{}", index).unwrap();
+            self.ser.write_arrow();
+            *index += 1;
+        }
+        value.serialize(&mut *self.ser)
+    }
+
+    fn key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.separator();
+        let start = self.ser.output.len();
+        key.serialize(KeySerializer { ser: self.ser })?;
+        self.key_start = start;
+        self.implicit_key = false;
+        if let Some(index) = self.next_index {
+            if !self.ser.options.explicit_keys && self.ser.output[start..] == index.to_string() {
+                self.ser.output.truncate(start);
+                self.implicit_key = true;
+                self.next_index = Some(index + 1);
+            } else {
+                self.next_index = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn field<T>(&mut self, key: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.kind == Kind::Instance {
+            return self.instance_field(key, value);
+        }
+        if matches!(self.kind, Kind::Object | Kind::SetState) {
+            return self.object_field(key, value);
+        }
+        self.separator();
+        let start = self.ser.output.len();
+        self.ser.write_str(key);
+        self.arrow(start);
+        value.serialize(&mut *self.ser)
+    }
+
+    /// Write the arrow after a key starting at `start`
+    fn arrow(&mut self, start: usize) {
+        if self.ser.options.align_arrows && self.ser.options.indent > 0 {
+            self.keys.push((start, self.ser.output.len()));
+        }
+        self.ser.write_arrow();
+    }
+
+    /// Pad the keys of the written entries so all arrows line up
+    fn align_arrows(&mut self) {
+        let width = |(start, end): (usize, usize)| self.ser.output[start..end].chars().count();
+        let max = self
+            .keys
+            .iter()
+            .copied()
+            .map(width)
+            .max()
+            .unwrap_or_default();
+        let padding: Vec<_> = self.keys.iter().map(|key| max - width(*key)).collect();
+        // insert from the back so the positions of earlier keys stay valid
+        for ((_, end), padding) in self.keys.iter().zip(padding).rev() {
+            self.ser.output.insert_str(*end, &" ".repeat(padding));
+        }
+    }
+
+    /// Write the class as `Class(` or the arguments of an object instantiation
+    fn instance_field<T>(&mut self, key: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.ser.pending = Some(if key == "class" {
+            Pending::Raw
+        } else {
+            Pending::Args
+        });
+        let result = value.serialize(&mut *self.ser);
+        self.ser.pending = None;
+        if key == "class" {
+            self.ser.output.push('(');
+        }
+        result
+    }
+
+    /// Write the class as `(object)` cast or `__set_state` call, or the properties of an object
+    fn object_field<T>(&mut self, key: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if key != "class" {
+            return value.serialize(&mut *self.ser);
+        }
+        match to_value(value)? {
+            Value::String(class) if class.eq_ignore_ascii_case("stdClass") => {
+                self.ser.output.push_str("(object) ");
+            }
+            Value::String(class) => {
+                write!(self.ser.output, "\\{}::__set_state(", class).unwrap();
+                self.kind = Kind::SetState;
+            }
+            _ => return Err(ser::Error::custom("invalid object")),
+        }
+        Ok(())
+    }
+
+    fn close(mut self) -> Result<()> {
+        self.align_arrows();
+        match self.kind {
+            Kind::Array => self.ser.close_array(self.first),
+            Kind::Variant => {
+                self.ser.close_array(self.first);
+                self.ser.close_array(false);
+            }
+            Kind::Instance | Kind::SetState => self.ser.output.push(')'),
+            Kind::Object => {}
+            Kind::Args => {}
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if !self.implicit_key {
+            self.arrow(self.key_start);
+        }
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+/// Writes map keys, only allowing the types that php accepts as array key
+struct KeySerializer<'a> {
+    ser: &'a mut Serializer,
+}
+
+impl ser::Serializer for KeySerializer<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    type SerializeSeq = Impossible<(), SerializeError>;
+    type SerializeTuple = Impossible<(), SerializeError>;
+    type SerializeTupleStruct = Impossible<(), SerializeError>;
+    type SerializeTupleVariant = Impossible<(), SerializeError>;
+    type SerializeMap = Impossible<(), SerializeError>;
+    type SerializeStruct = Impossible<(), SerializeError>;
+    type SerializeStructVariant = Impossible<(), SerializeError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.ser.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.ser.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.ser.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.ser.serialize_i64(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.ser.serialize_i128(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.ser.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.ser.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.ser.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.ser.serialize_u64(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.ser.serialize_u128(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.ser.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.ser.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.ser.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(SerializeError::InvalidKey)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(SerializeError::InvalidKey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_canonical_string, to_string, to_string_pretty, to_string_with_options};
+    use crate::{
+        from_str, from_str_with_options, ArraySyntax, ParserOptions, QuoteStyle, SerializeOptions,
+        Value,
+    };
+    use serde_derive::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_struct_roundtrip() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Test {
+            int: u32,
+            float: f64,
+            seq: Vec<String>,
+            map: BTreeMap<i64, Option<bool>>,
+            text: String,
+        }
+
+        let test = Test {
+            int: 1,
+            float: 2.0,
+            seq: vec!["a".into(), "b".into()],
+            map: vec![(1, None), (-5, Some(false))].into_iter().collect(),
+            text: r"it's a \ 'quote'".into(),
+        };
+        let expected = r#"['int' => 1, 'float' => 2.0, 'seq' => ['a', 'b'], 'map' => [-5 => false, 1 => null], 'text' => 'it\'s a \\ \'quote\'']"#;
+        let php = to_string(&test).unwrap();
+        assert_eq!(php, expected);
+        assert_eq!(from_str::<Test>(&php).unwrap(), test);
+    }
+
+    #[test]
+    fn test_enum() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum E {
+            Unit,
+            Newtype(u32),
+            Tuple(u32, u32),
+            Struct { a: u32 },
+        }
+
+        let cases = [
+            (E::Unit, r#"'Unit'"#),
+            (E::Newtype(1), r#"['Newtype' => 1]"#),
+            (E::Tuple(1, 2), r#"['Tuple' => [1, 2]]"#),
+            (E::Struct { a: 1 }, r#"['Struct' => ['a' => 1]]"#),
+        ];
+        for (value, expected) in cases {
+            let php = to_string(&value).unwrap();
+            assert_eq!(php, expected);
+            assert_eq!(from_str::<E>(&php).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        #[derive(Serialize)]
+        enum E {
+            Struct { a: Vec<u32>, b: &'static str },
+        }
+
+        let text = "$var \"\\\n\x01";
+        let options = SerializeOptions::new()
+            .array_syntax(ArraySyntax::Long)
+            .quote_style(QuoteStyle::Double)
+            .indent(2);
+        let php = to_string_with_options(&E::Struct { a: vec![], b: text }, &options).unwrap();
+        assert_eq!(
+            php,
+            r#"array(
+  "Struct" => array(
+    "a" => array(),
+    "b" => "\$var \"\\\n\x01"
+  )
+)"#
+        );
+        assert_eq!(from_str::<crate::Value>(&php).unwrap()["Struct"]["b"], text);
+    }
+
+    #[test]
+    fn test_value() {
+        let options = ParserOptions::new().lenient(true).capture_objects(true);
+        let source = r#"['a' => null, 'b' => [1.5, true], 'c' => new \Foo(['x' => 1], 'y'), 'd' => fn() => 1]"#;
+        let value = from_str_with_options::<Value>(source, &options).unwrap();
+        let php = to_string(&value).unwrap();
+        assert_eq!(
+            from_str_with_options::<Value>(&php, &options).unwrap(),
+            value
+        );
+        assert!(php.contains(r#"'c' => new \Foo(['x' => 1], 'y')"#));
+        assert!(php.contains(r#"'d' => fn() => 1"#));
+    }
+
+    #[test]
+    fn test_objects() {
+        let source =
+            r#"['a' => (object) ['x' => 1], 'b' => \Foo\Bar::__set_state(array('y' => [true]))]"#;
+        let value = from_str::<Value>(source).unwrap();
+        let php = to_string(&value).unwrap();
+        assert_eq!(
+            php,
+            r#"['a' => (object) ['x' => 1], 'b' => \Foo\Bar::__set_state(['y' => [true]])]"#
+        );
+        assert_eq!(from_str::<Value>(&php).unwrap(), value);
+        assert_eq!(
+            to_canonical_string(&from_str::<Value>("(object) ['b' => 1, 'a' => 2]").unwrap())
+                .unwrap(),
+            "(object) ['a'=>2,'b'=>1]"
+        );
+        assert_eq!(crate::to_value(&value).unwrap(), value);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let value = Value::Bytes(b"a\xFF\"\xC3\xA9\n".to_vec());
+        let php = to_string(&value).unwrap();
+        assert_eq!(php, r#""a\xFF\"é\n""#);
+        assert_eq!(from_str::<Value>(&php).unwrap(), value);
+        assert_eq!(crate::to_value(&value).unwrap(), value);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let options = SerializeOptions::new().reject_non_finite(true);
+        assert!(to_string_with_options(&f64::NAN, &options).is_err());
+        assert!(to_string_with_options(&[1.0, f64::INFINITY], &options).is_err());
+        assert!(to_string(&u64::MAX).is_err());
+        assert!(to_string(&vec![(vec![1], 1)].into_iter().collect::<BTreeMap<_, _>>()).is_err());
+        assert_eq!(to_string(&1e25).unwrap(), "1.0E+25");
+        assert_eq!(to_string(&-0.5).unwrap(), "-0.5");
+    }
+
+    #[test]
+    fn test_non_finite() {
+        assert_eq!(
+            to_string(&[f64::NAN, f64::INFINITY, f64::NEG_INFINITY]).unwrap(),
+            "[NAN, INF, -INF]"
+        );
+        assert_eq!(to_string(&f32::NAN).unwrap(), "NAN");
+
+        let floats: Vec<f64> = from_str("[NAN, INF, -INF]").unwrap();
+        assert!(floats[0].is_nan());
+        assert_eq!(floats[1..], [f64::INFINITY, f64::NEG_INFINITY]);
+        assert_eq!(from_str::<Value>("-INF").unwrap(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_pretty_sorted() {
+        #[derive(Serialize)]
+        struct Test {
+            z: HashMap<String, u32>,
+            a: Vec<HashMap<i64, &'static str>>,
+        }
+
+        let test = Test {
+            z: (0..20).map(|i| (format!("key{:02}", i), i)).collect(),
+            a: vec![vec![(3, "c"), (-1, "a"), (1, "b")].into_iter().collect()],
+        };
+        let php = to_string_pretty(&test).unwrap();
+        assert_eq!(php, to_string_pretty(&test).unwrap());
+        assert!(php.starts_with("[\n    'a' => [\n        [\n            -1 => 'a',\n            1 => 'b',\n            3 => 'c'\n        ]\n    ],\n    'z' => [\n        'key00' => 0,\n        'key01' => 1,"));
+        assert_eq!(
+            from_str::<Value>(&php).unwrap(),
+            crate::to_value(&test).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_float_roundtrip() {
+        let floats = [
+            0.1 + 0.2,
+            -0.0,
+            1.0 / 3.0,
+            1e15,
+            1e16,
+            123456789012345680.0,
+            1.5e-7,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            5e-324,
+            -2.2250738585072014e-308,
+        ];
+        for float in floats {
+            let php = to_string(&float).unwrap();
+            let parsed = from_str::<f64>(&php).unwrap();
+            assert_eq!(parsed.to_bits(), float.to_bits(), "{} => {}", float, php);
+            let value = from_str::<Value>(&php).unwrap();
+            assert_eq!(value.as_float().map(f64::to_bits), Some(float.to_bits()));
+        }
+        assert_eq!(to_string(&(0.1 + 0.2)).unwrap(), "0.30000000000000004");
+        assert_eq!(to_string(&1e25).unwrap(), "1.0E+25");
+    }
+
+    #[test]
+    fn test_lists() {
+        let value =
+            from_str::<Value>(r#"[[1 => "b", 0 => "a"], [0 => 1, 1 => 2, 5 => 3], [1 => 2]]"#)
+                .unwrap();
+        let php = to_string(&value).unwrap();
+        assert_eq!(php, r#"[['a', 'b'], [1, 2, 5 => 3], [1 => 2]]"#);
+        assert_eq!(from_str::<Value>(&php).unwrap(), value);
+
+        let options = SerializeOptions::new().explicit_keys(true);
+        let php = to_string_with_options(&value, &options).unwrap();
+        assert_eq!(
+            php,
+            r#"[0 => [0 => 'a', 1 => 'b'], 1 => [0 => 1, 1 => 2, 5 => 3], 2 => [1 => 2]]"#
+        );
+        assert_eq!(from_str::<Value>(&php).unwrap(), value);
+
+        let large: Vec<u32> = (0..100).collect();
+        let value = from_str::<Value>(&to_string(&large).unwrap()).unwrap();
+        assert_eq!(to_string(&value).unwrap(), to_string(&large).unwrap());
+    }
+
+    #[test]
+    fn test_code_style() {
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            long_name: Vec<u32>,
+            nested: BTreeMap<&'static str, bool>,
+        }
+
+        let test = Test {
+            a: 1,
+            long_name: vec![1],
+            nested: vec![("x", true), ("yyy", false)].into_iter().collect(),
+        };
+        let options = SerializeOptions::new()
+            .indent(4)
+            .trailing_comma(true)
+            .align_arrows(true);
+        let php = to_string_with_options(&test, &options).unwrap();
+        assert_eq!(
+            php,
+            r#"[
+    'a'         => 1,
+    'long_name' => [
+        1,
+    ],
+    'nested'    => [
+        'x'   => true,
+        'yyy' => false,
+    ],
+]"#
+        );
+        assert_eq!(
+            from_str::<Value>(&php).unwrap(),
+            crate::to_value(&test).unwrap()
+        );
+
+        let single_line = options.indent(0);
+        assert_eq!(
+            to_string_with_options(&test.nested, &single_line).unwrap(),
+            "['x' => true, 'yyy' => false]"
+        );
+    }
+
+    #[test]
+    fn test_canonical() {
+        let options = ParserOptions::new().capture_objects(true);
+        let a = from_str_with_options::<Value>(
+            "['z' => new Foo(1, ['y' => 2, 'x' => 1]), 3 => 1e3, 'a' => ['Variant' => [true, null]]]",
+            &options,
+        )
+        .unwrap();
+        let php = to_canonical_string(&a).unwrap();
+        assert_eq!(
+            php,
+            "[3=>1000.0,'a'=>['Variant'=>[true,null]],'z'=>new Foo(1,['x'=>1,'y'=>2])]"
+        );
+        assert_eq!(from_str_with_options::<Value>(&php, &options).unwrap(), a);
+    }
+}
@@ -1,30 +1,125 @@
+use logos::Logos;
+use serde::de::value::{BorrowedStrDeserializer, BytesDeserializer};
+use serde::de::DeserializeOwned;
 use serde::de::{
     self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
     Visitor,
 };
 use serde::Deserialize;
 
-use crate::error::{ArrayKeyError, ArrayKeyErrorKind, ExpectToken, ResultExt, TrailingError};
-use crate::lexer::{SpannedToken, Token};
+use crate::array::{into_ordered, NextIndex};
+use crate::duplicate::{DuplicateFields, SeenKeys};
+use crate::encoding::escape_invalid_utf8;
+use crate::error::{
+    ArrayKeyError, ArrayKeyErrorKind, Breadcrumb, DuplicateFieldError, ExpectToken, FieldError,
+    FieldErrors, FunctionCallError, NestingLimitError, ResultExt, TrailingError,
+};
+use crate::eval::{
+    evaluate, expression_error, is_expression, is_unsupported, starts_expression,
+    undefined_constant, TokenSource,
+};
+use crate::flatten::expand_dotted_keys;
+use crate::lexer::{closure_length, SpannedToken, Token, TokenStream};
+#[cfg(feature = "arbitrary_precision")]
+use crate::num::decimal_text;
 use crate::num::ParseIntError;
 use crate::parser::{ArraySyntax, Parser};
-use crate::{Key, ParseError};
-use std::collections::VecDeque;
+use crate::{Event, Key, ParseError, ParserOptions, Span, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::rc::Rc;
 
 type Result<T> = std::result::Result<T, ParseError>;
 
+/// Magic map key used to pass captured expressions to the `Value` visitor
+pub(crate) const EXPR_TOKEN: &str = "$php_literal_parser::private::Expr";
+/// Magic map key used to pass captured object instantiations to the `Value` visitor
+pub(crate) const INSTANCE_TOKEN: &str = "$php_literal_parser::private::Instance";
+/// Magic struct name used to pass objects to the php serializer
+pub(crate) const OBJECT_TOKEN: &str = "$php_literal_parser::private::Object";
+/// Magic map key serde_json uses to pass the text of arbitrary precision numbers,
+/// reused so number types that support serde_json's `arbitrary_precision` can be deserialized
+pub(crate) const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
 pub struct Deserializer<'de> {
     parser: Parser<'de>,
     peeked: VecDeque<SpannedToken<'de>>,
+    /// Keys leading to the value currently being deserialized
+    path: Vec<Key>,
+    /// Spans of the opening brackets of the arrays containing the entries in `path`
+    arrays: Vec<Span>,
+    /// Path of the value where the first error occurred
+    error_path: Option<Vec<Key>>,
+    /// Paths of entries that previously failed and are left out of the next attempt
+    skip: Vec<Vec<Key>>,
+    /// Spans of duplicate keys that were already passed to the duplicate key handler in a previous attempt
+    reported_duplicates: HashSet<Span>,
+    /// Results of the function calls evaluated in a previous attempt, by offset of the call,
+    /// so handlers are only called once per call in the input
+    calls: HashMap<usize, std::result::Result<Value, String>>,
+    /// Number of arrays and expressions the current value is nested in
+    depth: usize,
+    /// Span of the last consumed token, attached to errors from serde
+    last_span: Option<Span>,
+    /// Whether the arrays starting at these offsets are lists, see [`peek_is_list`](Deserializer::peek_is_list)
+    lists: HashMap<usize, bool>,
+}
+
+/// A bracket that is open while scanning ahead in [`peek_is_list`](Deserializer::peek_is_list)
+struct ScanFrame {
+    close: Token,
+    /// Offset of the opening token for arrays, `None` for other brackets
+    start: Option<usize>,
+    next_key: i64,
+    list: bool,
+}
+
+impl ScanFrame {
+    fn array(close: Token, open: &SpannedToken) -> Self {
+        ScanFrame {
+            close,
+            start: Some(open.span.start()),
+            next_key: 0,
+            list: true,
+        }
+    }
 }
 
 impl<'de> Deserializer<'de> {
     pub fn from_str(input: &'de str) -> Self {
-        Deserializer {
-            parser: Parser::new(input),
+        Self::with_options(input, ParserOptions::default())
+    }
+
+    pub fn with_options(input: &'de str, options: ParserOptions) -> Self {
+        Self::with_bytes(input, input.as_bytes(), options)
+    }
+
+    /// Deserialize `input` with the string literals taken from `bytes`, see [`Parser::with_bytes`]
+    pub(crate) fn with_bytes(input: &'de str, bytes: &'de [u8], options: ParserOptions) -> Self {
+        Self::with_parser(Parser::with_bytes(input, bytes, options))
+    }
+
+    fn with_parser(parser: Parser<'de>) -> Self {
+        let php_file = parser.options().php_file;
+        let mut deserializer = Deserializer {
+            parser,
             peeked: Default::default(),
+            path: Vec::new(),
+            arrays: Vec::new(),
+            error_path: None,
+            skip: Vec::new(),
+            reported_duplicates: HashSet::new(),
+            calls: HashMap::new(),
+            depth: 0,
+            last_span: None,
+            lists: HashMap::new(),
+        };
+        if php_file {
+            deserializer.skip_file_prelude();
         }
+        deserializer
     }
 
     pub fn source(&self) -> &'de str {
@@ -34,6 +129,11 @@ impl<'de> Deserializer<'de> {
 
 /// Parse a php literal
 ///
+/// When multiple fields fail to deserialize, all of them are reported as [`ParseError::Fields`].
+/// Serde stops at the first failing field, so to find the others the input is deserialized again with the failing
+/// entries left out, once for every failing field up to [`ParserOptions::max_errors`]. Input with many failing fields
+/// is parsed many times, lower `max_errors` to bound this.
+///
 /// ## Example
 ///
 /// ```rust
@@ -54,26 +154,402 @@ pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_str(s);
-    let t = T::deserialize(&mut deserializer)?;
-    match deserializer.next_token() {
-        None
-        | Some(SpannedToken {
-            token: Token::SemiColon,
-            ..
-        }) => Ok(t),
-        Some(token) => Err(TrailingError::new(s, token.span.start..token.span.start).into()),
+    from_str_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a php literal from bytes that don't have to be valid UTF-8
+///
+/// Php strings are byte strings, so string literals in php files can contain invalid UTF-8.
+/// These strings are parsed as [`Value::Bytes`], when deserializing them into a `String`
+/// the invalid bytes are replaced by `U+FFFD REPLACEMENT CHARACTER`.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_slice, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let map = from_slice::<Value>(b"['name' => 'Jos\xE9', 'valid' => true]")?;
+///
+/// assert_eq!(map["name"], Value::Bytes(b"Jos\xE9".to_vec()));
+/// assert_eq!(map["valid"], true);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_slice<T>(bytes: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    from_slice_with_options(bytes, &ParserOptions::default())
+}
+
+/// Parse a php literal from bytes that don't have to be valid UTF-8 using the provided options
+pub fn from_slice_with_options<T>(bytes: &[u8], options: &ParserOptions) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let source = escape_invalid_utf8(bytes);
+    from_bytes_after(&source, bytes, options, |_| Ok(()))
+}
+
+/// Parse a php literal from an [`io::Read`](std::io::Read) source
+///
+/// The input doesn't have to be valid UTF-8, see [`from_slice`].
+///
+/// This doesn't parse the input incrementally, the reader is read to the end and the whole input is buffered
+/// in memory before parsing starts. To bound the memory used for untrusted input, limit the reader
+/// with [`Read::take`].
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_reader, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let input = std::io::Cursor::new(b"['name' => 'config', 'size' => 3]");
+/// let map = from_reader::<_, Value>(input)?;
+///
+/// assert_eq!(map["name"], "config");
+/// assert_eq!(map["size"], 3);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    from_reader_with_options(reader, &ParserOptions::default())
+}
+
+/// Parse a php literal from an [`io::Read`](std::io::Read) source using the provided options
+pub fn from_reader_with_options<R, T>(mut reader: R, options: &ParserOptions) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    from_slice_with_options(&bytes, options)
+}
+
+/// Read a [`tokio::io::AsyncRead`] source to the end and parse it as a php literal
+///
+/// The input doesn't have to be valid UTF-8, see [`from_slice`].
+///
+/// Only the reading is asynchronous, the whole input is buffered in memory and then parsed in one go
+/// once the reader is exhausted. To bound the memory used for untrusted input, limit the reader with
+/// [`AsyncReadExt::take`](tokio::io::AsyncReadExt::take).
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_async_read_to_end, Value};
+/// # use std::error::Error;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), Box<dyn Error>> {
+/// let input: &[u8] = b"['name' => 'config', 'size' => 3]";
+/// let map = from_async_read_to_end::<_, Value>(input).await?;
+///
+/// assert_eq!(map["name"], "config");
+/// assert_eq!(map["size"], 3);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn from_async_read_to_end<R, T>(reader: R) -> Result<T>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    from_async_read_to_end_with_options(reader, &ParserOptions::default()).await
+}
+
+/// Read a [`tokio::io::AsyncRead`] source to the end and parse it as a php literal using the provided options
+#[cfg(feature = "tokio")]
+pub async fn from_async_read_to_end_with_options<R, T>(
+    mut reader: R,
+    options: &ParserOptions,
+) -> Result<T>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    from_slice_with_options(&bytes, options)
+}
+
+/// Parse a php literal using the provided options
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str_with_options, ParserOptions, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let options = ParserOptions::new().lenient(true);
+/// let map = from_str_with_options::<Value>(r#"["foo" => function () { return 1; }]"#, &options)?;
+///
+/// assert_eq!(map["foo"].as_expr(), Some("function () { return 1; }"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_str_with_options<'a, T>(s: &'a str, options: &ParserOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_str_after(s, options, |_| Ok(()))
+}
+
+/// Parse a single php literal from the start of the input
+///
+/// Unlike [`from_str`], the input can contain more content after the literal.
+/// Returns the parsed literal and the byte offset in the input where the literal ends.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str_prefix, Value};
+/// # use std::error::Error;
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let input = "['title' => 'Home'] ?>\n<h1>Home</h1>";
+/// let (value, length) = from_str_prefix::<Value>(input)?;
+///
+/// assert_eq!(value["title"], "Home");
+/// assert_eq!(&input[length..], " ?>\n<h1>Home</h1>");
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_str_prefix<'a, T>(s: &'a str) -> Result<(T, usize)>
+where
+    T: Deserialize<'a>,
+{
+    from_str_prefix_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a single php literal from the start of the input using the provided options
+///
+/// See [`from_str_prefix`].
+pub fn from_str_prefix_with_options<'a, T>(
+    s: &'a str,
+    options: &ParserOptions,
+) -> Result<(T, usize)>
+where
+    T: Deserialize<'a>,
+{
+    if options.expand_dotted_keys {
+        let options = options.clone().expand_dotted_keys(false);
+        let (value, length) = from_str_prefix_with_options::<Value>(s, &options)?;
+        return Ok((T::deserialize(expand_dotted_keys(value))?, length));
     }
+
+    let mut deserializer = Deserializer::with_options(s, options.clone());
+    let t = deserializer.deserialize_value(PhantomData::<T>)?;
+    let next = deserializer
+        .peek_token()
+        .map_or(s.len(), |token| token.span.start());
+    // only whitespace and comments are left between the literal and the next token
+    let length = TokenStream::new(Token::lexer(&s[..next]))
+        .last()
+        .map_or(0, |token| token.span.end());
+    Ok((t, length))
+}
+
+/// Parse a php literal, leaving out array entries that fail to parse
+///
+/// Returns the best-effort result together with the errors of all entries that were left out.
+/// Entries are skipped up to the next `,` or closing bracket, the result is `None` when the error
+/// can't be attributed to an entry, like an unclosed array, or when leaving out the entries makes the
+/// target type fail. Like with [`from_str`], the input is deserialized again for every failing entry.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str_partial, Value};
+/// use std::collections::HashMap;
+///
+/// let (map, errors) = from_str_partial::<HashMap<String, Value>>("['debug' => true, 'port' => 80 80, 'host' => 'localhost']");
+/// let map = map.unwrap();
+///
+/// assert_eq!(map["debug"], true);
+/// assert_eq!(map["host"], "localhost");
+/// assert!(!map.contains_key("port"));
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].path(), "port");
+/// ```
+pub fn from_str_partial<'a, T>(s: &'a str) -> (Option<T>, Vec<FieldError>)
+where
+    T: Deserialize<'a>,
+{
+    from_str_partial_with_options(s, &ParserOptions::default())
+}
+
+/// Parse a php literal, leaving out array entries that fail to parse using the provided options
+///
+/// See [`from_str_partial`].
+pub fn from_str_partial_with_options<'a, T>(
+    s: &'a str,
+    options: &ParserOptions,
+) -> (Option<T>, Vec<FieldError>)
+where
+    T: Deserialize<'a>,
+{
+    if options.expand_dotted_keys {
+        let options = options.clone().expand_dotted_keys(false);
+        let (value, mut errors) = from_str_partial_with_options::<Value>(s, &options);
+        let result = value.and_then(|value| {
+            T::deserialize(expand_dotted_keys(value))
+                .map_err(|error| errors.push(FieldError::new(String::new(), error)))
+                .ok()
+        });
+        return (result, errors);
+    }
+
+    deserialize_skipping(s, s.as_bytes(), options, |_| Ok(()))
+}
+
+/// Parse a php literal after running `prelude` to consume the tokens in front of it
+pub(crate) fn from_str_after<'a, T, P>(s: &'a str, options: &ParserOptions, prelude: P) -> Result<T>
+where
+    T: Deserialize<'a>,
+    P: FnMut(&mut Deserializer<'a>) -> Result<()>,
+{
+    from_bytes_after(s, s.as_bytes(), options, prelude)
+}
+
+/// Parse a php literal from `s` with the string literals taken from `bytes`, see [`Parser::with_bytes`]
+fn from_bytes_after<'a, T, P>(
+    s: &'a str,
+    bytes: &'a [u8],
+    options: &ParserOptions,
+    prelude: P,
+) -> Result<T>
+where
+    T: Deserialize<'a>,
+    P: FnMut(&mut Deserializer<'a>) -> Result<()>,
+{
+    if options.expand_dotted_keys {
+        let options = options.clone().expand_dotted_keys(false);
+        let value = from_bytes_after::<Value, P>(s, bytes, &options, prelude)?;
+        return T::deserialize(expand_dotted_keys(value));
+    }
+
+    let (result, mut errors) = deserialize_skipping(s, bytes, options, prelude);
+    match result {
+        Some(t) if errors.is_empty() => Ok(t),
+        _ if errors.len() == 1 && (!options.field_paths || errors[0].path().is_empty()) => {
+            Err(errors.pop().unwrap().error().clone())
+        }
+        _ => Err(FieldErrors::new(errors).into()),
+    }
+}
+
+/// Deserialize the input, retrying with the failed entries left out until it succeeds or the error can't be attributed to an entry
+///
+/// Returns the result of the last attempt if any attempt succeeded and the errors of all attempts.
+fn deserialize_skipping<'a, T, P>(
+    s: &'a str,
+    bytes: &'a [u8],
+    options: &ParserOptions,
+    mut prelude: P,
+) -> (Option<T>, Vec<FieldError>)
+where
+    T: Deserialize<'a>,
+    P: FnMut(&mut Deserializer<'a>) -> Result<()>,
+{
+    // serde stops at the first failing field, to report all of them we retry with the failed entries left out,
+    // the input is only lexed again once and the retries replay those tokens and reuse the function call results
+    let mut skip = Vec::new();
+    let mut reported_duplicates = HashSet::new();
+    let mut calls = HashMap::new();
+    let mut errors = Vec::new();
+    let mut tokens: Option<Rc<[SpannedToken<'a>]>> = None;
+    loop {
+        let parser = Parser::with_bytes(s, bytes, options.clone());
+        let parser = match &tokens {
+            Some(tokens) => parser.replay(tokens.clone()),
+            None => parser,
+        };
+        let mut deserializer = Deserializer::with_parser(parser);
+        deserializer.skip = skip;
+        deserializer.reported_duplicates = reported_duplicates;
+        deserializer.calls = calls;
+        let result =
+            prelude(&mut deserializer).and_then(|_| deserialize_complete(&mut deserializer));
+        let path = deserializer.error_path.take().unwrap_or_default();
+        skip = deserializer.skip;
+        reported_duplicates = deserializer.reported_duplicates;
+        calls = deserializer.calls;
+
+        let error = match result {
+            Ok(t) => return (Some(t), errors),
+            Err(error) => error,
+        };
+        // leaving out an entry makes the containing struct fail with a missing field, that's not a new error
+        let caused_by_skip = missing_field(&error).is_some_and(|field| {
+            skip.iter().any(|skipped| {
+                skipped.len() == path.len() + 1
+                    && skipped.starts_with(&path)
+                    && skipped[path.len()] == Key::String(field.into())
+            })
+        });
+        if !caused_by_skip {
+            errors.push(FieldError::new(format_path(&path), error));
+        }
+        if path.is_empty() || skip.contains(&path) || errors.len() >= options.error_limit() {
+            return (None, errors);
+        }
+        skip.push(path);
+        tokens.get_or_insert_with(|| TokenStream::new(Token::lexer(s)).collect());
+    }
+}
+
+fn deserialize_complete<'a, T>(deserializer: &mut Deserializer<'a>) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let t = deserializer
+        .deserialize_value(PhantomData::<T>)
+        .map_err(|error| deserializer.with_span(error))?;
+    deserializer.end()?;
+    Ok(t)
+}
+
+fn missing_field(error: &ParseError) -> Option<&'static str> {
+    match error {
+        ParseError::Serde(error) => error.missing_field.as_deref().copied(),
+        _ => None,
+    }
+}
+
+fn format_path(path: &[Key]) -> String {
+    path.iter()
+        .map(Key::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
 }
 
 impl<'de> Deserializer<'de> {
     fn next_token(&mut self) -> Option<SpannedToken<'de>> {
-        self.peeked.pop_front().or_else(|| self.parser.next_token())
+        let token = self
+            .peeked
+            .pop_front()
+            .or_else(|| self.parser.next_token())?;
+        self.last_span = Some(token.span.clone());
+        Some(token)
     }
 
     fn peek_token(&mut self) -> Option<&SpannedToken<'de>> {
         if self.peeked.is_empty() {
-            let next = self.next_token()?;
+            let next = self.parser.next_token()?;
             self.peeked.push_back(next)
         }
         self.peeked.front()
@@ -83,21 +559,722 @@ impl<'de> Deserializer<'de> {
         let _ = self.next_token();
     }
 
+    /// Check if the token `n` tokens ahead is the identifier `name`
+    fn peek_identifier(&mut self, n: usize, name: &str) -> bool {
+        self.peek_nth(n).is_some_and(|token| {
+            token.token == Token::Identifier && token.slice().eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Skip the `<?php` tag, `declare(...);` statements and `return` keyword in front of the literal in a php file
+    fn skip_file_prelude(&mut self) {
+        if self.peek_nth(0).map(|token| token.token) == Some(Token::OpenTag) {
+            self.eat_token();
+        }
+        while self.peek_identifier(0, "declare")
+            && self.peek_nth(1).map(|token| token.token) == Some(Token::BracketOpen)
+        {
+            while let Some(token) = self.next_token() {
+                if token.token == Token::SemiColon {
+                    break;
+                }
+            }
+        }
+        if self.peek_identifier(0, "return") {
+            self.eat_token();
+        }
+    }
+
+    /// Check that the token can start a value, also accepting expressions we can capture in lenient mode
+    fn expect_value(
+        &self,
+        token: Option<SpannedToken<'de>>,
+        expected: &[Token],
+    ) -> Result<SpannedToken<'de>> {
+        let options = self.parser.options();
+        match token {
+            Some(token) if token.token == Token::Closure && options.lenient => Ok(token),
+            // anything that can't be evaluated is captured in lenient mode
+            Some(token)
+                if options.lenient
+                    && matches!(
+                        token.token,
+                        Token::Variable | Token::Identifier | Token::Error
+                    ) =>
+            {
+                Ok(token)
+            }
+            Some(token) if starts_expression(&self.parser, &token) => Ok(token),
+            // function calls are evaluated before deserializing
+            Some(token)
+                if token.token == Token::Identifier && options.functions.handles(token.slice()) =>
+            {
+                Ok(token)
+            }
+            Some(token)
+                if token.token == Token::New && (options.lenient || options.capture_objects) =>
+            {
+                Ok(token)
+            }
+            token => token.expect_token(expected, self.source()),
+        }
+    }
+
+    /// Consume all tokens up to the end of the expression starting with `token`, returning the source of the expression
+    fn skip_expression(&mut self, token: SpannedToken<'de>) -> &'de str {
+        let source = self.source();
+        let end = token.span.start() + closure_length(&source[token.span.start()..]);
+        while self
+            .peek_token()
+            .map(|next| next.span.start() < end)
+            .unwrap_or_default()
+        {
+            self.eat_token();
+        }
+        &source[token.span.start()..end]
+    }
+
+    /// Consume the next token if it's an empty string literal
+    fn eat_empty_string(&mut self) -> bool {
+        let is_empty = self.peek_token().is_some_and(|token| {
+            token.token == Token::LiteralString && matches!(token.slice(), "''" | "\"\"")
+        });
+        if is_empty {
+            self.eat_token();
+        }
+        is_empty
+    }
+
+    /// Consume an empty string that should be deserialized as the default value for the expected type
+    fn eat_empty_string_as_default(&mut self) -> bool {
+        self.parser.options().empty_string_as_default && self.eat_empty_string()
+    }
+
     fn parse_bool(&mut self) -> Result<bool> {
+        if self.eat_empty_string_as_default() {
+            return Ok(false);
+        }
         let token = self
             .next_token()
             .expect_token(&[Token::Bool], self.source())?;
         self.parser.parse_bool_token(token)
     }
 
+    /// Put a consumed token back in front of the remaining tokens
     fn push_peeked(&mut self, peeked: SpannedToken<'de>) {
-        self.peeked.push_back(peeked)
+        self.peeked.push_front(peeked)
+    }
+
+    /// Look ahead `n` tokens past the next one, buffering the tokens in between
+    fn peek_nth(&mut self, n: usize) -> Option<&SpannedToken<'de>> {
+        while self.peeked.len() <= n {
+            let next = self.parser.next_token()?;
+            self.peeked.push_back(next);
+        }
+        self.peeked.get(n)
+    }
+
+    /// Check if the array starting at the next token only has consecutive integer keys starting at 0
+    ///
+    /// This buffers the tokens of the array until it's clear that it isn't a list,
+    /// arrays containing closures are never considered lists.
+    /// The result for every nested array that is scanned on the way is remembered,
+    /// so the tokens of nested arrays aren't scanned again when they are deserialized.
+    fn peek_is_list(&mut self) -> bool {
+        let first = match self.peek_token() {
+            Some(token) => token.clone(),
+            None => return false,
+        };
+        if let Some(&list) = self.lists.get(&first.span.start()) {
+            return list;
+        }
+        let mut frames = Vec::new();
+        let mut index = match first.token {
+            Token::SquareOpen => {
+                frames.push(ScanFrame::array(Token::SquareClose, &first));
+                1
+            }
+            Token::Array => {
+                frames.push(ScanFrame::array(Token::BracketClose, &first));
+                2
+            }
+            _ => return false,
+        };
+        // whether the next token starts an entry of the innermost array
+        let mut entry_start = true;
+        loop {
+            let token = match self.peek_nth(index) {
+                Some(token) => token.clone(),
+                None => return false,
+            };
+            let top = frames.last_mut().unwrap();
+            if token.token == top.close {
+                let frame = frames.pop().unwrap();
+                if let Some(start) = frame.start {
+                    self.lists.insert(start, frame.list);
+                }
+                if frames.is_empty() {
+                    return frame.list;
+                }
+                index += 1;
+                entry_start = false;
+                continue;
+            }
+            match token.token {
+                // mismatched brackets, the error is reported when the array is parsed
+                Token::BracketClose | Token::SquareClose | Token::Error => return false,
+                _ => {}
+            }
+
+            if entry_start && top.start.is_some() {
+                entry_start = false;
+                if token.token == Token::Ellipsis {
+                    top.list = false;
+                    index += 1;
+                    continue;
+                }
+                if self.peek_nth(index + 1).map(|next| next.token) == Some(Token::Arrow) {
+                    let top = frames.last_mut().unwrap();
+                    match self.parser.parse_array_key(token) {
+                        Ok(Key::Int(key)) if key == top.next_key => {}
+                        _ => top.list = false,
+                    }
+                    top.next_key += 1;
+                    index += 2;
+                    if frames.len() == 1 && !frames[0].list {
+                        self.lists.insert(first.span.start(), false);
+                        return false;
+                    }
+                    continue;
+                }
+                top.next_key += 1;
+            }
+
+            let top = frames.last_mut().unwrap();
+            match token.token {
+                Token::Comma if top.start.is_some() => entry_start = true,
+                // a key that is an expression
+                Token::Arrow if top.start.is_some() => top.list = false,
+                Token::Closure => frames.iter_mut().for_each(|frame| frame.list = false),
+                Token::SquareOpen => {
+                    frames.push(ScanFrame::array(Token::SquareClose, &token));
+                    entry_start = true;
+                }
+                Token::Array
+                    if self.peek_nth(index + 1).map(|next| next.token)
+                        == Some(Token::BracketOpen) =>
+                {
+                    frames.push(ScanFrame::array(Token::BracketClose, &token));
+                    entry_start = true;
+                    index += 1;
+                }
+                Token::BracketOpen => frames.push(ScanFrame {
+                    close: Token::BracketClose,
+                    start: None,
+                    next_key: 0,
+                    list: false,
+                }),
+                _ => {}
+            }
+            if frames.len() == 1 && !frames[0].list {
+                self.lists.insert(first.span.start(), false);
+                return false;
+            }
+            index += 1;
+        }
+    }
+
+    /// Whether the entry with `key` in the array currently being deserialized should be left out
+    fn is_skipped(&self, key: &Key) -> bool {
+        let depth = self.path.len();
+        self.skip.iter().any(|skipped| {
+            skipped.len() == depth + 1 && skipped.starts_with(&self.path) && &skipped[depth] == key
+        })
+    }
+
+    /// Consume the tokens of a value without deserializing it
+    fn skip_value(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek_token() {
+            match token.token {
+                Token::Comma | Token::BracketClose | Token::SquareClose if depth == 0 => break,
+                Token::BracketOpen | Token::SquareOpen => depth += 1,
+                Token::BracketClose | Token::SquareClose => depth -= 1,
+                _ => {}
+            }
+            self.eat_token();
+        }
+    }
+
+    /// Check if the next tokens are a call to a function with a registered handler or evaluator
+    fn is_handled_call(&mut self) -> bool {
+        let name = match self.peek_token() {
+            Some(token) if token.token == Token::Identifier => token.slice(),
+            _ => return false,
+        };
+        self.parser.options().functions.handles(name)
+            && self
+                .peek_nth(1)
+                .is_some_and(|token| token.token == Token::BracketOpen)
+    }
+
+    /// Evaluate the next value using its registered handler if it's a function call
+    fn parse_call(&mut self) -> Result<Option<Value>> {
+        if !self.is_handled_call() {
+            return Ok(None);
+        }
+        let name = self.next_token().unwrap();
+        self.eat_token();
+        let args = Vec::<Value>::deserialize(ArgumentsDeserializer { de: self })?;
+        let result = match self.calls.get(&name.span.start()) {
+            Some(result) => result.clone(),
+            None => {
+                let result = self.parser.options().functions.call(name.slice(), &args);
+                self.calls.insert(name.span.start(), result.clone());
+                result
+            }
+        };
+        result
+            .map(Some)
+            .map_err(|message| FunctionCallError::new(self.source(), name.span, message).into())
+    }
+
+    /// Check if the next token is a closing `?>` tag ending the php file
+    fn at_close_tag(&mut self) -> bool {
+        self.parser.options().php_file
+            && self.peek_token().map(|token| token.token) == Some(Token::CloseTag)
+    }
+
+    /// Consume the `;` ending a statement, which is implied by a closing `?>` tag
+    fn end_statement(&mut self) -> Result<()> {
+        if !self.at_close_tag() {
+            self.next_token()
+                .expect_token(&[Token::SemiColon], self.source())?;
+        }
+        Ok(())
+    }
+
+    /// Check that there is nothing but an optional `;` left after the parsed value
+    ///
+    /// When parsing a php file, the value can also be followed by a closing `?>` tag.
+    pub(crate) fn end(&mut self) -> Result<()> {
+        if self.peek_token().map(|token| token.token) == Some(Token::SemiColon) {
+            self.eat_token();
+        }
+        if self.at_close_tag() {
+            self.eat_token();
+        }
+        match self.next_token() {
+            None => Ok(()),
+            Some(token) => Err(TrailingError::new(self.source(), &token).into()),
+        }
+    }
+
+    /// Read the next `define('NAME', <value>);` statement
+    ///
+    /// The defined constant is available to the values of the statements after it.
+    pub(crate) fn next_define(&mut self) -> Result<Option<(String, Value)>> {
+        if self.at_close_tag() {
+            return self.end().map(|_| None);
+        }
+        let token = match self.next_token() {
+            Some(token) => token.expect_token(&[Token::Identifier], self.source())?,
+            None => return Ok(None),
+        };
+        if !token.slice().eq_ignore_ascii_case("define") {
+            return Err(FunctionCallError::new(
+                self.source(),
+                token.span.clone(),
+                format!("Expected a call to define(), found {}", token.slice()),
+            )
+            .into());
+        }
+        self.next_token()
+            .expect_token(&[Token::BracketOpen], self.source())?;
+        let name = self.parse_string()?;
+        self.next_token()
+            .expect_token(&[Token::Comma], self.source())?;
+        let value = self.deserialize_value(PhantomData::<Value>)?;
+        // the deprecated `case_insensitive` argument
+        if self.peek_token().map(|token| token.token) == Some(Token::Comma) {
+            self.eat_token();
+            self.deserialize_value(PhantomData::<Value>)?;
+        }
+        self.next_token()
+            .expect_token(&[Token::BracketClose], self.source())?;
+        self.end_statement()?;
+        self.parser.define_constant(&name, value.clone());
+        Ok(Some((name, value)))
+    }
+
+    /// Consume the `$VAR =` in front of an assigned value, returning the name of the variable without `$`
+    pub(crate) fn open_assignment(&mut self) -> Result<&'de str> {
+        let variable = self
+            .next_token()
+            .expect_token(&[Token::Variable], self.source())?;
+        self.next_token()
+            .expect_token(&[Token::Assign], self.source())?;
+        Ok(&variable.slice()[1..])
+    }
+
+    /// Read the next `$VAR['key'][] = <value>;` or `$VAR = <value>;` statement
+    pub(crate) fn next_assignment(&mut self) -> Result<Option<Assignment<'de>>> {
+        if self.at_close_tag() {
+            return self.end().map(|_| None);
+        }
+        let variable = match self.next_token() {
+            Some(token) => token.expect_token(&[Token::Variable], self.source())?,
+            None => return Ok(None),
+        };
+        let mut path = Vec::new();
+        while self
+            .next_token()
+            .expect_token(&[Token::SquareOpen, Token::Assign], self.source())?
+            .token
+            == Token::SquareOpen
+        {
+            let token = self.next_token().expect_token(
+                &[
+                    Token::LiteralString,
+                    Token::Integer,
+                    Token::Identifier,
+                    Token::SquareClose,
+                ],
+                self.source(),
+            )?;
+            if token.token == Token::SquareClose {
+                path.push(None);
+            } else {
+                path.push(Some(self.parser.parse_array_key(token)?));
+                self.next_token()
+                    .expect_token(&[Token::SquareClose], self.source())?;
+            }
+        }
+        let value = self.deserialize_value(PhantomData::<Value>)?;
+        self.end_statement()?;
+        Ok(Some(Assignment {
+            variable,
+            path,
+            value,
+        }))
+    }
+
+    /// Consume the start of an array, `[` or `array(`
+    fn open_array(&mut self) -> Result<ArraySyntax> {
+        let token = self
+            .next_token()
+            .expect_token(&[Token::Array, Token::SquareOpen], self.source())?;
+        match token.token {
+            Token::Array => {
+                self.next_token()
+                    .expect_token(&[Token::BracketOpen], self.source())?;
+                Ok(ArraySyntax::Long)
+            }
+            Token::SquareOpen => Ok(ArraySyntax::Short),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Start reading the entries of the array at the top level one at a time
+    pub(crate) fn open_entries(&mut self) -> Result<ArrayCursor> {
+        let syntax = self.open_array()?;
+        Ok(ArrayCursor {
+            syntax,
+            start: self.last_span.clone().unwrap_or_default(),
+            next_index: NextIndex::default(),
+            done: false,
+            entries: 0,
+            keys: SeenKeys::default(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Read the next entry of an array opened with [`open_entries`](Deserializer::open_entries)
+    pub(crate) fn next_entry(&mut self, cursor: &mut ArrayCursor) -> Result<Option<(Key, Value)>> {
+        let mut walker = ArrayWalker {
+            de: self,
+            next_index: cursor.next_index,
+            syntax: cursor.syntax,
+            start: cursor.start.clone(),
+            done: cursor.done,
+            entries: cursor.entries,
+            keys: std::mem::take(&mut cursor.keys),
+            fields: None,
+            key: None,
+            pending: std::mem::take(&mut cursor.pending),
+            spread_value: None,
+        };
+        let entry = walker.next_entry()?;
+        cursor.next_index = walker.next_index;
+        cursor.done = walker.done;
+        cursor.entries = walker.entries;
+        cursor.keys = walker.keys;
+        cursor.pending = walker.pending;
+        Ok(entry)
+    }
+
+    /// Read the next event of the document, see [`Events`](crate::Events)
+    pub(crate) fn next_event(&mut self, cursor: &mut EventCursor) -> Result<Option<(Event, Span)>> {
+        loop {
+            // values parsed in one go count the open arrays towards the nesting limit
+            self.depth = cursor.stack.len();
+            if let Some(event) = cursor.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            if cursor.expect_value {
+                cursor.expect_value = false;
+                self.value_events(cursor)?;
+                continue;
+            }
+            let close = match cursor.stack.last() {
+                Some(open) => open.syntax.close_bracket(),
+                None => {
+                    self.end()?;
+                    return Ok(None);
+                }
+            };
+
+            if cursor.separator {
+                cursor.separator = false;
+                let token = self
+                    .next_token()
+                    .expect_token(&[Token::Comma, close], self.source())?;
+                if token.token == close {
+                    cursor.stack.pop();
+                    cursor.separator = true;
+                    return Ok(Some((Event::ArrayEnd, token.span)));
+                }
+            }
+
+            let open = cursor.stack.last_mut().unwrap();
+            match self.peek_token().map(|token| token.token) {
+                Some(token) if token == close => {
+                    let token = self.next_token().unwrap();
+                    cursor.stack.pop();
+                    cursor.separator = true;
+                    return Ok(Some((Event::ArrayEnd, token.span)));
+                }
+                Some(Token::Ellipsis) => {
+                    let ellipsis = self.next_token().unwrap();
+                    let array = match self.deserialize_value(PhantomData::<Value>)? {
+                        Value::Array(array) => array,
+                        _ => {
+                            return Err(expression_error(
+                                &self.parser,
+                                &ellipsis,
+                                "Only arrays can be unpacked",
+                            ))
+                        }
+                    };
+                    // integer keys of unpacked entries are renumbered, string keys are kept
+                    for (key, value) in into_ordered(array) {
+                        open.entries += 1;
+                        self.parser.count_entry(open.entries)?;
+                        let key = match key {
+                            Key::Int(_) => implicit_key(
+                                &mut open.next_index,
+                                self.source(),
+                                ellipsis.span.clone(),
+                            )?,
+                            key => key,
+                        };
+                        self.track_key(&mut open.keys, &key, ellipsis.span.clone());
+                        cursor
+                            .pending
+                            .push_back((Event::Key(key), ellipsis.span.clone()));
+                        value_events(value, ellipsis.span.clone(), &mut cursor.pending);
+                    }
+                    cursor.separator = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            cursor.expect_value = true;
+            open.entries += 1;
+            self.parser.count_entry(open.entries)?;
+            if self.peek_nth(1).map(|token| token.token) == Some(Token::Arrow) {
+                let token = self.next_token().expect_token(
+                    &[
+                        Token::Bool,
+                        Token::Integer,
+                        Token::Float,
+                        Token::LiteralString,
+                        Token::Null,
+                        Token::Identifier,
+                    ],
+                    self.source(),
+                )?;
+                self.eat_token();
+                let span = token.span.clone();
+                let key = self.parser.parse_array_key(token)?;
+                open.next_index.insert(&key);
+                self.track_key(&mut open.keys, &key, span.clone());
+                return Ok(Some((Event::Key(key), span)));
+            }
+            // implicit key
+            let end = self.source().len();
+            let span = self
+                .peek_token()
+                .map_or_else(|| Span::new(end, end), |token| token.span.clone());
+            let key = implicit_key(&mut open.next_index, self.source(), span.clone())?;
+            self.track_key(&mut open.keys, &key, span.clone());
+            return Ok(Some((Event::Key(key), span)));
+        }
+    }
+
+    /// Read the events for the next value, array literals are opened and their entries read by [`next_event`](Deserializer::next_event)
+    fn value_events(&mut self, cursor: &mut EventCursor) -> Result<()> {
+        let first = self.peek_token().cloned();
+        let is_array_literal = first
+            .as_ref()
+            .is_some_and(|token| matches!(token.token, Token::SquareOpen | Token::Array));
+        let lenient = self.parser.options().lenient;
+        if is_array_literal && !is_expression(self) && !(lenient && is_unsupported(self)) {
+            let first = first.unwrap();
+            let limit = self.parser.options().depth_limit();
+            if cursor.stack.len() >= limit {
+                return Err(NestingLimitError::new(limit, self.source(), first.span).into());
+            }
+            let mut span = first.span;
+            if first.token == Token::Array {
+                if let Some(bracket) = self.peek_nth(1) {
+                    span = Span::new(span.start(), bracket.span.end());
+                }
+            }
+            let syntax = self.open_array()?;
+            cursor.stack.push(OpenArray {
+                syntax,
+                next_index: NextIndex::default(),
+                entries: 0,
+                keys: SeenKeys::default(),
+            });
+            cursor.pending.push_back((Event::ArrayStart, span));
+            return Ok(());
+        }
+
+        let source = self.source();
+        let start = first.map_or(source.len(), |token| token.span.start());
+        let value = self.deserialize_value(PhantomData::<Value>)?;
+        let next = self
+            .peek_token()
+            .map_or(source.len(), |token| token.span.start());
+        let end = source[..next].trim_end().len().max(start);
+        value_events(value, Span::new(start, end), &mut cursor.pending);
+        cursor.separator = true;
+        Ok(())
+    }
+
+    /// Record a key of an array, passing it to the duplicate key handler if the array already contains it
+    fn track_key(&mut self, keys: &mut SeenKeys, key: &Key, span: Span) {
+        let handler = match &self.parser.options().duplicate_key_handler {
+            Some(handler) => handler,
+            None => return,
+        };
+        if let Some(duplicate) = keys.insert(key, span) {
+            if self.reported_duplicates.insert(duplicate.duplicate.clone()) {
+                handler.call(&duplicate);
+            }
+        }
+    }
+
+    /// Pass the text of a number literal to the visitor as arbitrary precision number
+    ///
+    /// Like with `serde_json`, the number is passed as a map with a magic key. Types that buffer their input,
+    /// like untagged enums, see a map instead of a number and can't match number variants.
+    #[cfg(feature = "arbitrary_precision")]
+    fn deserialize_number<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let token = self.next_token().unwrap();
+        match decimal_text(token.slice(), token.token == Token::Float) {
+            Some(text) => visitor.visit_map(NumberAccess::new(text)),
+            None => match self.parser.parse_literal(token)? {
+                Value::Int(int) => visitor.visit_i64(int),
+                Value::Float(float) => visitor.visit_f64(float),
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    /// Deserialize a value, evaluating it first if it's a function call
+    fn deserialize_value<T>(&mut self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.parse_call()? {
+            Some(value) => seed.deserialize(value),
+            None if self.parser.options().lenient && is_unsupported(self) => {
+                let token = self.next_token().unwrap();
+                let expr = self.skip_expression(token);
+                seed.deserialize(Value::Expr(expr.into()))
+            }
+            None if is_expression(self) => seed.deserialize(evaluate(self)?),
+            None => seed.deserialize(self),
+        }
+    }
+
+    /// Consume the separator after an array entry, returning whether it was the closing bracket
+    fn finish_entry(&mut self, syntax: ArraySyntax) -> Result<bool> {
+        let next = self
+            .next_token()
+            .expect_token(&[Token::Comma, syntax.close_bracket()], self.source())?;
+        Ok(next.token == syntax.close_bracket())
+    }
+
+    /// Attach the span of the last consumed token to errors from serde, pointing to the value that failed
+    fn with_span(&self, error: ParseError) -> ParseError {
+        match error {
+            ParseError::Serde(error) => error.or_span(self.source(), self.last_span.clone()).into(),
+            error => error,
+        }
+    }
+
+    /// Run `f` for the value of the entry with `key` in the array opened at `array`, keeping track of where errors occur
+    fn track_entry<T>(
+        &mut self,
+        key: Key,
+        array: Span,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.path.push(key);
+        self.arrays.push(array);
+        let result = f(self).map_err(|error| {
+            let error = self.with_span(error);
+            match self.error_path {
+                Some(_) => error,
+                None => error.or_context(|| self.breadcrumbs()),
+            }
+        });
+        if result.is_err() && self.error_path.is_none() {
+            self.error_path = Some(self.path.clone());
+        }
+        self.arrays.pop();
+        self.path.pop();
+        result
+    }
+
+    /// The arrays containing the value currently being deserialized, innermost first
+    fn breadcrumbs(&self) -> Vec<Breadcrumb> {
+        self.path
+            .iter()
+            .zip(&self.arrays)
+            .rev()
+            .map(|(key, array)| Breadcrumb {
+                key: key.clone(),
+                array: array.clone().with_position(self.source()),
+            })
+            .collect()
     }
 
     fn parse_unsigned<T>(&mut self) -> Result<T>
     where
-        T: TryFrom<i64>,
+        T: TryFrom<i64> + Default,
     {
+        if self.eat_empty_string_as_default() {
+            return Ok(T::default());
+        }
         let token = self
             .next_token()
             .expect_token(&[Token::Integer], self.source())?;
@@ -113,8 +1290,11 @@ impl<'de> Deserializer<'de> {
 
     fn parse_signed<T>(&mut self) -> Result<T>
     where
-        T: TryFrom<i64>,
+        T: TryFrom<i64> + Default,
     {
+        if self.eat_empty_string_as_default() {
+            return Ok(T::default());
+        }
         let token = self
             .next_token()
             .expect_token(&[Token::Integer], self.source())?;
@@ -124,6 +1304,9 @@ impl<'de> Deserializer<'de> {
     }
 
     fn parse_float(&mut self) -> Result<f64> {
+        if self.eat_empty_string_as_default() {
+            return Ok(0.0);
+        }
         let token = self
             .next_token()
             .expect_token(&[Token::Float], self.source())?;
@@ -136,17 +1319,59 @@ impl<'de> Deserializer<'de> {
             .expect_token(&[Token::LiteralString], self.source())?;
         self.parser.parse_string_token(token)
     }
+
+    fn parse_string_bytes(&mut self) -> Result<Vec<u8>> {
+        let token = self
+            .next_token()
+            .expect_token(&[Token::LiteralString], self.source())?;
+        self.parser.parse_string_bytes(token)
+    }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> TokenSource<'de> for Deserializer<'de> {
+    fn parser(&self) -> &Parser<'de> {
+        &self.parser
+    }
+
+    fn peek_nth(&mut self, n: usize) -> Option<&SpannedToken<'de>> {
+        Deserializer::peek_nth(self, n)
+    }
+
+    fn next_token(&mut self) -> Option<SpannedToken<'de>> {
+        Deserializer::next_token(self)
+    }
+
+    fn parse_array(&mut self) -> Result<Value> {
+        PhantomData::<Value>.deserialize(self)
+    }
+
+    fn nested<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+        let limit = self.parser.options().depth_limit();
+        if self.depth >= limit {
+            let source = self.source();
+            let span = self.peek_token().map_or_else(
+                || Span::new(source.len(), source.len()),
+                |token| token.span.clone(),
+            );
+            return Err(NestingLimitError::new(limit, source, span).into());
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = ParseError;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let source = self.source();
-        let peek = self.peek_token().expect_token(
+        let peek = self.peek_token().cloned();
+        let peek = self.expect_value(
+            peek,
             &[
                 Token::Null,
                 Token::Bool,
@@ -156,15 +1381,56 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 Token::Array,
                 Token::SquareOpen,
             ],
-            source,
         )?;
         match peek.token {
             Token::Null => self.deserialize_unit(visitor),
             Token::Bool => self.deserialize_bool(visitor),
-            Token::LiteralString => self.deserialize_string(visitor),
-            Token::Integer => self.deserialize_i64(visitor),
+            Token::LiteralString => match Value::from_bytes(self.parse_string_bytes()?) {
+                Value::String(string) => visitor.visit_string(string),
+                Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+                _ => unreachable!(),
+            },
+            #[cfg(feature = "arbitrary_precision")]
+            Token::Integer | Token::Float => self.deserialize_number(visitor),
+            #[cfg(not(feature = "arbitrary_precision"))]
+            Token::Integer => {
+                let token = self.next_token().unwrap();
+                match self.parser.parse_number_token(token)? {
+                    Value::Float(float) => visitor.visit_f64(float),
+                    Value::Int(int) => visitor.visit_i64(int),
+                    _ => unreachable!(),
+                }
+            }
+            #[cfg(not(feature = "arbitrary_precision"))]
             Token::Float => self.deserialize_f64(visitor),
+            // lists are presented as sequences, so they can match sequence variants of untagged enums
+            Token::Array | Token::SquareOpen if self.peek_is_list() => {
+                self.deserialize_seq(visitor)
+            }
             Token::Array | Token::SquareOpen => self.deserialize_map(visitor),
+            Token::Closure => {
+                self.eat_token();
+                visitor.visit_map(ExprAccess::new(peek.slice()))
+            }
+            Token::New if !self.parser.options().capture_objects => {
+                let expr = self.skip_expression(peek);
+                visitor.visit_map(ExprAccess::new(expr))
+            }
+            Token::New => {
+                self.eat_token();
+                let class = self
+                    .next_token()
+                    .expect_token(&[Token::Identifier], self.source())?;
+                let has_args = match self.peek_token() {
+                    Some(token) if token.token == Token::BracketOpen => {
+                        self.eat_token();
+                        true
+                    }
+                    _ => false,
+                };
+                visitor.visit_map(InstanceAccess::new(self, class.slice(), has_args))
+            }
+            Token::Identifier => Err(undefined_constant(&self.parser, &peek)),
             _ => unreachable!(),
         }
     }
@@ -275,21 +1541,18 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_string(self.parse_string()?)
     }
 
-    // The `Serializer` implementation on the previous page serialized byte
-    // arrays as JSON arrays of bytes. Handle that representation here.
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        self.deserialize_byte_buf(visitor)
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        todo!()
-        // visitor.visit_string(self.parse_string()?.to_vec())
+        visitor.visit_byte_buf(self.parse_string_bytes()?)
     }
 
     // An absent optional is represented as the JSON `null` and a present
@@ -304,8 +1567,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let source = self.source();
-        let token = self.peek_token().expect_token(
+        if self.parser.options().empty_string_as_none && self.eat_empty_string() {
+            return visitor.visit_none();
+        }
+        let token = self.peek_token().cloned();
+        let token = self.expect_value(
+            token,
             &[
                 Token::Null,
                 Token::Bool,
@@ -315,7 +1582,6 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 Token::Array,
                 Token::SquareOpen,
             ],
-            source,
         )?;
         if token.token == Token::Null {
             let _ = self.next_token();
@@ -352,21 +1618,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let token = self
-            .next_token()
-            .expect_token(&[Token::Array, Token::SquareOpen], self.source())?;
-        let syntax = match token.token {
-            Token::Array => {
-                self.next_token()
-                    .expect_token(&[Token::BracketOpen], self.source())?;
-                ArraySyntax::Long
-            }
-            Token::SquareOpen => ArraySyntax::Short,
-            _ => unreachable!(),
-        };
-
-        let value = visitor.visit_seq(ArrayWalker::new(self, syntax))?;
-        Ok(value)
+        self.nested(|de| {
+            let syntax = de.open_array()?;
+            visitor.visit_seq(ArrayWalker::new(de, syntax))
+        })
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
@@ -392,33 +1647,31 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let token = self
-            .next_token()
-            .expect_token(&[Token::Array, Token::SquareOpen], self.source())?;
-        let syntax = match token.token {
-            Token::Array => {
-                self.next_token()
-                    .expect_token(&[Token::BracketOpen], self.source())?;
-                ArraySyntax::Long
-            }
-            Token::SquareOpen => ArraySyntax::Short,
-            _ => unreachable!(),
-        };
-
-        let value = visitor.visit_map(ArrayWalker::new(self, syntax))?;
-        Ok(value)
+        self.nested(|de| {
+            let syntax = de.open_array()?;
+            visitor.visit_map(ArrayWalker::new(de, syntax))
+        })
     }
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
-        _fields: &'static [&'static str],
+        name: &'static str,
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        match self.parser.options().duplicate_fields {
+            DuplicateFields::Error => self.nested(|de| {
+                let syntax = de.open_array()?;
+                visitor.visit_map(ArrayWalker::new(de, syntax).for_struct())
+            }),
+            // parsed into a value first so later entries can overwrite earlier ones
+            DuplicateFields::LastWins => {
+                Value::deserialize(&mut *self)?.deserialize_struct(name, fields, visitor)
+            }
+        }
     }
 
     fn deserialize_enum<V>(
@@ -447,10 +1700,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                     ArraySyntax::Short
                 };
 
-                let value = visitor.visit_enum(Enum::new(self))?;
-                self.next_token()
-                    .expect_token(&[syntax.close_bracket()], self.source())?;
-                Ok(value)
+                self.nested(|de| {
+                    let value = visitor.visit_enum(Enum::new(de))?;
+                    de.next_token()
+                        .expect_token(&[syntax.close_bracket()], de.source())?;
+                    Ok(value)
+                })
             }
             _ => unreachable!(),
         }
@@ -471,25 +1726,234 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
+/// Position in an array whose entries are read one at a time
+pub(crate) struct ArrayCursor {
+    syntax: ArraySyntax,
+    start: Span,
+    next_index: NextIndex,
+    done: bool,
+    entries: usize,
+    keys: SeenKeys,
+    pending: VecDeque<(Key, Value, Span)>,
+}
+
+/// Position in a document that's read as a sequence of [`Event`]s
+pub(crate) struct EventCursor {
+    /// The arrays that are currently open
+    stack: Vec<OpenArray>,
+    /// Whether the next token has to be the separator after an entry
+    separator: bool,
+    /// Whether the next token starts a value
+    expect_value: bool,
+    pending: VecDeque<(Event, Span)>,
+}
+
+impl Default for EventCursor {
+    fn default() -> Self {
+        EventCursor {
+            stack: Vec::new(),
+            separator: false,
+            expect_value: true,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// An array that's currently open while reading events
+struct OpenArray {
+    syntax: ArraySyntax,
+    next_index: NextIndex,
+    /// Number of entries read so far
+    entries: usize,
+    keys: SeenKeys,
+}
+
+/// Take the key for an entry without a key, failing like php once the array has an entry with key `i64::MAX`
+fn implicit_key(next_index: &mut NextIndex, source: &str, span: Span) -> Result<Key> {
+    match next_index.next() {
+        Some(key) => Ok(Key::Int(key)),
+        None => Err(next_element_occupied(source, span)),
+    }
+}
+
+fn next_element_occupied(source: &str, span: Span) -> ParseError {
+    ParseError::UnexpectedArrayKey(ArrayKeyError::new(
+        ArrayKeyErrorKind::NextElementOccupied,
+        source,
+        span,
+    ))
+}
+
+/// Add the events for a value that has already been parsed, using the span of its source for all events
+fn value_events(value: Value, span: Span, events: &mut VecDeque<(Event, Span)>) {
+    match value {
+        Value::Array(array) => {
+            events.push_back((Event::ArrayStart, span.clone()));
+            for (key, value) in into_ordered(array) {
+                events.push_back((Event::Key(key), span.clone()));
+                value_events(value, span.clone(), events);
+            }
+            events.push_back((Event::ArrayEnd, span));
+        }
+        value => events.push_back((Event::Scalar(value), span)),
+    }
+}
+
+/// An assignment to an index of a variable, `$VAR['key'][] = <value>;`
+pub(crate) struct Assignment<'de> {
+    pub variable: SpannedToken<'de>,
+    /// The indices assigned to, `None` for `[]`
+    pub path: Vec<Option<Key>>,
+    pub value: Value,
+}
+
 struct ArrayWalker<'source, 'a> {
     de: &'a mut Deserializer<'source>,
-    next_int_key: i64,
+    next_index: NextIndex,
     syntax: ArraySyntax,
+    /// The span of the opening bracket of the array
+    start: Span,
     done: bool,
+    /// Number of entries read so far
+    entries: usize,
+    keys: SeenKeys,
+    /// Fields of the struct read so far, `None` if the array isn't deserialized into a struct
+    fields: Option<SeenKeys>,
+    key: Option<Key>,
+    /// Entries of arrays unpacked with `...` that still have to be returned, with the span of the `...`
+    pending: VecDeque<(Key, Value, Span)>,
+    /// The value of the unpacked entry returned by the last call to `next_key_seed`
+    spread_value: Option<Value>,
 }
 
 impl<'source, 'a> ArrayWalker<'source, 'a> {
     pub fn new(de: &'a mut Deserializer<'source>, syntax: ArraySyntax) -> Self {
+        let start = de.last_span.clone().unwrap_or_default();
         ArrayWalker {
             de,
-            next_int_key: 0,
+            next_index: NextIndex::default(),
             syntax,
+            start,
             done: false,
+            entries: 0,
+            keys: SeenKeys::default(),
+            fields: None,
+            key: None,
+            pending: VecDeque::new(),
+            spread_value: None,
+        }
+    }
+
+    /// Walk the array as the fields of a struct, failing on fields that are set more than once
+    pub fn for_struct(mut self) -> Self {
+        self.fields = Some(SeenKeys::default());
+        self
+    }
+
+    fn source(&self) -> &'source str {
+        self.de.source()
+    }
+
+    /// Unpack arrays spread into this array with `...` until there are entries to return
+    ///
+    /// Integer keys of unpacked entries are renumbered, string keys are kept.
+    fn unpack_spread(&mut self) -> Result<()> {
+        while self.pending.is_empty() && !self.done {
+            match self.de.peek_token() {
+                Some(token) if token.token == Token::Ellipsis => {}
+                _ => return Ok(()),
+            }
+            let ellipsis = self.de.next_token().unwrap();
+            let array = match self.de.deserialize_value(PhantomData::<Value>)? {
+                Value::Array(array) => array,
+                _ => {
+                    return Err(expression_error(
+                        &self.de.parser,
+                        &ellipsis,
+                        "Only arrays can be unpacked",
+                    ))
+                }
+            };
+            for (key, value) in into_ordered(array) {
+                let key = match key {
+                    Key::Int(_) => implicit_key(
+                        &mut self.next_index,
+                        self.de.source(),
+                        ellipsis.span.clone(),
+                    )?,
+                    key => key,
+                };
+                self.pending.push_back((key, value, ellipsis.span.clone()));
+            }
+            self.finish_entry()?;
+        }
+        Ok(())
+    }
+
+    /// Count the next entry against the element limits
+    fn count_entry(&mut self) -> Result<()> {
+        self.entries += 1;
+        self.de.parser.count_entry(self.entries)
+    }
+
+    /// Record the key of the next entry to report duplicate keys
+    fn track_key(&mut self, key: &Key, span: Span) {
+        self.de.track_key(&mut self.keys, key, span);
+    }
+
+    /// Check that the key of the next entry isn't a struct field that was already set
+    ///
+    /// The error is attributed to the entry, so the entry can be left out when collecting errors.
+    fn check_field(&mut self, key: &Key, span: Span) -> Result<()> {
+        let duplicate = match &mut self.fields {
+            Some(fields) => fields.insert(key, span),
+            None => None,
+        };
+        match duplicate {
+            Some(duplicate) => {
+                let error = DuplicateFieldError::new(duplicate, self.source()).into();
+                self.de
+                    .track_entry(key.clone(), self.start.clone(), |_| Err(error))
+            }
+            None => Ok(()),
         }
     }
 
-    fn source(&self) -> &'source str {
-        self.de.source()
+    /// Consume the separator after an entry
+    fn finish_entry(&mut self) -> Result<()> {
+        self.done = self.de.finish_entry(self.syntax)?;
+        Ok(())
+    }
+
+    /// Check the first token of an entry with an implicit key, returning whether the entry was skipped
+    ///
+    /// An invalid token is attributed to the entry, so the entry can be left out when collecting errors.
+    fn check_implicit_entry(&mut self, expected: &[Token]) -> Result<bool> {
+        let token = match self.de.peek_token() {
+            Some(token) => token.clone(),
+            None => return Ok(false),
+        };
+        let is_explicit = self
+            .de
+            .peek_nth(1)
+            .is_some_and(|next| next.token == Token::Arrow);
+        let span = token.span.clone();
+        let error = match self.de.expect_value(Some(token), expected) {
+            Err(error) if !is_explicit => error,
+            _ => return Ok(false),
+        };
+        let key = match self.next_index.peek() {
+            Some(key) => Key::Int(key),
+            None => return Err(next_element_occupied(self.source(), span)),
+        };
+        if !self.de.is_skipped(&key) {
+            return self.de.track_entry(key, self.start.clone(), |_| Err(error));
+        }
+        self.next_index.next();
+        self.count_entry()?;
+        self.de.skip_value();
+        self.finish_entry()?;
+        Ok(true)
     }
 }
 
@@ -500,54 +1964,69 @@ impl<'de, 'a> SeqAccess<'de> for ArrayWalker<'de, 'a> {
     where
         T: DeserializeSeed<'de>,
     {
+        self.unpack_spread()?;
+        if let Some((key, value, span)) = self.pending.pop_front() {
+            self.count_entry()?;
+            if let Key::String(_) = key {
+                return Err(ParseError::UnexpectedArrayKey(ArrayKeyError::new(
+                    ArrayKeyErrorKind::IntegerExpected,
+                    self.source(),
+                    span,
+                )));
+            }
+            if self.de.is_skipped(&key) {
+                return self.next_element_seed(seed);
+            }
+            return self
+                .de
+                .track_entry(key, self.start.clone(), |_| seed.deserialize(value))
+                .map(Some);
+        }
         if self.done {
             return Ok(None);
         }
 
-        let token = self.de.next_token().expect_token(
-            &[
-                Token::Bool,
-                Token::Integer,
-                Token::Float,
-                Token::LiteralString,
-                Token::Null,
-                Token::Array,
-                Token::SquareOpen,
-                self.syntax.close_bracket(),
-            ],
-            self.source(),
-        )?;
+        let expected = [
+            Token::Bool,
+            Token::Integer,
+            Token::Float,
+            Token::LiteralString,
+            Token::Null,
+            Token::Array,
+            Token::SquareOpen,
+            self.syntax.close_bracket(),
+        ];
+        if self.check_implicit_entry(&expected)? {
+            return self.next_element_seed(seed);
+        }
+        let token = self.de.next_token();
+        let token = self.de.expect_value(token, &expected)?;
 
         if token.token == self.syntax.close_bracket() {
             self.done = true;
             return Ok(None);
         }
 
-        let next = self.de.next_token().expect_token(
-            &[self.syntax.close_bracket(), Token::Comma, Token::Arrow],
-            self.source(),
-        )?;
-
-        let value_token = match next.token {
-            Token::Comma => token,
-            Token::Arrow => {
-                let span = token.span.clone();
+        let span = token.span.clone();
+        match self.de.next_token() {
+            Some(next) if next.token == Token::Arrow => {
                 let key = self.de.parser.parse_array_key(token)?;
                 match key {
-                    Key::Int(key) if key == self.next_int_key => Ok(()),
+                    Key::Int(key) if Some(key) == self.next_index.peek() => Ok(()),
                     Key::Int(_) => Err(ParseError::UnexpectedArrayKey(ArrayKeyError::new(
                         ArrayKeyErrorKind::NonConsecutive,
                         self.source(),
-                        span,
+                        span.clone(),
                     ))),
                     _ => Err(ParseError::UnexpectedArrayKey(ArrayKeyError::new(
                         ArrayKeyErrorKind::IntegerExpected,
                         self.source(),
-                        span,
+                        span.clone(),
                     ))),
                 }?;
-                self.next_int_key += 1;
-                let value = self.de.next_token().expect_token(
+                let value = self.de.next_token();
+                let value = self.de.expect_value(
+                    value,
                     &[
                         Token::Bool,
                         Token::Integer,
@@ -557,27 +2036,37 @@ impl<'de, 'a> SeqAccess<'de> for ArrayWalker<'de, 'a> {
                         Token::Array,
                         Token::SquareOpen,
                     ],
-                    self.source(),
                 )?;
-                let next = self
-                    .de
-                    .next_token()
-                    .expect_token(&[Token::Comma, self.syntax.close_bracket()], self.source())?;
-                if next.token == self.syntax.close_bracket() {
-                    self.done = true;
-                }
-                value
+                self.de.push_peeked(value);
             }
-            peeked_token if peeked_token == self.syntax.close_bracket() => {
-                self.done = true;
-                token
+            next => {
+                // implicit key, the value can span multiple tokens
+                if let Some(next) = next {
+                    self.de.push_peeked(next);
+                }
+                self.de.push_peeked(token);
             }
-            _ => unreachable!(),
+        }
+        let key = match self.next_index.next() {
+            Some(key) => Key::Int(key),
+            None => return Err(next_element_occupied(self.source(), span)),
         };
+        self.count_entry()?;
+
+        if self.de.is_skipped(&key) {
+            self.de.skip_value();
+            self.finish_entry()?;
+            return self.next_element_seed(seed);
+        }
 
-        // Deserialize an array element.
-        self.de.push_peeked(value_token);
-        seed.deserialize(&mut *self.de).map(Some)
+        // Deserialize an array element, a missing separator is attributed to the element so it can be skipped
+        let syntax = self.syntax;
+        let (value, done) = self.de.track_entry(key, self.start.clone(), |de| {
+            let value = de.deserialize_value(seed)?;
+            Ok((value, de.finish_entry(syntax)?))
+        })?;
+        self.done = done;
+        Ok(Some(value))
     }
 }
 
@@ -588,24 +2077,45 @@ impl<'de, 'a> MapAccess<'de> for ArrayWalker<'de, 'a> {
     where
         K: DeserializeSeed<'de>,
     {
+        self.unpack_spread()?;
+        if let Some((key, value, span)) = self.pending.pop_front() {
+            self.count_entry()?;
+            self.track_key(&key, span.clone());
+            if self.de.is_skipped(&key) {
+                return self.next_key_seed(seed);
+            }
+            self.check_field(&key, span)?;
+            self.key = Some(key.clone());
+            self.spread_value = Some(value);
+            return match key {
+                Key::Int(key) => seed.deserialize(format!("{}", key).into_deserializer()),
+                Key::String(key) if self.de.parser.options().keep_numeric_string_keys => {
+                    seed.deserialize(BytesDeserializer::new(key.as_bytes()))
+                }
+                Key::String(key) => seed.deserialize(key.into_deserializer()),
+            }
+            .map(Some);
+        }
         if self.done {
             return Ok(None);
         }
 
-        let token = self.de.next_token().expect_token(
-            &[
-                Token::Bool,
-                Token::Integer,
-                Token::Float,
-                Token::LiteralString,
-                Token::Null,
-                self.syntax.close_bracket(),
-                // below is only when this token is a value with implicit key, not a when the token is a key
-                Token::Array,
-                Token::SquareOpen,
-            ],
-            self.source(),
-        )?;
+        let expected = [
+            Token::Bool,
+            Token::Integer,
+            Token::Float,
+            Token::LiteralString,
+            Token::Null,
+            self.syntax.close_bracket(),
+            // below is only when this token is a value with implicit key, not a when the token is a key
+            Token::Array,
+            Token::SquareOpen,
+        ];
+        if self.check_implicit_entry(&expected)? {
+            return self.next_key_seed(seed);
+        }
+        let token = self.de.next_token();
+        let token = self.de.expect_value(token, &expected)?;
 
         if token.token == self.syntax.close_bracket() {
             self.done = true;
@@ -631,23 +2141,65 @@ impl<'de, 'a> MapAccess<'de> for ArrayWalker<'de, 'a> {
                         Token::Float,
                         Token::LiteralString,
                         Token::Null,
+                        Token::Identifier,
                         self.syntax.close_bracket(),
                     ],
                     self.source(),
                 )?;
                 // Deserialize a map key.
-                if let Key::Int(int_key) = self.de.parser.parse_array_key(token.clone())? {
-                    self.next_int_key = int_key + 1;
+                let key = self.de.parser.parse_array_key(token.clone())?;
+                self.next_index.insert(&key);
+                self.count_entry()?;
+                self.track_key(&key, token.span.clone());
+                if self.de.is_skipped(&key) {
+                    self.de.skip_value();
+                    self.finish_entry()?;
+                    return self.next_key_seed(seed);
+                }
+                self.check_field(&key, token.span.clone())?;
+                self.key = Some(key.clone());
+                match key {
+                    // constants are evaluated as key, there is no literal to deserialize
+                    Key::Int(key) if token.token == Token::Identifier => {
+                        seed.deserialize(key.into_deserializer()).map(Some)
+                    }
+                    // number literals are passed as text, keys are always integers
+                    #[cfg(feature = "arbitrary_precision")]
+                    Key::Int(key) if matches!(token.token, Token::Integer | Token::Float) => {
+                        seed.deserialize(key.into_deserializer()).map(Some)
+                    }
+                    // passed as bytes so numeric strings aren't converted to integer keys by `Key`
+                    Key::String(key) if self.de.parser.options().keep_numeric_string_keys => seed
+                        .deserialize(BytesDeserializer::new(key.as_bytes()))
+                        .map(Some),
+                    Key::String(key)
+                        if token.token == Token::Identifier
+                            || self.de.parser.options().lowercase_keys =>
+                    {
+                        seed.deserialize(key.into_deserializer()).map(Some)
+                    }
+                    _ => {
+                        self.de.push_peeked(token);
+                        seed.deserialize(&mut *self.de).map(Some)
+                    }
                 }
-                self.de.push_peeked(token);
-                seed.deserialize(&mut *self.de).map(Some)
             }
             _ => {
                 // implicit key
-                let key = self.next_int_key;
-                self.next_int_key += 1;
-                self.de.push_peeked(token);
+                let key = match self.next_index.next() {
+                    Some(key) => key,
+                    None => return Err(next_element_occupied(self.source(), token.span)),
+                };
+                self.count_entry()?;
+                self.track_key(&Key::Int(key), token.span.clone());
                 self.de.push_peeked(next);
+                self.de.push_peeked(token);
+                if self.de.is_skipped(&Key::Int(key)) {
+                    self.de.skip_value();
+                    self.finish_entry()?;
+                    return self.next_key_seed(seed);
+                }
+                self.key = Some(Key::Int(key));
                 seed.deserialize(format!("{}", key).into_deserializer())
                     .map(Some)
             }
@@ -658,32 +2210,181 @@ impl<'de, 'a> MapAccess<'de> for ArrayWalker<'de, 'a> {
     where
         V: DeserializeSeed<'de>,
     {
-        let source = self.source();
-        self.de.peek_token().expect_token(
-            &[
-                Token::Bool,
-                Token::Integer,
-                Token::Float,
-                Token::LiteralString,
-                Token::Null,
-                Token::Array,
-                Token::SquareOpen,
-            ],
-            source,
-        )?;
+        let key = self
+            .key
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        if let Some(value) = self.spread_value.take() {
+            return self
+                .de
+                .track_entry(key, self.start.clone(), |_| seed.deserialize(value));
+        }
+
+        // Deserialize a map value, a missing separator is attributed to the entry so it can be skipped
+        let syntax = self.syntax;
+        let (value, done) = self.de.track_entry(key, self.start.clone(), |de| {
+            let token = de.peek_token().cloned();
+            de.expect_value(
+                token,
+                &[
+                    Token::Bool,
+                    Token::Integer,
+                    Token::Float,
+                    Token::LiteralString,
+                    Token::Null,
+                    Token::Array,
+                    Token::SquareOpen,
+                ],
+            )?;
+            let value = de.deserialize_value(seed)?;
+            Ok((value, de.finish_entry(syntax)?))
+        })?;
+        self.done = done;
+        Ok(value)
+    }
+}
 
-        // Deserialize a map key.
-        let value = seed.deserialize(&mut *self.de)?;
+/// Passes the text of a number to the visitor as a single entry map with a magic key
+#[cfg(feature = "arbitrary_precision")]
+struct NumberAccess {
+    text: Option<String>,
+}
 
-        let next = self
-            .de
-            .next_token()
-            .expect_token(&[Token::Comma, self.syntax.close_bracket()], self.source())?;
+#[cfg(feature = "arbitrary_precision")]
+impl NumberAccess {
+    fn new(text: String) -> Self {
+        NumberAccess { text: Some(text) }
+    }
+}
 
-        if next.token == self.syntax.close_bracket() {
-            self.done = true;
+#[cfg(feature = "arbitrary_precision")]
+impl<'de> MapAccess<'de> for NumberAccess {
+    type Error = ParseError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.text.is_none() {
+            return Ok(None);
         }
-        Ok(value)
+        seed.deserialize(BorrowedStrDeserializer::new(NUMBER_TOKEN))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let text = self.text.take().unwrap_or_default();
+        seed.deserialize(text.into_deserializer())
+    }
+}
+
+/// Passes a captured expression to the visitor as a single entry map with a magic key
+struct ExprAccess<'de> {
+    expr: Option<&'de str>,
+}
+
+impl<'de> ExprAccess<'de> {
+    fn new(expr: &'de str) -> Self {
+        ExprAccess { expr: Some(expr) }
+    }
+}
+
+impl<'de> MapAccess<'de> for ExprAccess<'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.expr.is_none() {
+            return Ok(None);
+        }
+        seed.deserialize(BorrowedStrDeserializer::new(EXPR_TOKEN))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let expr = self.expr.take().unwrap_or_default();
+        seed.deserialize(BorrowedStrDeserializer::new(expr))
+    }
+}
+
+/// Passes a captured object instantiation to the visitor as a map with a magic key for the class
+/// followed by the constructor arguments
+struct InstanceAccess<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+    class: Option<&'de str>,
+    has_args: bool,
+}
+
+impl<'a, 'de> InstanceAccess<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>, class: &'de str, has_args: bool) -> Self {
+        InstanceAccess {
+            de,
+            class: Some(class),
+            has_args,
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for InstanceAccess<'a, 'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.class.is_some() {
+            seed.deserialize(BorrowedStrDeserializer::new(INSTANCE_TOKEN))
+                .map(Some)
+        } else if self.has_args {
+            seed.deserialize(BorrowedStrDeserializer::new("args"))
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.class.take() {
+            Some(class) => seed.deserialize(BorrowedStrDeserializer::new(class)),
+            None => {
+                self.has_args = false;
+                seed.deserialize(ArgumentsDeserializer { de: &mut *self.de })
+            }
+        }
+    }
+}
+
+/// Deserializes the arguments of a function call or object instantiation as a sequence
+struct ArgumentsDeserializer<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ArgumentsDeserializer<'a, 'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.de
+            .nested(|de| visitor.visit_seq(ArrayWalker::new(de, ArraySyntax::Long)))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
     }
 }
 
@@ -789,6 +2490,13 @@ mod tests {
         assert_eq!(expected, from_str::<Vec<String>>(j).unwrap());
     }
 
+    #[test]
+    fn test_vec_nested() {
+        let j = r#"[[1, 2], 1 => [3], []]"#;
+        let expected: Vec<Vec<u8>> = vec![vec![1, 2], vec![3], vec![]];
+        assert_eq!(expected, from_str::<Vec<Vec<u8>>>(j).unwrap());
+    }
+
     #[test]
     fn test_struct() {
         #[derive(Deserialize, PartialEq, Debug)]
@@ -827,6 +2535,162 @@ mod tests {
         assert_eq!(expected, from_str(j).unwrap());
     }
 
+    #[test]
+    fn test_struct_field_errors() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Inner {
+            host: String,
+            port: u16,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            debug: bool,
+            db: Inner,
+            domains: Vec<String>,
+            name: String,
+        }
+
+        let j = r#"["debug" => "yes", "db" => ["host" => "localhost", "port" => 70000], "domains" => ["a", 1], "name" => "foo"]"#;
+        let err = from_str::<Test>(j).unwrap_err();
+        let errors = match err {
+            super::ParseError::Fields(errors) => errors,
+            err => panic!("expected field errors, got {:?}", err),
+        };
+        let paths: Vec<_> = errors.errors().iter().map(|err| err.path()).collect();
+        assert_eq!(paths, vec!["debug", "db.port", "domains.1"]);
+
+        // stop after the configured number of errors
+        let options = crate::ParserOptions::new().max_errors(2);
+        let err = super::from_str_with_options::<Test>(j, &options).unwrap_err();
+        let errors = match err {
+            super::ParseError::Fields(errors) => errors,
+            err => panic!("expected field errors, got {:?}", err),
+        };
+        let paths: Vec<_> = errors.errors().iter().map(|err| err.path()).collect();
+        assert_eq!(paths, vec!["debug", "db.port"]);
+
+        // a single error is returned as is
+        let j = r#"["debug" => true, "db" => ["host" => "localhost", "port" => 1], "domains" => [], "name" => 1]"#;
+        assert!(matches!(
+            from_str::<Test>(j).unwrap_err(),
+            super::ParseError::UnexpectedToken(_)
+        ));
+
+        // missing fields are reported next to invalid ones
+        let j =
+            r#"["debug" => 1, "db" => ["host" => "localhost"], "domains" => [], "name" => "foo"]"#;
+        let err = from_str::<Test>(j).unwrap_err();
+        let errors = match err {
+            super::ParseError::Fields(errors) => errors,
+            err => panic!("expected field errors, got {:?}", err),
+        };
+        let paths: Vec<_> = errors.errors().iter().map(|err| err.path()).collect();
+        assert_eq!(paths, vec!["debug", "db"]);
+        assert_eq!(
+            super::missing_field(errors.errors()[1].error()),
+            Some("port")
+        );
+    }
+
+    #[test]
+    fn test_empty_string_options() {
+        use crate::ParserOptions;
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            port: Option<u16>,
+            timeout: u32,
+            ratio: f64,
+            debug: bool,
+            name: Option<String>,
+        }
+
+        let j = r#"["port" => '', "timeout" => "", "ratio" => '', "debug" => '', "name" => 'foo']"#;
+        assert!(from_str::<Test>(j).is_err());
+
+        let options = ParserOptions::new()
+            .empty_string_as_none(true)
+            .empty_string_as_default(true);
+        let expected = Test {
+            port: None,
+            timeout: 0,
+            ratio: 0.0,
+            debug: false,
+            name: Some("foo".into()),
+        };
+        assert_eq!(expected, super::from_str_with_options(j, &options).unwrap());
+
+        let options = ParserOptions::new().empty_string_as_none(true);
+        assert_eq!(
+            None,
+            super::from_str_with_options::<Option<String>>("''", &options).unwrap()
+        );
+        assert!(super::from_str_with_options::<u32>("''", &options).is_err());
+    }
+
+    #[test]
+    fn test_expand_dotted_keys() {
+        use crate::ParserOptions;
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Db {
+            host: String,
+            port: u16,
+            replicas: Vec<String>,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            db: Db,
+            debug: Option<bool>,
+        }
+
+        let j = r#"[
+            "db.host" => "localhost",
+            "db" => ["port" => 3306],
+            "db.replicas.0" => "a",
+            "db.replicas.1" => "b",
+        ]"#;
+        assert!(from_str::<Test>(j).is_err());
+
+        let options = ParserOptions::new().expand_dotted_keys(true);
+        let expected = Test {
+            db: Db {
+                host: "localhost".into(),
+                port: 3306,
+                replicas: vec!["a".into(), "b".into()],
+            },
+            debug: None,
+        };
+        assert_eq!(expected, super::from_str_with_options(j, &options).unwrap());
+    }
+
+    #[test]
+    fn test_lowercase_keys() {
+        use crate::{ParserOptions, Value};
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            dbhost: String,
+            dbport: u16,
+        }
+
+        let j = r#"["DBHost" => "localhost", "dbPort" => 3306]"#;
+        let options = ParserOptions::new().lowercase_keys(true);
+        let expected = Test {
+            dbhost: "localhost".into(),
+            dbport: 3306,
+        };
+        assert_eq!(expected, super::from_str_with_options(j, &options).unwrap());
+
+        let value =
+            super::from_str_with_options::<Value>(r#"["DBHost" => "ÉTÉ", 1 => 2]"#, &options)
+                .unwrap();
+        assert_eq!(value["dbhost"], "ÉTÉ");
+        assert_eq!(value[1], 2);
+    }
+
     #[test]
     fn test_enum() {
         #[derive(Deserialize, PartialEq, Debug)]
@@ -858,4 +2722,158 @@ mod tests {
         let expected = E::Struct { a: 1 };
         assert_eq!(expected, from_str(j).unwrap());
     }
+
+    #[test]
+    fn test_untagged_enum() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(untagged)]
+        enum U {
+            Int(i64),
+            List(Vec<U>),
+            Struct { a: u32 },
+            Map(std::collections::HashMap<String, U>),
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(tag = "type", content = "value")]
+        enum A {
+            Pair(u32, u32),
+            Struct { a: u32 },
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(untagged)]
+        enum S {
+            Name(String),
+            List(Vec<S>),
+            Struct { a: String },
+        }
+
+        let j = r#"[1, [0 => 2, 1 => ["a" => 3]], array("b" => 4)]"#;
+        let expected = U::List(vec![
+            U::Int(1),
+            U::List(vec![U::Int(2), U::Struct { a: 3 }]),
+            U::Map(std::iter::once(("b".into(), U::Int(4))).collect()),
+        ]);
+        // with `arbitrary_precision` numbers are passed as maps, which untagged enums can't match to number variants
+        if cfg!(feature = "arbitrary_precision") {
+            assert!(from_str::<U>(j).is_err());
+        } else {
+            assert_eq!(expected, from_str(j).unwrap());
+        }
+
+        let j = r#"['x', [0 => 'y', 1 => ["a" => 'z']]]"#;
+        let expected = S::List(vec![
+            S::Name("x".into()),
+            S::List(vec![S::Name("y".into()), S::Struct { a: "z".into() }]),
+        ]);
+        assert_eq!(expected, from_str(j).unwrap());
+
+        let j = r#"[1 => 1, 0 => 2]"#;
+        assert!(from_str::<U>(j).is_err());
+
+        // the content is buffered when it comes before the tag
+        let j = r#"["value" => [1, 2], "type" => "Pair"]"#;
+        if cfg!(feature = "arbitrary_precision") {
+            assert!(from_str::<A>(j).is_err());
+        } else {
+            assert_eq!(A::Pair(1, 2), from_str(j).unwrap());
+        }
+
+        let j = r#"["type" => "Pair", "value" => [1, 2]]"#;
+        assert_eq!(A::Pair(1, 2), from_str(j).unwrap());
+
+        let j = r#"["type" => "Struct", "value" => ["a" => 1]]"#;
+        assert_eq!(A::Struct { a: 1 }, from_str(j).unwrap());
+    }
+
+    #[test]
+    fn test_mismatched_brackets() {
+        for source in ["[1)", "array(1]", "[[1)]", "array(1, [2)]", "[(1]"] {
+            assert!(from_str::<crate::Value>(source).is_err(), "{}", source);
+        }
+        assert!(from_str::<Vec<Vec<u8>>>("[[1, 2], [3)]").is_err());
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision() {
+        use super::NUMBER_TOKEN;
+        use crate::{Key, Value};
+        use serde::de::{MapAccess, Visitor};
+        use std::collections::HashMap;
+        use std::fmt::{self, Formatter};
+
+        /// The text of a number, read the way arbitrary precision number types do
+        #[derive(Debug, PartialEq)]
+        struct RawNumber(String);
+
+        impl<'de> serde::Deserialize<'de> for RawNumber {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct RawNumberVisitor;
+
+                impl<'de> Visitor<'de> for RawNumberVisitor {
+                    type Value = RawNumber;
+
+                    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                        formatter.write_str("a number")
+                    }
+
+                    fn visit_f64<E>(self, v: f64) -> Result<RawNumber, E> {
+                        Ok(RawNumber(v.to_string()))
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> Result<RawNumber, A::Error> {
+                        let key: String = map.next_key()?.unwrap();
+                        assert_eq!(key, NUMBER_TOKEN);
+                        Ok(RawNumber(map.next_value()?))
+                    }
+                }
+
+                deserializer.deserialize_any(RawNumberVisitor)
+            }
+        }
+
+        let numbers: Vec<RawNumber> = from_str(
+            "[0.1, 123456789012345678901234567890, -1_000.000_1, .5, 1.e3, 1.5E-7, 0x1F, 0o17, INF, 1., -1.]",
+        )
+        .unwrap();
+        assert_eq!(
+            numbers,
+            [
+                "0.1",
+                "123456789012345678901234567890",
+                "-1000.0001",
+                "0.5",
+                "1.0e3",
+                "1.5E-7",
+                "31",
+                "15",
+                "inf",
+                "1.0",
+                "-1.0"
+            ]
+            .iter()
+            .map(|text| RawNumber(text.to_string()))
+            .collect::<Vec<_>>()
+        );
+
+        let map: HashMap<Key, RawNumber> = from_str("[1 => 0.10, 2.5 => 3]").unwrap();
+        assert_eq!(map[&Key::Int(1)], RawNumber("0.10".into()));
+        assert_eq!(map[&Key::Int(2)], RawNumber("3".into()));
+
+        let value: Value = from_str("['a' => 1, 'b' => 1.5, 'c' => 99999999999999999999]").unwrap();
+        assert_eq!(value["a"], Value::Int(1));
+        assert_eq!(value["b"], Value::Float(1.5));
+        assert_eq!(value["c"], Value::Float(1e20));
+
+        // floats without fractional digits stay floats
+        let value: Value = from_str("['a' => 1., 'b' => -1., 'c' => 1.e3]").unwrap();
+        assert_eq!(value["a"], Value::Float(1.0));
+        assert_eq!(value["b"], Value::Float(-1.0));
+        assert_eq!(value["c"], Value::Float(1000.0));
+    }
 }
@@ -0,0 +1,127 @@
+#[cfg(feature = "miette")]
+use miette::{SourceOffset, SourceSpan};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// A byte range in the parsed source
+///
+/// Optionally carries the line and column of the start of the range,
+/// which can be resolved from the source using [`with_position`](Span::with_position).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    start: usize,
+    end: usize,
+    position: Option<Position>,
+}
+
+/// A line and column in the parsed source, both starting at 1
+///
+/// The column is counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span {
+            start,
+            end,
+            position: None,
+        }
+    }
+
+    /// Resolve the line and column of the start of the span
+    pub fn with_position(mut self, source: &str) -> Self {
+        let before = &source[..self.start.min(source.len())];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        self.position = Some(Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        });
+        self
+    }
+
+    /// Byte offset of the start of the span
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset of the end of the span, exclusive
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The line and column of the start of the span, if resolved
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span::new(range.start, range.end)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.range()
+    }
+}
+
+#[cfg(feature = "miette")]
+impl From<&Span> for SourceSpan {
+    fn from(span: &Span) -> Self {
+        SourceSpan::new(SourceOffset::from(span.start), span.len())
+    }
+}
+
+#[cfg(feature = "miette")]
+impl From<Span> for SourceSpan {
+    fn from(span: Span) -> Self {
+        (&span).into()
+    }
+}
+
+#[test]
+fn test_span_position() {
+    let source = "[\n  'a' => 1,\n  'é' => x\n]";
+    let start = source.find('x').unwrap();
+    let span = Span::new(start, start + 1).with_position(source);
+    assert_eq!(
+        span.position(),
+        Some(Position {
+            line: 3,
+            column: 10
+        })
+    );
+    assert_eq!(&source[span.range()], "x");
+    assert_eq!(
+        Span::new(0, 1)
+            .with_position(source)
+            .position()
+            .unwrap()
+            .to_string(),
+        "1:1"
+    );
+}
@@ -1,7 +1,85 @@
+use std::fmt::{self, Display, Formatter};
+
+/// An invalid escape sequence or otherwise malformed string literal
 #[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnescapeError {
+    /// Byte offset of the offending part within the literal, counting from the opening quote
+    pub offset: usize,
+    /// Length in bytes of the offending part
+    pub len: usize,
+    pub reason: UnescapeErrorReason,
+}
+
+impl UnescapeError {
+    fn new(reason: UnescapeErrorReason, offset: usize, len: usize) -> Self {
+        UnescapeError {
+            offset,
+            len,
+            reason,
+        }
+    }
+
+    /// Move the error by `offset` bytes, for errors found in a part of the literal
+    fn shift(mut self, offset: usize) -> Self {
+        self.offset += offset;
+        self
+    }
+}
+
+impl Display for UnescapeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.reason, f)
+    }
+}
 
-/// An error occurred while
-pub struct UnescapeError;
+impl std::error::Error for UnescapeError {}
+
+/// Why a string literal couldn't be unescaped
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum UnescapeErrorReason {
+    /// A `\u{}` escape for a value that isn't a unicode codepoint
+    InvalidCodepoint,
+    /// A `\u{` escape without closing `}`
+    UnterminatedUnicodeEscape,
+    /// The value of a `\u{}` escape doesn't fit in 32 bits
+    Overflow,
+    /// A `\` without a character to escape
+    TrailingBackslash,
+    /// A `{$` or `${` interpolation that isn't a variable name followed by `}`
+    InvalidInterpolation,
+    /// A heredoc without a newline after the opening identifier
+    MissingHeredocNewline,
+    /// A line of a heredoc that's indented less than the closing identifier
+    InvalidHeredocIndentation,
+}
+
+impl UnescapeErrorReason {
+    pub fn desc(&self) -> &'static str {
+        match self {
+            UnescapeErrorReason::InvalidCodepoint => "invalid unicode codepoint in escape sequence",
+            UnescapeErrorReason::UnterminatedUnicodeEscape => {
+                "unterminated unicode escape sequence, expected '}'"
+            }
+            UnescapeErrorReason::Overflow => "unicode escape sequence overflows",
+            UnescapeErrorReason::TrailingBackslash => "escape sequence without escaped character",
+            UnescapeErrorReason::InvalidInterpolation => {
+                "only variable names can be interpolated with '{$' or '${'"
+            }
+            UnescapeErrorReason::MissingHeredocNewline => {
+                "missing newline after heredoc identifier"
+            }
+            UnescapeErrorReason::InvalidHeredocIndentation => {
+                "heredoc line is indented less than the closing identifier"
+            }
+        }
+    }
+}
+
+impl Display for UnescapeErrorReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.desc())
+    }
+}
 
 type UnescapeResult<T> = Result<T, UnescapeError>;
 
@@ -29,13 +107,13 @@ impl UnescapeState {
         self.out.push(c);
     }
 
-    fn push_raw(&mut self, c: u32) -> UnescapeResult<()> {
+    fn push_raw(&mut self, c: u32) -> Result<(), UnescapeErrorReason> {
         match std::char::from_u32(c) {
             Some(c) => {
                 self.push_char(c);
                 Ok(())
             }
-            None => Err(UnescapeError),
+            None => Err(UnescapeErrorReason::InvalidCodepoint),
         }
     }
 
@@ -43,31 +121,36 @@ impl UnescapeState {
         self.out.extend_from_slice(slice);
     }
 
-    fn finalize(self) -> UnescapeResult<String> {
-        String::from_utf8(self.out).map_err(|_| UnescapeError)
+    /// Get the unescaped bytes, which don't have to be valid UTF-8
+    fn finalize(self) -> Vec<u8> {
+        self.out
     }
 }
 
+/// Parse up to `max` digits, overflowing values still consume all digits so the error covers the full number
 fn parse_u32(
     s: &mut PeekableBytes,
     radix: u32,
-    mut result: u32,
+    result: u32,
     max: Option<u8>,
-) -> UnescapeResult<u32> {
+) -> Result<u32, UnescapeErrorReason> {
     let mut max = max.unwrap_or(u8::MAX);
+    let mut result = Some(result);
     while let Some(digit) = s.peek().and_then(|digit| (digit as char).to_digit(radix)) {
         let _ = s.next(); // consume the digit we peeked
-        result = result.checked_mul(radix).ok_or(UnescapeError)?;
-        result = result.checked_add(digit).ok_or(UnescapeError)?;
+        result = result
+            .and_then(|result| result.checked_mul(radix))
+            .and_then(|result| result.checked_add(digit));
         max -= 1;
         if max == 0 {
             break;
         }
     }
-    Ok(result)
+    result.ok_or(UnescapeErrorReason::Overflow)
 }
 
 trait EscapedString {
+    /// Unescape the escape sequence at the start of `bytes`, errors are relative to the start of the escape sequence
     fn handle_escape<'a>(bytes: &'a [u8], state: &mut UnescapeState) -> UnescapeResult<&'a [u8]>;
 }
 
@@ -80,7 +163,11 @@ impl EscapedString for SingleQuoteString {
         debug_assert_eq!(_next, Some(b'\\'));
         match ins.next() {
             None => {
-                return Err(UnescapeError);
+                return Err(UnescapeError::new(
+                    UnescapeErrorReason::TrailingBackslash,
+                    0,
+                    1,
+                ));
             }
             Some(d) => match d {
                 b'\\' | b'\'' => state.push_u8(d),
@@ -101,9 +188,10 @@ impl EscapedString for DoubleQuoteString {
         let mut ins = PeekableBytes::new(bytes);
         let _next = ins.next();
         debug_assert_eq!(_next, Some(b'\\'));
+        let error = |reason, ins: &PeekableBytes| UnescapeError::new(reason, 0, ins.pos);
         match ins.next() {
             None => {
-                return Err(UnescapeError);
+                return Err(error(UnescapeErrorReason::TrailingBackslash, &ins));
             }
             Some(d) => {
                 match d {
@@ -114,16 +202,22 @@ impl EscapedString for DoubleQuoteString {
                     b'v' => state.push_u8(b'\x0B'), // vertical tab
                     b'f' => state.push_u8(b'\x0C'), // form feed
                     b'x' => {
-                        let val = parse_u32(&mut ins, 16, 0, Some(2))?;
-                        state.push_raw(val)?;
+                        let val = parse_u32(&mut ins, 16, 0, Some(2))
+                            .map_err(|reason| error(reason, &ins))?;
+                        state.push_u8(val as u8);
                     }
                     b'u' => match ins.next() {
                         Some(b'{') => {
-                            let val = parse_u32(&mut ins, 16, 0, None)?;
-                            state.push_raw(val)?;
-                            if !matches!(ins.next(), Some(b'}')) {
-                                return Err(UnescapeError);
+                            let val = parse_u32(&mut ins, 16, 0, None);
+                            if ins.peek() != Some(b'}') {
+                                return Err(error(
+                                    UnescapeErrorReason::UnterminatedUnicodeEscape,
+                                    &ins,
+                                ));
                             }
+                            let _ = ins.next();
+                            val.and_then(|val| state.push_raw(val))
+                                .map_err(|reason| error(reason, &ins))?;
                         }
                         Some(d) => {
                             state.push_u8(b'\\');
@@ -136,9 +230,10 @@ impl EscapedString for DoubleQuoteString {
                         }
                     },
                     b'0'..=b'7' => {
-                        let val =
-                            parse_u32(&mut ins, 8, (d as char).to_digit(8).unwrap(), Some(3))?;
-                        state.push_raw(val)?;
+                        let val = parse_u32(&mut ins, 8, (d as char).to_digit(8).unwrap(), Some(3))
+                            .map_err(|reason| error(reason, &ins))?;
+                        // like php, octal escapes above `\377` overflow
+                        state.push_u8(val as u8);
                     }
                     _ => {
                         state.push_u8(b'\\');
@@ -151,27 +246,218 @@ impl EscapedString for DoubleQuoteString {
     }
 }
 
-pub fn parse_string(literal: &str) -> Result<String, UnescapeError> {
+/// Parse a string literal into the bytes of the string, the literal doesn't have to be valid UTF-8
+pub fn parse_string(literal: &[u8]) -> Result<Vec<u8>, UnescapeError> {
+    if let Some(heredoc) = literal.strip_prefix(b"<<<") {
+        return parse_heredoc(heredoc);
+    }
     let inner = &literal[1..(literal.len()) - 1];
-    if literal.bytes().next().unwrap() == b'\'' {
+    if literal[0] == b'\'' {
         unescape::<SingleQuoteString>(inner)
     } else {
         unescape::<DoubleQuoteString>(inner)
     }
+    .map_err(|error| error.shift(1))
+}
+
+/// A part of an interpolated string literal
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StringPart {
+    Text(Vec<u8>),
+    /// The name of a variable, without the `$`
+    Variable(String),
+}
+
+/// Parse a string literal, splitting out the variables in double quoted strings and heredocs
+///
+/// Variables can be written as `$name`, `{$name}` or `${name}`, more complex expressions aren't supported.
+/// Single quoted strings and nowdocs are returned as a single text part.
+pub fn parse_interpolated(literal: &[u8]) -> UnescapeResult<Vec<StringPart>> {
+    if let Some(heredoc) = literal.strip_prefix(b"<<<") {
+        let body = heredoc_body(heredoc).map_err(|error| error.shift(3))?;
+        if body.nowdoc {
+            return Ok(vec![StringPart::Text(body.text)]);
+        }
+        return interpolate(&body.text).map_err(|error| body.map_error(error).shift(3));
+    }
+    let inner = &literal[1..(literal.len()) - 1];
+    if literal[0] == b'\'' {
+        unescape::<SingleQuoteString>(inner).map(|text| vec![StringPart::Text(text)])
+    } else {
+        interpolate(inner)
+    }
+    .map_err(|error| error.shift(1))
+}
+
+/// Unescape a double quoted string, splitting out the variables
+fn interpolate(s: &[u8]) -> UnescapeResult<Vec<StringPart>> {
+    let mut parts = Vec::new();
+    let mut state = UnescapeState::with_capacity(s.len());
+    let mut bytes = s;
+    while let Some(index) = memchr::memchr3(b'\\', b'$', b'{', bytes) {
+        state.push_slice(&bytes[0..index]);
+        bytes = &bytes[index..];
+        let offset = s.len() - bytes.len();
+        let invalid_interpolation = |name: &[u8]| {
+            UnescapeError::new(
+                UnescapeErrorReason::InvalidInterpolation,
+                offset,
+                name.len() + 2,
+            )
+        };
+        let (name, rest) = match bytes {
+            [b'\\', ..] => {
+                bytes = DoubleQuoteString::handle_escape(bytes, &mut state)
+                    .map_err(|error| error.shift(offset))?;
+                continue;
+            }
+            [b'{', b'$', rest @ ..] => {
+                let (name, rest) = split_variable_name(rest);
+                match rest {
+                    [b'}', rest @ ..] if !name.is_empty() => (name, rest),
+                    _ => return Err(invalid_interpolation(name)),
+                }
+            }
+            [b'$', b'{', rest @ ..] => {
+                let (name, rest) = split_variable_name(rest);
+                match rest {
+                    [b'}', rest @ ..] if !name.is_empty() => (name, rest),
+                    _ => return Err(invalid_interpolation(name)),
+                }
+            }
+            [b'$', rest @ ..] => match split_variable_name(rest) {
+                (name, rest) if !name.is_empty() && !name[0].is_ascii_digit() => (name, rest),
+                _ => {
+                    state.push_u8(b'$');
+                    bytes = rest;
+                    continue;
+                }
+            },
+            [first, rest @ ..] => {
+                state.push_u8(*first);
+                bytes = rest;
+                continue;
+            }
+            [] => unreachable!(),
+        };
+        let text = std::mem::replace(&mut state, UnescapeState::with_capacity(rest.len()));
+        if !text.out.is_empty() {
+            parts.push(StringPart::Text(text.finalize()));
+        }
+        let name = std::str::from_utf8(name).map_err(|_| invalid_interpolation(name))?;
+        parts.push(StringPart::Variable(name.into()));
+        bytes = rest;
+    }
+    state.push_slice(bytes);
+    if !state.out.is_empty() || parts.is_empty() {
+        parts.push(StringPart::Text(state.finalize()));
+    }
+    Ok(parts)
+}
+
+/// Split a variable name of the form `[a-zA-Z0-9_\x80-\xff]*` from the start of the bytes
+fn split_variable_name(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let len = bytes
+        .iter()
+        .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_' || **byte >= 0x80)
+        .count();
+    bytes.split_at(len)
+}
+
+/// Parse a heredoc or nowdoc, starting after the `<<<`
+///
+/// Heredocs are unescaped like double quoted strings while nowdocs are kept as is.
+fn parse_heredoc(heredoc: &[u8]) -> UnescapeResult<Vec<u8>> {
+    let body = heredoc_body(heredoc).map_err(|error| error.shift(3))?;
+    if body.nowdoc {
+        Ok(body.text)
+    } else {
+        unescape::<DoubleQuoteString>(&body.text).map_err(|error| body.map_error(error).shift(3))
+    }
+}
+
+/// The raw body of a heredoc or nowdoc, with the indentation of the closing identifier removed
+struct HeredocBody {
+    text: Vec<u8>,
+    nowdoc: bool,
+    /// Offset of the start of every line of `text` in the heredoc
+    line_offsets: Vec<usize>,
+}
+
+impl HeredocBody {
+    /// Move an error in the text to the position of the offending part in the heredoc
+    fn map_error(&self, mut error: UnescapeError) -> UnescapeError {
+        let before = &self.text[..error.offset];
+        let line_start = memchr::memrchr(b'\n', before).map_or(0, |newline| newline + 1);
+        let line = memchr::memchr_iter(b'\n', before).count();
+        error.offset = self.line_offsets[line] + error.offset - line_start;
+        error
+    }
+}
+
+/// Get the raw body of a heredoc or nowdoc starting after the `<<<`
+///
+/// The indentation of the closing identifier is removed from all lines.
+fn heredoc_body(heredoc: &[u8]) -> UnescapeResult<HeredocBody> {
+    let newline = memchr::memchr(b'\n', heredoc).ok_or_else(|| {
+        UnescapeError::new(UnescapeErrorReason::MissingHeredocNewline, 0, heredoc.len())
+    })?;
+    let (header, rest) = (&heredoc[..newline], &heredoc[newline + 1..]);
+    let nowdoc = header.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'\'');
+    let (body, closing) = match memchr::memrchr(b'\n', rest) {
+        Some(newline) => (&rest[..newline], &rest[newline + 1..]),
+        None => (&[][..], rest),
+    };
+    let indent_len = closing
+        .iter()
+        .take_while(|byte| matches!(byte, b' ' | b'\t'))
+        .count();
+    let indent = &closing[..indent_len];
+    let body = body.strip_suffix(b"\r").unwrap_or(body);
+
+    let mut lines = Vec::new();
+    let mut line_offsets = Vec::new();
+    let mut offset = header.len() + 1;
+    for line in body.split(|byte| *byte == b'\n') {
+        match line.strip_prefix(indent) {
+            Some(stripped) => {
+                lines.push(stripped);
+                line_offsets.push(offset + indent.len());
+            }
+            None if line.iter().all(|byte| *byte == b'\r') => {
+                lines.push(&[][..]);
+                line_offsets.push(offset);
+            }
+            None => {
+                return Err(UnescapeError::new(
+                    UnescapeErrorReason::InvalidHeredocIndentation,
+                    offset,
+                    line.len(),
+                ))
+            }
+        }
+        offset += line.len() + 1;
+    }
+    Ok(HeredocBody {
+        text: lines.join(&b'\n'),
+        nowdoc,
+        line_offsets,
+    })
 }
 
-fn unescape<S: EscapedString>(s: &str) -> UnescapeResult<String> {
+fn unescape<S: EscapedString>(s: &[u8]) -> UnescapeResult<Vec<u8>> {
     let mut state = UnescapeState::with_capacity(s.len());
-    let mut bytes = s.as_bytes();
+    let mut bytes = s;
     while let Some(escape_index) = memchr::memchr(b'\\', bytes) {
         state.push_slice(&bytes[0..escape_index]);
         bytes = &bytes[escape_index..];
-        bytes = S::handle_escape(bytes, &mut state)?;
+        let offset = s.len() - bytes.len();
+        bytes = S::handle_escape(bytes, &mut state).map_err(|error| error.shift(offset))?;
     }
 
     state.push_slice(&bytes[0..]);
 
-    state.finalize()
+    Ok(state.finalize())
 }
 
 struct PeekableBytes<'a> {
@@ -203,16 +489,19 @@ impl<'a> PeekableBytes<'a> {
     }
 }
 
+/// Check if a string key is converted to an integer key by php
+///
+/// Only canonical integers are converted, strings with a `+` sign, leading zeros, whitespace or `-0` are kept as string.
+/// Integers that don't fit in 64 bits are kept as string as well, so the string can always be parsed with
+/// `str::parse::<i64>()` when this returns `true`, including `i64::MIN`.
 pub fn is_array_key_numeric(string: &str) -> bool {
-    let mut bytes = string.bytes();
-    if !matches!(
-        (bytes.next(), string.len()),
-        (Some(b'-'), _) | (Some(b'0'..=b'9'), 1) | (Some(b'1'..=b'9'), _)
-    ) {
-        return false;
-    }
-
-    bytes.all(|byte| byte.is_ascii_digit())
+    let digits = string.strip_prefix('-').unwrap_or(string);
+    let canonical = match digits.as_bytes() {
+        [b'0'] => digits.len() == string.len(),
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    };
+    canonical && string.parse::<i64>().is_ok()
 }
 
 #[cfg(test)]
@@ -227,100 +516,253 @@ mod tests {
         assert!(!is_array_key_numeric("0123"));
         assert!(!is_array_key_numeric("123asd"));
         assert!(!is_array_key_numeric("+123"));
+        assert!(!is_array_key_numeric("-"));
+        assert!(!is_array_key_numeric("-0"));
+        assert!(!is_array_key_numeric("-01"));
+        assert!(!is_array_key_numeric("08"));
+        assert!(!is_array_key_numeric("1.0"));
+        assert!(!is_array_key_numeric(" 1"));
+        assert!(!is_array_key_numeric("1 "));
+        assert!(!is_array_key_numeric(""));
+        assert!(is_array_key_numeric("9223372036854775807"));
+        assert!(is_array_key_numeric("-9223372036854775808"));
+        assert!(!is_array_key_numeric("9223372036854775808"));
+        assert!(!is_array_key_numeric("-9223372036854775809"));
+        assert!(!is_array_key_numeric("99999999999999999999"));
+    }
+
+    #[test]
+    fn test_heredoc() {
+        assert_eq!(
+            parse_string("<<<EOT\n    a \\t $b\n\n      \"c\"\n    EOT".as_bytes()),
+            Ok("a \t $b\n\n  \"c\"".into())
+        );
+        assert_eq!(
+            parse_string("<<<'EOT'\n  a \\t\n  EOT".as_bytes()),
+            Ok("a \\t".into())
+        );
+        assert_eq!(parse_string("<<<\"EOT\"\nEOT".as_bytes()), Ok("".into()));
+        assert_eq!(
+            parse_string("<<<EOT\r\nline\r\nEOT".as_bytes()),
+            Ok("line".into())
+        );
+        assert_eq!(
+            parse_string("<<<EOT\n a\n  EOT".as_bytes()),
+            Err(UnescapeError::new(
+                UnescapeErrorReason::InvalidHeredocIndentation,
+                7,
+                2
+            ))
+        );
+    }
+
+    #[test]
+    fn test_error_offset() {
+        let error = |literal: &str| parse_string(literal.as_bytes()).unwrap_err();
+
+        assert_eq!(
+            error(r#""a\u{110000}b""#),
+            UnescapeError::new(UnescapeErrorReason::InvalidCodepoint, 2, 10)
+        );
+        assert_eq!(
+            error("<<<EOT\n  a\n  b\\u{d800}\n  EOT"),
+            UnescapeError::new(UnescapeErrorReason::InvalidCodepoint, 14, 8)
+        );
+        assert_eq!(
+            parse_interpolated("<<<EOT\n    x ${a b}\n    EOT".as_bytes()).unwrap_err(),
+            UnescapeError::new(UnescapeErrorReason::InvalidInterpolation, 13, 3)
+        );
+        assert_eq!(
+            error("<<<EOT"),
+            UnescapeError::new(UnescapeErrorReason::MissingHeredocNewline, 3, 3)
+        );
+    }
+
+    #[test]
+    fn test_interpolate() {
+        use StringPart::{Text, Variable};
+
+        assert_eq!(
+            parse_interpolated(r#""https://$host/path""#.as_bytes()),
+            Ok(vec![
+                Text("https://".into()),
+                Variable("host".into()),
+                Text("/path".into())
+            ])
+        );
+        assert_eq!(
+            parse_interpolated(r#""{$prefix}_table ${b}\$c $1 {a} $""#.as_bytes()),
+            Ok(vec![
+                Variable("prefix".into()),
+                Text("_table ".into()),
+                Variable("b".into()),
+                Text("$c $1 {a} $".into())
+            ])
+        );
+        assert_eq!(
+            parse_interpolated("'$a'".as_bytes()),
+            Ok(vec![Text("$a".into())])
+        );
+        assert_eq!(
+            parse_interpolated("<<<EOT\n  \\t$a\n  EOT".as_bytes()),
+            Ok(vec![Text("\t".into()), Variable("a".into())])
+        );
+        assert_eq!(
+            parse_interpolated("<<<'EOT'\n  $a\n  EOT".as_bytes()),
+            Ok(vec![Text("$a".into())])
+        );
+        assert_eq!(
+            parse_interpolated(r#""""#.as_bytes()),
+            Ok(vec![Text("".into())])
+        );
+        assert_eq!(
+            parse_interpolated(r#""{$a['b']}""#.as_bytes()),
+            Err(UnescapeError::new(
+                UnescapeErrorReason::InvalidInterpolation,
+                1,
+                3
+            ))
+        );
     }
 
     #[test]
     fn test_unescape_single() {
-        assert_eq!(unescape::<SingleQuoteString>(r#"abc"#), Ok("abc".into()));
         assert_eq!(
-            unescape::<SingleQuoteString>(r#"ab\nc"#),
+            unescape::<SingleQuoteString>(r#"abc"#.as_bytes()),
+            Ok("abc".into())
+        );
+        assert_eq!(
+            unescape::<SingleQuoteString>(r#"ab\nc"#.as_bytes()),
             Ok("ab\\nc".into())
         );
         assert_eq!(
-            unescape::<SingleQuoteString>(r#"ab\zc"#),
+            unescape::<SingleQuoteString>(r#"ab\zc"#.as_bytes()),
             Ok("ab\\zc".into())
         );
         assert_eq!(
-            unescape::<SingleQuoteString>(r#" \"abc\" "#),
+            unescape::<SingleQuoteString>(r#" \"abc\" "#.as_bytes()),
             Ok(" \\\"abc\\\" ".into())
         );
-        assert_eq!(unescape::<SingleQuoteString>(r#"𝄞"#), Ok("𝄞".into()));
-        assert_eq!(unescape::<SingleQuoteString>(r#"\𝄞"#), Ok("\\𝄞".into()));
         assert_eq!(
-            unescape::<SingleQuoteString>(r#"\xD834\xDD1E"#),
+            unescape::<SingleQuoteString>(r#"𝄞"#.as_bytes()),
+            Ok("𝄞".into())
+        );
+        assert_eq!(
+            unescape::<SingleQuoteString>(r#"\𝄞"#.as_bytes()),
+            Ok("\\𝄞".into())
+        );
+        assert_eq!(
+            unescape::<SingleQuoteString>(r#"\xD834\xDD1E"#.as_bytes()),
             Ok("\\xD834\\xDD1E".into())
         );
         assert_eq!(
-            unescape::<SingleQuoteString>(r#"\xD834"#),
+            unescape::<SingleQuoteString>(r#"\xD834"#.as_bytes()),
             Ok("\\xD834".into())
         );
         assert_eq!(
-            unescape::<SingleQuoteString>(r#"\xDD1E"#),
+            unescape::<SingleQuoteString>(r#"\xDD1E"#.as_bytes()),
             Ok("\\xDD1E".into())
         );
-        assert_eq!(unescape::<SingleQuoteString>("\t"), Ok("\t".into()));
+        assert_eq!(
+            unescape::<SingleQuoteString>("\t".as_bytes()),
+            Ok("\t".into())
+        );
     }
 
     #[test]
     fn test_unescape_double() {
-        assert_eq!(unescape::<DoubleQuoteString>(r#"abc"#), Ok("abc".into()));
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"ab\nc"#),
+            unescape::<DoubleQuoteString>(r#"abc"#.as_bytes()),
+            Ok("abc".into())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"ab\nc"#.as_bytes()),
             Ok("ab\nc".into())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"ab\zc"#),
+            unescape::<DoubleQuoteString>(r#"ab\zc"#.as_bytes()),
             Ok("ab\\zc".into())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#" \"abc\" "#),
+            unescape::<DoubleQuoteString>(r#" \"abc\" "#.as_bytes()),
             Ok(" \"abc\" ".into())
         );
-        assert_eq!(unescape::<DoubleQuoteString>(r#"𝄞"#), Ok("𝄞".into()));
-        assert_eq!(unescape::<DoubleQuoteString>(r#"\𝄞"#), Ok("\\𝄞".into()));
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\u{1D11E}"#),
+            unescape::<DoubleQuoteString>(r#"𝄞"#.as_bytes()),
             Ok("𝄞".into())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\xD834"#),
-            Ok("\u{D8}34".into())
+            unescape::<DoubleQuoteString>(r#"\𝄞"#.as_bytes()),
+            Ok("\\𝄞".into())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"\u{1D11E}"#.as_bytes()),
+            Ok("𝄞".into())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"\xD834"#.as_bytes()),
+            Ok(b"\xD834".to_vec())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"\xDD1E"#.as_bytes()),
+            Ok(b"\xDD1E".to_vec())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\xDD1E"#),
-            Ok("\u{DD}1E".into())
+            unescape::<DoubleQuoteString>(r#"\xC3\xA9"#.as_bytes()),
+            Ok("é".into())
         );
-        assert_eq!(unescape::<DoubleQuoteString>(r#"\xD"#), Ok("\u{D}".into()));
-        assert_eq!(unescape::<DoubleQuoteString>("\t"), Ok("\t".into()));
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\u{D834"#),
-            Err(UnescapeError)
+            unescape::<DoubleQuoteString>(r#"\xD"#.as_bytes()),
+            Ok("\u{D}".into())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\uD834"#),
+            unescape::<DoubleQuoteString>(r#"\377\400"#.as_bytes()),
+            Ok(b"\xFF\x00".to_vec())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>("\t".as_bytes()),
+            Ok("\t".into())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"\u{D834"#.as_bytes()),
+            Err(UnescapeError::new(
+                UnescapeErrorReason::UnterminatedUnicodeEscape,
+                0,
+                7
+            ))
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"\uD834"#.as_bytes()),
             Ok("\\uD834".into())
         );
-        assert_eq!(unescape::<DoubleQuoteString>(r#"\u"#), Ok("\\u".into()));
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\47foo"#),
+            unescape::<DoubleQuoteString>(r#"\u"#.as_bytes()),
+            Ok("\\u".into())
+        );
+        assert_eq!(
+            unescape::<DoubleQuoteString>(r#"\47foo"#.as_bytes()),
             Ok("'foo".into())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\48foo"#),
+            unescape::<DoubleQuoteString>(r#"\48foo"#.as_bytes()),
             Ok("\u{4}8foo".into())
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\87foo"#),
+            unescape::<DoubleQuoteString>(r#"\87foo"#.as_bytes()),
             Ok("\\87foo".into())
         );
 
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\u{999999}"#),
-            Err(UnescapeError)
+            unescape::<DoubleQuoteString>(r#"\u{999999}"#.as_bytes()),
+            Err(UnescapeError::new(
+                UnescapeErrorReason::InvalidCodepoint,
+                0,
+                10
+            ))
         );
         assert_eq!(
-            unescape::<DoubleQuoteString>(r#"\u{999999999999999999}"#),
-            Err(UnescapeError)
+            unescape::<DoubleQuoteString>(r#"\u{999999999999999999}"#.as_bytes()),
+            Err(UnescapeError::new(UnescapeErrorReason::Overflow, 0, 22))
         );
     }
 }
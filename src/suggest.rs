@@ -0,0 +1,67 @@
+/// Keywords that can be used as values, spelled the way they are suggested
+const VALUE_KEYWORDS: &[&str] = &["true", "false", "null", "array", "INF", "NAN"];
+
+/// Find the value keyword the identifier is most likely a misspelling of
+pub fn suggest_keyword(identifier: &str) -> Option<&'static str> {
+    let identifier = identifier.to_ascii_lowercase();
+    VALUE_KEYWORDS
+        .iter()
+        .map(|keyword| {
+            (
+                keyword,
+                edit_distance(&identifier, &keyword.to_ascii_lowercase()),
+            )
+        })
+        .filter(|(keyword, distance)| *distance <= max_distance(keyword))
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(keyword, _)| *keyword)
+}
+
+/// Short keywords only allow a single typo, so unrelated short identifiers aren't matched
+fn max_distance(keyword: &str) -> usize {
+    if keyword.len() <= 4 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Levenshtein distance between two ascii strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.bytes().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(a != *b);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, suggest_keyword};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("null", "null"));
+        assert_eq!(1, edit_distance("nul", "null"));
+        assert_eq!(1, edit_distance("trues", "true"));
+        assert_eq!(2, edit_distance("ture", "true"));
+        assert_eq!(3, edit_distance("", "abc"));
+    }
+
+    #[test]
+    fn test_suggest_keyword() {
+        assert_eq!(Some("true"), suggest_keyword("TRUES"));
+        assert_eq!(Some("null"), suggest_keyword("nul"));
+        assert_eq!(Some("array"), suggest_keyword("arrray"));
+        assert_eq!(Some("false"), suggest_keyword("fasle"));
+        assert_eq!(Some("INF"), suggest_keyword("inff"));
+        assert_eq!(None, suggest_keyword("PHP_EOL"));
+        assert_eq!(None, suggest_keyword("foo"));
+    }
+}
@@ -0,0 +1,190 @@
+//! Utilities for testing code that integrates with the parser
+//!
+//! Only available with the `test_utils` feature enabled.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use php_literal_parser::test_utils::{assert_roundtrip, ValueGenerator};
+//!
+//! for value in ValueGenerator::new(42).take(100) {
+//!     assert_roundtrip(&value);
+//! }
+//! ```
+use crate::{from_str, Array, Key, Value};
+use std::fs::{read_dir, read_to_string};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Assert that the value survives being written as a php literal and parsed back
+///
+/// ## Panics
+///
+/// Panics with the generated source if the parsed value differs from the original or can't be parsed.
+pub fn assert_roundtrip(value: &Value) {
+    let source = match value.to_php_string() {
+        Ok(source) => source,
+        Err(err) => panic!("failed to write {:?} as php: {}", value, err),
+    };
+    match from_str::<Value>(&source) {
+        Ok(parsed) => assert_eq!(
+            value, &parsed,
+            "value didn't survive round trip through:\n{}",
+            source
+        ),
+        Err(err) => panic!("failed to parse round trip source:\n{}\n{}", source, err),
+    }
+}
+
+/// A php source file used as test input
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub path: PathBuf,
+    pub source: String,
+}
+
+impl Fixture {
+    /// The file name of the fixture without extension
+    pub fn name(&self) -> &str {
+        self.path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    }
+}
+
+/// Load all `.php` files in a directory, sorted by path
+pub fn load_fixtures(dir: impl AsRef<Path>) -> io::Result<Vec<Fixture>> {
+    let mut fixtures = Vec::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("php") {
+            let source = read_to_string(&path)?;
+            fixtures.push(Fixture { path, source });
+        }
+    }
+    fixtures.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(fixtures)
+}
+
+/// Deterministic generator for arbitrary `Value` trees
+///
+/// Generated values only contain variants that can be expressed as a php literal,
+/// floats are always finite and string keys are never numeric.
+#[derive(Debug, Clone)]
+pub struct ValueGenerator {
+    state: u64,
+    max_depth: usize,
+    max_len: usize,
+}
+
+const CHARS: &[char] = &[
+    'a', 'b', 'z', 'A', 'Z', '0', '9', ' ', '_', '-', '.', '\'', '"', '\\', '$', '\n', '\t', 'é',
+    '€', '𝄞',
+];
+
+impl ValueGenerator {
+    /// Create a generator from a seed, the same seed always produces the same values
+    pub fn new(seed: u64) -> Self {
+        ValueGenerator {
+            // xorshift doesn't work with a zero state
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            max_depth: 3,
+            max_len: 5,
+        }
+    }
+
+    /// Set the maximum nesting depth of generated arrays
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set the maximum number of array entries and string characters
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Generate the next value
+    pub fn generate(&mut self) -> Value {
+        self.generate_value(self.max_depth)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn below(&mut self, max: usize) -> usize {
+        (self.next_u64() % (max as u64 + 1)) as usize
+    }
+
+    fn generate_value(&mut self, depth: usize) -> Value {
+        let kinds = if depth == 0 { 5 } else { 6 };
+        match self.below(kinds - 1) {
+            0 => Value::Null,
+            1 => Value::Bool(self.next_u64() % 2 == 0),
+            2 => Value::Int((self.next_u64() as i64) >> (1 + self.below(62))),
+            3 => {
+                let float = (self.next_u64() as i64 >> 12) as f64 / (1u64 << self.below(40)) as f64;
+                Value::Float(float)
+            }
+            4 => Value::String(self.generate_string()),
+            _ => {
+                let len = self.below(self.max_len);
+                let mut map = Array::with_capacity(len);
+                for _ in 0..len {
+                    let key = if self.next_u64() % 2 == 0 {
+                        Key::Int(self.below(10) as i64)
+                    } else {
+                        Key::String(format!("k{}", self.generate_string()))
+                    };
+                    map.insert(key, self.generate_value(depth - 1));
+                }
+                Value::Array(map)
+            }
+        }
+    }
+
+    fn generate_string(&mut self) -> String {
+        let len = self.below(self.max_len);
+        (0..len)
+            .map(|_| CHARS[self.below(CHARS.len() - 1)])
+            .collect()
+    }
+}
+
+impl Iterator for ValueGenerator {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.generate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_roundtrip() {
+        for value in ValueGenerator::new(1).take(500) {
+            assert_roundtrip(&value);
+        }
+    }
+
+    #[test]
+    fn test_fixtures() {
+        let fixtures =
+            load_fixtures(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).unwrap();
+        assert!(!fixtures.is_empty());
+        for fixture in fixtures {
+            let value = from_str::<Value>(&fixture.source)
+                .unwrap_or_else(|err| panic!("failed to parse {}: {}", fixture.name(), err));
+            assert_roundtrip(&value);
+        }
+    }
+}
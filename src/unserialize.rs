@@ -0,0 +1,238 @@
+use crate::error::NestingLimitError;
+use crate::string::is_array_key_numeric;
+use crate::{Array, Key, ParseError, ParserOptions, Span, Value};
+
+impl Value {
+    /// Replace string values containing a php `serialize()`'d array with the unserialized array
+    ///
+    /// Nested arrays are expanded recursively, including arrays found inside the unserialized data.
+    /// Strings that aren't a valid serialized array, or that contain serialized objects or references,
+    /// are kept as is. Arrays nested deeper than the default [`max_depth`](ParserOptions::max_depth)
+    /// fail with [`ParseError::NestingLimit`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use php_literal_parser::{from_str, Value};
+    /// # use std::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let mut value = from_str::<Value>(r#"[
+    ///     "options" => 'a:2:{s:5:"theme";s:4:"dark";i:0;b:1;}',
+    ///     "title" => "a:b",
+    /// ]"#)?;
+    /// value.expand_serialized()?;
+    ///
+    /// assert_eq!(value["options"]["theme"], "dark");
+    /// assert_eq!(value["options"][0], true);
+    /// assert_eq!(value["title"], "a:b");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expand_serialized(&mut self) -> Result<(), ParseError> {
+        self.expand_serialized_with_options(&ParserOptions::default())
+    }
+
+    /// Replace string values containing a php `serialize()`'d array with the unserialized array
+    /// using the nesting limit from the provided options, see [`expand_serialized`](Value::expand_serialized)
+    pub fn expand_serialized_with_options(
+        &mut self,
+        options: &ParserOptions,
+    ) -> Result<(), ParseError> {
+        self.expand_serialized_at(0, options.depth_limit())
+    }
+
+    /// Expand the serialized arrays in a value that is nested `depth` levels deep
+    fn expand_serialized_at(&mut self, depth: usize, limit: usize) -> Result<(), ParseError> {
+        match self {
+            Value::String(str) => {
+                if let Some(mut value) = unserialize_array(str, depth, limit)? {
+                    value.expand_serialized_at(depth, limit)?;
+                    *self = value;
+                }
+            }
+            Value::Array(map) => {
+                for value in map.values_mut() {
+                    value.expand_serialized_at(depth + 1, limit)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Parse the output of php's `serialize()` if it's an array
+fn unserialize_array(input: &str, depth: usize, limit: usize) -> Result<Option<Value>, ParseError> {
+    if !input.starts_with("a:") {
+        return Ok(None);
+    }
+    let mut reader = Reader {
+        input,
+        pos: 0,
+        depth,
+        limit,
+        error: None,
+    };
+    let value = reader.value();
+    if let Some(error) = reader.error {
+        return Err(error);
+    }
+    Ok(value.filter(|_| reader.pos == input.len()))
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+    /// The nesting depth of the value being read
+    depth: usize,
+    limit: usize,
+    /// Set when reading stopped because arrays are nested too deep
+    error: Option<ParseError>,
+}
+
+impl<'a> Reader<'a> {
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        if self.input.as_bytes().get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Read everything up to the terminator, consuming the terminator
+    fn until(&mut self, terminator: u8) -> Option<&'a str> {
+        let rest = &self.input[self.pos..];
+        let end = rest.bytes().position(|byte| byte == terminator)?;
+        self.pos += end + 1;
+        Some(&rest[..end])
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a str> {
+        let str = self.input.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(str)
+    }
+
+    fn string(&mut self) -> Option<&'a str> {
+        let len = self.until(b':')?.parse().ok()?;
+        self.expect(b'"')?;
+        let str = self.take(len)?;
+        self.expect(b'"')?;
+        self.expect(b';')?;
+        Some(str)
+    }
+
+    fn key(&mut self) -> Option<Key> {
+        let kind = *self.input.as_bytes().get(self.pos)?;
+        self.pos += 1;
+        self.expect(b':')?;
+        match kind {
+            b'i' => Some(Key::Int(self.until(b';')?.parse().ok()?)),
+            b's' => {
+                let str = self.string()?;
+                Some(match str.parse() {
+                    Ok(int) if is_array_key_numeric(str) => Key::Int(int),
+                    _ => Key::String(str.into()),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        let kind = *self.input.as_bytes().get(self.pos)?;
+        self.pos += 1;
+        if kind == b'N' {
+            self.expect(b';')?;
+            return Some(Value::Null);
+        }
+        self.expect(b':')?;
+        Some(match kind {
+            b'b' => match self.until(b';')? {
+                "0" => Value::Bool(false),
+                "1" => Value::Bool(true),
+                _ => return None,
+            },
+            b'i' => Value::Int(self.until(b';')?.parse().ok()?),
+            b'd' => Value::Float(match self.until(b';')? {
+                "INF" => f64::INFINITY,
+                "-INF" => f64::NEG_INFINITY,
+                "NAN" => f64::NAN,
+                float => float.parse().ok()?,
+            }),
+            b's' => Value::String(self.string()?.into()),
+            b'a' => {
+                if self.depth >= self.limit {
+                    let span = Span::new(self.pos - 2, self.pos);
+                    self.error = Some(NestingLimitError::new(self.limit, self.input, span).into());
+                    return None;
+                }
+                let len: usize = self.until(b':')?.parse().ok()?;
+                self.expect(b'{')?;
+                let mut map = Array::with_capacity(len.min(self.input.len()));
+                self.depth += 1;
+                for _ in 0..len {
+                    let key = self.key()?;
+                    map.insert(key, self.value()?);
+                }
+                self.depth -= 1;
+                self.expect(b'}')?;
+                Value::Array(map)
+            }
+            // objects and references don't have a `Value` representation
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, ParseError, ParserOptions, Value};
+
+    #[test]
+    fn test_expand_serialized() {
+        let mut value = from_str::<Value>(
+            r#"[
+                "nested" => 'a:3:{i:0;d:1.5;s:1:"5";N;s:4:"more";s:23:"a:1:{s:5:"été";i:-1;}";}',
+                "list" => ['a:1:{i:0;d:INF;}'],
+                "object" => 'a:1:{i:0;O:8:"stdClass":0:{}}',
+                "short" => 'a:1:{i:0;s:5:"abc";}',
+                "trailing" => 'a:0:{}x',
+            ]"#,
+        )
+        .unwrap();
+        value.expand_serialized().unwrap();
+
+        assert_eq!(value["nested"][0], 1.5);
+        assert!(value["nested"][5].is_null());
+        assert_eq!(value["nested"]["more"]["été"], -1);
+        assert_eq!(value["list"][0][0], f64::INFINITY);
+        assert_eq!(value["object"], r#"a:1:{i:0;O:8:"stdClass":0:{}}"#);
+        assert_eq!(value["short"], r#"a:1:{i:0;s:5:"abc";}"#);
+        assert_eq!(value["trailing"], "a:0:{}x");
+    }
+
+    #[test]
+    fn test_expand_serialized_nesting_limit() {
+        let nested =
+            |depth: usize| format!("{}i:1;{}", "a:1:{i:0;".repeat(depth), "}".repeat(depth));
+        let mut value = Value::from(nested(128));
+        value.expand_serialized().unwrap();
+        assert!(value.is_array());
+
+        let mut value = Value::from(nested(100_000));
+        assert!(matches!(
+            value.expand_serialized(),
+            Err(ParseError::NestingLimit(err)) if err.limit == 128
+        ));
+
+        // the depth of the containing array counts towards the limit
+        let mut value = from_str::<Value>("[null]").unwrap();
+        value[0] = nested(128).into();
+        assert!(value.expand_serialized().is_err());
+        let options = ParserOptions::new().max_depth(129);
+        assert!(value.expand_serialized_with_options(&options).is_ok());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{from_str_with_options, ParseError, ParserOptions};
+use serde::de::IgnoredAny;
+
+/// Check that the source is a valid php literal without building the parsed value
+///
+/// This is meant for editors and CI checks that only need to know if a literal is well-formed.
+/// When entries of arrays fail to parse, the remaining entries are still checked so all of the failing entries are reported.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::validate;
+///
+/// assert!(validate("['debug' => true, 'hosts' => ['localhost']]").is_ok());
+///
+/// let errors = validate("['debug' => UNDEFINED, 'hosts' => [1 + []]]").unwrap_err();
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn validate(source: &str) -> Result<(), Vec<ParseError>> {
+    validate_with_options(source, &ParserOptions::default())
+}
+
+/// Check that the source is a valid php literal using the provided options, see [`validate`]
+pub fn validate_with_options(source: &str, options: &ParserOptions) -> Result<(), Vec<ParseError>> {
+    match from_str_with_options::<IgnoredAny>(source, options) {
+        Ok(_) => Ok(()),
+        Err(ParseError::Fields(errors)) => Err(errors
+            .errors()
+            .iter()
+            .map(|error| error.error().clone())
+            .collect()),
+        Err(error) => Err(vec![error]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{validate, validate_with_options, ParseError, ParserOptions};
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("[1, 'foo' => [true, null], \"bar\\n\" => 1.5, ...[2]]").is_ok());
+        assert!(validate("'foo' . 'bar';").is_ok());
+
+        let errors = validate("[1, 2").unwrap_err();
+        assert!(matches!(errors[..], [ParseError::UnexpectedToken(_)]));
+
+        let errors = validate("['a' => FOO, 'b' => 1, 'c' => BAR]").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|error| matches!(error, ParseError::UnexpectedToken(_))));
+
+        assert!(matches!(
+            validate("[1] 2").unwrap_err()[..],
+            [ParseError::TrailingCharacters(_)]
+        ));
+
+        let options = ParserOptions::new().constant("FOO", 1).constant("BAR", 2);
+        assert!(validate_with_options("['a' => FOO, 'c' => BAR]", &options).is_ok());
+    }
+}
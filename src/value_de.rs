@@ -0,0 +1,645 @@
+use crate::serde_impl::{EXPR_TOKEN, INSTANCE_TOKEN, OBJECT_TOKEN};
+use crate::{Array, Key, ParseError, Value};
+use serde::de::value::{MapAccessDeserializer, SeqDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Unexpected, Visitor,
+};
+use std::vec::IntoIter;
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// Deserialize a type from an already parsed [`Value`]
+///
+/// To deserialize from a borrowed value, `&Value` also implements [`Deserializer`](serde::Deserializer).
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{from_str, from_value, Value};
+/// use serde::Deserialize;
+/// # use std::error::Error;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Database {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let value = from_str::<Value>(r#"["db" => ["host" => "localhost", "port" => 3306]]"#)?;
+///
+/// assert_eq!(
+///     Database::deserialize(&value["db"])?,
+///     Database { host: "localhost".into(), port: 3306 }
+/// );
+///
+/// let db: Database = from_value(value["db"].clone())?;
+/// assert_eq!(db.port, 3306);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_value<T>(value: Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl<'de> IntoDeserializer<'de, ParseError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl Value {
+    pub(crate) fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Bool(bool) => Unexpected::Bool(*bool),
+            Value::Int(int) => Unexpected::Signed(*int),
+            Value::Float(float) => Unexpected::Float(*float),
+            Value::String(str) => Unexpected::Str(str),
+            Value::Bytes(bytes) => Unexpected::Bytes(bytes),
+            Value::Array(_) => Unexpected::Map,
+            Value::Null => Unexpected::Unit,
+            Value::Object(_) => Unexpected::Other("object"),
+            Value::Expr(_) | Value::Instance(_) => Unexpected::Other("expression"),
+        }
+    }
+}
+
+/// Get the values of an array with consecutive integer keys starting at 0 in order
+pub(crate) fn into_list(map: Array) -> std::result::Result<Vec<Value>, Array> {
+    let is_list = (0..map.len()).all(|index| map.contains_key(&Key::Int(index as i64)));
+    if !is_list {
+        return Err(map);
+    }
+    let mut entries: Vec<_> = map.into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(entries.into_iter().map(|(_, value)| value).collect())
+}
+
+/// Get references to the values of an array with consecutive integer keys starting at 0 in order
+pub(crate) fn as_list(map: &Array) -> Option<Vec<&Value>> {
+    (0..map.len())
+        .map(|index| map.get(&Key::Int(index as i64)))
+        .collect()
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ParseError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Bool(bool) => visitor.visit_bool(bool),
+            Value::Int(int) => visitor.visit_i64(int),
+            Value::Float(float) => visitor.visit_f64(float),
+            Value::String(str) => visitor.visit_string(str),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::Array(map) => match into_list(map) {
+                Ok(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+                Err(map) => visitor.visit_map(ValueMapAccess::new(map.into_iter().collect())),
+            },
+            Value::Null => visitor.visit_unit(),
+            Value::Expr(expr) => visitor.visit_map(ValueMapAccess::new(vec![(
+                Key::String(EXPR_TOKEN.into()),
+                Value::String(expr),
+            )])),
+            Value::Instance(instance) => {
+                let args = instance
+                    .args
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, arg)| (Key::Int(index as i64), arg))
+                    .collect();
+                visitor.visit_map(ValueMapAccess::new(vec![
+                    (
+                        Key::String(INSTANCE_TOKEN.into()),
+                        Value::String(instance.class),
+                    ),
+                    (Key::String("args".into()), Value::Array(args)),
+                ]))
+            }
+            Value::Object(object) => {
+                let mut entries = Vec::with_capacity(object.properties.len() + 1);
+                entries.push((
+                    Key::String(OBJECT_TOKEN.into()),
+                    Value::String(object.class),
+                ));
+                entries.extend(object.properties);
+                visitor.visit_map(ValueMapAccess::new(entries))
+            }
+        }
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Object(object) => {
+                visitor.visit_map(ValueMapAccess::new(object.properties.into_iter().collect()))
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Array(map) => match into_list(map) {
+                Ok(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+                Err(map) => Err(de::Error::invalid_type(
+                    Value::Array(map).unexpected(),
+                    &visitor,
+                )),
+            },
+            value => Err(de::Error::invalid_type(value.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Array(map) if map.len() == 1 => visitor.visit_enum(MapAccessDeserializer::new(
+                ValueMapAccess::new(map.into_iter().collect()),
+            )),
+            value => Err(de::Error::invalid_type(value.unexpected(), &visitor)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ParseError> for &'de Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &'de Value {
+    type Error = ParseError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Bool(bool) => visitor.visit_bool(*bool),
+            Value::Int(int) => visitor.visit_i64(*int),
+            Value::Float(float) => visitor.visit_f64(*float),
+            Value::String(str) => visitor.visit_borrowed_str(str),
+            Value::Bytes(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Value::Array(map) => match as_list(map) {
+                Some(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+                None => visitor.visit_map(ValueRefMapAccess::new(map.iter().collect())),
+            },
+            Value::Null => visitor.visit_unit(),
+            Value::Expr(_) | Value::Instance(_) | Value::Object(_) => {
+                self.clone().deserialize_any(visitor)
+            }
+        }
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Object(object) => {
+                visitor.visit_map(ValueRefMapAccess::new(object.properties.iter().collect()))
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Array(map) => match as_list(map) {
+                Some(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+                None => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+            },
+            value => Err(de::Error::invalid_type(value.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            Value::Array(map) if map.len() == 1 => visitor.visit_enum(MapAccessDeserializer::new(
+                ValueRefMapAccess::new(map.iter().collect()),
+            )),
+            value => Err(de::Error::invalid_type(value.unexpected(), &visitor)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+struct ValueMapAccess {
+    entries: IntoIter<(Key, Value)>,
+    value: Option<Value>,
+}
+
+impl ValueMapAccess {
+    fn new(entries: Vec<(Key, Value)>) -> Self {
+        ValueMapAccess {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for ValueMapAccess {
+    type Error = ParseError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct ValueRefMapAccess<'de> {
+    entries: IntoIter<(&'de Key, &'de Value)>,
+    value: Option<&'de Value>,
+}
+
+impl<'de> ValueRefMapAccess<'de> {
+    fn new(entries: Vec<(&'de Key, &'de Value)>) -> Self {
+        ValueRefMapAccess {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for ValueRefMapAccess<'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyRefDeserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Array keys can be deserialized both as numbers and strings
+struct KeyDeserializer(Key);
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = ParseError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Key::Int(int) => visitor.visit_i64(int),
+            Key::String(str) => visitor.visit_string(str),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Key::Int(int) => visitor.visit_string(int.to_string()),
+            Key::String(str) => visitor.visit_string(str),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Key::Int(int) => visitor.visit_enum(int.to_string().into_deserializer()),
+            Key::String(str) => visitor.visit_enum(str.into_deserializer()),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct ignored_any
+    }
+}
+
+/// Borrowed array keys can be deserialized both as numbers and strings
+struct KeyRefDeserializer<'de>(&'de Key);
+
+impl<'de> de::Deserializer<'de> for KeyRefDeserializer<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Key::Int(int) => visitor.visit_i64(*int),
+            Key::String(str) => visitor.visit_borrowed_str(str),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Key::Int(int) => visitor.visit_string(int.to_string()),
+            Key::String(str) => visitor.visit_borrowed_str(str),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        KeyDeserializer(self.0.clone()).deserialize_enum(name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, from_str_with_options, from_value, ParserOptions, Value};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_value() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Mode {
+            Fast,
+            Slow { delay: u32 },
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Test<'a> {
+            name: &'a str,
+            ports: Vec<u16>,
+            modes: Vec<Mode>,
+            map: HashMap<i64, Option<f64>>,
+        }
+
+        let value = from_str::<Value>(
+            r#"["config" => [
+                "name" => "foo",
+                "ports" => [1 => 443, 0 => 80],
+                "modes" => ["Fast", ["Slow" => ["delay" => 5]]],
+                "map" => [5 => 1.5, "6" => null],
+            ]]"#,
+        )
+        .unwrap();
+        let expected = Test {
+            name: "foo",
+            ports: vec![80, 443],
+            modes: vec![Mode::Fast, Mode::Slow { delay: 5 }],
+            map: vec![(5, Some(1.5)), (6, None)].into_iter().collect(),
+        };
+
+        assert_eq!(Test::deserialize(&value["config"]).unwrap(), expected);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Owned {
+            ports: Vec<u16>,
+            modes: Vec<Mode>,
+        }
+        assert_eq!(
+            from_value::<Owned>(value["config"].clone()).unwrap(),
+            Owned {
+                ports: expected.ports,
+                modes: expected.modes
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_value_ref_roundtrip() {
+        let options = ParserOptions::new().lenient(true).capture_objects(true);
+        let value = from_str_with_options::<Value>(
+            r#"['a' => new Foo(1, [2]), 'b' => fn() => 1, 'c' => [1.5, null]]"#,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(Value::deserialize(&value).unwrap(), value);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::array::ArrayExt;
+use crate::error::SerializeError;
+use crate::serde_impl::{EXPR_TOKEN, INSTANCE_TOKEN, OBJECT_TOKEN};
+use crate::string::is_array_key_numeric;
+use crate::value_de::into_list;
+use crate::{Array, Instance, Key, Object, Value};
+use serde::ser::{self, Serialize};
+use std::convert::TryFrom;
+
+type Result<T> = std::result::Result<T, SerializeError>;
+
+/// Convert any serializable type into a [`Value`]
+///
+/// The value is structured the same way as it would be when writing it as php literal with [`to_string`](crate::to_string)
+/// and parsing the result, without going through the text representation.
+///
+/// ## Example
+///
+/// ```rust
+/// use php_literal_parser::{to_value, Value};
+/// use serde::Serialize;
+/// # use std::error::Error;
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     name: String,
+///     ports: Vec<u16>,
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let value = to_value(Config {
+///     name: "foo".into(),
+///     ports: vec![80, 443],
+/// })?;
+///
+/// assert_eq!(value["name"], "foo");
+/// assert_eq!(value["ports"][1], 443);
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_value<T>(value: T) -> Result<Value>
+where
+    T: Serialize,
+{
+    value.serialize(ValueSerializer)
+}
+
+fn list(values: Vec<Value>) -> Value {
+    Value::Array(
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| (Key::Int(index as i64), value))
+            .collect(),
+    )
+}
+
+fn variant(variant: &str, value: Value) -> Value {
+    Value::Array(std::iter::once((Key::String(variant.into()), value)).collect())
+}
+
+fn out_of_range<T: ToString>(int: T) -> SerializeError {
+    SerializeError::IntegerOutOfRange(int.to_string())
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeArray;
+    type SerializeStruct = SerializeArray;
+    type SerializeStructVariant = SerializeArray;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        Ok(Value::Int(i64::try_from(v).map_err(|_| out_of_range(v))?))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        Ok(Value::Int(i64::try_from(v).map_err(|_| out_of_range(v))?))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        Ok(Value::Int(i64::try_from(v).map_err(|_| out_of_range(v))?))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::String(v.into()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::String(v.into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::from_bytes(v.into()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        Ok(Value::String(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        match value.serialize(self)? {
+            Value::String(expr) if name == EXPR_TOKEN => Ok(Value::Expr(expr)),
+            value => Ok(value),
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(variant(variant_name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SerializeList {
+            values: Vec::with_capacity(len.unwrap_or_default()),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(SerializeList {
+            values: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(SerializeArray {
+            array: Array::with_capacity(len.unwrap_or_default()),
+            key: None,
+            variant: None,
+            instance: false,
+            object: false,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        let mut array = self.serialize_map(Some(len))?;
+        array.instance = name == INSTANCE_TOKEN;
+        array.object = name == OBJECT_TOKEN;
+        Ok(array)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        let mut array = self.serialize_map(Some(len))?;
+        array.variant = Some(variant);
+        Ok(array)
+    }
+}
+
+struct SerializeList {
+    values: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeList {
+    fn element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        let value = list(self.values);
+        Ok(match self.variant {
+            Some(name) => variant(name, value),
+            None => value,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+struct SerializeArray {
+    array: Array,
+    key: Option<Key>,
+    variant: Option<&'static str>,
+    /// Whether this is a captured object instantiation with `class` and `args` fields
+    instance: bool,
+    /// Whether this is an object with `class` and `properties` fields
+    object: bool,
+}
+
+impl SerializeArray {
+    fn field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.array
+            .insert(Key::String(key.into()), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Value> {
+        if self.instance {
+            let class = self.array.remove_key("class").and_then(Value::into_string);
+            let args = match self.array.remove_key("args") {
+                Some(Value::Array(args)) => into_list(args).ok(),
+                _ => None,
+            };
+            if let (Some(class), Some(args)) = (class, args) {
+                return Ok(Value::Instance(Instance { class, args }));
+            }
+            return Err(ser::Error::custom("invalid object instantiation"));
+        }
+        if self.object {
+            let class = self.array.remove_key("class").and_then(Value::into_string);
+            let properties = match self.array.remove_key("properties") {
+                Some(Value::Array(properties)) => Some(properties),
+                _ => None,
+            };
+            if let (Some(class), Some(properties)) = (class, properties) {
+                return Ok(Value::Object(Object { class, properties }));
+            }
+            return Err(ser::Error::custom("invalid object"));
+        }
+        let value = Value::Array(self.array);
+        Ok(match self.variant {
+            Some(name) => variant(name, value),
+            None => value,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeArray {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(match key.serialize(ValueSerializer)? {
+            Value::Int(int) => Key::Int(int),
+            Value::String(str) if is_array_key_numeric(&str) => match str.parse() {
+                Ok(int) => Key::Int(int),
+                Err(_) => Key::String(str),
+            },
+            Value::String(str) => Key::String(str),
+            _ => return Err(SerializeError::InvalidKey),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.array.insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeArray {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeArray {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_value;
+    use crate::{from_str, from_str_with_options, to_string, ParserOptions, Value};
+    use serde_derive::Serialize;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_to_value_matches_parsed() {
+        #[derive(Serialize)]
+        enum E {
+            Unit,
+            Tuple(u8, u8),
+            Struct { a: Option<f32> },
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            enums: Vec<E>,
+            map: BTreeMap<String, i32>,
+            tuple: (bool, char),
+        }
+
+        let test = Test {
+            enums: vec![E::Unit, E::Tuple(1, 2), E::Struct { a: None }],
+            map: vec![("5".into(), 1), ("b".into(), 2)].into_iter().collect(),
+            tuple: (true, 'x'),
+        };
+        let value = to_value(&test).unwrap();
+        assert_eq!(
+            value,
+            from_str::<Value>(&to_string(&test).unwrap()).unwrap()
+        );
+        assert_eq!(value["map"][5], 1);
+        assert_eq!(value["tuple"][1], "x");
+    }
+
+    #[test]
+    fn test_value_roundtrip() {
+        let options = ParserOptions::new().lenient(true).capture_objects(true);
+        let value = from_str_with_options::<Value>(
+            r#"['a' => new Foo(1, [2]), 'b' => fn() => 1, 'c' => [1.5, null]]"#,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(to_value(&value).unwrap(), value);
+    }
+}
@@ -0,0 +1,30 @@
+array (
+  'instanceid' => 'oc8c0fd71e03',
+  'passwordsalt' => 'yH8ljY9dPzZwsKSJgx1LR4mQEPZUA3',
+  'secret' => 'Ip6mPzVyQuEgu5xFDlExRI1kDR4s2IKHsCb6Ss4I5NmSeP5q',
+  'trusted_domains' =>
+  array (
+    0 => 'localhost',
+    1 => 'cloud.example.com',
+  ),
+  'datadirectory' => '/var/www/html/data',
+  'dbtype' => 'mysql',
+  'version' => '28.0.4.1',
+  'overwrite.cli.url' => 'https://cloud.example.com',
+  'dbname' => 'nextcloud',
+  'dbhost' => 'db:3306',
+  'dbport' => '',
+  'dbtableprefix' => 'oc_',
+  'mysql.utf8mb4' => true,
+  'installed' => true,
+  'maintenance' => false,
+  'loglevel' => 2,
+  'memcache.local' => '\\OC\\Memcache\\APCu',
+  'redis' =>
+  array (
+    'host' => 'redis',
+    'port' => 6379,
+    'timeout' => 1.5,
+    'password' => NULL,
+  ),
+)
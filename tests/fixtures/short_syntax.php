@@ -0,0 +1,14 @@
+[
+    // application settings
+    "name" => "Example",
+    "debug" => false,
+    "limits" => [
+        "upload" => 0x100000, # 1MiB
+        "ratio" => .75,
+        "retries" => 0b11,
+    ],
+    "locales" => ['en', 'de', "fr\u{00E7}ais"],
+    /* keys are coerced like php does */
+    "10" => 'ten',
+    true => 'one',
+];
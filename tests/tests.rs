@@ -1,5 +1,19 @@
+// with `preserve_order` arrays are stored in an `IndexMap`
+#[cfg(feature = "preserve_order")]
+use indexmap::indexmap as hashmap;
+#[cfg(not(feature = "preserve_order"))]
 use maplit::hashmap;
-use php_literal_parser::{from_str, Key, ParseError, Value};
+use php_literal_parser::{
+    entries_from_str_with_options, events_from_str_with_options, expr_from_str,
+    expr_from_str_with_options, from_reader, from_reader_with_options, from_slice, from_str,
+    from_str_partial, from_str_partial_with_options, from_str_prefix, from_str_with_options,
+    validate, value_from_str, value_from_str_with_options, DuplicateFields, ErrorKind, Event,
+    Instance, InvalidToken, Key, ParseError, ParserOptions, Span, SyntaxTree, UnescapeErrorReason,
+    Value,
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 fn parse(source: &str) -> Result<Value, ParseError> {
     match from_str(source) {
@@ -18,6 +32,9 @@ fn test_parse_value() {
     assert_eq!(Value::Int(12), parse("12").unwrap());
     assert_eq!(Value::Int(-1), parse("-1").unwrap());
     assert_eq!(Value::Float(1.12), parse("1.12").unwrap());
+    assert_eq!(Value::Float(1.0), parse("1.").unwrap());
+    assert_eq!(Value::Float(-1.0), parse("-1.").unwrap());
+    assert_eq!(Value::Float(1.0), parse("[1.]").unwrap()[0]);
     assert_eq!(
         Value::String("test".to_string()),
         parse(r#""test""#).unwrap()
@@ -109,6 +126,10 @@ fn test_parse_value() {
     assert_eq!(Value::Int(282), parse(r#"0432"#).unwrap());
     assert_eq!(Value::Int(26), parse(r#"0x1A"#).unwrap());
     assert_eq!(Value::Int(3), parse(r#"0b11"#).unwrap());
+    assert_eq!(Value::Int(15), parse(r#"0o17"#).unwrap());
+    assert_eq!(Value::Int(-15), parse(r#"-0O1_7"#).unwrap());
+    assert!(parse(r#"0o18"#).is_err());
+    assert!(parse(r#"0o"#).is_err());
     assert_eq!(Value::Int(12345), parse(r#"12_34_5"#).unwrap());
 
     assert_eq!(Value::Bool(true), parse(r#"True"#).unwrap());
@@ -159,3 +180,1734 @@ fn test_parse_value() {
 fn test_trailing_semi() {
     assert_eq!(Value::Int(12), parse(r#"12;"#).unwrap());
 }
+
+#[test]
+fn test_lenient_closure() {
+    let source = r#"[
+        'default' => 'file',
+        'resolver' => fn ($app) => $app->make('cache'),
+        'factory' => function () {
+            return new Cache(['driver' => 'file']);
+        },
+        'after' => true,
+    ]"#;
+    assert!(parse(source).is_err());
+
+    let options = ParserOptions::new().lenient(true);
+    let value: Value = from_str_with_options(source, &options).unwrap();
+    assert_eq!(value["default"], "file");
+    assert_eq!(
+        value["resolver"],
+        Value::Expr("fn ($app) => $app->make('cache')".into())
+    );
+    assert!(value["factory"].is_expr());
+    assert_eq!(value["after"], true);
+}
+
+#[test]
+fn test_lenient_expressions() {
+    let source = r#"[
+        'home' => $root . '/home',
+        'data' => getenv('DATA_DIR') ?: '/data',
+        'ttl' => 60 * 60,
+        'level' => LOG_LEVEL,
+        'nested' => ['a' => $a, 'b' => 2, $c],
+        'cast' => (int) '12',
+        'object' => (object) ['x' => 1],
+        'concat' => 'a' . 'b',
+        'union' => [1] + $defaults,
+        'after' => true,
+    ]"#;
+    assert!(parse(source).is_err());
+
+    let options = ParserOptions::new().lenient(true);
+    let value: Value = from_str_with_options(source, &options).unwrap();
+    assert_eq!(value["home"], Value::Expr("$root . '/home'".into()));
+    assert_eq!(
+        value["data"],
+        Value::Expr("getenv('DATA_DIR') ?: '/data'".into())
+    );
+    assert_eq!(value["ttl"], Value::Expr("60 * 60".into()));
+    assert_eq!(value["level"], Value::Expr("LOG_LEVEL".into()));
+    assert_eq!(value["nested"]["a"], Value::Expr("$a".into()));
+    assert_eq!(value["nested"]["b"], 2);
+    assert_eq!(value["nested"][0], Value::Expr("$c".into()));
+    assert_eq!(value["cast"], Value::Expr("(int) '12'".into()));
+    assert!(value["object"].is_object());
+    assert_eq!(value["concat"], "ab");
+    assert_eq!(value["union"], Value::Expr("[1] + $defaults".into()));
+    assert_eq!(value["after"], true);
+
+    assert_eq!(
+        from_str_with_options::<Value>("$CONFIG;", &options).unwrap(),
+        Value::Expr("$CONFIG".into())
+    );
+    assert!(from_str_with_options::<Value>("[$a => 1]", &options).is_err());
+}
+
+#[test]
+fn test_capture_objects() {
+    let source = r#"[
+        'timezone' => new DateTimeZone('UTC'),
+        'redis' => new \Redis(['host' => '127.0.0.1', 'port' => 6379], new Options),
+    ]"#;
+    assert!(parse(source).is_err());
+
+    let options = ParserOptions::new().capture_objects(true);
+    let value: Value = from_str_with_options(source, &options).unwrap();
+    assert_eq!(
+        value["timezone"],
+        Value::Instance(Instance {
+            class: "DateTimeZone".into(),
+            args: vec!["UTC".into()],
+        })
+    );
+    let redis = value["redis"].as_instance().unwrap();
+    assert_eq!(redis.class, "\\Redis");
+    assert_eq!(redis.args[0]["port"], 6379);
+    assert_eq!(
+        redis.args[1],
+        Value::Instance(Instance {
+            class: "Options".into(),
+            args: vec![],
+        })
+    );
+
+    let instances: Vec<Instance> =
+        from_str_with_options("[new DateTimeZone('UTC'), new \\Redis]", &options).unwrap();
+    assert_eq!(instances[0].class, "DateTimeZone");
+    assert!(instances[1].args.is_empty());
+    assert!(from_str_with_options::<Instance>("'UTC'", &options).is_err());
+
+    let options = ParserOptions::new().lenient(true);
+    let value: Value = from_str_with_options(source, &options).unwrap();
+    assert_eq!(
+        value["timezone"],
+        Value::Expr("new DateTimeZone('UTC')".into())
+    );
+}
+
+#[test]
+fn test_get_ignore_case() {
+    let value = parse("['dbhost' => 'a', 'DBHost' => 'b', 1 => 'c']").unwrap();
+    assert_eq!(value.get_ignore_case("DBHost"), Some(&Value::from("b")));
+    assert_eq!(value.get_ignore_case("dbhost"), Some(&Value::from("a")));
+    assert_eq!(value.get_ignore_case("1"), Some(&Value::from("c")));
+    assert_eq!(value.get_ignore_case("dbname"), None);
+    // the first key in source order with `preserve_order`, otherwise the smallest key
+    let expected = if cfg!(feature = "preserve_order") {
+        "a"
+    } else {
+        "b"
+    };
+    for _ in 0..10 {
+        let value = parse("['dbhost' => 'a', 'DBHost' => 'b']").unwrap();
+        assert_eq!(
+            value.get_ignore_case("DBHOST"),
+            Some(&Value::from(expected))
+        );
+    }
+}
+
+#[test]
+fn test_function_handlers() {
+    let options = ParserOptions::new()
+        .function("strrev", |args| match args {
+            [Value::String(str)] => Ok(str.chars().rev().collect::<String>().into()),
+            _ => Err("strrev expects a single string".into()),
+        })
+        .function("range", |args| match args {
+            [Value::Int(start), Value::Int(end)] => Ok(Value::Array(
+                (*start..=*end)
+                    .enumerate()
+                    .map(|(index, int)| (Key::Int(index as i64), Value::Int(int)))
+                    .collect(),
+            )),
+            _ => Err("range expects two integers".into()),
+        });
+
+    let source = r#"["name" => STRREV(\strrev("foo")), "ports" => range(1, 3), "strrev('x')"]"#;
+    let value: Value = from_str_with_options(source, &options).unwrap();
+    assert_eq!(value["name"], "foo");
+    assert_eq!(value["ports"][2], 3);
+    assert_eq!(value[0], "strrev('x')");
+
+    let ports: Vec<u16> = from_str_with_options("range(8080, 8082)", &options).unwrap();
+    assert_eq!(ports, vec![8080, 8081, 8082]);
+
+    assert!(matches!(
+        from_str_with_options::<Value>("strrev(1)", &options).unwrap_err(),
+        ParseError::FunctionCall(_)
+    ));
+    assert!(from_str_with_options::<Value>("base64_decode('Zm9v')", &options).is_err());
+}
+
+#[test]
+fn test_function_evaluator() {
+    let options = ParserOptions::new()
+        .function("strrev", |_| Ok("handler".into()))
+        .function_evaluator(|name: &str, args: &[Value]| match (name, args) {
+            ("getenv", [Value::String(name)]) => Some(Ok(format!("env:{}", name).into())),
+            ("strrev", _) => Some(Ok("evaluator".into())),
+            ("fail", _) => Some(Err("failed".into())),
+            _ => None,
+        });
+
+    let value: Value = from_str_with_options(
+        r#"["host" => \GETENV('DB_HOST'), "rev" => strrev('a'), "nested" => [getenv("A")]]"#,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(value["host"], "env:DB_HOST");
+    assert_eq!(value["rev"], "handler");
+    assert_eq!(value["nested"][0], "env:A");
+
+    assert!(matches!(
+        from_str_with_options::<Value>("[fail()]", &options).unwrap_err(),
+        ParseError::FunctionCall(_)
+    ));
+    assert!(matches!(
+        from_str_with_options::<Value>("[exec('ls')]", &options).unwrap_err(),
+        ParseError::FunctionCall(_)
+    ));
+    assert!(matches!(
+        from_str_with_options::<Value>("[UNDEFINED]", &options).unwrap_err(),
+        ParseError::Expression(_)
+    ));
+}
+
+#[test]
+fn test_function_handlers_with_field_errors() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        host: String,
+        port: u16,
+        debug: bool,
+        timeout: u32,
+        user: String,
+    }
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let options = ParserOptions::new().function_evaluator({
+        let calls = calls.clone();
+        move |name: &str, args: &[Value]| {
+            calls.lock().unwrap().push(name.to_string());
+            Some(Ok(args[0].clone()))
+        }
+    });
+
+    // every failing field retries the deserialization, the handlers are still called once per call
+    let source = r#"[
+        "host" => getenv("localhost"),
+        "port" => "http",
+        "debug" => "yes",
+        "timeout" => -1,
+        "user" => getenv(getenv("root")),
+    ]"#;
+    let err = from_str_with_options::<Config>(source, &options).unwrap_err();
+    let errors = match err {
+        ParseError::Fields(errors) => errors,
+        err => panic!("expected field errors, got {:?}", err),
+    };
+    assert_eq!(errors.errors().len(), 3);
+    assert_eq!(*calls.lock().unwrap(), vec!["getenv"; 3]);
+}
+
+#[test]
+fn test_serialize_json() {
+    let options = ParserOptions::new().capture_objects(true);
+    let value = from_str_with_options::<Value>(
+        r#"["name" => "foo", "ports" => [80], "cache" => new Redis("localhost"), "none" => null]"#,
+        &options,
+    )
+    .unwrap();
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "name": "foo",
+            "ports": [80],
+            "cache": {"class": "Redis", "args": ["localhost"]},
+            "none": null,
+        })
+    );
+}
+
+#[test]
+fn test_heredoc() {
+    let source = r#"[
+    'motd' => <<<EOT
+        Welcome to "$host"\t!
+          indented
+        EOT,
+    'raw' => <<<'EOT'
+    no \t escapes
+    EOT
+]"#;
+    let value = parse(source).unwrap();
+    assert_eq!(value["motd"], "Welcome to \"$host\"\t!\n  indented");
+    assert_eq!(value["raw"], r"no \t escapes");
+    assert_eq!(from_str::<String>("<<<EOT\nfoo\nEOT").unwrap(), "foo");
+    assert!(parse("<<<EOT\n  a\n    EOT").is_err());
+}
+
+#[test]
+fn test_concatenation() {
+    let source = r#"[
+    'path' => '/var/www' . '/data',
+    'name' => "a" . /* comment */ 'b' . "\tc",
+    'empty' => '' . 'x',
+]"#;
+    let value = parse(source).unwrap();
+    assert_eq!(value["path"], "/var/www/data");
+    assert_eq!(value["name"], "ab\tc");
+    assert_eq!(value["empty"], "x");
+    assert_eq!(from_str::<String>("'a'.'b'").unwrap(), "ab");
+    assert_eq!(parse("'a' . 1 . 1.5 . true").unwrap(), "a11.51");
+    assert!(parse("'a' . []").is_err());
+    assert!(parse("'a' .").is_err());
+}
+
+#[test]
+fn test_spread() {
+    let value = parse("[...[1, 2], 3, ...['a' => 1, 5 => 4], 'a' => 2, 10 => 5, ...[6]]").unwrap();
+    assert_eq!(value[0], 1);
+    assert_eq!(value[1], 2);
+    assert_eq!(value[2], 3);
+    assert_eq!(value[3], 4);
+    assert_eq!(value["a"], 2);
+    assert_eq!(value[10], 5);
+    assert_eq!(value[11], 6);
+    assert_eq!(value.keys().count(), 7);
+
+    assert_eq!(
+        from_str::<Vec<i32>>("[...[1, 2], ...array(3), 4]").unwrap(),
+        vec![1, 2, 3, 4]
+    );
+    assert!(from_str::<Vec<i32>>("[...['a' => 1]]").is_err());
+    assert!(parse("[...'a']").is_err());
+    assert!(parse("[...]").is_err());
+}
+
+#[test]
+fn test_interpolation() {
+    let source = r#"["https://$host:$port/", "{$prefix}_users", '$host', "\$host", <<<EOT
+    debug: $debug
+    EOT]"#;
+    assert_eq!(parse(source).unwrap()[0], "https://$host:$port/");
+
+    let options = ParserOptions::new()
+        .variable("host", "example.com")
+        .variable("port", 8080)
+        .variable("prefix", "oc")
+        .variable("debug", true);
+    let value: Value = from_str_with_options(source, &options).unwrap();
+    assert_eq!(value[0], "https://example.com:8080/");
+    assert_eq!(value[1], "oc_users");
+    assert_eq!(value[2], "$host");
+    assert_eq!(value[3], "$host");
+    assert_eq!(value[4], "debug: 1");
+
+    assert!(matches!(
+        from_str_with_options::<Value>(r#""$missing""#, &options),
+        Err(ParseError::Expression(_))
+    ));
+    let options = options.variable("list", Value::Array(Default::default()));
+    assert!(from_str_with_options::<Value>(r#""$list""#, &options).is_err());
+}
+
+#[test]
+fn test_array_union() {
+    let value = parse("['a' => 1] + ['a' => 2, 'b' => 3]").unwrap();
+    assert_eq!(value, parse("['a' => 1, 'b' => 3]").unwrap());
+    assert_eq!(
+        parse("[1, 2] + array(5, 6, 7) + [3 => ['x']]").unwrap(),
+        parse("[1, 2, 7, ['x']]").unwrap()
+    );
+    assert_eq!(
+        parse("['nested' => ['a'] + ['b', 'c'], 'after' => true]").unwrap(),
+        parse("['nested' => ['a', 'c'], 'after' => true]").unwrap()
+    );
+    assert!(parse("[1] + 1").is_err());
+    assert!(parse("1 + 1").is_err());
+    assert!(parse("[1] +").is_err());
+}
+
+#[test]
+fn test_byte_order_mark() {
+    assert_eq!(
+        parse("\u{FEFF}['a' => 1]").unwrap(),
+        parse("['a' => 1]").unwrap()
+    );
+    let options = ParserOptions::new().php_file(true);
+    assert_eq!(
+        from_str_with_options::<Value>("\u{FEFF}<?php return 1;", &options).unwrap(),
+        Value::Int(1)
+    );
+}
+
+#[test]
+fn test_from_slice() {
+    let value =
+        from_slice::<Value>(b"\xEF\xBB\xBF['\xC3\xA9' => '\xFF', 'b' => <<<'EOT'\n\xFE\nEOT]")
+            .unwrap();
+    assert_eq!(value["é"], Value::Bytes(vec![0xFF]));
+    assert_eq!(value["b"], Value::Bytes(vec![0xFE]));
+    assert!(from_slice::<Value>(b"[\xFF]").is_err());
+
+    let strings = from_slice::<Vec<String>>(b"['\xFF', 'a']").unwrap();
+    assert_eq!(strings, vec!["\u{FFFD}".to_string(), "a".to_string()]);
+
+    // valid characters are kept as is, even next to invalid bytes
+    let mut input = "['\u{10FFBF}' => '\u{10FFBF}', 'b' => ".as_bytes().to_vec();
+    input.extend_from_slice(b"\"\\x41\xFE\"]");
+    let value = from_slice::<Value>(&input).unwrap();
+    assert_eq!(value["\u{10FFBF}"], "\u{10FFBF}");
+    assert_eq!(value["b"], Value::Bytes(vec![0x41, 0xFE]));
+
+    // error spans are byte offsets in the input
+    let err = from_slice::<Value>(b"['\xFF\xFE\xFD' => 1, 2 3]").unwrap_err();
+    assert_eq!(Some(15..16), err.span().map(|span| span.range()));
+}
+
+#[test]
+fn test_bytes() {
+    assert_eq!(parse(r#""\xFF\x00""#).unwrap(), Value::Bytes(vec![0xFF, 0]));
+    assert_eq!(parse(r#""\xC3\xA9""#).unwrap(), Value::from("é"));
+    assert_eq!(parse(r#""\351""#).unwrap(), Value::Bytes(vec![0xE9]));
+    assert_eq!(
+        parse(r#""a" . "\xFF""#).unwrap(),
+        Value::Bytes(vec![b'a', 0xFF])
+    );
+    assert_eq!(parse(r#""\xC3" . "\xA9""#).unwrap(), Value::from("é"));
+
+    let value = parse(r#"["key" => "\x80abc"]"#).unwrap();
+    assert!(value["key"].is_bytes());
+    assert_eq!(value["key"].as_bytes(), Some(&b"\x80abc"[..]));
+    assert_eq!(value["key"].php_type(), "string");
+}
+
+#[test]
+fn test_php_file() {
+    let options = ParserOptions::new().php_file(true);
+    let expected = parse("['a' => 1]").unwrap();
+    for source in [
+        "<?php return ['a' => 1];",
+        "<?PHP\n\ndeclare(strict_types=1);\n// config\nreturn array('a' => 1);\n",
+        "<?php\ndeclare(strict_types=1);\ndeclare(ticks=1);\nRETURN ['a' => 1]",
+        "['a' => 1];",
+        "<?php return ['a' => 1]; ?>\n",
+        "<?php\nreturn ['a' => 1]\n?>",
+    ] {
+        assert_eq!(
+            from_str_with_options::<Value>(source, &options).unwrap(),
+            expected
+        );
+    }
+
+    assert!(parse("<?php return ['a' => 1];").is_err());
+    assert!(parse("['a' => 1]; ?>").is_err());
+    assert!(
+        from_str_with_options::<Value>("<?php return ['a' => 1]; ?> <html>", &options).is_err()
+    );
+    assert!(
+        from_str_with_options::<Value>("<?php return ['a' => 1]; return 1;", &options).is_err()
+    );
+}
+
+#[test]
+fn test_from_str_prefix() {
+    let input = "[1, 1 << 2] /* end */ some text";
+    let (value, length) = from_str_prefix::<Vec<i64>>(input).unwrap();
+    assert_eq!(value, vec![1, 4]);
+    assert_eq!(length, 11);
+
+    let (value, length) = from_str_prefix::<String>("'a' . 'b'").unwrap();
+    assert_eq!(value, "ab");
+    assert_eq!(length, 9);
+
+    let (value, length) = from_str_prefix::<Value>("true; false").unwrap();
+    assert_eq!(value, Value::Bool(true));
+    assert_eq!(length, 4);
+
+    assert!(from_str_prefix::<Value>("[1, 2").is_err());
+}
+
+#[test]
+fn test_from_reader() {
+    let reader = std::io::Cursor::new(b"<?php return ['a' => '\xFF', 'b' => [1]];".to_vec());
+    let options = ParserOptions::new().php_file(true);
+    let value = from_reader_with_options::<_, Value>(reader, &options).unwrap();
+    assert_eq!(value["a"], Value::Bytes(vec![0xFF]));
+    assert_eq!(value["b"][0], 1);
+
+    struct FailingReader;
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "broken"))
+        }
+    }
+
+    let err = from_reader::<_, Value>(FailingReader).unwrap_err();
+    assert!(matches!(err, ParseError::Io(_)));
+    assert_eq!(err.to_string(), "Failed to read the input: broken");
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "current_thread")]
+async fn test_from_async_read_to_end() {
+    let input: &[u8] = b"<?php return ['a' => '\xFF', 'b' => [1]];";
+    let reader = tokio::io::BufReader::with_capacity(4, input);
+    let options = ParserOptions::new().php_file(true);
+    let value =
+        php_literal_parser::from_async_read_to_end_with_options::<_, Value>(reader, &options)
+            .await
+            .unwrap();
+    assert_eq!(value["a"], Value::Bytes(vec![0xFF]));
+    assert_eq!(value["b"][0], 1);
+
+    let input: &[u8] = b"[1, 2";
+    assert!(
+        php_literal_parser::from_async_read_to_end::<_, Value>(input)
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn test_nesting_limit() {
+    fn nested(open: &str, value: &str, close: &str, depth: usize) -> String {
+        format!("{}{}{}", open.repeat(depth), value, close.repeat(depth))
+    }
+
+    let value = parse(&nested("[", "1", "]", 128)).unwrap();
+    assert!(value.is_array());
+    assert!(matches!(
+        parse(&nested("[", "1", "]", 129)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        parse(&nested("array(", "1", ")", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        parse(&nested("(", "1", ")", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        parse(&nested("~", "1", "", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        from_str::<Vec<Value>>(&nested("[", "", "]", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        SyntaxTree::parse(&nested("[", "", "]", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        expr_from_str(&nested("[(", "1", ")]", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        value_from_str(&nested("[", "1", "]", 10_000)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        value_from_str(&nested("[", "[(1)]", "]", 127)),
+        Err(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+    assert!(matches!(
+        events_from_str_with_options(&nested("[", "1", "]", 129), &ParserOptions::default())
+            .find_map(Result::err),
+        Some(ParseError::NestingLimit(err)) if err.limit == 128
+    ));
+
+    // nested arrays are parsed with an explicit stack
+    let options = ParserOptions::new().max_depth(2_000);
+    let value = value_from_str_with_options(&nested("array(", "1", ")", 2_000), &options).unwrap();
+    let mut depth = 0;
+    let mut current = &value;
+    while current.is_array() {
+        depth += 1;
+        current = &current[0];
+    }
+    assert_eq!(depth, 2_000);
+    assert_eq!(current, &Value::Int(1));
+
+    let options = ParserOptions::new().max_depth(2);
+    assert!(from_str_with_options::<Value>("[[1], [2]]", &options).is_ok());
+    assert!(from_str_with_options::<Value>("[[[1]]]", &options).is_err());
+    assert!(from_str_with_options::<Value>("[(1)]", &options).is_ok());
+    assert!(from_str_with_options::<Value>("[[(1)]]", &options).is_err());
+
+    // the error points to the opening bracket that exceeds the limit
+    let source = "[\n  [\n    array(1),\n  ],\n]";
+    for err in [
+        from_str_with_options::<Value>(source, &options).unwrap_err(),
+        SyntaxTree::parse_with_options(source, &options).unwrap_err(),
+    ] {
+        let err = match err {
+            ParseError::NestingLimit(err) => err,
+            err => panic!("unexpected error {}", err),
+        };
+        assert_eq!((2, 3), (err.limit, err.depth));
+        assert_eq!((3, 5), (err.line(), err.column()));
+    }
+    let err = expr_from_str_with_options("1 + (2 + (3))", &options).unwrap_err();
+    assert_eq!(Some(10), err.offset());
+}
+
+#[test]
+fn test_size_limits() {
+    let options = ParserOptions::new().max_elements(4);
+    assert!(from_str_with_options::<Value>("[1, [2, 3]]", &options).is_ok());
+    assert!(matches!(
+        from_str_with_options::<Value>("[1, [2, 3], 4]", &options),
+        Err(ParseError::ElementLimit(4))
+    ));
+    assert!(matches!(
+        from_str_with_options::<Vec<Vec<i64>>>("[[1, 2], [3, 4]]", &options),
+        Err(ParseError::ElementLimit(4))
+    ));
+    assert!(matches!(
+        from_str_with_options::<Value>("[...[1, 2], ...[3]]", &options),
+        Err(ParseError::ElementLimit(4))
+    ));
+
+    let options = ParserOptions::new().max_array_entries(2);
+    assert!(from_str_with_options::<Value>("[[1, 2], ['a' => 3]]", &options).is_ok());
+    assert!(matches!(
+        from_str_with_options::<Value>("['a' => 1, 'b' => 2, 'c' => 3]", &options),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+    assert!(matches!(
+        from_str_with_options::<Vec<i64>>("[1, 2, 3]", &options),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+    assert!(matches!(
+        from_str_with_options::<Value>("[1, ...[2, 3]]", &options),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+    assert!(matches!(
+        events_from_str_with_options("[[1, 2, 3]]", &options).collect::<Result<Vec<_>, _>>(),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+    assert!(matches!(
+        entries_from_str_with_options("[1, 2, 3]", &options).collect::<Result<Vec<_>, _>>(),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+
+    let options = ParserOptions::new()
+        .max_string_length(3)
+        .variable("name", "foo");
+    assert_eq!(
+        from_str_with_options::<String>("'foo'", &options).unwrap(),
+        "foo"
+    );
+    assert!(matches!(
+        from_str_with_options::<String>("'\\x66oo!'", &options),
+        Err(ParseError::StringLengthLimit(3))
+    ));
+    assert!(matches!(
+        from_str_with_options::<Value>(r#"["$name$name"]"#, &options),
+        Err(ParseError::StringLengthLimit(3))
+    ));
+    assert!(matches!(
+        from_str_with_options::<Value>("['a' . 'bc' . 'd']", &options),
+        Err(ParseError::StringLengthLimit(3))
+    ));
+}
+
+#[test]
+fn test_duplicate_keys() {
+    fn duplicates(source: &str, options: ParserOptions) -> Vec<(Key, &str, &str)> {
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let collected = found.clone();
+        let options = options
+            .on_duplicate_key(move |duplicate| collected.lock().unwrap().push(duplicate.clone()));
+        let _ = from_str_with_options::<Value>(source, &options);
+        let found = found.lock().unwrap();
+        found
+            .iter()
+            .map(|duplicate| {
+                (
+                    duplicate.key.clone(),
+                    &source[duplicate.first.start()..duplicate.first.end()],
+                    &source[duplicate.duplicate.start()..duplicate.duplicate.end()],
+                )
+            })
+            .collect()
+    }
+
+    assert_eq!(
+        duplicates("['a' => 1, 'b' => 2, \"a\" => 3]", ParserOptions::new()),
+        vec![(Key::String("a".into()), "'a'", "\"a\"")]
+    );
+    assert_eq!(
+        duplicates(
+            "[1 => 'a', 0 => 'b', 'c', '1' => 'd']",
+            ParserOptions::new()
+        ),
+        vec![(Key::Int(1), "1", "'1'")]
+    );
+    assert_eq!(
+        duplicates("['a' => 1, ...['a' => 2]]", ParserOptions::new()),
+        vec![(Key::String("a".into()), "'a'", "...")]
+    );
+    assert_eq!(
+        duplicates(
+            "['a' => ['a' => 1], 'b' => ['a' => 2]]",
+            ParserOptions::new()
+        ),
+        vec![]
+    );
+    assert_eq!(
+        duplicates(
+            "['Key' => 1, 'KEY' => 2]",
+            ParserOptions::new().lowercase_keys(true)
+        ),
+        vec![(Key::String("key".into()), "'Key'", "'KEY'")]
+    );
+
+    // entries are parsed again after a field fails, duplicates are only reported once
+    let found = Arc::new(Mutex::new(Vec::new()));
+    let collected = found.clone();
+    let options = ParserOptions::new()
+        .on_duplicate_key(move |duplicate| collected.lock().unwrap().push(duplicate.clone()));
+    assert!(from_str_with_options::<HashMap<String, i64>>(
+        "['a' => 1, 'a' => 2, 'b' => 'x', 'c' => 'y', 'c' => 3]",
+        &options
+    )
+    .is_err());
+    let keys: Vec<Key> = found
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|d| d.key.clone())
+        .collect();
+    assert_eq!(keys, vec![Key::String("a".into()), Key::String("c".into())]);
+
+    found.lock().unwrap().clear();
+    events_from_str_with_options("[['x' => 1, 'x' => 2]]", &options).for_each(drop);
+    assert_eq!(found.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_numeric_string_keys() {
+    let value =
+        parse(r#"["1" => 1, "-1" => 2, "08" => 3, "+1" => 4, "1.0" => 5, " 1" => 6, "-0" => 7]"#)
+            .unwrap();
+    assert_eq!(
+        value.keys().cloned().collect::<HashSet<_>>(),
+        HashSet::from([
+            Key::Int(1),
+            Key::Int(-1),
+            Key::String("08".into()),
+            Key::String("+1".into()),
+            Key::String("1.0".into()),
+            Key::String(" 1".into()),
+            Key::String("-0".into()),
+        ])
+    );
+    assert_eq!(parse(r#"["1" => 1, 1 => 2]"#).unwrap().keys().count(), 1);
+
+    let value = parse("['-9223372036854775808' => 1, '9223372036854775808' => 2]").unwrap();
+    assert_eq!(value[i64::MIN], 1);
+    assert_eq!(value["9223372036854775808"], 2);
+    let value = value_from_str("['-9223372036854775808' => 1]").unwrap();
+    assert_eq!(value[i64::MIN], 1);
+
+    let options = ParserOptions::new().keep_numeric_string_keys(true);
+    let value =
+        from_str_with_options::<Value>(r#"["1" => 1, 1 => 2, 2.5 => 3]"#, &options).unwrap();
+    assert_eq!(
+        value.keys().cloned().collect::<HashSet<_>>(),
+        HashSet::from([Key::String("1".into()), Key::Int(1), Key::Int(2)])
+    );
+    let map =
+        from_str_with_options::<HashMap<String, i64>>(r#"["1" => 1, "a" => 2]"#, &options).unwrap();
+    assert_eq!(map["1"], 1);
+}
+
+#[test]
+fn test_overflowing_numeric_string_keys() {
+    let value = parse(
+        r#"["99999999999999999999" => 1, "9223372036854775807" => 2, "-9223372036854775809" => 3]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        value.keys().cloned().collect::<HashSet<_>>(),
+        HashSet::from([
+            Key::String("99999999999999999999".into()),
+            Key::Int(i64::MAX),
+            Key::String("-9223372036854775809".into()),
+        ])
+    );
+    assert_eq!(value["99999999999999999999"], 1);
+
+    let value: Value = serde_json::from_str(r#"{"99999999999999999999": 1}"#).unwrap();
+    assert_eq!(
+        value.keys().cloned().collect::<Vec<_>>(),
+        vec![Key::String("99999999999999999999".into())]
+    );
+}
+
+#[test]
+fn test_next_element_occupied() {
+    fn is_occupied(result: Result<impl std::fmt::Debug, ParseError>) -> bool {
+        matches!(
+            result,
+            Err(ParseError::UnexpectedArrayKey(error))
+                if format!("{:?}", error).contains("NextElementOccupied")
+        )
+    }
+
+    // php can't pick a key for an entry after `PHP_INT_MAX`, it doesn't wrap or overwrite the last entry
+    let source = "[9223372036854775807 => 1, 2]";
+    assert!(is_occupied(from_str::<Value>(source)));
+    assert!(is_occupied(from_str::<HashMap<i64, i64>>(source)));
+    assert!(is_occupied(from_str::<Value>(
+        "[9223372036854775807 => 1, ...[2]]"
+    )));
+    assert!(is_occupied(from_str::<Value>(
+        "['a' => [1, 2], 'b' => [9223372036854775807 => 1, 2]]"
+    )));
+    assert!(is_occupied(SyntaxTree::parse(source)));
+    assert!(is_occupied(
+        entries_from_str_with_options(source, &ParserOptions::default())
+            .collect::<Result<Vec<_>, _>>()
+    ));
+    assert!(is_occupied(
+        events_from_str_with_options(source, &ParserOptions::default())
+            .collect::<Result<Vec<_>, _>>()
+    ));
+    assert!(matches!(
+        validate(source).unwrap_err()[..],
+        [ParseError::UnexpectedArrayKey(_)]
+    ));
+    assert_eq!(
+        expr_from_str(source)
+            .unwrap()
+            .evaluate(&ParserOptions::default()),
+        Err("Cannot add element to the array as the next element is already occupied".into())
+    );
+
+    let value = from_str::<Value>("[9223372036854775807 => 1, 'a' => 2]").unwrap();
+    assert_eq!(value[i64::MAX], 1);
+    assert_eq!(value["a"], 2);
+}
+
+#[test]
+fn test_integer_overflow_to_float() {
+    assert_eq!(parse("9223372036854775807").unwrap(), Value::Int(i64::MAX));
+    assert_eq!(
+        parse("9223372036854775808").unwrap(),
+        Value::Float(9223372036854775808.0)
+    );
+    assert_eq!(
+        parse("-9223372036854775809").unwrap(),
+        Value::Float(-9223372036854775809.0)
+    );
+    assert_eq!(
+        parse("99_999_999_999_999_999_999").unwrap(),
+        Value::Float(1e20)
+    );
+    assert_eq!(
+        parse("0x10000000000000000").unwrap(),
+        Value::Float(18446744073709551616.0)
+    );
+    assert_eq!(
+        parse("[1, 99999999999999999999]").unwrap()[1],
+        Value::Float(1e20)
+    );
+
+    // integer targets can't hold the value
+    assert!(matches!(
+        from_str::<i64>("9223372036854775808"),
+        Err(ParseError::InvalidPrimitive(_))
+    ));
+}
+
+#[test]
+fn test_options_for_every_parser() {
+    let options = ParserOptions::new()
+        .constant("LEVEL", 3)
+        .max_array_entries(2);
+
+    let tree =
+        SyntaxTree::parse_with_options("['level' => LEVEL, 'debug' => false]", &options).unwrap();
+    assert_eq!(tree.to_value()["level"], 3);
+    assert!(SyntaxTree::parse("['level' => LEVEL]").unwrap().to_value()["level"].is_expr());
+    assert!(matches!(
+        SyntaxTree::parse_with_options("[1, 2, 3]", &options),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+
+    assert!(expr_from_str_with_options("[LEVEL, $foo]", &options).is_ok());
+    assert!(matches!(
+        expr_from_str_with_options("[1, 2, 3]", &options),
+        Err(ParseError::ArrayEntryLimit(2))
+    ));
+    let options = ParserOptions::new().max_string_length(2);
+    assert!(matches!(
+        expr_from_str_with_options(r#"["foo$bar"]"#, &options),
+        Err(ParseError::StringLengthLimit(2))
+    ));
+}
+
+#[test]
+fn test_implicit_keys() {
+    // entries without a key get one more than the largest integer key, not the last one
+    let source = "[5 => 'a', 2 => 'b', 'c', ...[-1 => 'd'], 'e']";
+    let expected = from_str::<Value>("[5 => 'a', 2 => 'b', 6 => 'c', 7 => 'd', 8 => 'e']").unwrap();
+    assert_eq!(from_str::<Value>(source).unwrap(), expected);
+    assert_eq!(SyntaxTree::parse(source).unwrap().to_value(), expected);
+
+    let entries = entries_from_str_with_options(source, &ParserOptions::default())
+        .map(|entry| entry.map(|(key, _)| key))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(entries, [5, 2, 6, 7, 8].map(Key::Int));
+
+    let keys = events_from_str_with_options(source, &ParserOptions::default())
+        .filter_map(|event| match event.unwrap().0 {
+            Event::Key(key) => Some(key),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(keys, [5, 2, 6, 7, 8].map(Key::Int));
+
+    assert_eq!(
+        from_str::<Value>("[-5 => 'a', 'b']").unwrap(),
+        from_str::<Value>("[-5 => 'a', -4 => 'b']").unwrap()
+    );
+}
+
+#[test]
+fn test_from_str_partial() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        #[serde(default)]
+        port: Option<u16>,
+        hosts: Vec<String>,
+    }
+
+    let (config, errors) = from_str_partial::<Config>(
+        "['name' => 'test', 'port' => 'eighty', 'hosts' => ['a', 1 +, 'b']]",
+    );
+    assert_eq!(
+        Some(Config {
+            name: "test".into(),
+            port: None,
+            hosts: vec!["a".into(), "b".into()],
+        }),
+        config
+    );
+    let paths: Vec<_> = errors.iter().map(|error| error.path()).collect();
+    assert_eq!(vec!["port", "hosts.1"], paths);
+
+    // leaving out a required field fails the whole value, without reporting the missing field separately
+    let (config, errors) = from_str_partial::<Config>("['name' => FOO, 'hosts' => []]");
+    assert_eq!(None, config);
+    assert_eq!(
+        vec!["name"],
+        errors.iter().map(|error| error.path()).collect::<Vec<_>>()
+    );
+
+    // errors outside of array entries can't be recovered from
+    let (value, errors) = from_str_partial::<Value>("[1, 2");
+    assert_eq!(None, value);
+    assert_eq!(1, errors.len());
+
+    let (value, errors) = from_str_partial::<Value>("[1, 2, 3]");
+    assert_eq!(Some(Value::Int(3)), value.map(|value| value[2].clone()));
+    assert!(errors.is_empty());
+
+    let options = ParserOptions::new().expand_dotted_keys(true);
+    let (value, errors) =
+        from_str_partial_with_options::<Value>("['a.b' => 1, 'a.c' => FOO]", &options);
+    assert_eq!(
+        Some(1),
+        value.as_ref().and_then(|value| value["a"]["b"].as_int())
+    );
+    assert_eq!(1, errors.len());
+}
+
+#[test]
+fn test_max_errors() {
+    let source = format!("[{}]", vec!["1 +"; 150].join(", "));
+    let errors = match from_str::<Vec<i64>>(&source).unwrap_err() {
+        ParseError::Fields(errors) => errors,
+        err => panic!("expected field errors, got {:?}", err),
+    };
+    assert_eq!(100, errors.errors().len());
+    assert_eq!("99", errors.errors()[99].path());
+
+    let source = "['a' => 1 +, 'b' => [FOO, 2], 'c' => 'foo' . , 'd' => true]";
+    let options = ParserOptions::new().max_errors(2);
+    let errors = match from_str_with_options::<Value>(source, &options).unwrap_err() {
+        ParseError::Fields(errors) => errors,
+        err => panic!("expected field errors, got {:?}", err),
+    };
+    let paths: Vec<_> = errors.errors().iter().map(|error| error.path()).collect();
+    assert_eq!(vec!["a", "b.0"], paths);
+
+    let options = ParserOptions::new().max_errors(1);
+    assert!(matches!(
+        from_str_with_options::<Value>(source, &options),
+        Err(ParseError::UnexpectedToken(_))
+    ));
+
+    let (value, errors) = from_str_partial_with_options::<Value>(source, &options);
+    assert_eq!(None, value);
+    assert_eq!(1, errors.len());
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_diagnostic() {
+    use miette::Diagnostic;
+
+    let err = from_str::<Value>("[1, 2 3]").unwrap_err();
+    assert_eq!(
+        "php_literal_parser::unexpected_token",
+        err.code().unwrap().to_string()
+    );
+    let labels: Vec<_> = err.labels().unwrap().collect();
+    assert_eq!(1, labels.len());
+    assert_eq!(6, labels[0].offset());
+    assert_eq!(1, labels[0].len());
+    assert!(err.source_code().is_none());
+    assert!(err.with_source("[1, 2 3]").source_code().is_some());
+
+    let err =
+        from_str_with_options::<Value>("[[1]]", &ParserOptions::new().max_depth(1)).unwrap_err();
+    assert_eq!(
+        "the limit can be changed with `ParserOptions::max_depth`",
+        err.help().unwrap().to_string()
+    );
+
+    let source = "['a' => 'foo', 'b' => 256]";
+    let err = from_str::<HashMap<String, u8>>(source).unwrap_err();
+    let related: Vec<_> = err.related().unwrap().collect();
+    assert_eq!(2, related.len());
+    assert!(related[1].labels().is_some());
+
+    // related errors are rendered with the source of the error containing them
+    let mut rendered = String::new();
+    miette::GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode_nocolor())
+        .render_report(&mut rendered, &err.with_source(source))
+        .unwrap();
+    assert_eq!(2, rendered.matches(source).count());
+}
+
+#[test]
+fn test_error_span() {
+    let source = "[\n    'é' => 1,\n    'b' => 2 3,\n]";
+    let err = from_str::<Value>(source).unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!(&source[span.range()], "3");
+    assert_eq!(Some(span.start()), err.offset());
+    assert_eq!(Some(3), err.line());
+    assert_eq!(Some(14), err.column());
+    match &err {
+        ParseError::UnexpectedToken(inner) => {
+            assert_eq!(span, inner.span());
+            assert_eq!(3, inner.line());
+            assert_eq!(14, inner.column());
+            assert_eq!(span.start(), inner.offset());
+        }
+        err => panic!("expected unexpected token, got {:?}", err),
+    }
+
+    let err = from_str::<HashMap<String, u8>>("['a' => 'foo',\n'é' => 256]").unwrap_err();
+    assert!(matches!(err, ParseError::Fields(_)));
+    assert_eq!(Some(1), err.line());
+    assert_eq!(Some(9), err.column());
+
+    let err = from_str_with_options::<Value>("[1, 2]", &ParserOptions::new().max_elements(1))
+        .unwrap_err();
+    assert_eq!(None, err.span());
+    assert_eq!(None, err.line());
+
+    let err =
+        from_str_with_options::<Value>("[[1]]", &ParserOptions::new().max_depth(1)).unwrap_err();
+    assert_eq!(Some(1..2), err.span().map(|span| span.range()));
+}
+
+#[test]
+fn test_error_report() {
+    let err = from_str::<Value>("[\n  1 2\n]").unwrap_err();
+    assert_eq!("PLP0001", err.error_code());
+    assert_eq!(
+        serde_json::json!({
+            "code": "PLP0001",
+            "message": err.to_string(),
+            "path": null,
+            "span": {"start": 6, "end": 7, "line": 2, "column": 5},
+            "expected": ["','", "']'"],
+            "related": [],
+        }),
+        serde_json::to_value(err.report()).unwrap()
+    );
+
+    let err = from_str::<HashMap<String, u8>>("['a' => 'foo', 'b' => 256]").unwrap_err();
+    let report = err.report();
+    assert_eq!("PLP0006", report.code);
+    let paths: Vec<_> = report
+        .related
+        .iter()
+        .map(|related| related.path.as_deref().unwrap())
+        .collect();
+    assert_eq!(vec!["a", "b"], paths);
+    assert_eq!("PLP0001", report.related[0].code);
+    assert_eq!("PLP0002", report.related[1].code);
+
+    let err = from_str_with_options::<Value>("[1, 2]", &ParserOptions::new().max_elements(1))
+        .unwrap_err();
+    let report = err.report();
+    assert_eq!("PLP0011", report.code);
+    assert_eq!(None, report.span);
+}
+
+#[cfg(feature = "fancy")]
+#[test]
+fn test_render() {
+    use php_literal_parser::RenderStyle;
+
+    let source = "['a' => 1 2]";
+    let err = from_str::<Value>(source).unwrap_err();
+
+    let colored = err.render(source, RenderStyle::Colored);
+    assert!(colored.contains('\u{1b}'));
+    assert!(colored.contains(source));
+
+    let plain = err.render(source, RenderStyle::Plain);
+    assert!(plain.is_ascii());
+    assert!(plain.contains(source));
+    assert!(plain.contains("php_literal_parser::unexpected_token"));
+
+    let source = "['a' => 'foo',\n'b' => 256]";
+    let err = from_str::<HashMap<String, u8>>(source).unwrap_err();
+    let compact = err.render(source, RenderStyle::Compact);
+    assert!(!compact.contains('\n'));
+    assert!(compact.starts_with("1:9: a: "));
+    assert!(compact.ends_with("[PLP0006]"));
+
+    let err =
+        from_str_with_options::<Value>("[[1]]", &ParserOptions::new().max_depth(1)).unwrap_err();
+    assert_eq!(
+        "1:2: Maximum nesting depth of 1 exceeded [PLP0010]",
+        err.render("[[1]]", RenderStyle::Compact)
+    );
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_source_name() {
+    use miette::{Diagnostic, GraphicalReportHandler, GraphicalTheme};
+
+    let source = "['a' => 'foo',\n'b' => 256]";
+    let err = from_str::<HashMap<String, u8>>(source).unwrap_err();
+    let named = err.with_source(source).with_name("config/config.php");
+    assert_eq!(Some("config/config.php"), named.name());
+    let contents = named
+        .source_code()
+        .unwrap()
+        .read_span(&(8..13).into(), 0, 0)
+        .unwrap();
+    assert_eq!(Some("config/config.php"), contents.name());
+
+    let mut rendered = String::new();
+    GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+        .render_report(&mut rendered, &named)
+        .unwrap();
+    assert!(rendered.contains("config/config.php:1:9"));
+    assert!(rendered.contains("config/config.php:2:8"));
+}
+
+#[test]
+fn test_serde_error_span() {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Single,
+        Cluster,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Redis {
+        host: String,
+        mode: Mode,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        redis: Redis,
+    }
+
+    let source =
+        "[\n  'redis' => [\n    'host' => 'localhost',\n    'mode' => 'sentinel',\n  ],\n]";
+    let err = from_str::<Config>(source).unwrap_err();
+    let serde = match &err {
+        ParseError::Serde(err) => err,
+        err => panic!("expected serde error, got {:?}", err),
+    };
+    assert!(serde.message.starts_with("unknown variant `sentinel`"));
+    let span = err.span().unwrap();
+    assert_eq!("'sentinel'", &source[span.range()]);
+    assert_eq!(Some(4), err.line());
+    assert_eq!(Some(15), err.column());
+
+    // missing fields point to the end of the array missing the field
+    let source = "['redis' => ['host' => 'localhost']]";
+    let err = from_str::<Config>(source).unwrap_err();
+    assert!(matches!(err, ParseError::Serde(_)));
+    assert_eq!(Some(34), err.offset());
+}
+
+#[test]
+fn test_field_paths() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Redis {
+        host: String,
+        timeouts: Vec<u32>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        redis: Redis,
+    }
+
+    let source = "['redis' => ['host' => 'localhost', 'timeouts' => [1, 2, 'three']]]";
+    assert!(matches!(
+        from_str::<Config>(source).unwrap_err(),
+        ParseError::UnexpectedToken(_)
+    ));
+
+    let options = ParserOptions::new().field_paths(true);
+    let err = from_str_with_options::<Config>(source, &options).unwrap_err();
+    let errors = match &err {
+        ParseError::Fields(errors) => errors,
+        err => panic!("expected field errors, got {:?}", err),
+    };
+    assert_eq!(1, errors.errors().len());
+    assert_eq!("redis.timeouts.2", errors.errors()[0].path());
+    assert!(matches!(
+        errors.errors()[0].error(),
+        ParseError::UnexpectedToken(_)
+    ));
+    assert!(err
+        .to_string()
+        .starts_with("1 field failed to deserialize\n  redis.timeouts.2: "));
+
+    // errors outside of any array don't have a path
+    assert!(matches!(
+        from_str_with_options::<Config>(
+            "['redis' => ['host' => 'localhost', 'timeouts' => []]] 1",
+            &options
+        )
+        .unwrap_err(),
+        ParseError::TrailingCharacters(_)
+    ));
+}
+
+#[test]
+fn test_invalid_token() {
+    fn invalid(source: &str) -> (Option<InvalidToken>, String, Span) {
+        let err = match from_str::<Value>(source).unwrap_err() {
+            ParseError::Fields(errors) => errors.errors()[0].error().clone(),
+            err => err,
+        };
+        match err {
+            ParseError::UnexpectedToken(err) => {
+                (err.invalid().cloned(), err.to_string(), err.span())
+            }
+            err => panic!("expected unexpected token, got {:?}", err),
+        }
+    }
+
+    let (token, message, span) = invalid("['a' => 'abc]");
+    assert_eq!(Some(InvalidToken::UnterminatedString), token);
+    assert!(message.starts_with("Unterminated string literal, expected one of"));
+    assert_eq!(8..13, span.range());
+
+    let (token, _, _) = invalid("[<<<EOT\nabc\n]");
+    assert_eq!(Some(InvalidToken::UnterminatedString), token);
+
+    let (token, message, span) = invalid("[1, $]");
+    assert_eq!(Some(InvalidToken::StrayDollar), token);
+    assert!(message.starts_with("'$' without variable name"));
+    assert_eq!(4..5, span.range());
+
+    let (token, message, span) = invalid("[1 @ 2]");
+    assert_eq!(Some(InvalidToken::Character('@')), token);
+    assert_eq!(
+        "Unexpected character '@', expected one of ',' or ']'",
+        message
+    );
+    assert_eq!(3..4, span.range());
+
+    let (token, message, _) = invalid("['a' => ture]");
+    assert_eq!(
+        Some(InvalidToken::UnknownIdentifier {
+            name: "ture".into(),
+            suggestion: None
+        }),
+        token
+    );
+
+    let (token, _, _) = invalid("['a' => nul]");
+    assert_eq!(
+        Some(InvalidToken::UnknownIdentifier {
+            name: "nul".into(),
+            suggestion: Some("null")
+        }),
+        token
+    );
+    assert!(message.starts_with("Unknown constant or keyword 'ture', expected one of"));
+
+    // identifiers where no value is expected aren't reported as unknown
+    let (token, message, _) = invalid("[1 PHP_EOL]");
+    assert_eq!(None, token);
+    assert!(message.starts_with("Unexpected token, found identifier"));
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_keyword_suggestion() {
+    use miette::Diagnostic;
+
+    let err = from_str::<Value>("['debug' => TRUES]").unwrap_err();
+    assert_eq!("did you mean `true`?", err.help().unwrap().to_string());
+
+    let err = from_str::<Value>("['debug' => arrray(1)]").unwrap_err();
+    assert_eq!("did you mean `array`?", err.help().unwrap().to_string());
+
+    let err = from_str::<Value>("['debug' => FOO]").unwrap_err();
+    assert!(err.help().is_none());
+}
+
+#[test]
+fn test_error_context() {
+    let source =
+        "[\n  'db' => [\n    'hosts' => array(\n      'localhost', 'db' 2,\n    ),\n  ],\n]";
+    let err = from_str::<Value>(source).unwrap_err();
+    let context: Vec<_> = err
+        .context()
+        .iter()
+        .map(|crumb| (crumb.key.clone(), crumb.array.position().unwrap().line))
+        .collect();
+    assert_eq!(
+        vec![
+            (Key::Int(1), 3),
+            (Key::from("hosts"), 2),
+            (Key::from("db"), 1)
+        ],
+        context
+    );
+    assert_eq!(
+        "while parsing the value for key 'hosts' (array started at line 2)",
+        err.context()[1].to_string()
+    );
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        mode: Mode,
+    }
+
+    #[derive(Debug, Deserialize)]
+    enum Mode {
+        Fast,
+    }
+
+    let err = from_str::<HashMap<String, Config>>("['main' => ['mode' => 'slow']]").unwrap_err();
+    let keys: Vec<_> = err.context().iter().map(|crumb| &crumb.key).collect();
+    assert_eq!(vec![&Key::from("mode"), &Key::from("main")], keys);
+
+    assert!(from_str::<Value>("[1] 2").unwrap_err().context().is_empty());
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_error_context_diagnostic() {
+    use miette::Diagnostic;
+
+    let err = from_str::<Value>("['a' => [1, 2 3]]").unwrap_err();
+    let related: Vec<_> = err
+        .related()
+        .unwrap()
+        .map(|related| related.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "while parsing the value for key '1' (array started at line 1)",
+            "while parsing the value for key 'a' (array started at line 1)",
+        ],
+        related
+    );
+}
+
+#[test]
+fn test_error_kind() {
+    let err = from_str::<Value>("[1 2]").unwrap_err();
+    assert_eq!(ErrorKind::UnexpectedToken, err.kind());
+    assert!(err.is_unexpected_token());
+    assert!(!err.is_trailing());
+
+    let err = from_str::<Value>("[1] 2").unwrap_err();
+    assert_eq!(ErrorKind::TrailingCharacters, err.kind());
+    assert!(err.is_trailing());
+
+    let err = from_str::<i64>("9223372036854775808").unwrap_err();
+    assert!(err.is_invalid_primitive());
+
+    let err = from_str::<u8>("'foo'").unwrap_err();
+    assert!(!err.is_limit());
+
+    let err =
+        from_str_with_options::<Value>("[[1]]", &ParserOptions::new().max_depth(1)).unwrap_err();
+    assert_eq!(ErrorKind::NestingLimit, err.kind());
+    assert!(err.is_limit());
+    assert!(err.kind().is_limit());
+}
+
+#[test]
+fn test_trailing_characters() {
+    fn trailing(source: &str) -> (String, String, Span) {
+        match from_str::<Value>(source).unwrap_err() {
+            ParseError::TrailingCharacters(err) => {
+                (err.found.to_string(), err.text.clone(), err.span())
+            }
+            err => panic!("unexpected error {}", err),
+        }
+    }
+
+    let (_, text, span) = trailing("[1, 2];;");
+    assert_eq!(";", text);
+    assert_eq!(7..8, span.range());
+
+    let (found, text, span) = trailing("[1, 2] 'foo'");
+    assert_eq!("string literal", found);
+    assert_eq!("'foo'", text);
+    assert_eq!(7..12, span.range());
+
+    let (_, text, _) = trailing(&format!("1 '{}'", "a".repeat(50)));
+    assert_eq!(format!("'{}...", "a".repeat(31)), text);
+
+    let err = from_str::<Value>("[1, 2] 3").unwrap_err();
+    assert_eq!(
+        "Trailing characters after parsing, found `3`",
+        err.to_string()
+    );
+    assert_eq!(Some(8), err.column());
+}
+
+#[test]
+fn test_unescape_error() {
+    let source = r#"['name' => "caf\u{e9} \u{110000}"]"#;
+    let err = match from_str::<Value>(source).unwrap_err() {
+        ParseError::InvalidPrimitive(err) => err,
+        err => panic!("unexpected error {}", err),
+    };
+    let unescape = err.unescape_error().unwrap();
+    assert_eq!(UnescapeErrorReason::InvalidCodepoint, unescape.reason);
+    assert_eq!(11, unescape.offset);
+    assert_eq!(r#"\u{110000}"#, &source[err.span().range()]);
+    assert_eq!(
+        "Invalid string literal: invalid unicode codepoint in escape sequence",
+        err.to_string()
+    );
+
+    let err = from_str::<Value>(r#""\u{41""#).unwrap_err();
+    assert_eq!(Some(2), err.column());
+    assert_eq!(
+        "Invalid string literal: unterminated unicode escape sequence, expected '}'",
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_duplicate_fields() {
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        debug: bool,
+        name: String,
+    }
+
+    let source = "['debug' => false, 'name' => 'a', 'debug' => true]";
+    let err = match from_str::<Config>(source).unwrap_err() {
+        ParseError::DuplicateField(err) => err,
+        err => panic!("unexpected error {}", err),
+    };
+    assert_eq!(Key::from("debug"), err.duplicate().key);
+    assert_eq!(1..8, err.duplicate().first.range());
+    assert_eq!(34..41, err.duplicate().duplicate.range());
+    assert_eq!(Some(35), ParseError::from(err).column());
+
+    // duplicates are collected with the errors of other fields
+    let source = "['inner' => ['debug' => false, 'name' => 'a', 'debug' => true], 'count' => 'x']";
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Outer {
+        inner: Config,
+        count: u32,
+    }
+    let err = from_str::<Outer>(source).unwrap_err();
+    let paths: Vec<_> = match &err {
+        ParseError::Fields(errors) => errors
+            .errors()
+            .iter()
+            .map(|error| (error.path().to_string(), error.error().is_duplicate_field()))
+            .collect(),
+        err => panic!("unexpected error {}", err),
+    };
+    assert_eq!(
+        vec![
+            ("inner.debug".to_string(), true),
+            ("count".to_string(), false)
+        ],
+        paths
+    );
+
+    // only struct fields are checked, maps keep the last value
+    let map = from_str::<HashMap<String, bool>>("['debug' => false, 'debug' => true]").unwrap();
+    assert!(map["debug"]);
+
+    let options = ParserOptions::new().duplicate_fields(DuplicateFields::LastWins);
+    let config = from_str_with_options::<Config>(
+        "['debug' => false, 'name' => 'a', 'debug' => true]",
+        &options,
+    )
+    .unwrap();
+    assert!(config.debug);
+    assert_eq!("a", config.name);
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_duplicate_fields_diagnostic() {
+    use miette::Diagnostic;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        debug: bool,
+    }
+
+    let err = from_str::<Config>("['debug' => false,\n 'debug' => true]").unwrap_err();
+    let labels: Vec<_> = err
+        .labels()
+        .unwrap()
+        .map(|label| (label.label().unwrap().to_string(), label.offset()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("first set here".to_string(), 1),
+            ("set again here".to_string(), 20)
+        ],
+        labels
+    );
+    assert_eq!("PLP0014", err.error_code());
+}
+
+#[test]
+fn test_bareword_strings() {
+    let options = ParserOptions::new().bareword_strings(true);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        host: String,
+        mode: String,
+        debug: bool,
+    }
+    assert_eq!(
+        Config {
+            host: "localhost".into(),
+            mode: "fast".into(),
+            debug: true,
+        },
+        from_str_with_options::<Config>(
+            "[host => localhost, mode => fast, debug => true]",
+            &options
+        )
+        .unwrap()
+    );
+
+    let value = from_str_with_options::<Value>("[foo => bar, baz, 'a' . b]", &options).unwrap();
+    assert_eq!(value["foo"], "bar");
+    assert_eq!(value[0], "baz");
+    assert_eq!(value[1], "ab");
+
+    // known constants are still resolved
+    let options = options.constant("FOO", 1);
+    let value = from_str_with_options::<Value>("[FOO => FOO]", &options).unwrap();
+    assert_eq!(value[1], 1);
+
+    // namespaced and class constants aren't barewords
+    assert!(from_str_with_options::<Value>(r"[\Foo\BAR]", &options).is_err());
+    assert!(from_str_with_options::<Value>("[Foo::BAR]", &options).is_err());
+
+    let tree = SyntaxTree::parse_with_options("[foo => bar]", &options).unwrap();
+    assert_eq!(tree.to_value()["foo"], "bar");
+
+    assert!(from_str::<Value>("[foo => bar]").is_err());
+}
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn test_preserve_order() {
+    let keys: Vec<String> = (0..20).rev().map(|i| format!("key{}", i)).collect();
+    let source = format!(
+        "[{}]",
+        keys.iter()
+            .map(|key| format!("'{}' => 1", key))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let value = from_str::<Value>(&source).unwrap();
+    assert_eq!(source, php_literal_parser::to_string(&value).unwrap());
+
+    let mut array = match value {
+        Value::Array(array) => array,
+        value => panic!("unexpected value {:?}", value),
+    };
+    let parsed: Vec<_> = array.keys().map(|key| key.to_string()).collect();
+    assert_eq!(keys, parsed);
+
+    array.shift_remove(&Key::from("key10"));
+    array.insert(Key::Int(0), Value::Null);
+    let keys: Vec<_> = array.keys().map(|key| key.to_string()).collect();
+    assert_eq!(["key19", "key18"], keys[..2]);
+    assert_eq!(["key11", "key9"], keys[8..10]);
+    assert_eq!("0", keys[19]);
+}
+
+#[test]
+fn test_value_get_mut() {
+    let mut value =
+        from_str::<Value>("['db' => ['host' => 'localhost', 'port' => 3306], 'debug' => false]")
+            .unwrap();
+
+    *value.get_mut("debug").unwrap() = Value::Bool(true);
+    let db = value.get_mut("db").unwrap();
+    *db.get_mut("port").unwrap() = Value::Int(3307);
+    if let Some(Value::String(host)) = db.get_mut("host") {
+        host.push_str(".local");
+    }
+
+    assert_eq!(value["debug"], true);
+    assert_eq!(value["db"]["host"], "localhost.local");
+    assert_eq!(value["db"]["port"], 3307);
+
+    assert_eq!(value.get_mut("missing"), None);
+    assert_eq!(value.get_mut(&0), None);
+    assert_eq!(value["debug"].clone().get_mut("foo"), None);
+
+    let mut value = from_str::<Value>("['0' => 'a', '01' => 'b']").unwrap();
+    *value.get_mut("0").unwrap() = "c".into();
+    assert_eq!(value.get_mut(&0), Some(&mut Value::from("c")));
+    assert_eq!(value.get_mut("01"), Some(&mut Value::from("b")));
+
+    let options = ParserOptions::new().keep_numeric_string_keys(true);
+    let mut value = from_str_with_options::<Value>("['1' => 'a', 1 => 'b']", &options).unwrap();
+    assert_eq!(value.get_mut("1"), Some(&mut Value::from("a")));
+    assert_eq!(value.get_mut(&1), Some(&mut Value::from("b")));
+}
+
+#[test]
+fn test_value_index_mut() {
+    let mut value =
+        from_str::<Value>("['db' => ['host' => 'localhost'], 'hosts' => ['a']]").unwrap();
+
+    value["db"]["host"] = "db.example.com".into();
+    value["db"]["port"] = Value::Int(3306);
+    value["hosts"][1] = "b".into();
+    value["hosts"][Key::Int(0)] = "c".into();
+    value[&String::from("debug")] = Value::Bool(true);
+    value["cache"]["redis"][0] = "localhost".into();
+
+    let expected = from_str::<Value>(
+        "['db' => ['host' => 'db.example.com', 'port' => 3306], 'hosts' => ['c', 'b'], \
+        'debug' => true, 'cache' => ['redis' => ['localhost']]]",
+    )
+    .unwrap();
+    assert_eq!(expected, value);
+
+    let mut value = from_str::<Value>("['0' => 'a']").unwrap();
+    assert_eq!(value["0"], "a");
+    value["0"] = "b".into();
+    value[Key::from("1")] = "c".into();
+    value["01"] = "d".into();
+    assert_eq!(value.keys().count(), 3);
+    assert_eq!(
+        value,
+        from_str::<Value>("[0 => 'b', 1 => 'c', '01' => 'd']").unwrap()
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot access key 'foo' in php value of type string")]
+fn test_value_index_mut_non_array() {
+    let mut value = Value::from("bar");
+    value["foo"] = Value::Null;
+}
+
+#[test]
+fn test_value_entry() {
+    let mut config =
+        from_str::<Value>("['debug' => true, 'cache' => ['ttl' => 60], 'retries' => 1]").unwrap();
+    let defaults = from_str::<Value>(
+        "['debug' => false, 'cache' => ['ttl' => 300, 'prefix' => 'app'], 'timeout' => 30]",
+    )
+    .unwrap();
+
+    for (key, default) in defaults.iter() {
+        if let Value::Array(cache) = config.entry(key.clone()).or_insert_with(|| default.clone()) {
+            for (key, default) in default.iter() {
+                cache.entry(key.clone()).or_insert(default.clone());
+            }
+        }
+    }
+    config
+        .entry("retries")
+        .and_modify(|retries| *retries = Value::Int(3))
+        .or_insert(Value::Int(0));
+    config
+        .entry("workers")
+        .and_modify(|workers| *workers = Value::Int(3))
+        .or_insert(Value::Int(0));
+
+    let expected = from_str::<Value>(
+        "['debug' => true, 'cache' => ['ttl' => 60, 'prefix' => 'app'], 'retries' => 3, \
+        'timeout' => 30, 'workers' => 0]",
+    )
+    .unwrap();
+    assert_eq!(expected, config);
+
+    let mut value = Value::Null;
+    let entry = value.entry(1);
+    assert_eq!(&Key::Int(1), entry.key());
+    entry.or_insert("foo".into());
+    assert_eq!(value[1], "foo");
+
+    let entry = value.entry("1");
+    assert_eq!(&Key::Int(1), entry.key());
+    entry.and_modify(|value| *value = "bar".into());
+    value.entry("2").or_insert("baz".into());
+    assert_eq!(
+        value,
+        from_str::<Value>("[1 => 'bar', 2 => 'baz']").unwrap()
+    );
+    assert_eq!(value.keys().count(), 2);
+}